@@ -19,29 +19,242 @@ export interface DownloadProgress {
   percent: number
   error?: string
 }
+/**
+ * Keep the model under `path` (in a parakeet-tdt-v3 subfolder) instead of the app data
+ * directory, e.g. on an external disk; an empty path restores the default. Persisted across
+ * restarts. Existing files aren't moved, and an already loaded model stays loaded
+ */
+export declare function setParakeetModelDir(path: string): void
+/** Directory set with set_parakeet_model_dir, or null when using the default */
+export declare function getParakeetModelDir(): string | null
 export declare function isParakeetDownloaded(): boolean
 export declare function getParakeetModelInfo(): ParakeetModelInfo
 export declare function getParakeetLanguages(): Array<string>
 export declare function getParakeetDownloadProgress(): DownloadProgress
 export declare function downloadParakeetModel(): boolean
-export declare function initParakeet(): boolean
+/**
+ * Stop the running model download, deleting the partial file and resetting progress.
+ * Returns false if no download was in progress
+ */
+export declare function cancelParakeetDownload(): boolean
+/** Decoder tuning for configure_parakeet; unset fields keep their current value */
+export interface ParakeetDecodeConfig {
+  /**
+   * Max non-blank tokens per encoder frame (1 - 50, default 10)
+   * Lower values stop noisy audio from emitting the same token over and over
+   */
+  maxTokensPerStep?: number
+  /** Encoder subsampling of the model export (1 - 16, default 8) */
+  subsamplingFactor?: number
+  /** Preprocessor hop in seconds (0.005 - 0.05, default 0.01) */
+  windowSizeSecs?: number
+}
+/**
+ * Adjust decode constants for experimenting with other model exports
+ * Applies from the next init_parakeet; fields are validated together, so nothing changes on error
+ */
+export declare function configureParakeet(config: ParakeetDecodeConfig): void
+/** Options for loading the Parakeet model */
+export interface ParakeetInitOptions {
+  /** Max seconds of audio per encoder call; bounds latency for streaming (min 1s) */
+  maxEncoderWindowSecs?: number
+  /**
+   * Run the encoder on the CoreML execution provider (Apple Silicon), falling back to CPU
+   * Defaults to the PARAKEET_USE_COREML=1 environment flag
+   */
+  useCoreml?: boolean
+  /**
+   * ORT intra/inter-op threads per session (default: half the CPU cores, at least 1)
+   * More threads transcribe faster but compete with the audio capture threads,
+   * which can cause dropouts on laptops while recording
+   */
+  threads?: number
+  /** Run warmup_parakeet right after loading, so the first real transcription doesn't stall */
+  warmup?: boolean
+  /**
+   * Stop decoding after this many ms and return what was transcribed so far, with
+   * `timed_out` set (unset or 0 = no limit). transcribe_wav_file always runs to the end
+   */
+  maxDurationMs?: number
+}
+export declare function initParakeet(options?: ParakeetInitOptions | undefined | null): boolean
+/**
+ * Run a short silent clip through preprocess → encode → decode so ORT allocates and
+ * optimizes its graphs now rather than on the first caption
+ * Returns the warmup time in ms, or null if the model isn't loaded
+ */
+export declare function warmupParakeet(): number | null
 export declare function isParakeetReady(): boolean
+/** Detailed Parakeet state, distinguishing "never loaded" from "failed to load" */
+export declare function getParakeetState(): EngineStatus
 /** A segment of transcribed text with its timestamp */
 export interface TranscriptSegment {
   text: string
   startTime: number
   endTime: number
+  speaker?: string
+  confidence?: number
 }
 /** Result containing segments with timestamps */
 export interface TranscriptWithTimestamps {
   segments: Array<TranscriptSegment>
   fullText: string
+  /** Language hint the transcript was produced with (None = auto) */
+  language?: string
+  /** Decoder output before normalization (only set when normalize_text was requested) */
+  rawText?: string
+  /** True when max_duration_ms stopped decoding early; the transcript covers only the audio before that */
+  timedOut?: boolean
 }
+/**
+ * `channels` (default 1) is the number of interleaved channels, mixed to mono. It used to be
+ * ignored and every buffer read as mono, so callers passing it for mono audio must drop it.
+ * Fails with TIMEOUT if max_duration_ms cut decoding short; the timestamped variants
+ * return the partial transcript instead
+ */
 export declare function transcribeAudioBuffer(audioData: Buffer, sampleRate?: number | undefined | null, channels?: number | undefined | null): string
-/** Transcribe audio and return segments with timestamps */
-export declare function transcribeAudioBufferWithTimestamps(audioData: Buffer, sampleRate?: number | undefined | null, channels?: number | undefined | null): TranscriptWithTimestamps
-export declare function deleteParakeetModel(): boolean
+/** Options for transcribe_audio_buffer_with_timestamps */
+export interface TranscribeOptions {
+  /** Rate of the PCM16 input (default 16000) */
+  sampleRate?: number
+  /** Interleaved channels of the input (default 1), mixed to mono, e.g. from flush_retained_chunks */
+  channels?: number
+  /** Tokens with confidence below this (0.0 - 1.0) are dropped */
+  minConfidence?: number
+  /** Segments break every this many seconds (default 2.5) */
+  segmentIntervalSecs?: number
+  /** Also break segments after tokens ending in . ? or ! (default true) */
+  splitOnSentence?: boolean
+  /** A code from get_parakeet_languages; "auto" (or unset) lets the model detect it */
+  language?: string
+  /** Tidy punctuation spacing and sentence casing; the decoder output is kept in `rawText` */
+  normalizeText?: boolean
+}
+/** Transcribe PCM16 audio and return segments with timestamps */
+export declare function transcribeAudioBufferWithTimestamps(audioData: Buffer, options?: TranscribeOptions | undefined | null): TranscriptWithTimestamps
+/**
+ * Same as transcribe_audio_buffer_with_timestamps, but inference runs on a blocking
+ * worker thread so the JS event loop stays responsive; resolves with the transcript
+ */
+export declare function transcribeAudioBufferAsync(audioData: Buffer, sampleRate?: number | undefined | null): Promise<TranscriptWithTimestamps>
+/** A word with the time span of its SentencePiece pieces */
+export interface WordTiming {
+  word: string
+  /** Seconds from the start of the buffer: first piece's timestamp */
+  startTime: number
+  /** Last piece's timestamp plus one encoder frame */
+  endTime: number
+  /** Mean confidence of the word's pieces (0.0 - 1.0) */
+  confidence?: number
+}
+/**
+ * Transcribe mono PCM16 and return per-word timings (for click-to-seek)
+ * Fails with TIMEOUT if max_duration_ms cut decoding short
+ */
+export declare function transcribeAudioBufferWords(audioData: Buffer, sampleRate?: number | undefined | null): Array<WordTiming>
+/** Transcribe a WAV file (16-bit PCM or 32-bit float, any rate, channels mixed to mono) */
+export declare function transcribeWavFile(path: string): TranscriptWithTimestamps
+/**
+ * Transcribe a long buffer on a background thread, calling back with each segment as it completes
+ * Called as callback(json) with a JSON TranscriptSegment, then "[DONE]" (or "[ERROR] message")
+ * Audio is decoded about one segment interval at a time and the model is only locked per
+ * step, so other transcriptions aren't shut out for the length of the buffer
+ */
+export declare function transcribeAudioBufferStreaming(audioData: Buffer, sampleRate: number | undefined | null, callback: (...args: any[]) => any): void
+/** Per-speaker transcripts of a stereo capture (L=system "them", R=mic "me") */
+export interface StereoTranscript {
+  me: TranscriptWithTimestamps
+  them: TranscriptWithTimestamps
+  /** Segments from both tracks sorted by start_time, with speaker "me"/"them" */
+  merged: Array<TranscriptSegment>
+  /** Time spent transcribing each channel, in milliseconds */
+  meMs: number
+  themMs: number
+}
+/**
+ * Transcribe interleaved stereo 16-bit PCM (L=system, R=mic) one channel at a time
+ * The passes run sequentially so CPU use matches a single mono transcription
+ */
+export declare function transcribeStereoBuffer(audioData: Buffer, sampleRate?: number | undefined | null): StereoTranscript
+/** Outcome of a queued transcription */
+export interface TranscriptionJobResult {
+  id: number
+  /** "pending", "done" or "error" */
+  status: string
+  transcript?: TranscriptWithTimestamps
+  error?: string
+}
+/**
+ * Queue a 16-bit mono PCM buffer for transcription on the background worker
+ * Jobs run in submission order; returns the job id for poll_transcription_result
+ */
+export declare function enqueueTranscription(audioData: Buffer, sampleRate?: number | undefined | null): number
+/**
+ * Result of a queued job: status "pending" while queued or running, then the finished
+ * result (returned once, then forgotten). Returns null for unknown or already-polled ids
+ */
+export declare function pollTranscriptionResult(id: number): TranscriptionJobResult | null
+/** Number of queued or running transcription jobs */
+export declare function getTranscriptionQueueLength(): number
+/**
+ * Register a callback invoked with each TranscriptionJobResult as queued jobs finish
+ * While a callback is registered, results are delivered only to it (not kept for polling)
+ */
+export declare function setTranscriptionResultCallback(callback: (...args: any[]) => any): void
+/** What one call into the live transcription stream produced */
+export interface StreamTranscript {
+  /** Segments decoded from this chunk, timed from limited context (interim, for captions) */
+  segments: Array<TranscriptSegment>
+  fullText: string
+  /** Interim per-word timings of this chunk */
+  words: Array<WordTiming>
+  /**
+   * Segments finalized by this call, at a sentence end or the end of the stream. They are
+   * re-decoded with the audio around them, so their timings replace the interim ones
+   */
+  finalizedSegments: Array<TranscriptSegment>
+  finalizedWords: Array<WordTiming>
+}
+/**
+ * Feed a chunk (16-bit mono PCM) to the live transcription stream
+ * Returns only tokens decoded from this chunk, plus any segment it finalized;
+ * timestamps are seconds since the stream started
+ */
+export declare function transcribeChunk(audioData: Buffer, sampleRate?: number | undefined | null): StreamTranscript
+/**
+ * Decode any audio still held back at the end of the stream and finalize the last segment,
+ * then reset it
+ */
+export declare function finishTranscribeStream(): StreamTranscript
+/** Discard the live transcription stream without decoding pending audio */
+export declare function resetTranscribeStream(): void
+/** A speaker turn from an external diarization tool */
+export interface SpeakerTurn {
+  start: number
+  end: number
+  speaker: string
+}
+/**
+ * Tag each transcript segment with the speaker from an external diarization
+ * Segments are assigned to the turn they overlap most; a segment that spans a
+ * speaker change is split at the turn boundary (text is divided by word position)
+ */
+export declare function applyDiarization(transcript: TranscriptWithTimestamps, turns: Array<SpeakerTurn>): TranscriptWithTimestamps
+/**
+ * Resample float samples, returning an error instead of silently passing input through
+ * Intended for tests/CI that need to assert resampling actually happened
+ */
+export declare function resampleAudioChecked(samples: Float32Array, fromRate: number, toRate: number): Float32Array
+/**
+ * Delete the downloaded model files, unloading the model first unless `keep_loaded` is true
+ * A kept model works until unloaded but can't be loaded again without a new download
+ * (on Windows, files of a loaded model may be locked and fail to delete)
+ */
+export declare function deleteParakeetModel(keepLoaded?: boolean | undefined | null): boolean
 export declare function getParakeetModelPath(): string
+/** Free the model's memory but keep the downloaded files; init_parakeet loads it again */
+export declare function unloadParakeet(): void
+/** Same as unload_parakeet */
 export declare function shutdownParakeet(): void
 export interface LlmModelInfo {
   ready: boolean
@@ -49,11 +262,17 @@ export interface LlmModelInfo {
   modelRepo: string
   modelFile: string
   estimatedSize: number
+  /** "metal" or "cpu" once loaded */
+  device?: string
 }
 export interface LlmInitProgress {
   isLoading: boolean
   status: string
   error?: string
+  /** Download progress across the GGUF and tokenizer files (0 when already cached) */
+  bytesDownloaded: number
+  totalBytes: number
+  percent: number
 }
 export interface LlmDownloadProgress {
   isDownloading: boolean
@@ -68,35 +287,97 @@ export interface LlmResponse {
   completionTokens: number
   tokensPerSecond: number
 }
+/** Optional sampling controls; omitted fields keep mistral.rs defaults */
+export interface LlmSamplingOptions {
+  /** Nucleus sampling: sample from the smallest token set whose probability reaches top_p */
+  topP?: number
+  /** Sample from the k most likely tokens only */
+  topK?: number
+  /** Same seed + prompt + temperature gives the same output */
+  seed?: number
+}
 export declare function getLlmModelInfo(): LlmModelInfo
 export declare function getLlmInitProgress(): LlmInitProgress
 export declare function isLlmReady(): boolean
+/** Detailed LLM state, distinguishing "never loaded" from "failed to load" */
+export declare function getLlmState(): EngineStatus
 /** Check if LLM model is downloaded (cached by HuggingFace Hub) */
 export declare function isLlmDownloaded(): boolean
-/** Get download progress by checking HuggingFace cache for .part files */
+/** Download progress of the current init, or 100% when the model is already cached */
 export declare function getLlmDownloadProgress(): LlmDownloadProgress
 /**
  * Initialize the LLM - downloads model from HuggingFace if not cached
  * This is handled automatically by mistral.rs
  */
 export declare function initLlm(): boolean
-/** Synchronous init that blocks until model is ready */
+/**
+ * Initialize the LLM from any GGUF repo/file, e.g. a smaller model on low-memory machines.
+ * The config is remembered, so download/delete checks keep targeting it after a restart.
+ * A different model that is already loaded is unloaded first
+ */
+export declare function initLlmWithConfig(repo: string, file: string, tokenizerRepo: string): boolean
+/**
+ * Initialize the configured LLM on "cpu", "metal" or "auto" (Metal on macOS).
+ * If Metal fails to initialize the model loads on CPU and the init status says so.
+ * A model already loaded on a different device is reloaded
+ */
+export declare function initLlmWithDevice(device: string): boolean
+/** Synchronous init that blocks until model is ready (uses the last configured model) */
 export declare function initLlmSync(): boolean
+/** Free the model's memory but keep the downloaded files; init_llm loads it again */
+export declare function unloadLlm(): void
 export declare function shutdownLlm(): void
-/** Delete the downloaded LLM model from HuggingFace cache */
-export declare function deleteLlmModel(): boolean
-/** Generate text completion using the local LLM */
-export declare function llmGenerate(prompt: string, maxTokens?: number | undefined | null, temperature?: number | undefined | null): LlmResponse
+/**
+ * Unload the model after `secs` seconds without llm_generate/llm_chat/llm_chat_stream
+ * calls to free its memory (0 disables, the default). The next request starts reloading it in
+ * the background and fails with a [RELOADING] error; retry once get_llm_state() is "ready".
+ * get_llm_init_progress reports the unload and the reload
+ */
+export declare function setLlmIdleTimeout(secs: number): void
+/**
+ * Delete the configured LLM model from HuggingFace cache, unloading it first unless
+ * `keep_loaded` is true. A kept model works until unloaded (the weights are mmapped, so on
+ * Windows the delete may fail while it's loaded); with an idle timeout set, the next
+ * idle unload is final since there's nothing left to reload from
+ */
+export declare function deleteLlmModel(keepLoaded?: boolean | undefined | null): boolean
+/** Number of tokens the loaded model's tokenizer produces for `text` */
+export declare function llmCountTokens(text: string): number
+/**
+ * Generate text completion using the local LLM
+ * Temperature defaults to 0.7; 0 gives deterministic (greedy) output
+ * Output ends before the first of the `stop` sequences, if given
+ */
+export declare function llmGenerate(prompt: string, maxTokens?: number | undefined | null, temperature?: number | undefined | null, stop?: Array<string> | undefined | null, sampling?: LlmSamplingOptions | undefined | null): LlmResponse
 /**
  * Chat completion - takes messages array and returns response
  * Messages format: [{"role": "system", "content": "..."}, {"role": "user", "content": "..."}]
+ * Output ends before the first of the `stop` sequences, if given
  */
-export declare function llmChat(messagesJson: string, maxTokens?: number | undefined | null, temperature?: number | undefined | null): LlmResponse
+export declare function llmChat(messagesJson: string, maxTokens?: number | undefined | null, temperature?: number | undefined | null, stop?: Array<string> | undefined | null, sampling?: LlmSamplingOptions | undefined | null): LlmResponse
 /**
  * Stream chat completion - returns chunks as they're generated
  * This is useful for showing real-time responses
+ * Returns a stream id that can be passed to cancel_llm_stream
+ * max_tokens and temperature work as in llm_chat
+ * Streaming ends before the first of the `stop` sequences, if given
+ */
+export declare function llmChatStream(messagesJson: string, callback: (...args: any[]) => any, maxTokens?: number | undefined | null, temperature?: number | undefined | null, stop?: Array<string> | undefined | null, sampling?: LlmSamplingOptions | undefined | null): number
+/**
+ * Stop an llm_chat_stream early; its callback receives "[CANCELLED]" instead of "[DONE]"
+ * Returns false if the stream already finished or the id is unknown
  */
-export declare function llmChatStream(messagesJson: string, callback: (...args: any[]) => any): void
+export declare function cancelLlmStream(streamId: number): boolean
+/**
+ * Embed `text` with the loaded LLM (pooled over the prompt, L2-normalized), so chat-only
+ * setups can do semantic search without also loading the MiniLM embedding model
+ *
+ * The vector has the LLM's hidden size (2048 for Qwen2.5 3B), not MiniLM's 384, and lives in
+ * a different space: never compare or index these against generate_embedding vectors.
+ * Fails with "Model does not support embeddings" if the loaded model's architecture can't
+ * produce them
+ */
+export declare function llmEmbed(text: string): Array<number>
 export interface EmbeddingDownloadProgress {
   isDownloading: boolean
   currentFile: string
@@ -109,13 +390,109 @@ export interface EmbeddingDownloadProgress {
 }
 export declare function isEmbeddingDownloaded(): boolean
 export declare function downloadEmbeddingModel(): boolean
+/**
+ * Stop the running model download, deleting the partial file and resetting progress.
+ * Returns false if no download was in progress
+ */
+export declare function cancelEmbeddingDownload(): boolean
 export declare function getEmbeddingDownloadProgress(): EmbeddingDownloadProgress
 export declare function initEmbeddingModel(): boolean
+/**
+ * How to load a different BERT-style ONNX embedding model (bge-small, e5...);
+ * every field defaults to the bundled all-MiniLM-L6-v2 setup
+ */
+export interface EmbeddingModelConfig {
+  /**
+   * Directory holding model.onnx plus tokenizer.json (or vocab.txt)
+   * Default: the downloaded MiniLM model
+   */
+  modelDir?: string
+  /** ONNX input names (default "input_ids", "attention_mask", "token_type_ids") */
+  inputIdsName?: string
+  attentionMaskName?: string
+  tokenTypeIdsName?: string
+  /** Feed token_type_ids; default: only if the model declares that input */
+  useTokenTypeIds?: boolean
+  /** "mean" (masked mean over tokens, default) or "cls" (first token, e.g. bge) */
+  pooling?: string
+  /** Expected embedding size; loading fails if the model outputs something else */
+  dimension?: number
+  /** Output holding the hidden states (default: last_hidden_state or the first output) */
+  outputName?: string
+}
+/**
+ * Load an embedding model, optionally a custom export described by `config`
+ * Replaces any loaded model; get_embedding_dimension then reports the new model's size
+ */
+export declare function initEmbeddingModelWithConfig(config?: EmbeddingModelConfig | undefined | null): boolean
 export declare function isEmbeddingReady(): boolean
+/** Detailed embedding model state, distinguishing "never loaded" from "failed to load" */
+export declare function getEmbeddingState(): EngineStatus
 export declare function generateEmbedding(text: string): Array<number>
 export declare function generateEmbeddingsBatch(texts: Array<string>): Array<Array<number>>
-export declare function deleteEmbeddingModel(): boolean
+/** Embedding of a long document and how many windows it was split into */
+export interface LongEmbedding {
+  embedding: Array<number>
+  windowCount: number
+}
+/**
+ * Embed a document longer than the model's 512-token window by averaging
+ * overlapping windows. `stride` is the token overlap between windows (default 128)
+ */
+export declare function generateEmbeddingLong(text: string, stride?: number | undefined | null): LongEmbedding
+/** Free the model's memory but keep the downloaded files; init_embedding_model loads it again */
+export declare function unloadEmbeddingModel(): void
+/**
+ * Delete the downloaded model files, unloading the model first unless `keep_loaded` is true
+ * A kept model works until unloaded but can't be loaded again without a new download
+ */
+export declare function deleteEmbeddingModel(keepLoaded?: boolean | undefined | null): boolean
+/**
+ * Keep the model under `path` (in an embedding-model subfolder) instead of the cache
+ * directory, e.g. on an external disk; an empty path restores the default. Persisted across
+ * restarts. Existing files aren't moved, and an already loaded model stays loaded
+ */
+export declare function setEmbeddingModelDir(path: string): void
+/** Directory set with set_embedding_model_dir, or null when using the default */
+export declare function getEmbeddingModelDir(): string | null
+/** Embedding size of the loaded model (MiniLM's 384 when none is loaded) */
 export declare function getEmbeddingDimension(): number
+export interface SimilarityResult {
+  /** Position in the corpus passed to top_k_similar */
+  index: number
+  score: number
+}
+/** Cosine similarity of two embeddings (assumes L2-normalized input, as generate_embedding returns) */
+export declare function cosineSimilarity(a: Array<number>, b: Array<number>): number
+/** Rank corpus embeddings by similarity to the query, best first */
+export declare function topKSimilar(query: Array<number>, corpus: Array<Array<number>>, k: number): Array<SimilarityResult>
+export interface IndexSearchResult {
+  id: string
+  score: number
+}
+/**
+ * Add a vector to the in-memory index under `id` (replacing any vector with that id)
+ * The first vector fixes the index dimension; later ones must match
+ */
+export declare function embeddingIndexAdd(id: string, vector: Array<number>): void
+/**
+ * The `k` indexed vectors most similar to `query` (dot product, so pass L2-normalized
+ * vectors as generate_embedding returns), best first
+ */
+export declare function embeddingIndexSearch(query: Array<number>, k: number): Array<IndexSearchResult>
+/** Number of vectors in the index */
+export declare function embeddingIndexLen(): number
+/** Remove every vector (the next add sets a new dimension) */
+export declare function embeddingIndexClear(): void
+/** Write the index to `path` in a compact binary format (written to a temp file, then renamed) */
+export declare function embeddingIndexSave(path: string): void
+/** Replace the index with one saved by embedding_index_save; returns the number of vectors */
+export declare function embeddingIndexLoad(path: string): number
+/** Engine state: "unloaded" | "loading" | "ready" | "error" (with the last error message) */
+export interface EngineStatus {
+  state: string
+  error?: string
+}
 /** Window information returned from native APIs */
 export interface WindowInfo {
   pid: number
@@ -123,16 +500,201 @@ export interface WindowInfo {
   ownerName: string
   title: string
   bundleId?: string
+  /**
+   * Heuristic key (pid + bundle id + normalized title) that survives window id changes
+   * Not guaranteed unique across windows of the same app with identical titles
+   */
+  stableKey: string
+  /** Known meeting app (Zoom, Teams, Webex...) or a browser showing a meeting URL */
+  isMeetingApp: boolean
+  /** Minimized or otherwise not visible (off screen / fully transparent on macOS) */
+  isMinimized: boolean
+  /** The window with keyboard focus in the frontmost app */
+  isFocused: boolean
+  /**
+   * Stacking position in get_active_windows, 0 = topmost (overlays and always-on-top
+   * windows included); -1 for windows looked up on their own
+   */
+  zOrder: number
+}
+/** Display name, bundle id and icon of a running application */
+export interface AppInfo {
+  /** Localized app name (macOS) or executable name (Windows) */
+  name: string
+  bundleId?: string
+  /** 64x64 PNG of the app icon, when one could be rendered */
+  iconPng?: Buffer
+}
+/** Combined guess at whether a meeting is live, from detect_meeting_state */
+export interface MeetingState {
+  /** Confidence reached the meeting threshold (needs the mic plus a meeting app or URL) */
+  active: boolean
+  /** Owner name of the meeting window, if one was found */
+  app?: string
+  /** 0.0 - 1.0 */
+  confidence: number
+  /** Meeting URL when the meeting is in a browser tab */
+  url?: string
+}
+/** Active tab of a browser window */
+export interface BrowserTabInfo {
+  url: string
+  /** Active tab/page title, when the browser exposes one */
+  title?: string
+}
+/** Currently playing media reported by the OS */
+export interface NowPlayingInfo {
+  app?: string
+  title: string
+  artist?: string
 }
 /** Audio capture configuration */
 export interface AudioCaptureOptions {
+  /**
+   * System audio rate of the saved recording (default 48000)
+   * macOS: 8000, 16000, 24000 or 48000; Windows: 8000 - 192000
+   */
   sampleRate?: number
+  /** System audio channels, 1 or 2 (default 2) */
   channels?: number
+  /**
+   * Where to write the recording; its directory must exist and be writable
+   * (default `<data dir>/ghost/recordings/ghost_recording_<timestamp>.wav`, owner-only)
+   */
   outputPath?: string
   includeMicrophone?: boolean
+  /** Also write `<output>_system.wav` and `<output>_mic.wav` as mono tracks at their native rates (macOS) */
+  separateTracks?: boolean
+  /** Max streaming chunks (~100ms each) kept for get_audio_chunks before the oldest are dropped (default 200) */
+  maxQueuedChunks?: number
+  /** Saved WAV layout: "stereo-split" (L=system, R=mic, default) or "mono-mix" (macOS) */
+  wavLayout?: string
+  /** Microphone device id from list_input_devices (default input device if unset or missing) */
+  inputDeviceId?: string
+  /** Saved WAV bit depth: 16 (PCM, default), 24 (PCM) or 32 (IEEE float) */
+  wavBitDepth?: number
+  /**
+   * Main recording format: "wav" (default), "mp3" or "opus" (the latter two need the
+   * matching cargo feature); the returned path gets the format's extension
+   */
+  outputFormat?: string
+  /** Keep a copy of the last N seconds of streaming chunks for flush_retained_chunks (default 0 = off) */
+  retainChunksSecs?: number
+  /**
+   * Display id from list_displays to anchor the ScreenCaptureKit filter to (default: main display) (macOS)
+   * Audio is display-agnostic; this only matters when the default display misbehaves
+   */
+  displayId?: number
+  /**
+   * Write the recording to disk on stop (default true); false for streaming-only use,
+   * in which case stop returns an empty path and output_path is never touched
+   */
+  saveWav?: boolean
+  /**
+   * Cut leading and trailing silence longer than a few seconds from the saved recording
+   * (default false); streaming chunks and separate tracks stay full length
+   */
+  trimSilence?: boolean
+}
+/** A display ScreenCaptureKit can capture */
+export interface DisplayInfo {
+  /** CGDirectDisplayID */
+  id: number
+  name: string
+  isMain: boolean
+  /** Bounds in global display coordinates (points) */
+  x: number
+  y: number
+  width: number
+  height: number
+}
+/** Audio input device */
+export interface AudioDeviceInfo {
+  /** Stable device UID */
+  id: string
+  name: string
+  isDefault: boolean
+}
+/** An application capturing from the microphone */
+export interface MicUser {
+  pid: number
+  appName: string
+  bundleId?: string
+}
+/** Live format of the running capture (all zeros when not capturing) */
+export interface CaptureFormat {
+  /** Native rate of the system audio stream (0 until the first buffer arrives) */
+  systemSampleRate: number
+  systemChannels: number
+  /** Native rate of the microphone (0 if the mic isn't being captured) */
+  micSampleRate: number
+  /** Rate of the stereo PCM16 chunks returned by get_audio_chunks */
+  streamSampleRate: number
 }
-/** Get all visible windows on the system */
+/** Files produced by a capture */
+export interface CaptureOutput {
+  /** Main recording (stereo L=system, R=mic, or mono mix depending on wav_layout) */
+  path: string
+  systemTrackPath?: string
+  micTrackPath?: string
+  /** Seconds cut from the start by trim_silence; subtract from capture-relative timestamps */
+  trimmedStartSecs: number
+  /** Seconds cut from the end by trim_silence */
+  trimmedEndSecs: number
+  /** Length of the saved recording (0 when nothing was saved) */
+  durationSecs: number
+}
+/**
+ * Get all windows on the system, minimized ones included with is_minimized set
+ * Sorted with the focused window first, then by stacking order (front to back), pid and window id
+ */
 export declare function getActiveWindows(): Array<WindowInfo>
+/** Get the window the user is currently looking at (focused window of the frontmost app) */
+export declare function getFrontmostWindow(): WindowInfo | null
+/** Fresh info for one window by the windowId from get_active_windows, or null if it has closed */
+export declare function getWindowById(windowId: number): WindowInfo | null
+/**
+ * Name, bundle id and icon for a pid from WindowInfo or get_microphone_users, or null if
+ * no such process is running
+ */
+export declare function getAppInfo(pid: number): AppInfo | null
+/** Get the most likely meeting window (Zoom, Teams, Webex, or a browser meeting tab) */
+export declare function getActiveMeeting(): WindowInfo | null
+/**
+ * Decide whether a meeting is live from the mic being in use, a meeting app window
+ * and a meeting URL in a browser, in one call instead of stitching them together in JS
+ */
+export declare function detectMeetingState(): MeetingState
+/**
+ * Replace the list of native meeting apps used for WindowInfo.isMeetingApp
+ * Entries are bundle ids on macOS and process names (e.g. "Zoom.exe") on Windows
+ */
+export declare function setMeetingAppBundles(bundles: Array<string>): void
+/**
+ * Call callback(windowInfo) whenever the user switches to another app or window
+ * Fires once immediately with the current frontmost window. On macOS, window
+ * switches within an app need accessibility permission; app switches don't
+ */
+export declare function watchActiveWindow(callback: (...args: any[]) => any): void
+/**
+ * Stop the watch started by watch_active_window
+ * Returns false if no watch was active
+ */
+export declare function unwatchActiveWindow(): boolean
+/** Permission state, distinguishing "never asked" (prompt) from "denied" (send to Settings) */
+export const enum PermissionStatus {
+  Granted = 'Granted',
+  Denied = 'Denied',
+  NotDetermined = 'NotDetermined',
+  /** Blocked by policy (e.g. MDM); the user can't change it */
+  Restricted = 'Restricted'
+}
+/** Accessibility permission status (macOS; always Granted elsewhere) */
+export declare function accessibilityPermissionStatus(): PermissionStatus
+/** Screen recording permission status (macOS; always Granted elsewhere) */
+export declare function screenRecordingPermissionStatus(): PermissionStatus
+/** Microphone permission status (macOS; always Granted elsewhere) */
+export declare function microphonePermissionStatus(): PermissionStatus
 /** Check if accessibility permissions are granted (macOS) */
 export declare function checkAccessibilityPermission(): boolean
 /** Request accessibility permissions (macOS) - opens System Settings */
@@ -141,6 +703,20 @@ export declare function requestAccessibilityPermission(): boolean
 export declare function checkScreenRecordingPermission(): boolean
 /** Request screen recording permission (macOS) - prompts user if not granted */
 export declare function requestScreenRecordingPermission(): boolean
+/** Check if microphone permission is granted (macOS) */
+export declare function checkMicrophonePermission(): boolean
+/**
+ * Request microphone permission (macOS) - shows the system prompt if not yet asked
+ * Resolves to whether access is granted
+ */
+export declare function requestMicrophonePermission(): Promise<boolean>
+/**
+ * Call callback() once screen recording permission is granted, then stop watching
+ * Use after request_screen_recording_permission sends the user to System Settings
+ */
+export declare function watchScreenRecordingPermission(callback: (...args: any[]) => any): void
+/** Stop the watcher started by watch_screen_recording_permission */
+export declare function stopWatchingScreenRecordingPermission(): void
 /**
  * Trigger ScreenCaptureKit to add app to Screen Recording permissions list
  * This ensures the app appears in System Settings for the user to enable
@@ -148,26 +724,214 @@ export declare function requestScreenRecordingPermission(): boolean
 export declare function triggerScreenRecordingPrompt(): void
 /** Get the URL from a browser window (requires accessibility permission) */
 export declare function getBrowserUrl(pid: number): string | null
-/** Start capturing audio from a specific process */
+/**
+ * Get the URLs of all of a browser's windows, not just the focused one, front to back and
+ * de-duplicated (requires accessibility permission). Finds meetings in background windows
+ */
+export declare function getBrowserUrls(pid: number): Array<string>
+/**
+ * Get the URL the user is looking at: the focused tab of the frontmost app, or null when that
+ * app isn't a known browser (requires accessibility permission)
+ */
+export declare function getFrontmostBrowserUrl(): string | null
+/** Get the URL and page title of a browser's focused window (requires accessibility permission) */
+export declare function getBrowserTabInfo(pid: number): BrowserTabInfo | null
+/**
+ * Get the currently playing media (macOS MediaRemote)
+ * Returns null when nothing is playing or the API is unavailable on this OS version
+ */
+export declare function getNowPlaying(): NowPlayingInfo | null
+/**
+ * Start capturing audio from a specific process (pid 0 = all system audio)
+ * Invalid options (unsupported rate or channels, unwritable output path) are rejected up front
+ */
 export declare function startAudioCapture(pid: number, options?: AudioCaptureOptions | undefined | null): Promise<void>
-/** Stop capturing audio and return the path to the recorded file */
+/**
+ * Stop capturing audio and return the path to the recorded file
+ * Returns an empty string when the capture was started with save_wav: false
+ */
 export declare function stopAudioCapture(): Promise<string>
-/** Get current audio level (0.0 - 1.0) */
+/** Stop capturing audio and return the stereo mix path plus any separate track paths */
+export declare function stopAudioCaptureWithTracks(): Promise<CaptureOutput>
+/**
+ * Register a callback invoked whenever a capture ends
+ * Called as callback(reason, outputPath) where reason is "user" after a normal stop, or
+ * "error" when stopping or encoding failed (outputPath is then the raw WAV, if any) or the
+ * stream stopped on its own. In that last case call stop_audio_capture to write what was
+ * recorded up to then
+ */
+export declare function setCaptureStoppedCallback(callback: (...args: any[]) => any): void
+/**
+ * Concatenate WAV recordings into one file, inserting gaps_secs[i] of silence
+ * between part i and i+1. Parts are resampled to the first file's sample rate;
+ * all parts must have the same channel count
+ */
+export declare function concatWavs(paths: Array<string>, output: string, gapsSecs: Array<number>): void
+/**
+ * Pause capture without finalizing the recording
+ * Streams stay alive but no samples are recorded or streamed until resumed
+ */
+export declare function pauseAudioCapture(): void
+/** Resume a paused capture */
+export declare function resumeAudioCapture(): void
+/** Check if capture is currently paused */
+export declare function isCapturePaused(): boolean
+/** Get current audio level (0.0 - 1.0) - the louder of mic and system audio */
 export declare function getAudioLevel(): number
+/** Get current microphone level (0.0 - 1.0) */
+export declare function getMicLevel(): number
+/** Get current system audio level (0.0 - 1.0) - other meeting participants */
+export declare function getSystemLevel(): number
+/** Sample rates and channel count of the running capture, as discovered from the live streams */
+export declare function getCaptureFormat(): CaptureFormat
 /** Check if currently capturing */
 export declare function isCapturing(): boolean
-/** Get capture duration in milliseconds */
+/** Get capture duration in milliseconds (excluding time spent paused) */
 export declare function getCaptureDuration(): number
 /**
  * Check if the microphone is currently being used by any application
  * This is the definitive way to know if a meeting is still active
  */
 export declare function isMicrophoneInUse(): boolean
+/**
+ * List the applications currently using the microphone (macOS 14.2+)
+ * Lets the app tell a meeting apart from e.g. a voice memo
+ */
+export declare function getMicrophoneUsers(): Array<MicUser>
+/**
+ * Call callback(inUse) whenever the default microphone starts or stops being used
+ * Replaces polling isMicrophoneInUse(); rapid toggles are debounced (250ms)
+ */
+export declare function watchMicrophone(callback: (...args: any[]) => any): void
+/**
+ * Stop the microphone watch started by watch_microphone
+ * Returns false if no watch was active
+ */
+export declare function unwatchMicrophone(): boolean
+/**
+ * Take the chunks retained during capture (see retain_chunks_secs) as one buffer of
+ * interleaved stereo 16kHz PCM16 (L=system, R=mic), e.g. for a local transcription pass with
+ * transcribe_audio_buffer(buffer, 16000, 2) when cloud streaming failed. Clears the buffer
+ */
+export declare function flushRetainedChunks(): Buffer
+/**
+ * List displays available to ScreenCaptureKit, for the display_id capture option (macOS)
+ * Empty on headless Macs and other platforms
+ */
+export declare function listDisplays(): Promise<Array<DisplayInfo>>
+/** List audio input devices (microphones) */
+export declare function listInputDevices(): Array<AudioDeviceInfo>
+/**
+ * Check if the default microphone is muted at the OS/hardware level
+ * Returns null if the device doesn't expose a mute control
+ */
+export declare function isMicrophoneMuted(): boolean | null
 /**
  * Get queued stereo audio chunks for streaming to Deepgram
  * Returns Vec of stereo 16-bit PCM chunks (interleaved L=system, R=mic)
  * Each chunk is ~100ms of audio at 16kHz
  */
 export declare function getAudioChunks(): Array<Buffer>
+/**
+ * Get queued mono 16-bit PCM chunks (~100ms at 16kHz) of one stream: "system", "mic" or
+ * "mix" (system + boosted mic). Each source has its own queue, filled alongside the stereo
+ * chunks of get_audio_chunks and bounded the same way. A queue only fills once its source
+ * has been asked for, so call this once before start_audio_capture to get every chunk
+ */
+export declare function getMonoChunks(source: string): Array<Buffer>
 /** Check if there are audio chunks ready for streaming */
 export declare function hasAudioChunks(): boolean
+/** Skip streaming chunks where both system and mic are below the VAD threshold */
+export declare function setVadEnabled(enabled: boolean): void
+/** Enable a ~80Hz high-pass filter on the microphone to remove DC offset and rumble (macOS) */
+export declare function setMicHighpassEnabled(enabled: boolean): void
+/**
+ * Normalize system and mic to the auto gain target level (with a limiter) when writing the
+ * final recording, replacing the fixed mic boost. Streaming chunks are unaffected. Off by default
+ */
+export declare function setAutoGainEnabled(enabled: boolean): void
+/** Set the auto gain target level in dBFS (-40 to -3, default -20) */
+export declare function setAutoGainTarget(dbfs: number): void
+/**
+ * Set the streaming chunk length in ms (20 - 2000, default 100). Shorter chunks lower
+ * latency (e.g. 30ms VAD frames), longer ones cut per-chunk overhead (e.g. 1s batch uploads).
+ * Takes effect from the next chunk; max_queued_chunks still counts chunks, not time
+ */
+export declare function setChunkDurationMs(ms: number): void
+/** Set the VAD RMS threshold (0.0 - 1.0, default 0.01) */
+export declare function setVadThreshold(threshold: number): void
+/** Fraction of streaming chunks in the current capture that were silence (0.0 - 1.0) */
+export declare function getSilenceRatio(): number
+/** Number of streaming chunks dropped in the current capture because get_audio_chunks wasn't called often enough */
+export declare function getDroppedChunkCount(): number
+/** Estimated resident memory per loaded engine, in bytes (0 = not loaded) */
+export interface LoadedModelMemory {
+  parakeetBytes: number
+  embeddingBytes: number
+  llmBytes: number
+}
+/**
+ * Get an estimate of how much RAM each loaded model is using
+ * ONNX Runtime doesn't expose per-session allocator stats, so this is the size of the
+ * weights each engine loaded times a runtime overhead multiplier (the same figures as
+ * get_model_resource_usage)
+ */
+export declare function getLoadedModelMemory(): LoadedModelMemory
+/** Loaded models, their estimated footprint, and the process's actual usage */
+export interface ModelResourceUsage {
+  parakeetLoaded: boolean
+  embeddingLoaded: boolean
+  llmLoaded: boolean
+  /** Estimated resident bytes per model (0 = not loaded) */
+  parakeetBytes: number
+  embeddingBytes: number
+  llmBytes: number
+  /** Sum of the per-model estimates */
+  totalModelBytes: number
+  /** Measured resident memory of the whole process (null if unavailable) */
+  processResidentBytes?: number
+  /** CPU seconds used by the process so far; diff two readings for a usage percentage */
+  processCpuSecs?: number
+}
+/**
+ * Which models are loaded and roughly how much RAM each takes (weights × a runtime
+ * overhead multiplier), alongside the process's measured RSS and CPU time, so the UI
+ * can warn before loading another model on a memory-constrained machine
+ */
+export declare function getModelResourceUsage(): ModelResourceUsage
+/**
+ * Download "parakeet" or "embedding" model files from a mirror (S3 bucket, corporate proxy)
+ * instead of Hugging Face. Files keep their Hugging Face paths relative to `url`, e.g.
+ * `<url>/encoder-model.int8.onnx`. Null or empty restores the default; not persisted
+ */
+export declare function setModelBaseUrl(subsystem: string, url?: string | undefined | null): void
+/** Base URL model files for "parakeet" or "embedding" are downloaded from */
+export declare function getModelBaseUrl(subsystem: string): string
+/** Result of native_self_test; a false probe points at the broken subsystem */
+export interface SelfTestReport {
+  /** Version of this native module */
+  version: string
+  /** "macos", "windows", ... */
+  platform: string
+  arch: string
+  /** Window enumeration ran without crashing */
+  windowsOk: boolean
+  /** ONNX Runtime initialized (Parakeet and embeddings depend on it) */
+  onnxRuntimeOk: boolean
+  /**
+   * A ScreenCaptureKit content filter could be created (macOS). False without probing when
+   * Screen Recording permission isn't granted, since the probe could prompt for it.
+   * Null where system audio isn't captured through ScreenCaptureKit
+   */
+  screenCaptureOk?: boolean
+  /** The permission status APIs could be queried */
+  permissionsOk: boolean
+  /** Why each failed probe failed */
+  errors: Array<string>
+}
+/**
+ * Probe each subsystem (windows, ONNX Runtime, ScreenCaptureKit, permissions) without
+ * starting anything, so support can tell "the native module is broken" from "a permission
+ * is missing" in one call
+ */
+export declare function nativeSelfTest(): Promise<SelfTestReport>
@@ -310,53 +310,147 @@ if (!nativeBinding) {
   throw new Error(`Failed to load native binding`)
 }
 
-const { isParakeetDownloaded, getParakeetModelInfo, getParakeetLanguages, getParakeetDownloadProgress, downloadParakeetModel, initParakeet, isParakeetReady, transcribeAudioBuffer, transcribeAudioBufferWithTimestamps, deleteParakeetModel, getParakeetModelPath, shutdownParakeet, getLlmModelInfo, getLlmInitProgress, isLlmReady, isLlmDownloaded, getLlmDownloadProgress, initLlm, initLlmSync, shutdownLlm, deleteLlmModel, llmGenerate, llmChat, llmChatStream, isEmbeddingDownloaded, downloadEmbeddingModel, getEmbeddingDownloadProgress, initEmbeddingModel, isEmbeddingReady, generateEmbedding, generateEmbeddingsBatch, deleteEmbeddingModel, getEmbeddingDimension, getActiveWindows, checkAccessibilityPermission, requestAccessibilityPermission, checkScreenRecordingPermission, requestScreenRecordingPermission, triggerScreenRecordingPrompt, getBrowserUrl, startAudioCapture, stopAudioCapture, getAudioLevel, isCapturing, getCaptureDuration, isMicrophoneInUse, getAudioChunks, hasAudioChunks } = nativeBinding
+const { setParakeetModelDir, getParakeetModelDir, isParakeetDownloaded, getParakeetModelInfo, getParakeetLanguages, getParakeetDownloadProgress, downloadParakeetModel, cancelParakeetDownload, configureParakeet, initParakeet, warmupParakeet, isParakeetReady, getParakeetState, transcribeAudioBuffer, transcribeAudioBufferWithTimestamps, transcribeAudioBufferAsync, transcribeAudioBufferWords, transcribeWavFile, transcribeAudioBufferStreaming, transcribeStereoBuffer, enqueueTranscription, pollTranscriptionResult, getTranscriptionQueueLength, setTranscriptionResultCallback, transcribeChunk, finishTranscribeStream, resetTranscribeStream, applyDiarization, resampleAudioChecked, deleteParakeetModel, getParakeetModelPath, unloadParakeet, shutdownParakeet, getLlmModelInfo, getLlmInitProgress, isLlmReady, getLlmState, isLlmDownloaded, getLlmDownloadProgress, initLlm, initLlmWithConfig, initLlmWithDevice, initLlmSync, unloadLlm, shutdownLlm, setLlmIdleTimeout, deleteLlmModel, llmCountTokens, llmGenerate, llmChat, llmChatStream, cancelLlmStream, llmEmbed, isEmbeddingDownloaded, downloadEmbeddingModel, cancelEmbeddingDownload, getEmbeddingDownloadProgress, initEmbeddingModel, initEmbeddingModelWithConfig, isEmbeddingReady, getEmbeddingState, generateEmbedding, generateEmbeddingsBatch, generateEmbeddingLong, unloadEmbeddingModel, deleteEmbeddingModel, setEmbeddingModelDir, getEmbeddingModelDir, getEmbeddingDimension, cosineSimilarity, topKSimilar, embeddingIndexAdd, embeddingIndexSearch, embeddingIndexLen, embeddingIndexClear, embeddingIndexSave, embeddingIndexLoad, getActiveWindows, getFrontmostWindow, getWindowById, getAppInfo, getActiveMeeting, detectMeetingState, setMeetingAppBundles, watchActiveWindow, unwatchActiveWindow, PermissionStatus, accessibilityPermissionStatus, screenRecordingPermissionStatus, microphonePermissionStatus, checkAccessibilityPermission, requestAccessibilityPermission, checkScreenRecordingPermission, requestScreenRecordingPermission, checkMicrophonePermission, requestMicrophonePermission, watchScreenRecordingPermission, stopWatchingScreenRecordingPermission, triggerScreenRecordingPrompt, getBrowserUrl, getBrowserUrls, getFrontmostBrowserUrl, getBrowserTabInfo, getNowPlaying, startAudioCapture, stopAudioCapture, stopAudioCaptureWithTracks, setCaptureStoppedCallback, concatWavs, pauseAudioCapture, resumeAudioCapture, isCapturePaused, getAudioLevel, getMicLevel, getSystemLevel, getCaptureFormat, isCapturing, getCaptureDuration, isMicrophoneInUse, getMicrophoneUsers, watchMicrophone, unwatchMicrophone, flushRetainedChunks, listDisplays, listInputDevices, isMicrophoneMuted, getAudioChunks, getMonoChunks, hasAudioChunks, setVadEnabled, setMicHighpassEnabled, setAutoGainEnabled, setAutoGainTarget, setChunkDurationMs, setVadThreshold, getSilenceRatio, getDroppedChunkCount, getLoadedModelMemory, getModelResourceUsage, setModelBaseUrl, getModelBaseUrl, nativeSelfTest } = nativeBinding
 
+module.exports.setParakeetModelDir = setParakeetModelDir
+module.exports.getParakeetModelDir = getParakeetModelDir
 module.exports.isParakeetDownloaded = isParakeetDownloaded
 module.exports.getParakeetModelInfo = getParakeetModelInfo
 module.exports.getParakeetLanguages = getParakeetLanguages
 module.exports.getParakeetDownloadProgress = getParakeetDownloadProgress
 module.exports.downloadParakeetModel = downloadParakeetModel
+module.exports.cancelParakeetDownload = cancelParakeetDownload
+module.exports.configureParakeet = configureParakeet
 module.exports.initParakeet = initParakeet
+module.exports.warmupParakeet = warmupParakeet
 module.exports.isParakeetReady = isParakeetReady
+module.exports.getParakeetState = getParakeetState
 module.exports.transcribeAudioBuffer = transcribeAudioBuffer
 module.exports.transcribeAudioBufferWithTimestamps = transcribeAudioBufferWithTimestamps
+module.exports.transcribeAudioBufferAsync = transcribeAudioBufferAsync
+module.exports.transcribeAudioBufferWords = transcribeAudioBufferWords
+module.exports.transcribeWavFile = transcribeWavFile
+module.exports.transcribeAudioBufferStreaming = transcribeAudioBufferStreaming
+module.exports.transcribeStereoBuffer = transcribeStereoBuffer
+module.exports.enqueueTranscription = enqueueTranscription
+module.exports.pollTranscriptionResult = pollTranscriptionResult
+module.exports.getTranscriptionQueueLength = getTranscriptionQueueLength
+module.exports.setTranscriptionResultCallback = setTranscriptionResultCallback
+module.exports.transcribeChunk = transcribeChunk
+module.exports.finishTranscribeStream = finishTranscribeStream
+module.exports.resetTranscribeStream = resetTranscribeStream
+module.exports.applyDiarization = applyDiarization
+module.exports.resampleAudioChecked = resampleAudioChecked
 module.exports.deleteParakeetModel = deleteParakeetModel
 module.exports.getParakeetModelPath = getParakeetModelPath
+module.exports.unloadParakeet = unloadParakeet
 module.exports.shutdownParakeet = shutdownParakeet
 module.exports.getLlmModelInfo = getLlmModelInfo
 module.exports.getLlmInitProgress = getLlmInitProgress
 module.exports.isLlmReady = isLlmReady
+module.exports.getLlmState = getLlmState
 module.exports.isLlmDownloaded = isLlmDownloaded
 module.exports.getLlmDownloadProgress = getLlmDownloadProgress
 module.exports.initLlm = initLlm
+module.exports.initLlmWithConfig = initLlmWithConfig
+module.exports.initLlmWithDevice = initLlmWithDevice
 module.exports.initLlmSync = initLlmSync
+module.exports.unloadLlm = unloadLlm
 module.exports.shutdownLlm = shutdownLlm
+module.exports.setLlmIdleTimeout = setLlmIdleTimeout
 module.exports.deleteLlmModel = deleteLlmModel
+module.exports.llmCountTokens = llmCountTokens
 module.exports.llmGenerate = llmGenerate
 module.exports.llmChat = llmChat
 module.exports.llmChatStream = llmChatStream
+module.exports.cancelLlmStream = cancelLlmStream
+module.exports.llmEmbed = llmEmbed
 module.exports.isEmbeddingDownloaded = isEmbeddingDownloaded
 module.exports.downloadEmbeddingModel = downloadEmbeddingModel
+module.exports.cancelEmbeddingDownload = cancelEmbeddingDownload
 module.exports.getEmbeddingDownloadProgress = getEmbeddingDownloadProgress
 module.exports.initEmbeddingModel = initEmbeddingModel
+module.exports.initEmbeddingModelWithConfig = initEmbeddingModelWithConfig
 module.exports.isEmbeddingReady = isEmbeddingReady
+module.exports.getEmbeddingState = getEmbeddingState
 module.exports.generateEmbedding = generateEmbedding
 module.exports.generateEmbeddingsBatch = generateEmbeddingsBatch
+module.exports.generateEmbeddingLong = generateEmbeddingLong
+module.exports.unloadEmbeddingModel = unloadEmbeddingModel
 module.exports.deleteEmbeddingModel = deleteEmbeddingModel
+module.exports.setEmbeddingModelDir = setEmbeddingModelDir
+module.exports.getEmbeddingModelDir = getEmbeddingModelDir
 module.exports.getEmbeddingDimension = getEmbeddingDimension
+module.exports.cosineSimilarity = cosineSimilarity
+module.exports.topKSimilar = topKSimilar
+module.exports.embeddingIndexAdd = embeddingIndexAdd
+module.exports.embeddingIndexSearch = embeddingIndexSearch
+module.exports.embeddingIndexLen = embeddingIndexLen
+module.exports.embeddingIndexClear = embeddingIndexClear
+module.exports.embeddingIndexSave = embeddingIndexSave
+module.exports.embeddingIndexLoad = embeddingIndexLoad
 module.exports.getActiveWindows = getActiveWindows
+module.exports.getFrontmostWindow = getFrontmostWindow
+module.exports.getWindowById = getWindowById
+module.exports.getAppInfo = getAppInfo
+module.exports.getActiveMeeting = getActiveMeeting
+module.exports.detectMeetingState = detectMeetingState
+module.exports.setMeetingAppBundles = setMeetingAppBundles
+module.exports.watchActiveWindow = watchActiveWindow
+module.exports.unwatchActiveWindow = unwatchActiveWindow
+module.exports.PermissionStatus = PermissionStatus
+module.exports.accessibilityPermissionStatus = accessibilityPermissionStatus
+module.exports.screenRecordingPermissionStatus = screenRecordingPermissionStatus
+module.exports.microphonePermissionStatus = microphonePermissionStatus
 module.exports.checkAccessibilityPermission = checkAccessibilityPermission
 module.exports.requestAccessibilityPermission = requestAccessibilityPermission
 module.exports.checkScreenRecordingPermission = checkScreenRecordingPermission
 module.exports.requestScreenRecordingPermission = requestScreenRecordingPermission
+module.exports.checkMicrophonePermission = checkMicrophonePermission
+module.exports.requestMicrophonePermission = requestMicrophonePermission
+module.exports.watchScreenRecordingPermission = watchScreenRecordingPermission
+module.exports.stopWatchingScreenRecordingPermission = stopWatchingScreenRecordingPermission
 module.exports.triggerScreenRecordingPrompt = triggerScreenRecordingPrompt
 module.exports.getBrowserUrl = getBrowserUrl
+module.exports.getBrowserUrls = getBrowserUrls
+module.exports.getFrontmostBrowserUrl = getFrontmostBrowserUrl
+module.exports.getBrowserTabInfo = getBrowserTabInfo
+module.exports.getNowPlaying = getNowPlaying
 module.exports.startAudioCapture = startAudioCapture
 module.exports.stopAudioCapture = stopAudioCapture
+module.exports.stopAudioCaptureWithTracks = stopAudioCaptureWithTracks
+module.exports.setCaptureStoppedCallback = setCaptureStoppedCallback
+module.exports.concatWavs = concatWavs
+module.exports.pauseAudioCapture = pauseAudioCapture
+module.exports.resumeAudioCapture = resumeAudioCapture
+module.exports.isCapturePaused = isCapturePaused
 module.exports.getAudioLevel = getAudioLevel
+module.exports.getMicLevel = getMicLevel
+module.exports.getSystemLevel = getSystemLevel
+module.exports.getCaptureFormat = getCaptureFormat
 module.exports.isCapturing = isCapturing
 module.exports.getCaptureDuration = getCaptureDuration
 module.exports.isMicrophoneInUse = isMicrophoneInUse
+module.exports.getMicrophoneUsers = getMicrophoneUsers
+module.exports.watchMicrophone = watchMicrophone
+module.exports.unwatchMicrophone = unwatchMicrophone
+module.exports.flushRetainedChunks = flushRetainedChunks
+module.exports.listDisplays = listDisplays
+module.exports.listInputDevices = listInputDevices
+module.exports.isMicrophoneMuted = isMicrophoneMuted
 module.exports.getAudioChunks = getAudioChunks
+module.exports.getMonoChunks = getMonoChunks
 module.exports.hasAudioChunks = hasAudioChunks
+module.exports.setVadEnabled = setVadEnabled
+module.exports.setMicHighpassEnabled = setMicHighpassEnabled
+module.exports.setAutoGainEnabled = setAutoGainEnabled
+module.exports.setAutoGainTarget = setAutoGainTarget
+module.exports.setChunkDurationMs = setChunkDurationMs
+module.exports.setVadThreshold = setVadThreshold
+module.exports.getSilenceRatio = getSilenceRatio
+module.exports.getDroppedChunkCount = getDroppedChunkCount
+module.exports.getLoadedModelMemory = getLoadedModelMemory
+module.exports.getModelResourceUsage = getModelResourceUsage
+module.exports.setModelBaseUrl = setModelBaseUrl
+module.exports.getModelBaseUrl = getModelBaseUrl
+module.exports.nativeSelfTest = nativeSelfTest
@@ -1,5 +1,16 @@
 //! Cross-platform audio capture utilities
 
+use parking_lot::Mutex;
+use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, AtomicUsize, Ordering};
+
+/// Sample rate used for streaming and transcription
+pub const TARGET_SAMPLE_RATE: u32 = 16000;
+
+/// Input frames per sinc pass for the streaming resampler (~21ms at 48kHz)
+const STREAM_RESAMPLER_CHUNK: usize = 1024;
+
 /// Audio capture error types
 #[derive(Debug, thiserror::Error)]
 pub enum AudioError {
@@ -15,36 +26,125 @@ pub enum AudioError {
     #[error("Failed to write audio: {0}")]
     WriteError(String),
     
+    #[error("Failed to read audio: {0}")]
+    ReadError(String),
+    
+    #[error("Invalid audio format: {0}")]
+    InvalidFormat(String),
+    
     #[error("Not capturing")]
     NotCapturing,
     
+    #[error("No display available for ScreenCaptureKit (headless Mac, SSH session, or lid closed without an external monitor) and no window to capture audio from")]
+    NoDisplay,
+    
     #[error("Platform not supported")]
     UnsupportedPlatform,
 }
 
+/// Create (or truncate) a recording file readable only by its owner (0600 on Unix)
+pub fn create_private_file(path: &str) -> std::io::Result<std::fs::File> {
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create(true).truncate(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)
+}
+
+/// Sample encoding of a written WAV file
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavSampleFormat {
+    Pcm16,
+    Pcm24,
+    /// 32-bit IEEE float
+    Float32,
+}
+
+impl WavSampleFormat {
+    /// 16 and 24 are integer PCM, 32 is float
+    pub fn from_bit_depth(bits: u32) -> Option<Self> {
+        match bits {
+            16 => Some(WavSampleFormat::Pcm16),
+            24 => Some(WavSampleFormat::Pcm24),
+            32 => Some(WavSampleFormat::Float32),
+            _ => None,
+        }
+    }
+    
+    pub fn bits_per_sample(self) -> u16 {
+        match self {
+            WavSampleFormat::Pcm16 => 16,
+            WavSampleFormat::Pcm24 => 24,
+            WavSampleFormat::Float32 => 32,
+        }
+    }
+    
+    /// WAVE AudioFormat tag (1 = PCM, 3 = IEEE float)
+    fn audio_format(self) -> u16 {
+        match self {
+            WavSampleFormat::Float32 => 3,
+            _ => 1,
+        }
+    }
+    
+    /// Encode samples in [-1, 1] as little-endian WAV data
+    pub fn encode(self, samples: &[f32]) -> Vec<u8> {
+        let mut data = Vec::with_capacity(samples.len() * self.bits_per_sample() as usize / 8);
+        for &sample in samples {
+            let sample = sample.clamp(-1.0, 1.0);
+            match self {
+                WavSampleFormat::Pcm16 => data.extend_from_slice(&((sample * 32767.0) as i16).to_le_bytes()),
+                WavSampleFormat::Pcm24 => data.extend_from_slice(&((sample * 8388607.0) as i32).to_le_bytes()[..3]),
+                WavSampleFormat::Float32 => data.extend_from_slice(&sample.to_le_bytes()),
+            }
+        }
+        data
+    }
+}
+
 /// WAV file header for writing audio
 pub struct WavHeader {
     pub sample_rate: u32,
     pub channels: u16,
     pub bits_per_sample: u16,
+    /// WAVE AudioFormat tag (1 = PCM, 3 = IEEE float)
+    pub audio_format: u16,
 }
 
 impl WavHeader {
+    /// Integer PCM header
     pub fn new(sample_rate: u32, channels: u16, bits_per_sample: u16) -> Self {
         Self {
             sample_rate,
             channels,
             bits_per_sample,
+            audio_format: 1,
+        }
+    }
+    
+    pub fn with_format(sample_rate: u32, channels: u16, format: WavSampleFormat) -> Self {
+        Self {
+            sample_rate,
+            channels,
+            bits_per_sample: format.bits_per_sample(),
+            audio_format: format.audio_format(),
         }
     }
     
     /// Write WAV header to buffer
+    /// Non-PCM formats get the 18-byte fmt chunk (with cbSize) and a fact chunk, as the spec requires
     pub fn write_header(&self, data_size: u32) -> Vec<u8> {
+        let is_pcm = self.audio_format == 1;
         let byte_rate = self.sample_rate * self.channels as u32 * self.bits_per_sample as u32 / 8;
         let block_align = self.channels * self.bits_per_sample / 8;
-        let file_size = 36 + data_size;
+        let fmt_size: u32 = if is_pcm { 16 } else { 18 };
+        let fact_size: u32 = if is_pcm { 0 } else { 12 };
+        let file_size = 4 + (8 + fmt_size) + fact_size + 8 + data_size;
         
-        let mut header = Vec::with_capacity(44);
+        let mut header = Vec::with_capacity(58);
         
         // RIFF header
         header.extend_from_slice(b"RIFF");
@@ -53,14 +153,24 @@ impl WavHeader {
         
         // fmt subchunk
         header.extend_from_slice(b"fmt ");
-        header.extend_from_slice(&16u32.to_le_bytes()); // Subchunk1Size (16 for PCM)
-        header.extend_from_slice(&1u16.to_le_bytes()); // AudioFormat (1 = PCM)
+        header.extend_from_slice(&fmt_size.to_le_bytes()); // Subchunk1Size
+        header.extend_from_slice(&self.audio_format.to_le_bytes());
         header.extend_from_slice(&self.channels.to_le_bytes());
         header.extend_from_slice(&self.sample_rate.to_le_bytes());
         header.extend_from_slice(&byte_rate.to_le_bytes());
         header.extend_from_slice(&block_align.to_le_bytes());
         header.extend_from_slice(&self.bits_per_sample.to_le_bytes());
         
+        if !is_pcm {
+            header.extend_from_slice(&0u16.to_le_bytes()); // cbSize (no extension)
+            
+            // fact subchunk: sample frames per channel
+            let frames = if block_align > 0 { data_size / block_align as u32 } else { 0 };
+            header.extend_from_slice(b"fact");
+            header.extend_from_slice(&4u32.to_le_bytes());
+            header.extend_from_slice(&frames.to_le_bytes());
+        }
+        
         // data subchunk
         header.extend_from_slice(b"data");
         header.extend_from_slice(&data_size.to_le_bytes());
@@ -69,9 +179,879 @@ impl WavHeader {
     }
 }
 
+/// Interleaved 16-bit PCM audio loaded from (or destined for) a WAV file
+pub struct WavAudio {
+    pub sample_rate: u32,
+    pub channels: u16,
+    pub samples: Vec<i16>,
+}
+
+impl WavAudio {
+    /// Read a 16/24-bit PCM or 32-bit float WAV file (samples are converted to 16-bit)
+    pub fn read(path: &str) -> Result<Self, AudioError> {
+        let bytes = std::fs::read(path)
+            .map_err(|e| AudioError::ReadError(format!("{}: {}", path, e)))?;
+        
+        if bytes.len() < 12 || &bytes[0..4] != b"RIFF" || &bytes[8..12] != b"WAVE" {
+            return Err(AudioError::InvalidFormat(format!("{}: not a RIFF/WAVE file", path)));
+        }
+        
+        let mut format: Option<(u16, u16, u32, u16)> = None; // (audio_format, channels, rate, bits)
+        let mut subformat: Option<u16> = None; // WAVE_FORMAT_EXTENSIBLE sub-format GUID prefix
+        let mut data: Option<&[u8]> = None;
+        let mut pos = 12;
+        
+        // Walk the chunk list; chunks are word-aligned
+        while pos + 8 <= bytes.len() {
+            let id = &bytes[pos..pos + 4];
+            let size = u32::from_le_bytes([bytes[pos + 4], bytes[pos + 5], bytes[pos + 6], bytes[pos + 7]]) as usize;
+            let body_start = pos + 8;
+            let body_end = body_start.saturating_add(size).min(bytes.len());
+            let body = &bytes[body_start..body_end];
+            
+            if id == b"fmt " && body.len() >= 16 {
+                format = Some((
+                    u16::from_le_bytes([body[0], body[1]]),
+                    u16::from_le_bytes([body[2], body[3]]),
+                    u32::from_le_bytes([body[4], body[5], body[6], body[7]]),
+                    u16::from_le_bytes([body[14], body[15]]),
+                ));
+                if body.len() >= 26 {
+                    subformat = Some(u16::from_le_bytes([body[24], body[25]]));
+                }
+            } else if id == b"data" {
+                data = Some(body);
+            }
+            
+            pos = body_start.saturating_add(size + (size & 1));
+        }
+        
+        let (audio_format, channels, sample_rate, bits) = format
+            .ok_or_else(|| AudioError::InvalidFormat(format!("{}: missing fmt chunk", path)))?;
+        let data = data
+            .ok_or_else(|| AudioError::InvalidFormat(format!("{}: missing data chunk", path)))?;
+        
+        if channels == 0 || sample_rate == 0 {
+            return Err(AudioError::InvalidFormat(format!("{}: invalid channel count or sample rate", path)));
+        }
+        
+        // 1 = PCM, 3 = IEEE float, 0xFFFE = WAVE_FORMAT_EXTENSIBLE (real format in the sub-format)
+        let effective_format = if audio_format == 0xFFFE { subformat.unwrap_or(1) } else { audio_format };
+        let samples = match (effective_format, bits) {
+            (1, 16) => data
+                .chunks_exact(2)
+                .map(|c| i16::from_le_bytes([c[0], c[1]]))
+                .collect(),
+            (1, 24) => data
+                .chunks_exact(3)
+                .map(|c| i16::from_le_bytes([c[1], c[2]]))
+                .collect(),
+            (3, 32) => data
+                .chunks_exact(4)
+                .map(|c| (f32::from_le_bytes([c[0], c[1], c[2], c[3]]).clamp(-1.0, 1.0) * 32767.0) as i16)
+                .collect(),
+            _ => {
+                return Err(AudioError::InvalidFormat(format!(
+                    "{}: only 16/24-bit PCM and 32-bit float are supported (format {}, {} bits)",
+                    path, effective_format, bits
+                )));
+            }
+        };
+        
+        Ok(Self { sample_rate, channels, samples })
+    }
+    
+    /// Write as a 16-bit PCM WAV file
+    pub fn write(&self, path: &str) -> Result<(), AudioError> {
+        use std::io::Write;
+        
+        let pcm: Vec<u8> = self.samples.iter().flat_map(|s| s.to_le_bytes()).collect();
+        let mut f = std::fs::File::create(path).map_err(|e| AudioError::WriteError(e.to_string()))?;
+        f.write_all(&WavHeader::new(self.sample_rate, self.channels, 16).write_header(pcm.len() as u32))
+            .map_err(|e| AudioError::WriteError(e.to_string()))?;
+        f.write_all(&pcm)
+            .map_err(|e| AudioError::WriteError(e.to_string()))?;
+        Ok(())
+    }
+    
+    /// Resample every channel to `to_rate`
+    pub fn resampled(self, to_rate: u32) -> Result<Self, AudioError> {
+        if self.sample_rate == to_rate {
+            return Ok(self);
+        }
+        
+        let channels = self.channels as usize;
+        let mut resampled_channels = Vec::with_capacity(channels);
+        for ch in 0..channels {
+            let channel: Vec<f32> = self.samples.iter()
+                .skip(ch)
+                .step_by(channels)
+                .map(|&s| s as f32 / 32768.0)
+                .collect();
+            let resampled = try_resample_audio(&channel, self.sample_rate, to_rate)
+                .map_err(AudioError::InvalidFormat)?;
+            resampled_channels.push(resampled);
+        }
+        
+        let frames = resampled_channels.iter().map(|c| c.len()).min().unwrap_or(0);
+        let mut samples = Vec::with_capacity(frames * channels);
+        for i in 0..frames {
+            for channel in &resampled_channels {
+                samples.push((channel[i].clamp(-1.0, 1.0) * 32767.0) as i16);
+            }
+        }
+        
+        Ok(Self { sample_rate: to_rate, channels: self.channels, samples })
+    }
+}
+
+/// Concatenate WAV files into one, inserting `gaps_secs[i]` of silence between part i and i+1
+/// All parts are resampled to the first part's rate; channel counts must match
+pub fn concat_wav_files(paths: &[String], output_path: &str, gaps_secs: &[f64]) -> Result<(), AudioError> {
+    if paths.is_empty() {
+        return Err(AudioError::InvalidFormat("No input files".into()));
+    }
+    if gaps_secs.len() > paths.len() - 1 {
+        return Err(AudioError::InvalidFormat(format!(
+            "{} gaps given for {} files (expected at most {})", gaps_secs.len(), paths.len(), paths.len() - 1
+        )));
+    }
+    if let Some(gap) = gaps_secs.iter().find(|g| !g.is_finite() || **g < 0.0) {
+        return Err(AudioError::InvalidFormat(format!("Invalid gap: {}s", gap)));
+    }
+    
+    let first = WavAudio::read(&paths[0])?;
+    let sample_rate = first.sample_rate;
+    let channels = first.channels;
+    let mut samples = first.samples;
+    
+    for (i, path) in paths.iter().enumerate().skip(1) {
+        let part = WavAudio::read(path)?;
+        if part.channels != channels {
+            return Err(AudioError::InvalidFormat(format!(
+                "{} has {} channels but {} has {}", path, part.channels, paths[0], channels
+            )));
+        }
+        
+        let gap_secs = gaps_secs.get(i - 1).copied().unwrap_or(0.0);
+        let gap_frames = (gap_secs * sample_rate as f64).round() as usize;
+        samples.resize(samples.len() + gap_frames * channels as usize, 0);
+        
+        samples.extend(part.resampled(sample_rate)?.samples);
+    }
+    
+    println!("[Audio] Concatenated {} files into {} ({:.1}s)",
+        paths.len(), output_path, samples.len() as f64 / (sample_rate as f64 * channels as f64));
+    
+    WavAudio { sample_rate, channels, samples }.write(output_path)
+}
+
+/// First-order (RC) high-pass filter that removes DC offset and low-frequency rumble
+/// State carries across calls, so feeding a stream in pieces doesn't add transients
+pub struct HighPassFilter {
+    alpha: f32,
+    prev_input: f32,
+    prev_output: f32,
+}
+
+impl HighPassFilter {
+    /// Below speech, above mains hum harmonics that matter for a laptop mic
+    pub const DEFAULT_CUTOFF_HZ: f32 = 80.0;
+    
+    pub fn new(cutoff_hz: f32, sample_rate: f32) -> Self {
+        let rc = 1.0 / (2.0 * std::f32::consts::PI * cutoff_hz);
+        let dt = 1.0 / sample_rate;
+        Self { alpha: rc / (rc + dt), prev_input: 0.0, prev_output: 0.0 }
+    }
+    
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        samples.iter()
+            .map(|&x| {
+                let y = self.alpha * (self.prev_output + x - self.prev_input);
+                self.prev_input = x;
+                self.prev_output = y;
+                y
+            })
+            .collect()
+    }
+}
+
+// ============================================================================
+// Auto Gain (saved recordings)
+// ============================================================================
+
+static AUTO_GAIN_ENABLED: AtomicBool = AtomicBool::new(false);
+static AUTO_GAIN_TARGET_DBFS: Mutex<f32> = Mutex::new(AUTO_GAIN_DEFAULT_TARGET_DBFS);
+
+pub const AUTO_GAIN_DEFAULT_TARGET_DBFS: f32 = -20.0;
+
+/// Blocks quieter than this don't count towards a track's level, so pauses don't inflate the gain
+const AUTO_GAIN_GATE_DBFS: f32 = -50.0;
+/// Cap on amplification (+24 dB), so a near-silent track doesn't become amplified noise
+const AUTO_GAIN_MAX_GAIN: f32 = 15.85;
+/// ~40ms at 48kHz; the exact duration doesn't matter for gating
+const AUTO_GAIN_BLOCK: usize = 2048;
+/// The limiter is transparent below this (-1 dBFS) and approaches full scale above it
+const LIMITER_THRESHOLD: f32 = 0.89;
+
+pub fn set_auto_gain_enabled(enabled: bool) {
+    AUTO_GAIN_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+pub fn set_auto_gain_target(dbfs: f32) {
+    *AUTO_GAIN_TARGET_DBFS.lock() = dbfs;
+}
+
+fn dbfs_to_amplitude(dbfs: f32) -> f32 {
+    10f32.powf(dbfs / 20.0)
+}
+
+/// RMS over the blocks above the gate, or None if the track is silent throughout
+fn gated_rms(samples: &[f32]) -> Option<f32> {
+    let gate = dbfs_to_amplitude(AUTO_GAIN_GATE_DBFS);
+    let (sum, count) = samples
+        .chunks(AUTO_GAIN_BLOCK)
+        .map(|block| block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32)
+        .filter(|mean_square| mean_square.sqrt() >= gate)
+        .fold((0.0f64, 0usize), |(sum, count), mean_square| (sum + mean_square as f64, count + 1));
+    
+    (count > 0).then(|| (sum / count as f64).sqrt() as f32)
+}
+
+/// Scale a whole track so its (gated) RMS lands on `target_dbfs`; returns the gain applied
+pub fn normalize_loudness(samples: &mut [f32], target_dbfs: f32) -> f32 {
+    let Some(rms) = gated_rms(samples) else {
+        return 1.0;
+    };
+    
+    let gain = (dbfs_to_amplitude(target_dbfs) / rms).min(AUTO_GAIN_MAX_GAIN);
+    for sample in samples.iter_mut() {
+        *sample *= gain;
+    }
+    gain
+}
+
+/// Soft-knee limiter: unchanged below the threshold, smoothly compressed towards ±1.0 above it
+pub fn soft_limit(sample: f32) -> f32 {
+    let magnitude = sample.abs();
+    if magnitude <= LIMITER_THRESHOLD {
+        return sample;
+    }
+    let headroom = 1.0 - LIMITER_THRESHOLD;
+    let limited = LIMITER_THRESHOLD + headroom * ((magnitude - LIMITER_THRESHOLD) / headroom).tanh();
+    limited.copysign(sample)
+}
+
+/// If auto gain is on, normalize the system and mic tracks of a finished recording to the
+/// target level (each on its own, so both sides of the call end up equally loud) and return
+/// true; the caller then skips its fixed mic boost and runs the mix through soft_limit.
+/// Off by default, leaving raw captures untouched
+pub fn apply_auto_gain(system: &mut [f32], mic: &mut [f32]) -> bool {
+    if !AUTO_GAIN_ENABLED.load(Ordering::SeqCst) {
+        return false;
+    }
+    
+    let target = *AUTO_GAIN_TARGET_DBFS.lock();
+    let system_gain = normalize_loudness(system, target);
+    let mic_gain = normalize_loudness(mic, target);
+    println!("[Audio] Auto gain to {:.0} dBFS: system {:+.1} dB, mic {:+.1} dB",
+        target, 20.0 * system_gain.log10(), 20.0 * mic_gain.log10());
+    true
+}
+
+// ============================================================================
+// Silence Trimming (saved recordings)
+// ============================================================================
+
+/// Both tracks below this level (-40 dBFS) count as silence
+const TRIM_SILENCE_THRESHOLD: f32 = 0.01;
+/// Leading or trailing silence shorter than this is left in place
+const TRIM_MIN_SILENCE_SECS: f64 = 3.0;
+/// Silence kept next to the first and last sound so speech onsets aren't clipped
+const TRIM_PADDING_SECS: f64 = 0.5;
+/// Level measurement window
+const TRIM_WINDOW_SECS: f64 = 0.02;
+
+/// What trim_silence cut from a saved recording, in seconds
+#[derive(Clone, Copy, Debug, Default, PartialEq)]
+pub struct SilenceTrim {
+    pub leading_secs: f64,
+    pub trailing_secs: f64,
+    /// Length of the recording after trimming
+    pub duration_secs: f64,
+}
+
+impl SilenceTrim {
+    /// Nothing trimmed from a recording of `frames` frames
+    pub fn untrimmed(frames: usize, rate: u32) -> Self {
+        Self { duration_secs: frames as f64 / rate.max(1) as f64, ..Self::default() }
+    }
+}
+
+/// Cut long leading and trailing stretches where both the system and mic track (same rate)
+/// are silent, keeping TRIM_PADDING_SECS around the audio. A fully silent recording is kept whole
+pub fn trim_silence(system: &mut Vec<f32>, mic: &mut Vec<f32>, rate: u32) -> SilenceTrim {
+    let frames = system.len().max(mic.len());
+    let secs_to_frames = |secs: f64| (secs * rate as f64) as usize;
+    let window = secs_to_frames(TRIM_WINDOW_SECS).max(1);
+    
+    let is_loud = |start: usize| {
+        let end = (start + window).min(frames);
+        let track_rms = |track: &[f32]| {
+            let block = &track[start.min(track.len())..end.min(track.len())];
+            if block.is_empty() {
+                return 0.0;
+            }
+            (block.iter().map(|s| s * s).sum::<f32>() / block.len() as f32).sqrt()
+        };
+        track_rms(system) >= TRIM_SILENCE_THRESHOLD || track_rms(mic) >= TRIM_SILENCE_THRESHOLD
+    };
+    
+    let windows: Vec<usize> = (0..frames).step_by(window).collect();
+    let (Some(&first), Some(&last)) = (windows.iter().find(|&&w| is_loud(w)), windows.iter().rev().find(|&&w| is_loud(w))) else {
+        return SilenceTrim::untrimmed(frames, rate);
+    };
+    let sound_end = (last + window).min(frames);
+    
+    let min_silence = secs_to_frames(TRIM_MIN_SILENCE_SECS);
+    let padding = secs_to_frames(TRIM_PADDING_SECS);
+    let start = if first >= min_silence { first - padding } else { 0 };
+    let end = if frames - sound_end >= min_silence { sound_end + padding } else { frames };
+    
+    for track in [system, mic] {
+        track.truncate(end);
+        track.drain(..start.min(track.len()));
+    }
+    
+    let to_secs = |frames: usize| frames as f64 / rate.max(1) as f64;
+    SilenceTrim {
+        leading_secs: to_secs(start),
+        trailing_secs: to_secs(frames - end),
+        duration_secs: to_secs(end - start),
+    }
+}
+
+fn sinc_params() -> SincInterpolationParameters {
+    SincInterpolationParameters {
+        sinc_len: 256,
+        f_cutoff: 0.95,
+        interpolation: SincInterpolationType::Linear,
+        oversampling_factor: 256,
+        window: WindowFunction::BlackmanHarris2,
+    }
+}
+
+/// Resample audio using high-quality sinc interpolation, surfacing any error
+pub fn try_resample_audio(input: &[f32], from_rate: u32, to_rate: u32) -> Result<Vec<f32>, String> {
+    if from_rate == 0 || to_rate == 0 {
+        return Err(format!("Invalid sample rate: {}Hz -> {}Hz", from_rate, to_rate));
+    }
+    
+    if from_rate == to_rate || input.is_empty() {
+        return Ok(input.to_vec());
+    }
+    
+    let ratio = to_rate as f64 / from_rate as f64;
+    
+    let mut resampler = SincFixedIn::<f32>::new(ratio, 2.0, sinc_params(), input.len(), 1)
+        .map_err(|e| format!("Failed to create resampler: {}", e))?;
+    
+    let waves_in = vec![input.to_vec()];
+    let waves_out = resampler.process(&waves_in, None)
+        .map_err(|e| format!("Resampling failed: {}", e))?;
+    
+    waves_out.into_iter().next().ok_or_else(|| "Resampler returned no channels".to_string())
+}
+
+/// Streaming sinc resampler (to 16kHz unless built with_target) for capture callbacks
+///
+/// Callbacks deliver arbitrary buffer sizes, so input is queued until a full
+/// resampler chunk is available. The filter state carries over between chunks,
+/// which avoids the discontinuity a fresh resampler per callback would cause.
+pub struct StreamResampler {
+    resampler: SincFixedIn<f32>,
+    from_rate: f64,
+    to_rate: f64,
+    pending: Vec<f32>,
+}
 
+impl StreamResampler {
+    pub fn new(from_rate: f64) -> Result<Self, String> {
+        Self::with_target(from_rate, TARGET_SAMPLE_RATE as f64)
+    }
+    
+    pub fn with_target(from_rate: f64, to_rate: f64) -> Result<Self, String> {
+        let ratio = to_rate / from_rate;
+        let resampler = SincFixedIn::<f32>::new(ratio, 2.0, sinc_params(), STREAM_RESAMPLER_CHUNK, 1)
+            .map_err(|e| format!("Failed to create resampler: {}", e))?;
+        
+        Ok(Self { resampler, from_rate, to_rate, pending: Vec::new() })
+    }
+    
+    pub fn from_rate(&self) -> f64 {
+        self.from_rate
+    }
+    
+    pub fn to_rate(&self) -> f64 {
+        self.to_rate
+    }
+    
+    /// Push the queued partial chunk through (zero-padded) and return its output,
+    /// trimmed so the total output length matches the input length times the ratio
+    pub fn flush(&mut self) -> Vec<f32> {
+        if self.pending.is_empty() {
+            return Vec::new();
+        }
+        
+        let expected = (self.pending.len() as f64 * self.to_rate / self.from_rate).round() as usize;
+        let needed = self.resampler.input_frames_next();
+        self.pending.resize(needed.max(self.pending.len()), 0.0);
+        
+        let mut output = match self.resampler.process(&[&self.pending[..needed]], None) {
+            Ok(mut waves_out) => std::mem::take(&mut waves_out[0]),
+            Err(e) => {
+                println!("[Audio] Resampling failed: {}", e);
+                Vec::new()
+            }
+        };
+        self.pending.clear();
+        output.truncate(expected);
+        output
+    }
+    
+    /// Queue mono samples and return whatever 16kHz output is ready
+    pub fn process(&mut self, samples: &[f32]) -> Vec<f32> {
+        self.pending.extend_from_slice(samples);
+        
+        let mut output = Vec::new();
+        while self.pending.len() >= self.resampler.input_frames_next() {
+            let needed = self.resampler.input_frames_next();
+            match self.resampler.process(&[&self.pending[..needed]], None) {
+                Ok(mut waves_out) => output.append(&mut waves_out[0]),
+                Err(e) => println!("[Audio] Resampling failed: {}", e),
+            }
+            self.pending.drain(..needed);
+        }
+        
+        output
+    }
+}
+
+/// Resample through the stream's persistent resampler, (re)creating it if the rate changed
+pub fn resample_stream_to_16k(slot: &mut Option<StreamResampler>, samples: &[f32], from_rate: f64) -> Vec<f32> {
+    resample_stream(slot, samples, from_rate, TARGET_SAMPLE_RATE as f64)
+}
 
+/// Resample through a persistent resampler. When the input rate changes mid-stream (e.g. the
+/// output device switched), the old resampler's queued tail is flushed first so the output
+/// stays continuous and its length stays in step with wall-clock time
+pub fn resample_stream(slot: &mut Option<StreamResampler>, samples: &[f32], from_rate: f64, to_rate: f64) -> Vec<f32> {
+    let mut output = Vec::new();
+    
+    if slot.as_ref().map(|r| r.from_rate() != from_rate || r.to_rate() != to_rate).unwrap_or(true) {
+        if let Some(previous) = slot.as_mut() {
+            println!("[Audio] Resampler input changed {}Hz -> {}Hz, flushing", previous.from_rate(), from_rate);
+            output = previous.flush();
+        }
+        *slot = match StreamResampler::with_target(from_rate, to_rate) {
+            Ok(resampler) => Some(resampler),
+            Err(e) => {
+                println!("[Audio] {}, falling back to one-shot resampling", e);
+                None
+            }
+        };
+    }
+    
+    match slot.as_mut() {
+        Some(resampler) => output.extend(resampler.process(samples)),
+        None => output.extend(
+            try_resample_audio(samples, from_rate.round() as u32, to_rate.round() as u32)
+                .unwrap_or_else(|_| samples.to_vec()),
+        ),
+    }
+    output
+}
 
+// ============================================================================
+// Chunk Duration
+// ============================================================================
 
+/// Streaming chunk length bounds in ms (default 100ms suits Deepgram)
+pub const DEFAULT_CHUNK_DURATION_MS: u32 = 100;
+pub const MIN_CHUNK_DURATION_MS: u32 = 20;
+pub const MAX_CHUNK_DURATION_MS: u32 = 2000;
 
+/// Samples per channel in each streaming chunk at TARGET_SAMPLE_RATE
+static CHUNK_SAMPLES: AtomicUsize = AtomicUsize::new(samples_for_ms(DEFAULT_CHUNK_DURATION_MS));
 
+const fn samples_for_ms(ms: u32) -> usize {
+    TARGET_SAMPLE_RATE as usize * ms as usize / 1000
+}
+
+/// Set the streaming chunk length, clamped to MIN..=MAX_CHUNK_DURATION_MS. Chunk builders
+/// read this once per pass under both buffer locks and drain both channels by the same
+/// count, so a change mid-stream applies from the next chunk without desyncing L/R
+pub fn set_chunk_duration_ms(ms: u32) {
+    let ms = ms.clamp(MIN_CHUNK_DURATION_MS, MAX_CHUNK_DURATION_MS);
+    CHUNK_SAMPLES.store(samples_for_ms(ms), Ordering::SeqCst);
+}
+
+/// Samples per channel in each streaming chunk
+pub fn chunk_samples() -> usize {
+    CHUNK_SAMPLES.load(Ordering::SeqCst)
+}
+
+// ============================================================================
+// Retained Chunks
+// ============================================================================
+
+/// Bytes per second of the stereo 16-bit chunks handed out by get_audio_chunks
+const STREAM_BYTES_PER_SEC: usize = TARGET_SAMPLE_RATE as usize * 2 * 2;
+
+/// Ring buffer holding a copy of every emitted streaming chunk, so a local transcription
+/// pass can recover what a failed cloud stream lost. Oldest chunks go first when full
+pub struct RetainedChunks {
+    chunks: VecDeque<Vec<u8>>,
+    bytes: usize,
+    max_bytes: usize,
+}
+
+impl RetainedChunks {
+    const fn new() -> Self {
+        Self { chunks: VecDeque::new(), bytes: 0, max_bytes: 0 }
+    }
+    
+    fn push(&mut self, chunk: &[u8]) {
+        if self.max_bytes == 0 {
+            return;
+        }
+        
+        self.chunks.push_back(chunk.to_vec());
+        self.bytes += chunk.len();
+        while self.bytes > self.max_bytes {
+            match self.chunks.pop_front() {
+                Some(oldest) => self.bytes -= oldest.len(),
+                None => break,
+            }
+        }
+    }
+    
+    fn take(&mut self) -> Vec<u8> {
+        let mut pcm = Vec::with_capacity(self.bytes);
+        for chunk in self.chunks.drain(..) {
+            pcm.extend_from_slice(&chunk);
+        }
+        self.bytes = 0;
+        pcm
+    }
+}
+
+static RETAINED_CHUNKS: Mutex<RetainedChunks> = Mutex::new(RetainedChunks::new());
+
+/// Keep up to `max_secs` of emitted chunks (0 disables retention); clears anything retained
+pub fn set_chunk_retention(max_secs: u32) {
+    let mut retained = RETAINED_CHUNKS.lock();
+    retained.take();
+    retained.max_bytes = max_secs as usize * STREAM_BYTES_PER_SEC;
+}
+
+/// Copy an emitted chunk into the ring buffer (no-op while retention is off)
+pub fn retain_chunk(chunk: &[u8]) {
+    RETAINED_CHUNKS.lock().push(chunk);
+}
+
+/// Take everything retained as one buffer of interleaved stereo 16kHz PCM16
+pub fn take_retained_chunks() -> Vec<u8> {
+    RETAINED_CHUNKS.lock().take()
+}
+
+// ============================================================================
+// Mono Chunk Queues
+// ============================================================================
+
+/// Which stream get_mono_chunks returns
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum MonoSource {
+    System,
+    Mic,
+    /// System plus (boosted) mic, as heard in the saved mix
+    Mix,
+}
+
+impl MonoSource {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "system" => Some(MonoSource::System),
+            "mic" => Some(MonoSource::Mic),
+            "mix" => Some(MonoSource::Mix),
+            _ => None,
+        }
+    }
+    
+    fn index(self) -> usize {
+        match self {
+            MonoSource::System => 0,
+            MonoSource::Mic => 1,
+            MonoSource::Mix => 2,
+        }
+    }
+}
+
+/// Mono 16-bit chunks built alongside each stereo chunk, one queue per source
+static MONO_CHUNK_QUEUES: Mutex<[VecDeque<Vec<u8>>; 3]> = Mutex::new([VecDeque::new(), VecDeque::new(), VecDeque::new()]);
+/// Sources someone has asked for; the others aren't built, so captures without a consumer
+/// pay nothing for them
+static MONO_SOURCES_ENABLED: [AtomicBool; 3] = [AtomicBool::new(false), AtomicBool::new(false), AtomicBool::new(false)];
+
+fn pcm16_bytes(samples: impl Iterator<Item = f32>) -> Vec<u8> {
+    samples
+        .flat_map(|s| ((s.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes())
+        .collect()
+}
+
+/// Queue `len` frames of 16kHz audio as system, mic and mix chunks (shorter inputs are
+/// zero-padded, like the stereo chunk), dropping each queue's oldest beyond `max_chunks`
+/// Only sources enabled by take_mono_chunks are built
+pub fn queue_mono_chunks(system: &[f32], mic: &[f32], len: usize, mic_gain: f32, max_chunks: usize) {
+    let system_at = |i: usize| system.get(i).copied().unwrap_or(0.0);
+    let mic_at = |i: usize| mic.get(i).copied().unwrap_or(0.0) * mic_gain;
+    
+    for source in [MonoSource::System, MonoSource::Mic, MonoSource::Mix] {
+        if !MONO_SOURCES_ENABLED[source.index()].load(Ordering::SeqCst) {
+            continue;
+        }
+        
+        let chunk = match source {
+            MonoSource::System => pcm16_bytes((0..len).map(system_at)),
+            MonoSource::Mic => pcm16_bytes((0..len).map(mic_at)),
+            MonoSource::Mix => pcm16_bytes((0..len).map(|i| system_at(i) + mic_at(i))),
+        };
+        
+        let mut queues = MONO_CHUNK_QUEUES.lock();
+        let queue = &mut queues[source.index()];
+        queue.push_back(chunk);
+        while queue.len() > max_chunks {
+            queue.pop_front();
+        }
+    }
+}
+
+/// Take every queued mono chunk of `source`, enabling its queue from now on
+pub fn take_mono_chunks(source: MonoSource) -> Vec<Vec<u8>> {
+    MONO_SOURCES_ENABLED[source.index()].store(true, Ordering::SeqCst);
+    MONO_CHUNK_QUEUES.lock()[source.index()].drain(..).collect()
+}
+
+pub fn clear_mono_chunks() {
+    for queue in MONO_CHUNK_QUEUES.lock().iter_mut() {
+        queue.clear();
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mono_chunks_only_fill_once_requested() {
+        queue_mono_chunks(&[0.5; 4], &[0.25; 4], 4, 1.0, 10);
+        assert!(take_mono_chunks(MonoSource::Mic).is_empty());
+        
+        queue_mono_chunks(&[0.5; 4], &[0.25; 4], 4, 1.0, 10);
+        let mic = take_mono_chunks(MonoSource::Mic);
+        assert_eq!(mic.len(), 1);
+        assert_eq!(i16::from_le_bytes([mic[0][0], mic[0][1]]), (0.25 * 32767.0) as i16);
+        assert!(MONO_CHUNK_QUEUES.lock()[MonoSource::Mix.index()].is_empty());
+    }
+
+    #[test]
+    fn test_trim_silence_cuts_long_edges_only() {
+        // 5s silence, 2s tone on the mic, 1s silence at 1kHz
+        let rate = 1000;
+        let mut system = vec![0.0f32; 8000];
+        let mut mic: Vec<f32> = (0..8000).map(|i| if (5000..7000).contains(&i) { 0.5 } else { 0.0 }).collect();
+        
+        let trim = trim_silence(&mut system, &mut mic, rate);
+        
+        assert!((trim.leading_secs - 4.5).abs() < 1e-9);
+        assert_eq!(trim.trailing_secs, 0.0);
+        assert!((trim.duration_secs - 3.5).abs() < 1e-9);
+        assert_eq!(system.len(), 3500);
+        assert_eq!(mic.len(), 3500);
+        assert_eq!(mic[500], 0.5);
+        
+        let mut silent = vec![0.0f32; 8000];
+        let trim = trim_silence(&mut silent, &mut Vec::new(), rate);
+        assert_eq!(trim, SilenceTrim::untrimmed(8000, rate));
+        assert_eq!(silent.len(), 8000);
+    }
+    
+    #[test]
+    fn test_stream_resampler_is_independent_of_callback_size() {
+        // 1s 48kHz sine sweep, 100Hz -> 7kHz
+        let input: Vec<f32> = (0..48000)
+            .map(|i| {
+                let t = i as f32 / 48000.0;
+                (2.0 * std::f32::consts::PI * (100.0 + 3450.0 * t) * t).sin()
+            })
+            .collect();
+        
+        let mut small = StreamResampler::new(48000.0).unwrap();
+        let small_out: Vec<f32> = input.chunks(480).flat_map(|c| small.process(c)).collect();
+        
+        let mut large = StreamResampler::new(48000.0).unwrap();
+        let large_out: Vec<f32> = input.chunks(4096).flat_map(|c| large.process(c)).collect();
+        
+        let n = small_out.len().min(large_out.len());
+        assert!(n > 15000, "expected ~16000 output samples, got {}", n);
+        assert!(small_out[..n].iter().zip(&large_out[..n]).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+
+    #[test]
+    fn test_stream_resampler_noise_floor_beats_linear() {
+        // 1s 48kHz sweep from 10kHz to 20kHz, all above the 8kHz Nyquist of the output:
+        // anything left after resampling is aliasing
+        let input: Vec<f32> = (0..48000)
+            .map(|i| {
+                let t = i as f32 / 48000.0;
+                (2.0 * std::f32::consts::PI * (10000.0 + 5000.0 * t) * t).sin()
+            })
+            .collect();
+
+        let mut resampler = StreamResampler::new(48000.0).unwrap();
+        let sinc: Vec<f32> = input.chunks(480).flat_map(|c| resampler.process(c)).collect();
+
+        // The old per-callback linear interpolation, which at 3:1 keeps every third sample
+        let linear: Vec<f32> = (0..16000)
+            .map(|i| {
+                let pos = i as f64 * 3.0;
+                let index = pos as usize;
+                let frac = (pos - index as f64) as f32;
+                input[index] * (1.0 - frac) + input.get(index + 1).copied().unwrap_or(0.0) * frac
+            })
+            .collect();
+
+        // Skip the filter's warm-up and the sweep's abrupt start and end
+        let rms = |samples: &[f32]| {
+            let middle = &samples[1000..samples.len() - 1000];
+            (middle.iter().map(|s| s * s).sum::<f32>() / middle.len() as f32).sqrt()
+        };
+        let (sinc_rms, linear_rms) = (rms(&sinc), rms(&linear));
+        assert!(linear_rms > 0.5, "linear baseline should alias at full level, got {}", linear_rms);
+        assert!(sinc_rms < 0.01, "sinc noise floor too high: {} (linear {})", sinc_rms, linear_rms);
+    }
+
+    #[test]
+    fn test_stream_resampler_flushes_on_rate_change() {
+        // 0.5s at 48kHz then 0.5s at 44.1kHz, in callback-sized buffers
+        let mut slot = None;
+        let mut output = Vec::new();
+        for chunk in vec![0.1f32; 24000].chunks(480) {
+            output.extend(resample_stream_to_16k(&mut slot, chunk, 48000.0));
+        }
+        for chunk in vec![0.1f32; 22050].chunks(441) {
+            output.extend(resample_stream_to_16k(&mut slot, chunk, 44100.0));
+        }
+        if let Some(resampler) = slot.as_mut() {
+            output.extend(resampler.flush());
+        }
+        
+        // Both halves map to 8000 samples at 16kHz; nothing queued in the 48kHz resampler is lost
+        assert!((output.len() as i64 - 16000).abs() <= 4, "got {} samples", output.len());
+    }
+    
+    #[test]
+    fn test_retained_chunks_drop_oldest_when_full() {
+        let mut retained = RetainedChunks::new();
+        retained.push(&[1; 4]);
+        assert!(retained.take().is_empty(), "retention is off by default");
+        
+        retained.max_bytes = 8;
+        for value in 1..=3u8 {
+            retained.push(&[value; 4]);
+        }
+        assert_eq!(retained.take(), vec![2, 2, 2, 2, 3, 3, 3, 3]);
+        assert!(retained.take().is_empty());
+    }
+    
+    #[test]
+    fn test_normalize_loudness_hits_target_and_limiter_bounds_peaks() {
+        // A quiet tone with a silent second half, which the gate must ignore
+        let mut samples: Vec<f32> = (0..96000)
+            .map(|i| if i < 48000 { 0.01 * (i as f32 * 0.05).sin() } else { 0.0 })
+            .collect();
+        
+        normalize_loudness(&mut samples, -20.0);
+        let rms = (samples[..48000].iter().map(|s| s * s).sum::<f32>() / 48000.0).sqrt();
+        assert!((20.0 * rms.log10() + 20.0).abs() < 0.5, "rms {} dBFS", 20.0 * rms.log10());
+        
+        assert_eq!(soft_limit(0.5), 0.5);
+        assert!(soft_limit(3.0) < 1.0 && soft_limit(3.0) > LIMITER_THRESHOLD);
+        assert!(soft_limit(-3.0) > -1.0);
+    }
+    
+    #[test]
+    fn test_high_pass_removes_dc_offset() {
+        // 1s of a 440Hz tone riding on a 0.3 DC offset at 48kHz, fed in uneven buffers
+        let input: Vec<f32> = (0..48000)
+            .map(|i| 0.3 + 0.1 * (2.0 * std::f32::consts::PI * 440.0 * i as f32 / 48000.0).sin())
+            .collect();
+        
+        let mut filter = HighPassFilter::new(HighPassFilter::DEFAULT_CUTOFF_HZ, 48000.0);
+        let output: Vec<f32> = input.chunks(517).flat_map(|c| filter.process(c)).collect();
+        
+        // After settling (~10 time constants), the DC component integrates to ~0
+        let tail = &output[24000..];
+        let mean = tail.iter().sum::<f32>() / tail.len() as f32;
+        assert!(mean.abs() < 1e-3, "residual DC {}", mean);
+        
+        // ...while the tone passes through nearly unchanged
+        let peak = tail.iter().fold(0.0f32, |m, s| m.max(s.abs()));
+        assert!((peak - 0.1).abs() < 0.01, "tone peak {}", peak);
+        
+        let mut whole = HighPassFilter::new(HighPassFilter::DEFAULT_CUTOFF_HZ, 48000.0);
+        assert!(whole.process(&input).iter().zip(&output).all(|(a, b)| (a - b).abs() < 1e-6));
+    }
+    
+    #[test]
+    fn test_wav_header_round_trips_for_each_format() {
+        let samples: Vec<f32> = (0..480).map(|i| (i as f32 / 480.0 * 2.0 - 1.0) * 0.5).collect();
+        
+        for format in [WavSampleFormat::Pcm16, WavSampleFormat::Pcm24, WavSampleFormat::Float32] {
+            let data = format.encode(&samples);
+            let header = WavHeader::with_format(48000, 2, format).write_header(data.len() as u32);
+            
+            let u16_at = |pos: usize| u16::from_le_bytes([header[pos], header[pos + 1]]);
+            let u32_at = |pos: usize| u32::from_le_bytes([header[pos], header[pos + 1], header[pos + 2], header[pos + 3]]);
+            
+            let bits = format.bits_per_sample();
+            let is_float = format == WavSampleFormat::Float32;
+            assert_eq!(u32_at(4) as usize, header.len() - 8 + data.len(), "{:?} RIFF size", format);
+            assert_eq!(u32_at(16), if is_float { 18 } else { 16 }, "{:?} fmt size", format);
+            assert_eq!(u16_at(20), if is_float { 3 } else { 1 }, "{:?} audio format", format);
+            assert_eq!(u16_at(22), 2);
+            assert_eq!(u32_at(24), 48000);
+            assert_eq!(u32_at(28), 48000 * 2 * bits as u32 / 8, "{:?} byte rate", format);
+            assert_eq!(u16_at(32), 2 * bits / 8, "{:?} block align", format);
+            assert_eq!(u16_at(34), bits);
+            if is_float {
+                assert_eq!(&header[38..42], b"fact");
+                assert_eq!(u32_at(46), 240);
+            }
+            assert_eq!(&header[header.len() - 8..header.len() - 4], b"data");
+            
+            // WavAudio::read must accept the file and recover the samples
+            let path = std::env::temp_dir().join(format!("ghost_wav_header_test_{}.wav", bits));
+            let mut bytes = header.clone();
+            bytes.extend_from_slice(&data);
+            std::fs::write(&path, bytes).unwrap();
+            let audio = WavAudio::read(path.to_str().unwrap()).unwrap();
+            let _ = std::fs::remove_file(&path);
+            
+            assert_eq!(audio.sample_rate, 48000);
+            assert_eq!(audio.channels, 2);
+            assert_eq!(audio.samples.len(), samples.len());
+            assert!(audio.samples.iter().zip(&samples).all(|(&a, &b)| (a as f32 / 32767.0 - b).abs() < 1e-3));
+        }
+    }
+}
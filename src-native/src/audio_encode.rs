@@ -0,0 +1,216 @@
+//! Compressed output formats for finished recordings
+//!
+//! Capture always writes a WAV first; when another format is requested the WAV is
+//! transcoded on stop and replaced by the encoded file. Encoders are behind the
+//! `mp3` and `opus` cargo features so the default build doesn't pull them in.
+
+use crate::audio::{create_private_file, AudioError, WavAudio};
+
+/// Container/codec of the main recording
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum OutputFormat {
+    Wav,
+    Mp3,
+    Opus,
+}
+
+impl OutputFormat {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "wav" => Some(OutputFormat::Wav),
+            "mp3" => Some(OutputFormat::Mp3),
+            "opus" => Some(OutputFormat::Opus),
+            _ => None,
+        }
+    }
+    
+    pub fn extension(self) -> &'static str {
+        match self {
+            OutputFormat::Wav => "wav",
+            OutputFormat::Mp3 => "mp3",
+            OutputFormat::Opus => "opus",
+        }
+    }
+    
+    /// Whether this build has the encoder compiled in
+    pub fn is_available(self) -> bool {
+        match self {
+            OutputFormat::Wav => true,
+            OutputFormat::Mp3 => cfg!(feature = "mp3"),
+            OutputFormat::Opus => cfg!(feature = "opus"),
+        }
+    }
+}
+
+/// Transcode a finished WAV into `format`, delete the WAV, and return the new path
+/// (same name with the format's extension)
+pub fn encode_wav_file(wav_path: &str, format: OutputFormat) -> Result<String, AudioError> {
+    if format == OutputFormat::Wav {
+        return Ok(wav_path.to_string());
+    }
+    
+    let audio = WavAudio::read(wav_path)?;
+    let base = wav_path.strip_suffix(".wav").unwrap_or(wav_path);
+    let output_path = format!("{}.{}", base, format.extension());
+    
+    let encoded = match format {
+        OutputFormat::Wav => unreachable!(),
+        OutputFormat::Mp3 => encode_mp3(&audio)?,
+        OutputFormat::Opus => encode_opus(audio)?,
+    };
+    
+    create_private_file(&output_path)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, &encoded))
+        .map_err(|e| AudioError::WriteError(e.to_string()))?;
+    let _ = std::fs::remove_file(wav_path);
+    
+    println!("[Audio] ✅ Encoded {} -> {} ({} bytes)", wav_path, output_path, encoded.len());
+    Ok(output_path)
+}
+
+// ============================================================================
+// MP3 (LAME)
+// ============================================================================
+
+/// Constant bitrate, so players derive an exact duration from the file size
+#[cfg(feature = "mp3")]
+const MP3_BITRATE: mp3lame_encoder::Bitrate = mp3lame_encoder::Bitrate::Kbps128;
+
+#[cfg(feature = "mp3")]
+fn encode_mp3(audio: &WavAudio) -> Result<Vec<u8>, AudioError> {
+    use mp3lame_encoder::{Builder, FlushNoGap, InterleavedPcm, MonoPcm, Quality};
+    
+    let err = |e: &dyn std::fmt::Debug| AudioError::WriteError(format!("MP3 encoder: {:?}", e));
+    
+    if audio.channels > 2 {
+        return Err(AudioError::InvalidFormat(format!("MP3 supports 1 or 2 channels, got {}", audio.channels)));
+    }
+    
+    let mut builder = Builder::new().ok_or_else(|| AudioError::WriteError("Failed to create MP3 encoder".into()))?;
+    builder.set_num_channels(audio.channels as u8).map_err(|e| err(&e))?;
+    builder.set_sample_rate(audio.sample_rate).map_err(|e| err(&e))?;
+    builder.set_brate(MP3_BITRATE).map_err(|e| err(&e))?;
+    builder.set_quality(Quality::Good).map_err(|e| err(&e))?;
+    let mut encoder = builder.build().map_err(|e| err(&e))?;
+    
+    let frames = audio.samples.len() / audio.channels as usize;
+    let mut out: Vec<u8> = Vec::with_capacity(mp3lame_encoder::max_required_buffer_size(frames));
+    
+    let written = if audio.channels == 2 {
+        encoder.encode(InterleavedPcm(&audio.samples), out.spare_capacity_mut())
+    } else {
+        encoder.encode(MonoPcm(&audio.samples), out.spare_capacity_mut())
+    }
+    .map_err(|e| err(&e))?;
+    unsafe { out.set_len(out.len() + written) };
+    
+    out.reserve(7200); // LAME's documented worst case for a flush
+    let written = encoder.flush::<FlushNoGap>(out.spare_capacity_mut()).map_err(|e| err(&e))?;
+    unsafe { out.set_len(out.len() + written) };
+    
+    Ok(out)
+}
+
+#[cfg(not(feature = "mp3"))]
+fn encode_mp3(_audio: &WavAudio) -> Result<Vec<u8>, AudioError> {
+    Err(AudioError::InvalidFormat("mp3 output needs the native module built with the \"mp3\" feature".into()))
+}
+
+// ============================================================================
+// Opus (in an Ogg container, RFC 7845)
+// ============================================================================
+
+/// Opus always runs at 48kHz; granule positions count 48kHz samples
+#[cfg(feature = "opus")]
+const OPUS_RATE: u32 = 48000;
+
+/// 20ms frames
+#[cfg(feature = "opus")]
+const OPUS_FRAME_SAMPLES: usize = 960;
+
+/// Encoder lookahead at 48kHz, trimmed by players via the OpusHead pre-skip
+#[cfg(feature = "opus")]
+const OPUS_PRE_SKIP: u16 = 312;
+
+#[cfg(feature = "opus")]
+const OPUS_BITRATE: i32 = 64_000;
+
+#[cfg(feature = "opus")]
+fn encode_opus(audio: WavAudio) -> Result<Vec<u8>, AudioError> {
+    use ogg::writing::{PacketWriteEndInfo, PacketWriter};
+    
+    let err = |e: opus::Error| AudioError::WriteError(format!("Opus encoder: {}", e));
+    
+    let input_rate = audio.sample_rate;
+    let channels = audio.channels as usize;
+    let opus_channels = match channels {
+        1 => opus::Channels::Mono,
+        2 => opus::Channels::Stereo,
+        n => return Err(AudioError::InvalidFormat(format!("Opus output supports 1 or 2 channels, got {}", n))),
+    };
+    
+    let audio = audio.resampled(OPUS_RATE)?;
+    let total_frames = audio.samples.len() / channels;
+    
+    let mut encoder = opus::Encoder::new(OPUS_RATE, opus_channels, opus::Application::Audio).map_err(err)?;
+    encoder.set_bitrate(opus::Bitrate::Bits(OPUS_BITRATE)).map_err(err)?;
+    
+    let serial = std::process::id();
+    let mut out: Vec<u8> = Vec::new();
+    let mut writer = PacketWriter::new(&mut out);
+    let write_err = |e: std::io::Error| AudioError::WriteError(format!("Ogg writer: {}", e));
+    
+    // OpusHead: the original input rate is informational, playback is always 48kHz
+    let mut head = Vec::with_capacity(19);
+    head.extend_from_slice(b"OpusHead");
+    head.push(1); // version
+    head.push(channels as u8);
+    head.extend_from_slice(&OPUS_PRE_SKIP.to_le_bytes());
+    head.extend_from_slice(&input_rate.to_le_bytes());
+    head.extend_from_slice(&0i16.to_le_bytes()); // output gain
+    head.push(0); // channel mapping family (mono/stereo)
+    writer.write_packet(head, serial, PacketWriteEndInfo::EndPage, 0).map_err(write_err)?;
+    
+    let vendor = b"ghost-native";
+    let mut tags = Vec::with_capacity(8 + 4 + vendor.len() + 4);
+    tags.extend_from_slice(b"OpusTags");
+    tags.extend_from_slice(&(vendor.len() as u32).to_le_bytes());
+    tags.extend_from_slice(vendor);
+    tags.extend_from_slice(&0u32.to_le_bytes()); // no user comments
+    writer.write_packet(tags, serial, PacketWriteEndInfo::EndPage, 0).map_err(write_err)?;
+    
+    // Encode enough frames to flush the lookahead, so the tail of the recording is decodable
+    let frame_len = OPUS_FRAME_SAMPLES * channels;
+    let frame_count = (total_frames + OPUS_PRE_SKIP as usize).div_ceil(OPUS_FRAME_SAMPLES);
+    let mut packet = vec![0u8; 4000];
+    let mut frame = vec![0i16; frame_len];
+    
+    for index in 0..frame_count {
+        // Frames past the end are zero-padded
+        let start = (index * frame_len).min(audio.samples.len());
+        let end = (start + frame_len).min(audio.samples.len());
+        frame.fill(0);
+        frame[..end - start].copy_from_slice(&audio.samples[start..end]);
+        
+        let len = encoder.encode(&frame, &mut packet).map_err(err)?;
+        
+        // Granule positions count decoded samples including the pre-skip; the last one
+        // marks where the real audio ends, which gives players the exact duration
+        let is_last = index + 1 == frame_count;
+        let granule = if is_last {
+            OPUS_PRE_SKIP as u64 + total_frames as u64
+        } else {
+            ((index + 1) * OPUS_FRAME_SAMPLES) as u64
+        };
+        let end_info = if is_last { PacketWriteEndInfo::EndStream } else { PacketWriteEndInfo::NormalPacket };
+        writer.write_packet(packet[..len].to_vec(), serial, end_info, granule).map_err(write_err)?;
+    }
+    
+    drop(writer);
+    Ok(out)
+}
+
+#[cfg(not(feature = "opus"))]
+fn encode_opus(_audio: WavAudio) -> Result<Vec<u8>, AudioError> {
+    Err(AudioError::InvalidFormat("opus output needs the native module built with the \"opus\" feature".into()))
+}
@@ -0,0 +1,281 @@
+//! Resumable model downloads shared by the Parakeet, embedding and LLM engines
+//!
+//! A file streams into `<file>.part` and is renamed into place once complete, so a cancelled
+//! or failed transfer never looks like a finished one. A later attempt resumes with a Range
+//! request guarded by If-Range, using the ETag (or Last-Modified) of the response that started
+//! the .part: if the remote file changed since, the server sends the whole new file instead of
+//! bytes that would be spliced onto the old ones
+
+use std::fs;
+use std::io::{Read, Write};
+use std::path::{Path, PathBuf};
+use std::time::Duration;
+
+pub(crate) const USER_AGENT: &str = "Mozilla/5.0 ghost-app/1.0";
+
+/// Tries per file before a download error is reported
+const DOWNLOAD_ATTEMPTS: u32 = 3;
+/// Wait before the first retry, doubled after each further failure
+const DOWNLOAD_RETRY_DELAY_MS: u64 = 1000;
+/// How often a retry wait checks for cancellation
+const CANCEL_POLL_MS: u64 = 100;
+/// Progress is reported about once per this many bytes
+const PROGRESS_INTERVAL: u64 = 1024 * 1024;
+
+/// One file to fetch
+pub(crate) struct Download<'a> {
+    pub url: &'a str,
+    pub dest: &'a Path,
+    /// Exact size of the complete file when known; a short or oversized result is an error
+    pub expected_size: Option<u64>,
+    /// Log prefix of the calling engine, e.g. "[Parakeet]"
+    pub log_prefix: &'a str,
+    pub is_cancelled: &'a dyn Fn() -> bool,
+    /// Called with the bytes of this file on disk so far, resumed .part bytes included
+    pub on_progress: &'a mut dyn FnMut(u64),
+}
+
+pub(crate) fn part_path(dest: &Path) -> PathBuf {
+    sidecar_path(dest, ".part")
+}
+
+/// Validator of the response that started the .part, sent back as If-Range on resume
+fn validator_path(dest: &Path) -> PathBuf {
+    sidecar_path(dest, ".part.validator")
+}
+
+fn sidecar_path(dest: &Path, suffix: &str) -> PathBuf {
+    let mut name = dest.file_name().unwrap_or_default().to_os_string();
+    name.push(suffix);
+    dest.with_file_name(name)
+}
+
+/// Delete the partial download of `dest` so the next attempt starts from scratch
+pub(crate) fn discard_partial(dest: &Path) {
+    let _ = fs::remove_file(part_path(dest));
+    let _ = fs::remove_file(validator_path(dest));
+}
+
+/// Download with retries and exponential backoff; each retry resumes from the .part file.
+/// Returns the size of the finished file. Errors carry the attempt count
+pub(crate) fn download_with_retry(download: &mut Download) -> Result<u64, String> {
+    let mut attempt = 1;
+    loop {
+        match download_once(download) {
+            Ok(size) => return Ok(size),
+            Err(e) if (download.is_cancelled)() => return Err(e),
+            Err(e) if attempt >= DOWNLOAD_ATTEMPTS => {
+                return Err(format!("{} (after {} attempts)", e, attempt));
+            }
+            Err(e) => {
+                let delay = DOWNLOAD_RETRY_DELAY_MS << (attempt - 1);
+                println!("{} ⚠️ Attempt {} of {} failed: {}, retrying in {}ms", download.log_prefix, attempt, DOWNLOAD_ATTEMPTS, e, delay);
+                if !sleep_unless_cancelled(delay, download.is_cancelled) {
+                    discard_partial(download.dest);
+                    return Err("Download cancelled".to_string());
+                }
+                attempt += 1;
+            }
+        }
+    }
+}
+
+/// Sleep for `ms`, waking early on cancellation; false if cancelled
+fn sleep_unless_cancelled(ms: u64, is_cancelled: &dyn Fn() -> bool) -> bool {
+    let mut remaining = ms;
+    while remaining > 0 {
+        if is_cancelled() {
+            return false;
+        }
+        let step = remaining.min(CANCEL_POLL_MS);
+        std::thread::sleep(Duration::from_millis(step));
+        remaining -= step;
+    }
+    !is_cancelled()
+}
+
+fn download_once(download: &mut Download) -> Result<u64, String> {
+    let dest = download.dest;
+    let prefix = download.log_prefix;
+    let filename = dest.file_name().unwrap_or_default().to_string_lossy().to_string();
+    let part_path = part_path(dest);
+    let validator_path = validator_path(dest);
+    
+    let mut existing = fs::metadata(&part_path).map(|m| m.len()).unwrap_or(0);
+    let validator = fs::read_to_string(&validator_path).ok().filter(|v| !v.trim().is_empty());
+    
+    // Without a validator there's no telling whether the .part still matches the remote file
+    if existing > 0 && validator.is_none() {
+        println!("{} Partial {} can't be validated, starting over", prefix, filename);
+        discard_partial(dest);
+        existing = 0;
+    }
+    if download.expected_size.is_some_and(|size| existing > size) {
+        discard_partial(dest);
+        existing = 0;
+    }
+    
+    println!("{} Downloading {} (resuming at {} bytes)", prefix, download.url, existing);
+    
+    let response = match request(download.url, existing, validator.as_deref()) {
+        Ok(response) => response,
+        Err(ureq::Error::Status(416, response)) => {
+            // The range starts at or past the end: the .part may already hold the whole file
+            let total = response.header("content-range")
+                .and_then(content_range_total)
+                .or(download.expected_size);
+            if total == Some(existing) {
+                println!("{} {} was already complete", prefix, filename);
+                return finish(download, existing);
+            }
+            
+            println!("{} ⚠️ Range not satisfiable for {}, restarting", prefix, filename);
+            discard_partial(dest);
+            existing = 0;
+            request(download.url, 0, None).map_err(|e| format!("HTTP request failed: {}", e))?
+        }
+        Err(e) => return Err(format!("HTTP request failed: {}", e)),
+    };
+    
+    let resumed = match response.status() {
+        206 => {
+            let start = response.header("content-range").and_then(content_range_start);
+            if start != Some(existing) {
+                return Err(format!("Server resumed {} at {:?} instead of byte {}", filename, start, existing));
+            }
+            true
+        }
+        200 => {
+            if existing > 0 {
+                println!("{} {} changed on the server or Range was ignored, restarting", prefix, filename);
+            }
+            existing = 0;
+            match response_validator(&response) {
+                Some(validator) => fs::write(&validator_path, validator),
+                None => fs::remove_file(&validator_path).or(Ok(())),
+            }
+            .map_err(|e| format!("Failed to record validator: {}", e))?;
+            false
+        }
+        status => return Err(format!("HTTP {}: {}", status, response.status_text())),
+    };
+    
+    // Content-Length of a 206 covers only the remaining range
+    let content_length: Option<u64> = response.header("content-length").and_then(|v| v.parse().ok());
+    
+    let mut file = fs::OpenOptions::new()
+        .create(true)
+        .write(true)
+        .append(resumed)
+        .truncate(!resumed)
+        .open(&part_path)
+        .map_err(|e| format!("Failed to create file: {}", e))?;
+    
+    let mut reader = response.into_reader();
+    let mut buffer = [0u8; 65536];
+    let mut file_downloaded: u64 = 0;
+    let mut next_report: u64 = 0;
+    (download.on_progress)(existing);
+    
+    loop {
+        if (download.is_cancelled)() {
+            drop(file);
+            discard_partial(dest);
+            return Err("Download cancelled".to_string());
+        }
+        
+        let bytes_read = reader.read(&mut buffer)
+            .map_err(|e| format!("Failed to read: {}", e))?;
+        if bytes_read == 0 {
+            break;
+        }
+        
+        file.write_all(&buffer[..bytes_read])
+            .map_err(|e| format!("Failed to write: {}", e))?;
+        file_downloaded += bytes_read as u64;
+        
+        if file_downloaded >= next_report {
+            (download.on_progress)(existing + file_downloaded);
+            next_report = file_downloaded + PROGRESS_INTERVAL;
+        }
+    }
+    drop(file);
+    
+    if let Some(expected_len) = content_length {
+        if file_downloaded != expected_len {
+            return Err(format!("Incomplete download: got {} of {} bytes", file_downloaded, expected_len));
+        }
+    }
+    
+    finish(download, existing + file_downloaded)
+}
+
+/// Check the size of a complete .part and move it into place
+fn finish(download: &mut Download, size: u64) -> Result<u64, String> {
+    let filename = download.dest.file_name().unwrap_or_default().to_string_lossy().to_string();
+    if let Some(expected) = download.expected_size {
+        if size != expected {
+            discard_partial(download.dest);
+            return Err(format!("Incomplete download of {}: got {} of {} bytes", filename, size, expected));
+        }
+    }
+    
+    fs::rename(part_path(download.dest), download.dest)
+        .map_err(|e| format!("Failed to finalize {}: {}", filename, e))?;
+    let _ = fs::remove_file(validator_path(download.dest));
+    (download.on_progress)(size);
+    
+    println!("{} ✓ Downloaded {} ({} bytes)", download.log_prefix, filename, size);
+    Ok(size)
+}
+
+fn request(url: &str, existing: u64, validator: Option<&str>) -> Result<ureq::Response, ureq::Error> {
+    let mut request = ureq::get(url).set("User-Agent", USER_AGENT);
+    if existing > 0 {
+        request = request.set("Range", &format!("bytes={}-", existing));
+        if let Some(validator) = validator {
+            request = request.set("If-Range", validator.trim());
+        }
+    }
+    request.call()
+}
+
+/// Strong ETag, else Last-Modified; weak ETags can't be used with If-Range
+fn response_validator(response: &ureq::Response) -> Option<String> {
+    response.header("etag")
+        .filter(|etag| !etag.starts_with("W/"))
+        .or_else(|| response.header("last-modified"))
+        .map(str::to_string)
+}
+
+/// First byte of "bytes 100-199/200"
+fn content_range_start(header: &str) -> Option<u64> {
+    header.strip_prefix("bytes ")?.split('-').next()?.trim().parse().ok()
+}
+
+/// Total size of "bytes 100-199/200" or "bytes */200"
+fn content_range_total(header: &str) -> Option<u64> {
+    header.rsplit('/').next()?.trim().parse().ok()
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_content_range_parsing() {
+        assert_eq!(content_range_start("bytes 100-199/200"), Some(100));
+        assert_eq!(content_range_total("bytes 100-199/200"), Some(200));
+        assert_eq!(content_range_total("bytes */4096"), Some(4096));
+        assert_eq!(content_range_start("bytes */4096"), None);
+        assert_eq!(content_range_total("bytes 0-99/*"), None);
+    }
+
+    #[test]
+    fn test_sleep_unless_cancelled_stops_early() {
+        let start = std::time::Instant::now();
+        assert!(!sleep_unless_cancelled(10_000, &|| true));
+        assert!(start.elapsed() < Duration::from_secs(1));
+        assert!(sleep_unless_cancelled(1, &|| false));
+    }
+}
@@ -2,12 +2,13 @@
 //!
 //! This module provides local text embeddings using the all-MiniLM-L6-v2 model
 //! via ONNX Runtime. Generates 384-dimensional embeddings for semantic search.
+//! Other BERT-style exports (bge, e5) load through init_embedding_model_with_config.
 
 use napi::bindgen_prelude::*;
 use napi_derive::napi;
 use parking_lot::Mutex;
 use std::path::PathBuf;
-use std::io::{Read, Write};
+use std::sync::atomic::{AtomicU64, Ordering};
 
 use ndarray::{Array2, ArrayD, IxDyn};
 use once_cell::sync::Lazy;
@@ -18,13 +19,22 @@ use ort::session::Session;
 use ort::value::TensorRef;
 use std::fs;
 use std::collections::HashMap;
+use tokenizers::{PaddingParams, PaddingStrategy, Tokenizer, TruncationParams};
+
+use crate::{EngineState, EngineStatus};
+use crate::download;
+use crate::settings::{self, ModelDirConfig};
+use crate::model_error::ModelError;
 
 // ============================================================================
 // Constants
 // ============================================================================
 
+/// MiniLM's output size, reported by get_embedding_dimension until a model is loaded
 const EMBEDDING_DIM: usize = 384;
 const MAX_SEQUENCE_LENGTH: usize = 512;
+const EMBEDDING_BATCH_SIZE: usize = 32; // Bounds the [batch, 512, 384] output tensor (~25MB)
+const DEFAULT_LONG_STRIDE: u32 = 128; // Tokens shared between consecutive long-document windows
 
 // Model files from HuggingFace
 const MODEL_REPO: &str = "sentence-transformers/all-MiniLM-L6-v2";
@@ -38,10 +48,12 @@ const MODEL_FILES: &[(&str, &str, u64)] = &[
 // Types
 // ============================================================================
 
-type ModelResult<T> = std::result::Result<T, String>;
+type ModelResult<T> = std::result::Result<T, ModelError>;
 
-fn ort_err(e: ort::Error) -> String { e.to_string() }
-fn io_err(e: std::io::Error) -> String { e.to_string() }
+fn ort_err(e: ort::Error) -> ModelError { ModelError::InferenceFailed(e.to_string()) }
+fn load_err(e: ort::Error) -> ModelError { ModelError::LoadFailed(e.to_string()) }
+fn io_err(e: std::io::Error) -> ModelError { ModelError::Io(e.to_string()) }
+fn tokenizer_err(e: tokenizers::Error) -> ModelError { ModelError::InvalidInput(e.to_string()) }
 
 // ============================================================================
 // Download Progress Tracking
@@ -63,6 +75,10 @@ pub struct EmbeddingDownloadProgress {
 static DOWNLOAD_PROGRESS: Lazy<Mutex<EmbeddingDownloadProgress>> = 
     Lazy::new(|| Mutex::new(EmbeddingDownloadProgress::default()));
 
+/// Bumped to cancel the running download; each download thread remembers the value it started with
+static DOWNLOAD_GENERATION: AtomicU64 = AtomicU64::new(0);
+static DOWNLOAD_THREAD: Mutex<Option<std::thread::JoinHandle<()>>> = Mutex::new(None);
+
 // ============================================================================
 // Tokenizer
 // ============================================================================
@@ -99,10 +115,16 @@ impl SimpleTokenizer {
     }
     
     fn tokenize(&self, text: &str, max_length: usize) -> (Vec<i64>, Vec<i64>, Vec<i64>) {
+        let mut pieces = self.word_pieces(text);
+        pieces.truncate(max_length.saturating_sub(2));
+        wrap_tokens(&pieces, self.cls_token_id, self.sep_token_id, self.pad_token_id, max_length)
+    }
+    
+    /// Wordpiece ids for the whole text, without special tokens or truncation
+    fn word_pieces(&self, text: &str) -> Vec<i64> {
         // Simple wordpiece-like tokenization
         let text = text.to_lowercase();
-        let mut input_ids = vec![self.cls_token_id];
-        let mut attention_mask = vec![1i64];
+        let mut input_ids = Vec::new();
         
         // Split on whitespace and punctuation
         for word in text.split(|c: char| c.is_whitespace() || c.is_ascii_punctuation()) {
@@ -110,16 +132,13 @@ impl SimpleTokenizer {
             
             // Try to find the word in vocab, otherwise split into subwords
             if let Some(&id) = self.vocab.get(word) {
-                if input_ids.len() < max_length - 1 {
-                    input_ids.push(id);
-                    attention_mask.push(1);
-                }
+                input_ids.push(id);
             } else {
                 // Try character-level fallback with ## prefix
                 let mut remaining = word;
                 let mut is_first = true;
                 
-                while !remaining.is_empty() && input_ids.len() < max_length - 1 {
+                while !remaining.is_empty() {
                     let mut found = false;
                     
                     // Get character boundary indices for safe UTF-8 slicing
@@ -142,7 +161,6 @@ impl SimpleTokenizer {
                         
                         if let Some(&id) = self.vocab.get(&lookup) {
                             input_ids.push(id);
-                            attention_mask.push(1);
                             remaining = &remaining[end_byte..];
                             is_first = false;
                             found = true;
@@ -153,7 +171,6 @@ impl SimpleTokenizer {
                     if !found {
                         // Use UNK token for unknown character, skip one character (not one byte)
                         input_ids.push(self.unk_token_id);
-                        attention_mask.push(1);
                         let first_char_len = remaining.chars().next().map(|c| c.len_utf8()).unwrap_or(1);
                         remaining = &remaining[first_char_len..];
                         is_first = false;
@@ -162,22 +179,141 @@ impl SimpleTokenizer {
             }
         }
         
-        // Add SEP token
-        if input_ids.len() < max_length {
-            input_ids.push(self.sep_token_id);
-            attention_mask.push(1);
+        input_ids
+    }
+}
+
+/// [CLS] content [SEP] followed by padding, as (input_ids, attention_mask, token_type_ids)
+fn wrap_tokens(content: &[i64], cls_id: i64, sep_id: i64, pad_id: i64, max_length: usize) -> TokenizedInput {
+    let mut input_ids = Vec::with_capacity(max_length);
+    input_ids.push(cls_id);
+    input_ids.extend_from_slice(content);
+    input_ids.push(sep_id);
+    let mut attention_mask = vec![1i64; input_ids.len()];
+    
+    // Pad to max_length
+    input_ids.resize(max_length, pad_id);
+    attention_mask.resize(max_length, 0);
+    
+    // Token type IDs (all zeros for single sequence)
+    let token_type_ids = vec![0i64; max_length];
+    
+    (input_ids, attention_mask, token_type_ids)
+}
+
+/// (input_ids, attention_mask, token_type_ids), each padded to max_length
+type TokenizedInput = (Vec<i64>, Vec<i64>, Vec<i64>);
+
+/// The model's own tokenizer.json when available, the vocab.txt approximation otherwise
+enum EmbeddingTokenizer {
+    HuggingFace(Tokenizer),
+    Simple(SimpleTokenizer),
+}
+
+impl EmbeddingTokenizer {
+    fn load(model_dir: &PathBuf) -> ModelResult<Self> {
+        let tokenizer_path = model_dir.join("tokenizer.json");
+        if tokenizer_path.exists() {
+            match Self::load_huggingface(&tokenizer_path) {
+                Ok(tokenizer) => {
+                    println!("[Embedding] Using tokenizer.json");
+                    return Ok(EmbeddingTokenizer::HuggingFace(tokenizer));
+                }
+                Err(e) => println!("[Embedding] ⚠️ Failed to load tokenizer.json ({}), using vocab.txt", e),
+            }
+        } else {
+            println!("[Embedding] ⚠️ tokenizer.json missing, using vocab.txt");
         }
         
-        // Pad to max_length
-        while input_ids.len() < max_length {
-            input_ids.push(self.pad_token_id);
-            attention_mask.push(0);
+        Ok(EmbeddingTokenizer::Simple(SimpleTokenizer::from_vocab_file(&model_dir.join("vocab.txt"))?))
+    }
+    
+    fn load_huggingface(path: &PathBuf) -> ModelResult<Tokenizer> {
+        let mut tokenizer = Tokenizer::from_file(path).map_err(|e| ModelError::CorruptModel(e.to_string()))?;
+        
+        // tokenizer.json ships with a 128-token limit; pad/truncate to the model's full window instead
+        tokenizer
+            .with_truncation(Some(TruncationParams {
+                max_length: MAX_SEQUENCE_LENGTH,
+                ..Default::default()
+            }))
+            .map_err(|e| ModelError::LoadFailed(e.to_string()))?;
+        let pad_id = tokenizer.token_to_id("[PAD]").unwrap_or(0);
+        tokenizer.with_padding(Some(PaddingParams {
+            strategy: PaddingStrategy::Fixed(MAX_SEQUENCE_LENGTH),
+            pad_id,
+            pad_token: "[PAD]".to_string(),
+            ..Default::default()
+        }));
+        
+        Ok(tokenizer)
+    }
+    
+    fn tokenize(&self, text: &str, max_length: usize) -> ModelResult<TokenizedInput> {
+        match self {
+            EmbeddingTokenizer::HuggingFace(tokenizer) => {
+                // add_special_tokens wraps the sequence in [CLS] ... [SEP]
+                let encoding = tokenizer.encode(text, true).map_err(tokenizer_err)?;
+                let widen = |values: &[u32]| -> Vec<i64> {
+                    let mut out: Vec<i64> = values.iter().map(|&v| v as i64).collect();
+                    out.resize(max_length, 0);
+                    out
+                };
+                Ok((
+                    widen(encoding.get_ids()),
+                    widen(encoding.get_attention_mask()),
+                    widen(encoding.get_type_ids()),
+                ))
+            }
+            EmbeddingTokenizer::Simple(tokenizer) => Ok(tokenizer.tokenize(text, max_length)),
         }
+    }
+    
+    /// Split text into windows of up to max_length tokens (special tokens included),
+    /// consecutive windows sharing `stride` content tokens. Returns each window with
+    /// its content token count
+    fn tokenize_windows(&self, text: &str, max_length: usize, stride: usize) -> ModelResult<Vec<(TokenizedInput, usize)>> {
+        let window = max_length.saturating_sub(2);
+        if stride >= window {
+            return Err(ModelError::InvalidInput(format!("stride must be less than {} tokens, got {}", window, stride)));
+        }
+        
+        let (content, cls_id, sep_id, pad_id) = match self {
+            EmbeddingTokenizer::HuggingFace(tokenizer) => {
+                // The shared tokenizer truncates to one window, so encode with a copy that doesn't
+                let mut full = tokenizer.clone();
+                full.with_truncation(None).map_err(tokenizer_err)?;
+                full.with_padding(None);
+                let encoding = full.encode(text, false).map_err(tokenizer_err)?;
+                let id = |token: &str, fallback: u32| full.token_to_id(token).unwrap_or(fallback) as i64;
+                (
+                    encoding.get_ids().iter().map(|&v| v as i64).collect::<Vec<i64>>(),
+                    id("[CLS]", 101),
+                    id("[SEP]", 102),
+                    id("[PAD]", 0),
+                )
+            }
+            EmbeddingTokenizer::Simple(tokenizer) => (
+                tokenizer.word_pieces(text),
+                tokenizer.cls_token_id,
+                tokenizer.sep_token_id,
+                tokenizer.pad_token_id,
+            ),
+        };
         
-        // Token type IDs (all zeros for single sequence)
-        let token_type_ids = vec![0i64; max_length];
+        let mut windows = Vec::new();
+        let mut start = 0;
+        loop {
+            let end = (start + window).min(content.len());
+            let piece = &content[start..end];
+            windows.push((wrap_tokens(piece, cls_id, sep_id, pad_id, max_length), piece.len()));
+            if end == content.len() {
+                break;
+            }
+            start += window - stride;
+        }
         
-        (input_ids, attention_mask, token_type_ids)
+        Ok(windows)
     }
 }
 
@@ -185,116 +321,265 @@ impl SimpleTokenizer {
 // Embedding Model
 // ============================================================================
 
+/// How per-token hidden states are reduced to one vector
+#[derive(Clone, Copy, Debug, PartialEq)]
+enum Pooling {
+    /// Attention-masked mean over tokens (MiniLM, e5)
+    Mean,
+    /// The [CLS] token's hidden state (bge)
+    Cls,
+}
+
+impl Pooling {
+    fn parse(value: &str) -> Option<Self> {
+        match value {
+            "mean" => Some(Pooling::Mean),
+            "cls" => Some(Pooling::Cls),
+            _ => None,
+        }
+    }
+}
+
+/// How to feed and read a particular ONNX export; the default matches MiniLM
+#[derive(Clone, Debug)]
+struct ModelSpec {
+    input_ids_name: String,
+    attention_mask_name: String,
+    token_type_ids_name: String,
+    /// None = feed token_type_ids only if the model declares that input
+    use_token_type_ids: Option<bool>,
+    pooling: Pooling,
+    /// Expected embedding size, checked at load
+    dimension: Option<usize>,
+    output_name: Option<String>,
+}
+
+impl Default for ModelSpec {
+    fn default() -> Self {
+        Self {
+            input_ids_name: "input_ids".to_string(),
+            attention_mask_name: "attention_mask".to_string(),
+            token_type_ids_name: "token_type_ids".to_string(),
+            use_token_type_ids: None,
+            pooling: Pooling::Mean,
+            dimension: None,
+            output_name: None,
+        }
+    }
+}
+
 struct EmbeddingModel {
     session: Session,
-    tokenizer: SimpleTokenizer,
+    tokenizer: EmbeddingTokenizer,
+    weights_bytes: u64,
+    spec: ModelSpec,
+    /// Input name for token_type_ids, when the model takes it
+    token_type_ids_input: Option<String>,
+    /// Output size, measured with a probe run at load
+    dimension: usize,
 }
 
 impl EmbeddingModel {
     fn new(model_dir: &PathBuf) -> ModelResult<Self> {
+        Self::with_spec(model_dir, ModelSpec::default())
+    }
+    
+    fn with_spec(model_dir: &PathBuf, spec: ModelSpec) -> ModelResult<Self> {
         let model_path = model_dir.join("model.onnx");
-        let vocab_path = model_dir.join("vocab.txt");
         
         println!("[Embedding] Loading model from: {:?}", model_path);
         
         let providers = vec![CPUExecutionProvider::default().build()];
         
         let session = Session::builder()
-            .map_err(ort_err)?
+            .map_err(load_err)?
             .with_execution_providers(providers)
-            .map_err(ort_err)?
+            .map_err(load_err)?
             .with_optimization_level(GraphOptimizationLevel::Level3)
-            .map_err(ort_err)?
+            .map_err(load_err)?
             .with_intra_threads(4)
-            .map_err(ort_err)?
+            .map_err(load_err)?
             .commit_from_file(&model_path)
-            .map_err(ort_err)?;
+            .map_err(load_err)?;
+        
+        // Only feed the inputs the export declares
+        let input_names: Vec<String> = session.inputs.iter().map(|input| input.name.clone()).collect();
+        for name in [&spec.input_ids_name, &spec.attention_mask_name] {
+            if !input_names.contains(name) {
+                return Err(ModelError::LoadFailed(format!("Model has no input '{}' (inputs: {:?})", name, input_names)));
+            }
+        }
+        let declares_token_types = input_names.contains(&spec.token_type_ids_name);
+        let token_type_ids_input = match spec.use_token_type_ids {
+            Some(true) if !declares_token_types => {
+                return Err(ModelError::LoadFailed(format!(
+                    "Model has no input '{}' (inputs: {:?})", spec.token_type_ids_name, input_names
+                )));
+            }
+            Some(false) => None,
+            _ => declares_token_types.then(|| spec.token_type_ids_name.clone()),
+        };
+        
+        let tokenizer = EmbeddingTokenizer::load(model_dir)?;
+        let weights_bytes = fs::metadata(&model_path).map(|m| m.len()).unwrap_or(0);
         
-        let tokenizer = SimpleTokenizer::from_vocab_file(&vocab_path)?;
+        let mut model = Self { session, tokenizer, weights_bytes, spec, token_type_ids_input, dimension: 0 };
         
-        println!("[Embedding] Model loaded successfully");
+        // Probe run: measures the output size and doubles as a warmup
+        model.dimension = model.generate_embedding("")?.len();
+        if let Some(expected) = model.spec.dimension {
+            if expected != model.dimension {
+                return Err(ModelError::LoadFailed(format!(
+                    "Model outputs {}-dim embeddings, expected {}", model.dimension, expected
+                )));
+            }
+        }
+        
+        println!("[Embedding] Model loaded successfully ({} dims, {:?} pooling, token_type_ids: {})",
+            model.dimension, model.spec.pooling, model.token_type_ids_input.is_some());
         
-        Ok(Self { session, tokenizer })
+        Ok(model)
     }
     
     fn generate_embedding(&mut self, text: &str) -> ModelResult<Vec<f32>> {
-        let (input_ids, attention_mask, token_type_ids) = 
-            self.tokenizer.tokenize(text, MAX_SEQUENCE_LENGTH);
+        self.generate_embeddings(&[text])?
+            .pop()
+            .ok_or_else(|| ModelError::InferenceFailed("No embedding produced".to_string()))
+    }
+    
+    /// Embed texts in sub-batches of EMBEDDING_BATCH_SIZE, one session run per sub-batch
+    fn generate_embeddings(&mut self, texts: &[&str]) -> ModelResult<Vec<Vec<f32>>> {
+        let mut embeddings = Vec::with_capacity(texts.len());
+        for batch in texts.chunks(EMBEDDING_BATCH_SIZE) {
+            let tokenized = batch.iter()
+                .map(|text| self.tokenizer.tokenize(text, MAX_SEQUENCE_LENGTH))
+                .collect::<ModelResult<Vec<_>>>()?;
+            embeddings.extend(self.run_batch(tokenized)?);
+        }
+        Ok(embeddings)
+    }
+    
+    /// Embed text of any length as the token-count-weighted mean of overlapping
+    /// window embeddings. Returns the embedding and the number of windows used
+    fn generate_embedding_long(&mut self, text: &str, stride: usize) -> ModelResult<(Vec<f32>, usize)> {
+        let windows = self.tokenizer.tokenize_windows(text, MAX_SEQUENCE_LENGTH, stride)?;
+        let window_count = windows.len();
         
-        // Create input tensors as dynamic arrays
-        let input_ids_array: ArrayD<i64> = Array2::from_shape_vec((1, MAX_SEQUENCE_LENGTH), input_ids)
-            .map_err(|e| e.to_string())?.into_dyn();
-        let attention_mask_array: ArrayD<i64> = Array2::from_shape_vec((1, MAX_SEQUENCE_LENGTH), attention_mask)
-            .map_err(|e| e.to_string())?.into_dyn();
-        let token_type_ids_array: ArrayD<i64> = Array2::from_shape_vec((1, MAX_SEQUENCE_LENGTH), token_type_ids)
-            .map_err(|e| e.to_string())?.into_dyn();
+        let mut pooled = vec![0.0f32; self.dimension];
+        for batch in windows.chunks(EMBEDDING_BATCH_SIZE) {
+            let tokenized = batch.iter().map(|(input, _)| input.clone()).collect();
+            let embeddings = self.run_batch(tokenized)?;
+            for ((_, token_count), embedding) in batch.iter().zip(embeddings) {
+                // An empty text still yields one [CLS] [SEP] window; give it a nonzero weight
+                let weight = (*token_count).max(1) as f32;
+                for (p, v) in pooled.iter_mut().zip(embedding) {
+                    *p += v * weight;
+                }
+            }
+        }
         
-        // Run inference using TensorRef like parakeet does
-        let model_inputs = inputs![
-            "input_ids" => TensorRef::from_array_view(input_ids_array.view()).map_err(ort_err)?,
-            "attention_mask" => TensorRef::from_array_view(attention_mask_array.view()).map_err(ort_err)?,
-            "token_type_ids" => TensorRef::from_array_view(token_type_ids_array.view()).map_err(ort_err)?
-        ];
+        // Weighted mean then L2 normalize; the division is absorbed by normalization
+        let norm: f32 = pooled.iter().map(|x| x * x).sum::<f32>().sqrt();
+        if norm > 0.0 {
+            for v in &mut pooled {
+                *v /= norm;
+            }
+        }
         
-        let outputs = self.session.run(model_inputs).map_err(ort_err)?;
+        Ok((pooled, window_count))
+    }
+    
+    fn run_batch(&mut self, tokenized: Vec<TokenizedInput>) -> ModelResult<Vec<Vec<f32>>> {
+        let batch_size = tokenized.len();
+        let mut input_ids = Vec::with_capacity(batch_size * MAX_SEQUENCE_LENGTH);
+        let mut attention_mask = Vec::with_capacity(batch_size * MAX_SEQUENCE_LENGTH);
+        let mut token_type_ids = Vec::with_capacity(batch_size * MAX_SEQUENCE_LENGTH);
+        
+        for (ids, mask, type_ids) in tokenized {
+            input_ids.extend(ids);
+            attention_mask.extend(mask);
+            token_type_ids.extend(type_ids);
+        }
+        let attention_mask_vec = attention_mask.clone();
+        
+        // Create [batch, seq] input tensors as dynamic arrays
+        let input_ids_array: ArrayD<i64> = Array2::from_shape_vec((batch_size, MAX_SEQUENCE_LENGTH), input_ids)
+            .map_err(|e| ModelError::InferenceFailed(e.to_string()))?.into_dyn();
+        let attention_mask_array: ArrayD<i64> = Array2::from_shape_vec((batch_size, MAX_SEQUENCE_LENGTH), attention_mask)
+            .map_err(|e| ModelError::InferenceFailed(e.to_string()))?.into_dyn();
+        let token_type_ids_array: ArrayD<i64> = Array2::from_shape_vec((batch_size, MAX_SEQUENCE_LENGTH), token_type_ids)
+            .map_err(|e| ModelError::InferenceFailed(e.to_string()))?.into_dyn();
+        
+        // Run inference using TensorRef like parakeet does
+        let ids_name = self.spec.input_ids_name.as_str();
+        let mask_name = self.spec.attention_mask_name.as_str();
+        let outputs = match &self.token_type_ids_input {
+            Some(type_ids_name) => self.session.run(inputs![
+                ids_name => TensorRef::from_array_view(input_ids_array.view()).map_err(ort_err)?,
+                mask_name => TensorRef::from_array_view(attention_mask_array.view()).map_err(ort_err)?,
+                type_ids_name.as_str() => TensorRef::from_array_view(token_type_ids_array.view()).map_err(ort_err)?
+            ]),
+            None => self.session.run(inputs![
+                ids_name => TensorRef::from_array_view(input_ids_array.view()).map_err(ort_err)?,
+                mask_name => TensorRef::from_array_view(attention_mask_array.view()).map_err(ort_err)?
+            ]),
+        }
+        .map_err(ort_err)?;
         
         // Get the sentence embedding - the model outputs "last_hidden_state"
         // For MiniLM, the output is typically last_hidden_state with shape [batch, seq, hidden]
-        let output_name = outputs.iter()
+        let output_name = self.spec.output_name.clone().unwrap_or_else(|| outputs.iter()
             .map(|(name, _)| name.to_string())
             .find(|n| n.contains("last_hidden_state") || n.contains("embedding") || n.contains("output"))
-            .unwrap_or_else(|| outputs.iter().next().map(|(n, _)| n.to_string()).unwrap_or_default());
+            .unwrap_or_else(|| outputs.iter().next().map(|(n, _)| n.to_string()).unwrap_or_default()));
         
         let output_tensor = outputs.get(&output_name)
-            .ok_or_else(|| format!("No output found. Available outputs: {:?}", 
-                outputs.iter().map(|(n, _)| n.to_string()).collect::<Vec<_>>()))?
+            .ok_or_else(|| ModelError::InferenceFailed(format!("No output found. Available outputs: {:?}", 
+                outputs.iter().map(|(n, _)| n.to_string()).collect::<Vec<_>>())))?
             .try_extract_array::<f32>()
             .map_err(ort_err)?;
         
         let dims = output_tensor.shape();
+        let mut embeddings = Vec::with_capacity(batch_size);
         
-        // Mean pooling: average across sequence length dimension
-        let embedding = if dims.len() == 3 {
-            // Shape: [1, seq_len, hidden_size] -> mean over seq_len
-            let seq_len = dims[1];
-            let hidden_size = dims[2];
-            
-            // Get the attention mask we used
-            let (_, attention_mask_vec, _) = self.tokenizer.tokenize(text, MAX_SEQUENCE_LENGTH);
-            
-            let mut pooled = vec![0.0f32; hidden_size];
-            let mut count = 0.0f32;
-            
-            for i in 0..seq_len {
-                // Only pool where attention mask is 1
-                if attention_mask_vec.get(i).copied().unwrap_or(0) == 1 {
-                    for j in 0..hidden_size {
-                        pooled[j] += output_tensor[[0, i, j]];
+        for b in 0..batch_size {
+            let mut embedding = if dims.len() == 3 && self.spec.pooling == Pooling::Cls {
+                // [CLS] is always the first token
+                (0..dims[2]).map(|j| output_tensor[[b, 0, j]]).collect::<Vec<f32>>()
+            } else if dims.len() == 3 {
+                // Mean pooling: average across sequence length dimension
+                // Shape: [batch, seq_len, hidden_size] -> mean over seq_len, masked by this row's attention
+                let seq_len = dims[1];
+                let hidden_size = dims[2];
+                let row_mask = &attention_mask_vec[b * MAX_SEQUENCE_LENGTH..(b + 1) * MAX_SEQUENCE_LENGTH];
+                
+                let mut pooled = vec![0.0f32; hidden_size];
+                let mut count = 0.0f32;
+                
+                for i in 0..seq_len {
+                    // Only pool where attention mask is 1
+                    if row_mask.get(i).copied().unwrap_or(0) == 1 {
+                        for j in 0..hidden_size {
+                            pooled[j] += output_tensor[[b, i, j]];
+                        }
+                        count += 1.0;
                     }
-                    count += 1.0;
                 }
-            }
-            
-            // Normalize by count
-            for v in &mut pooled {
-                *v /= count.max(1.0);
-            }
-            
-            // L2 normalize the embedding
-            let norm: f32 = pooled.iter().map(|x| x * x).sum::<f32>().sqrt();
-            if norm > 0.0 {
+                
+                // Normalize by count
                 for v in &mut pooled {
-                    *v /= norm;
+                    *v /= count.max(1.0);
                 }
-            }
-            
-            pooled
-        } else if dims.len() == 2 {
-            // Shape: [1, hidden_size] - already pooled
-            let hidden_size = dims[1];
-            let mut embedding: Vec<f32> = (0..hidden_size).map(|i| output_tensor[[0, i]]).collect();
+                
+                pooled
+            } else if dims.len() == 2 {
+                // Shape: [batch, hidden_size] - already pooled
+                (0..dims[1]).map(|i| output_tensor[[b, i]]).collect::<Vec<f32>>()
+            } else {
+                return Err(ModelError::InferenceFailed(format!("Unexpected output shape: {:?}", dims)));
+            };
             
-            // L2 normalize
+            // L2 normalize the embedding
             let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
             if norm > 0.0 {
                 for v in &mut embedding {
@@ -302,12 +587,10 @@ impl EmbeddingModel {
                 }
             }
             
-            embedding
-        } else {
-            return Err(format!("Unexpected output shape: {:?}", dims));
-        };
+            embeddings.push(embedding);
+        }
         
-        Ok(embedding)
+        Ok(embeddings)
     }
 }
 
@@ -315,18 +598,30 @@ impl EmbeddingModel {
 static EMBEDDING_MODEL: Lazy<Mutex<Option<EmbeddingModel>>> = 
     Lazy::new(|| Mutex::new(None));
 
+static EMBEDDING_ENGINE_STATE: Lazy<Mutex<EngineState>> = 
+    Lazy::new(|| Mutex::new(EngineState::Unloaded));
+
+/// Directory set with set_embedding_model_dir (None = cache dir), restored from disk
+static MODEL_DIR_OVERRIDE: Lazy<Mutex<Option<PathBuf>>> =
+    Lazy::new(|| Mutex::new(settings::load::<ModelDirConfig>(CONFIG_FILE).model_dir));
+
 // ============================================================================
 // Path Utilities
 // ============================================================================
 
+const MODEL_DIR_NAME: &str = "embedding-model";
+const CONFIG_FILE: &str = "embedding-config.json";
+
 fn get_model_dir() -> PathBuf {
-    let cache_dir = dirs::cache_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("ghost")
-        .join("embedding-model");
+    let base = MODEL_DIR_OVERRIDE.lock().clone().unwrap_or_else(|| {
+        dirs::cache_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ghost")
+    });
+    let model_dir = base.join(MODEL_DIR_NAME);
     
-    fs::create_dir_all(&cache_dir).ok();
-    cache_dir
+    fs::create_dir_all(&model_dir).ok();
+    model_dir
 }
 
 fn check_model_files() -> bool {
@@ -352,64 +647,15 @@ fn check_model_files() -> bool {
 // Download Functions
 // ============================================================================
 
-fn download_file_with_progress(
-    url: &str,
-    dest: &PathBuf,
-    file_index: usize,
-    total_files: usize,
-    expected_size: u64,
-    total_expected: u64,
-    bytes_so_far: &mut u64,
-) -> ModelResult<()> {
-    let filename = dest.file_name()
-        .and_then(|n| n.to_str())
-        .unwrap_or("unknown");
-    
-    println!("[Embedding] Downloading {}", url);
-    
-    {
-        let mut progress = DOWNLOAD_PROGRESS.lock();
-        progress.current_file = filename.to_string();
-        progress.current_file_index = file_index as u32;
-        progress.total_files = total_files as u32;
-    }
-    
-    let response = ureq::get(url)
-        .call()
-        .map_err(|e| format!("HTTP error: {}", e))?;
-    
-    let content_length = response.header("content-length")
-        .and_then(|s| s.parse::<u64>().ok())
-        .unwrap_or(expected_size);
-    
-    let mut reader = response.into_reader();
-    let mut file = fs::File::create(dest).map_err(io_err)?;
-    
-    let mut buffer = [0u8; 65536];
-    let mut file_downloaded: u64 = 0;
-    
-    loop {
-        let bytes_read = reader.read(&mut buffer).map_err(io_err)?;
-        if bytes_read == 0 { break; }
-        
-        file.write_all(&buffer[..bytes_read]).map_err(io_err)?;
-        file_downloaded += bytes_read as u64;
-        *bytes_so_far += bytes_read as u64;
-        
-        let mut progress = DOWNLOAD_PROGRESS.lock();
-        progress.bytes_downloaded = *bytes_so_far as i64;
-        progress.percent = ((*bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
-    }
-    
-    println!("[Embedding] ✓ Downloaded {} ({} bytes)", filename, file_downloaded);
-    Ok(())
+fn is_download_cancelled(generation: u64) -> bool {
+    DOWNLOAD_GENERATION.load(Ordering::SeqCst) != generation
 }
 
-fn do_download() {
+fn do_download(generation: u64) {
     println!("[Embedding] Starting model download...");
     
     let model_dir = get_model_dir();
-    let base_url = format!("https://huggingface.co/{}/resolve/main", MODEL_REPO);
+    let base_url = download_base_url();
     
     let files: Vec<(&str, String, u64)> = MODEL_FILES.iter()
         .map(|(name, path, size)| (*name, format!("{}/{}", base_url, path), *size))
@@ -420,6 +666,10 @@ fn do_download() {
     let mut bytes_so_far: u64 = 0;
     
     for (index, (filename, url, expected_size)) in files.iter().enumerate() {
+        if is_download_cancelled(generation) {
+            return;
+        }
+        
         let dest = model_dir.join(filename);
         
         // Skip if already downloaded
@@ -435,9 +685,32 @@ fn do_download() {
             }
         }
         
-        if let Err(e) = download_file_with_progress(
-            &url, &dest, index, total_files, *expected_size, total_expected, &mut bytes_so_far
-        ) {
+        {
+            let mut progress = DOWNLOAD_PROGRESS.lock();
+            progress.current_file = filename.to_string();
+            progress.current_file_index = index as u32;
+            progress.total_files = total_files as u32;
+        }
+        
+        let bytes_before = bytes_so_far;
+        if let Err(e) = download::download_with_retry(&mut download::Download {
+            url: url.as_str(),
+            dest: &dest,
+            expected_size: None,
+            log_prefix: "[Embedding]",
+            is_cancelled: &|| is_download_cancelled(generation),
+            on_progress: &mut |file_bytes| {
+                bytes_so_far = bytes_before + file_bytes;
+                let mut progress = DOWNLOAD_PROGRESS.lock();
+                progress.bytes_downloaded = bytes_so_far as i64;
+                progress.percent = ((bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
+            },
+        }) {
+            if is_download_cancelled(generation) {
+                // cancel_embedding_download already reset the progress
+                println!("[Embedding] Download of {} cancelled", filename);
+                return;
+            }
             let mut progress = DOWNLOAD_PROGRESS.lock();
             progress.is_downloading = false;
             progress.error = Some(format!("Failed to download {}: {}", filename, e));
@@ -487,7 +760,31 @@ pub fn download_embedding_model() -> bool {
         };
     }
     
-    std::thread::spawn(|| { do_download(); });
+    let generation = DOWNLOAD_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut thread = DOWNLOAD_THREAD.lock();
+    let previous = thread.take();
+    *thread = Some(std::thread::spawn(move || {
+        // A cancelled download may still be cleaning up its .part file
+        if let Some(previous) = previous {
+            let _ = previous.join();
+        }
+        do_download(generation);
+    }));
+    true
+}
+
+/// Stop the running model download, deleting the partial file and resetting progress.
+/// Returns false if no download was in progress
+#[napi]
+pub fn cancel_embedding_download() -> bool {
+    let mut progress = DOWNLOAD_PROGRESS.lock();
+    if !progress.is_downloading {
+        return false;
+    }
+    
+    DOWNLOAD_GENERATION.fetch_add(1, Ordering::SeqCst);
+    *progress = EmbeddingDownloadProgress::default();
+    println!("[Embedding] Download cancelled");
     true
 }
 
@@ -498,26 +795,99 @@ pub fn get_embedding_download_progress() -> EmbeddingDownloadProgress {
 
 #[napi]
 pub fn init_embedding_model() -> Result<bool> {
+    init_embedding_model_with_config(None)
+}
+
+/// How to load a different BERT-style ONNX embedding model (bge-small, e5...);
+/// every field defaults to the bundled all-MiniLM-L6-v2 setup
+#[napi(object)]
+pub struct EmbeddingModelConfig {
+    /// Directory holding model.onnx plus tokenizer.json (or vocab.txt)
+    /// Default: the downloaded MiniLM model
+    pub model_dir: Option<String>,
+    /// ONNX input names (default "input_ids", "attention_mask", "token_type_ids")
+    pub input_ids_name: Option<String>,
+    pub attention_mask_name: Option<String>,
+    pub token_type_ids_name: Option<String>,
+    /// Feed token_type_ids; default: only if the model declares that input
+    pub use_token_type_ids: Option<bool>,
+    /// "mean" (masked mean over tokens, default) or "cls" (first token, e.g. bge)
+    pub pooling: Option<String>,
+    /// Expected embedding size; loading fails if the model outputs something else
+    pub dimension: Option<u32>,
+    /// Output holding the hidden states (default: last_hidden_state or the first output)
+    pub output_name: Option<String>,
+}
+
+impl EmbeddingModelConfig {
+    fn into_spec(self) -> ModelResult<ModelSpec> {
+        let defaults = ModelSpec::default();
+        let pooling = match self.pooling.as_deref() {
+            None => defaults.pooling,
+            Some(value) => Pooling::parse(value)
+                .ok_or_else(|| ModelError::InvalidInput(format!("Unknown pooling '{}', expected \"mean\" or \"cls\"", value)))?,
+        };
+        if self.dimension == Some(0) {
+            return Err(ModelError::InvalidInput("dimension must be greater than 0".to_string()));
+        }
+        
+        Ok(ModelSpec {
+            input_ids_name: self.input_ids_name.unwrap_or(defaults.input_ids_name),
+            attention_mask_name: self.attention_mask_name.unwrap_or(defaults.attention_mask_name),
+            token_type_ids_name: self.token_type_ids_name.unwrap_or(defaults.token_type_ids_name),
+            use_token_type_ids: self.use_token_type_ids,
+            pooling,
+            dimension: self.dimension.map(|d| d as usize),
+            output_name: self.output_name,
+        })
+    }
+}
+
+/// Load an embedding model, optionally a custom export described by `config`
+/// Replaces any loaded model; get_embedding_dimension then reports the new model's size
+#[napi]
+pub fn init_embedding_model_with_config(config: Option<EmbeddingModelConfig>) -> Result<bool> {
     println!("[Embedding] Initializing model...");
     
-    let model_dir = get_model_dir();
+    let custom_dir = config.as_ref().and_then(|c| c.model_dir.clone()).map(PathBuf::from);
+    let spec = config.map(EmbeddingModelConfig::into_spec).transpose()?.unwrap_or_default();
     
-    if !check_model_files() {
-        return Err(Error::from_reason("Model not downloaded"));
-    }
+    let model_dir = match custom_dir {
+        Some(dir) => {
+            let has_tokenizer = dir.join("tokenizer.json").exists() || dir.join("vocab.txt").exists();
+            if !dir.join("model.onnx").exists() || !has_tokenizer {
+                let error = ModelError::InvalidInput(format!(
+                    "{} needs model.onnx and tokenizer.json or vocab.txt", dir.display()
+                ));
+                *EMBEDDING_ENGINE_STATE.lock() = EngineState::Error(error.to_string());
+                return Err(error.into());
+            }
+            dir
+        }
+        None => {
+            if !check_model_files() {
+                *EMBEDDING_ENGINE_STATE.lock() = EngineState::Error(ModelError::NotDownloaded.to_string());
+                return Err(ModelError::NotDownloaded.into());
+            }
+            get_model_dir()
+        }
+    };
     
     println!("[Embedding] Loading from: {:?}", model_dir);
+    *EMBEDDING_ENGINE_STATE.lock() = EngineState::Loading;
     
-    match EmbeddingModel::new(&model_dir) {
+    match EmbeddingModel::with_spec(&model_dir, spec) {
         Ok(model) => {
             let mut state = EMBEDDING_MODEL.lock();
             *state = Some(model);
+            *EMBEDDING_ENGINE_STATE.lock() = EngineState::Ready;
             println!("[Embedding] ✅ Model initialized successfully");
             Ok(true)
         }
         Err(e) => {
-            println!("[Embedding] ❌ Init failed: {:?}", e);
-            Err(Error::from_reason(format!("Init failed: {:?}", e)))
+            println!("[Embedding] ❌ Init failed: {}", e);
+            *EMBEDDING_ENGINE_STATE.lock() = EngineState::Error(e.to_string());
+            Err(e.into())
         }
     }
 }
@@ -527,14 +897,27 @@ pub fn is_embedding_ready() -> bool {
     EMBEDDING_MODEL.lock().is_some()
 }
 
+/// Detailed embedding model state, distinguishing "never loaded" from "failed to load"
+#[napi]
+pub fn get_embedding_state() -> EngineStatus {
+    EMBEDDING_ENGINE_STATE.lock().to_status()
+}
+
+/// Estimated resident memory of the loaded embedding session (0 when not loaded)
+pub(crate) fn loaded_memory_bytes() -> u64 {
+    EMBEDDING_MODEL.lock()
+        .as_ref()
+        .map(|model| model.weights_bytes)
+        .unwrap_or(0)
+}
+
 #[napi]
 pub fn generate_embedding(text: String) -> Result<Vec<f64>> {
     let mut state = EMBEDDING_MODEL.lock();
     let model = state.as_mut()
-        .ok_or_else(|| Error::from_reason("Embedding model not initialized"))?;
+        .ok_or(ModelError::NotInitialized("Embedding model"))?;
     
-    let embedding = model.generate_embedding(&text)
-        .map_err(|e| Error::from_reason(e))?;
+    let embedding = model.generate_embedding(&text)?;
     
     // Convert f32 to f64 for JavaScript compatibility
     Ok(embedding.iter().map(|&x| x as f64).collect())
@@ -544,27 +927,57 @@ pub fn generate_embedding(text: String) -> Result<Vec<f64>> {
 pub fn generate_embeddings_batch(texts: Vec<String>) -> Result<Vec<Vec<f64>>> {
     let mut state = EMBEDDING_MODEL.lock();
     let model = state.as_mut()
-        .ok_or_else(|| Error::from_reason("Embedding model not initialized"))?;
+        .ok_or(ModelError::NotInitialized("Embedding model"))?;
     
-    let mut results = Vec::with_capacity(texts.len());
+    let texts: Vec<&str> = texts.iter().map(|t| t.as_str()).collect();
+    let embeddings = model.generate_embeddings(&texts)?;
     
-    for text in texts {
-        let embedding = model.generate_embedding(&text)
-            .map_err(|e| Error::from_reason(e))?;
-        results.push(embedding.iter().map(|&x| x as f64).collect());
-    }
+    Ok(embeddings.into_iter()
+        .map(|embedding| embedding.iter().map(|&x| x as f64).collect())
+        .collect())
+}
+
+/// Embedding of a long document and how many windows it was split into
+#[napi(object)]
+pub struct LongEmbedding {
+    pub embedding: Vec<f64>,
+    pub window_count: u32,
+}
+
+/// Embed a document longer than the model's 512-token window by averaging
+/// overlapping windows. `stride` is the token overlap between windows (default 128)
+#[napi]
+pub fn generate_embedding_long(text: String, stride: Option<u32>) -> Result<LongEmbedding> {
+    let mut state = EMBEDDING_MODEL.lock();
+    let model = state.as_mut()
+        .ok_or(ModelError::NotInitialized("Embedding model"))?;
+    
+    let stride = stride.unwrap_or(DEFAULT_LONG_STRIDE) as usize;
+    let (embedding, window_count) = model.generate_embedding_long(&text, stride)?;
     
-    Ok(results)
+    Ok(LongEmbedding {
+        embedding: embedding.iter().map(|&x| x as f64).collect(),
+        window_count: window_count as u32,
+    })
+}
+
+/// Free the model's memory but keep the downloaded files; init_embedding_model loads it again
+#[napi]
+pub fn unload_embedding_model() {
+    let mut state = EMBEDDING_MODEL.lock();
+    *state = None;
+    *EMBEDDING_ENGINE_STATE.lock() = EngineState::Unloaded;
+    println!("[Embedding] Unloaded");
 }
 
+/// Delete the downloaded model files, unloading the model first unless `keep_loaded` is true
+/// A kept model works until unloaded but can't be loaded again without a new download
 #[napi]
-pub fn delete_embedding_model() -> bool {
+pub fn delete_embedding_model(keep_loaded: Option<bool>) -> bool {
     let model_dir = get_model_dir();
     
-    // Clear the loaded model
-    {
-        let mut state = EMBEDDING_MODEL.lock();
-        *state = None;
+    if !keep_loaded.unwrap_or(false) {
+        unload_embedding_model();
     }
     
     // Delete the model directory
@@ -575,8 +988,466 @@ pub fn delete_embedding_model() -> bool {
     }
 }
 
+/// Keep the model under `path` (in an embedding-model subfolder) instead of the cache
+/// directory, e.g. on an external disk; an empty path restores the default. Persisted across
+/// restarts. Existing files aren't moved, and an already loaded model stays loaded
+#[napi]
+pub fn set_embedding_model_dir(path: String) -> Result<()> {
+    if DOWNLOAD_PROGRESS.lock().is_downloading {
+        return Err(ModelError::InvalidInput("Can't change the model directory while a download is running".into()).into());
+    }
+    
+    settings::set_model_dir(CONFIG_FILE, MODEL_DIR_NAME, &MODEL_DIR_OVERRIDE, &path)?;
+    println!("[Embedding] Model directory: {}", get_model_dir().display());
+    Ok(())
+}
+
+/// Directory set with set_embedding_model_dir, or null when using the default
+#[napi]
+pub fn get_embedding_model_dir() -> Option<String> {
+    MODEL_DIR_OVERRIDE.lock().as_ref().map(|dir| dir.to_string_lossy().to_string())
+}
+
+/// Mirror set with set_model_base_url (None = Hugging Face)
+static BASE_URL_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Fetch the model files from a mirror with the same file paths; None restores Hugging Face
+pub(crate) fn set_download_base_url(url: Option<String>) -> ModelResult<()> {
+    if DOWNLOAD_PROGRESS.lock().is_downloading {
+        return Err(ModelError::InvalidInput("Can't change the download URL while a download is running".into()));
+    }
+    
+    *BASE_URL_OVERRIDE.lock() = url;
+    println!("[Embedding] Download base URL: {}", download_base_url());
+    Ok(())
+}
+
+/// Base URL the MODEL_FILES paths are appended to
+pub(crate) fn download_base_url() -> String {
+    BASE_URL_OVERRIDE.lock()
+        .clone()
+        .unwrap_or_else(|| format!("https://huggingface.co/{}/resolve/main", MODEL_REPO))
+}
+
+/// Embedding size of the loaded model (MiniLM's 384 when none is loaded)
 #[napi]
 pub fn get_embedding_dimension() -> u32 {
-    EMBEDDING_DIM as u32
+    EMBEDDING_MODEL.lock()
+        .as_ref()
+        .map(|model| model.dimension)
+        .unwrap_or(EMBEDDING_DIM) as u32
+}
+
+// ============================================================================
+// Similarity Search
+// ============================================================================
+
+#[napi(object)]
+pub struct SimilarityResult {
+    /// Position in the corpus passed to top_k_similar
+    pub index: u32,
+    pub score: f64,
+}
+
+/// Embeddings from generate_embedding are L2-normalized, so cosine similarity is the dot product
+fn dot(a: &[f64], b: &[f64]) -> ModelResult<f64> {
+    if a.len() != b.len() {
+        return Err(ModelError::InvalidInput(format!("Dimension mismatch: {} vs {}", a.len(), b.len())));
+    }
+    Ok(a.iter().zip(b).map(|(x, y)| x * y).sum())
+}
+
+/// Cosine similarity of two embeddings (assumes L2-normalized input, as generate_embedding returns)
+#[napi]
+pub fn cosine_similarity(a: Vec<f64>, b: Vec<f64>) -> Result<f64> {
+    Ok(dot(&a, &b)?)
+}
+
+/// Rank corpus embeddings by similarity to the query, best first
+#[napi]
+pub fn top_k_similar(query: Vec<f64>, corpus: Vec<Vec<f64>>, k: u32) -> Result<Vec<SimilarityResult>> {
+    let mut scored = Vec::with_capacity(corpus.len());
+    for (index, embedding) in corpus.iter().enumerate() {
+        if embedding.len() != query.len() {
+            return Err(ModelError::InvalidInput(format!(
+                "Corpus item {}: dimension mismatch: {} vs {}", index, embedding.len(), query.len()
+            )).into());
+        }
+        let score = dot(&query, embedding)?;
+        scored.push(SimilarityResult { index: index as u32, score });
+    }
+    
+    scored.sort_by(|a, b| b.score.partial_cmp(&a.score).unwrap_or(std::cmp::Ordering::Equal));
+    scored.truncate(k as usize);
+    Ok(scored)
+}
+
+// ============================================================================
+// Embedding Index
+// ============================================================================
+
+/// File magic and version of the embedding_index_save format:
+/// "GEIX", version, dim, count (u32 LE), then per entry a u32 id length + UTF-8 id,
+/// then all vectors as f32 LE, `dim` values each, in entry order
+const INDEX_MAGIC: &[u8; 4] = b"GEIX";
+const INDEX_VERSION: u32 = 1;
+
+/// In-memory vector store; vectors are kept flat as f32 to halve memory and speed up scoring
+#[derive(Default)]
+struct EmbeddingIndex {
+    /// Set by the first vector added (0 = empty index)
+    dim: usize,
+    ids: Vec<String>,
+    positions: HashMap<String, usize>,
+    vectors: Vec<f32>,
+}
+
+impl EmbeddingIndex {
+    /// Insert or replace the vector stored under `id`
+    fn add(&mut self, id: String, vector: &[f64]) -> ModelResult<()> {
+        if vector.is_empty() {
+            return Err(ModelError::InvalidInput("Empty vector".to_string()));
+        }
+        if self.dim == 0 {
+            self.dim = vector.len();
+        } else if vector.len() != self.dim {
+            return Err(ModelError::InvalidInput(format!("Dimension mismatch: {} vs index {}", vector.len(), self.dim)));
+        }
+        
+        let values = vector.iter().map(|&x| x as f32);
+        match self.positions.get(&id) {
+            Some(&position) => {
+                let start = position * self.dim;
+                for (slot, value) in self.vectors[start..start + self.dim].iter_mut().zip(values) {
+                    *slot = value;
+                }
+            }
+            None => {
+                self.positions.insert(id.clone(), self.ids.len());
+                self.ids.push(id);
+                self.vectors.extend(values);
+            }
+        }
+        Ok(())
+    }
+    
+    /// Top `k` entries by dot product (cosine similarity for normalized vectors), best first
+    fn search(&self, query: &[f64], k: usize) -> ModelResult<Vec<IndexSearchResult>> {
+        if self.ids.is_empty() {
+            return Ok(Vec::new());
+        }
+        if query.len() != self.dim {
+            return Err(ModelError::InvalidInput(format!("Dimension mismatch: {} vs index {}", query.len(), self.dim)));
+        }
+        
+        let query: Vec<f32> = query.iter().map(|&x| x as f32).collect();
+        let mut scored: Vec<(usize, f32)> = self.vectors
+            .chunks_exact(self.dim)
+            .map(|vector| vector.iter().zip(&query).map(|(a, b)| a * b).sum::<f32>())
+            .enumerate()
+            .collect();
+        
+        let by_score = |a: &(usize, f32), b: &(usize, f32)| b.1.partial_cmp(&a.1).unwrap_or(std::cmp::Ordering::Equal);
+        let k = k.min(scored.len());
+        if k == 0 {
+            return Ok(Vec::new());
+        }
+        if k < scored.len() {
+            scored.select_nth_unstable_by(k - 1, by_score);
+            scored.truncate(k);
+        }
+        scored.sort_by(by_score);
+        
+        Ok(scored
+            .into_iter()
+            .map(|(position, score)| IndexSearchResult { id: self.ids[position].clone(), score: score as f64 })
+            .collect())
+    }
+    
+    fn to_bytes(&self) -> Vec<u8> {
+        let mut bytes = Vec::with_capacity(16 + self.vectors.len() * 4 + self.ids.iter().map(|id| 4 + id.len()).sum::<usize>());
+        bytes.extend_from_slice(INDEX_MAGIC);
+        bytes.extend_from_slice(&INDEX_VERSION.to_le_bytes());
+        bytes.extend_from_slice(&(self.dim as u32).to_le_bytes());
+        bytes.extend_from_slice(&(self.ids.len() as u32).to_le_bytes());
+        for id in &self.ids {
+            bytes.extend_from_slice(&(id.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(id.as_bytes());
+        }
+        for value in &self.vectors {
+            bytes.extend_from_slice(&value.to_le_bytes());
+        }
+        bytes
+    }
+    
+    fn from_bytes(bytes: &[u8]) -> ModelResult<Self> {
+        fn corrupt(what: &str) -> ModelError {
+            ModelError::InvalidInput(format!("Not a valid embedding index: {}", what))
+        }
+        fn take<'a>(bytes: &'a [u8], offset: &mut usize, len: usize) -> ModelResult<&'a [u8]> {
+            let slice = offset.checked_add(len)
+                .and_then(|end| bytes.get(*offset..end))
+                .ok_or_else(|| corrupt("truncated"))?;
+            *offset += len;
+            Ok(slice)
+        }
+        fn read_u32(bytes: &[u8], offset: &mut usize) -> ModelResult<u32> {
+            let slice = take(bytes, offset, 4)?;
+            Ok(u32::from_le_bytes([slice[0], slice[1], slice[2], slice[3]]))
+        }
+        
+        let mut offset = 0;
+        if take(bytes, &mut offset, 4)? != INDEX_MAGIC.as_slice() {
+            return Err(corrupt("bad magic"));
+        }
+        let version = read_u32(bytes, &mut offset)?;
+        if version != INDEX_VERSION {
+            return Err(corrupt(&format!("unsupported version {}", version)));
+        }
+        let dim = read_u32(bytes, &mut offset)? as usize;
+        let count = read_u32(bytes, &mut offset)? as usize;
+        // search splits the vectors into dim-sized chunks
+        if dim == 0 && count > 0 {
+            return Err(corrupt("zero dimension"));
+        }
+        
+        let mut index = EmbeddingIndex { dim, ..Default::default() };
+        for position in 0..count {
+            let len = read_u32(bytes, &mut offset)? as usize;
+            let id = std::str::from_utf8(take(bytes, &mut offset, len)?).map_err(|_| corrupt("id is not UTF-8"))?.to_string();
+            if index.positions.insert(id.clone(), position).is_some() {
+                return Err(corrupt(&format!("duplicate id {:?}", id)));
+            }
+            index.ids.push(id);
+        }
+        
+        let vector_len = dim.checked_mul(4)
+            .and_then(|row| count.checked_mul(row))
+            .ok_or_else(|| corrupt("too large"))?;
+        let vector_bytes = take(bytes, &mut offset, vector_len)?;
+        index.vectors = vector_bytes
+            .chunks_exact(4)
+            .map(|chunk| f32::from_le_bytes([chunk[0], chunk[1], chunk[2], chunk[3]]))
+            .collect();
+        Ok(index)
+    }
+}
+
+static EMBEDDING_INDEX: Lazy<Mutex<EmbeddingIndex>> = Lazy::new(|| Mutex::new(EmbeddingIndex::default()));
+
+#[napi(object)]
+pub struct IndexSearchResult {
+    pub id: String,
+    pub score: f64,
+}
+
+/// Add a vector to the in-memory index under `id` (replacing any vector with that id)
+/// The first vector fixes the index dimension; later ones must match
+#[napi]
+pub fn embedding_index_add(id: String, vector: Vec<f64>) -> Result<()> {
+    Ok(EMBEDDING_INDEX.lock().add(id, &vector)?)
+}
+
+/// The `k` indexed vectors most similar to `query` (dot product, so pass L2-normalized
+/// vectors as generate_embedding returns), best first
+#[napi]
+pub fn embedding_index_search(query: Vec<f64>, k: u32) -> Result<Vec<IndexSearchResult>> {
+    Ok(EMBEDDING_INDEX.lock().search(&query, k as usize)?)
+}
+
+/// Number of vectors in the index
+#[napi]
+pub fn embedding_index_len() -> u32 {
+    EMBEDDING_INDEX.lock().ids.len() as u32
+}
+
+/// Remove every vector (the next add sets a new dimension)
+#[napi]
+pub fn embedding_index_clear() {
+    *EMBEDDING_INDEX.lock() = EmbeddingIndex::default();
+}
+
+/// Write the index to `path` in a compact binary format (written to a temp file, then renamed)
+#[napi]
+pub fn embedding_index_save(path: String) -> Result<()> {
+    let bytes = EMBEDDING_INDEX.lock().to_bytes();
+    let tmp_path = format!("{}.tmp", path);
+    fs::write(&tmp_path, &bytes).map_err(io_err)?;
+    fs::rename(&tmp_path, &path).map_err(io_err)?;
+    println!("[Embedding] ✅ Saved index ({} bytes) to {}", bytes.len(), path);
+    Ok(())
 }
 
+/// Replace the index with one saved by embedding_index_save; returns the number of vectors
+#[napi]
+pub fn embedding_index_load(path: String) -> Result<u32> {
+    let bytes = fs::read(&path).map_err(io_err)?;
+    let index = EmbeddingIndex::from_bytes(&bytes)?;
+    let count = index.ids.len() as u32;
+    *EMBEDDING_INDEX.lock() = index;
+    println!("[Embedding] ✅ Loaded index with {} vectors from {}", count, path);
+    Ok(count)
+}
+
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_embedding_model_config_into_spec() {
+        let config = |pooling: Option<&str>, dimension: Option<u32>| EmbeddingModelConfig {
+            model_dir: None,
+            input_ids_name: None,
+            attention_mask_name: None,
+            token_type_ids_name: None,
+            use_token_type_ids: Some(false),
+            pooling: pooling.map(str::to_string),
+            dimension,
+            output_name: None,
+        };
+
+        let spec = config(Some("cls"), Some(384)).into_spec().unwrap();
+        assert_eq!(spec.pooling, Pooling::Cls);
+        assert_eq!(spec.dimension, Some(384));
+        assert_eq!(spec.input_ids_name, "input_ids");
+        assert_eq!(spec.use_token_type_ids, Some(false));
+
+        assert_eq!(config(None, None).into_spec().unwrap().pooling, Pooling::Mean);
+        assert!(config(Some("max"), None).into_spec().is_err());
+        assert!(config(None, Some(0)).into_spec().is_err());
+    }
+
+    #[test]
+    fn test_embedding_index_round_trip() {
+        let mut index = EmbeddingIndex::default();
+        index.add("a".to_string(), &[1.0, 0.0]).unwrap();
+        index.add("b".to_string(), &[0.0, 1.0]).unwrap();
+        index.add("c".to_string(), &[0.6, 0.8]).unwrap();
+        index.add("a".to_string(), &[0.8, 0.6]).unwrap();
+        assert!(index.add("d".to_string(), &[1.0, 0.0, 0.0]).is_err());
+
+        let loaded = EmbeddingIndex::from_bytes(&index.to_bytes()).unwrap();
+        let results = loaded.search(&[1.0, 0.0], 2).unwrap();
+        let ids: Vec<&str> = results.iter().map(|r| r.id.as_str()).collect();
+        assert_eq!(ids, vec!["a", "c"]);
+        assert!((results[0].score - 0.8).abs() < 1e-6);
+
+        assert!(EmbeddingIndex::from_bytes(&index.to_bytes()[..20]).is_err());
+        assert!(EmbeddingIndex::from_bytes(b"nope").is_err());
+    }
+
+    #[test]
+    fn test_embedding_index_rejects_inconsistent_files() {
+        let header = |dim: u32, count: u32| {
+            let mut bytes = INDEX_MAGIC.to_vec();
+            bytes.extend_from_slice(&INDEX_VERSION.to_le_bytes());
+            bytes.extend_from_slice(&dim.to_le_bytes());
+            bytes.extend_from_slice(&count.to_le_bytes());
+            bytes
+        };
+        let push_id = |bytes: &mut Vec<u8>, id: &str| {
+            bytes.extend_from_slice(&(id.len() as u32).to_le_bytes());
+            bytes.extend_from_slice(id.as_bytes());
+        };
+
+        let mut zero_dim = header(0, 1);
+        push_id(&mut zero_dim, "a");
+        assert!(EmbeddingIndex::from_bytes(&zero_dim).is_err());
+
+        let mut duplicate = header(1, 2);
+        push_id(&mut duplicate, "a");
+        push_id(&mut duplicate, "a");
+        duplicate.extend_from_slice(&1.0f32.to_le_bytes());
+        duplicate.extend_from_slice(&1.0f32.to_le_bytes());
+        assert!(EmbeddingIndex::from_bytes(&duplicate).is_err());
+
+        assert!(EmbeddingIndex::from_bytes(&header(0, 0)).is_ok());
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_reference_tokenization
+    #[test]
+    #[ignore]
+    fn test_reference_tokenization() {
+        let model_dir = get_model_dir();
+        if !check_model_files() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+
+        // bert-base-uncased ids, as produced by the HF reference tokenizer
+        let tokenizer = EmbeddingTokenizer::load(&model_dir).unwrap();
+        let (ids, mask, type_ids) = tokenizer.tokenize("Hello, world!", MAX_SEQUENCE_LENGTH).unwrap();
+        assert_eq!(&ids[..6], &[101, 7592, 1010, 2088, 999, 102]);
+        assert_eq!(mask.iter().sum::<i64>(), 6);
+        assert_eq!(ids.len(), MAX_SEQUENCE_LENGTH);
+        assert!(type_ids.iter().all(|&t| t == 0));
+
+        let mut model = EmbeddingModel::new(&model_dir).unwrap();
+        let embedding = model.generate_embedding("Hello, world!").unwrap();
+        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert_eq!(embedding.len(), EMBEDDING_DIM);
+        assert!((norm - 1.0).abs() < 1e-4);
+
+        // Paraphrases must land closer than unrelated text
+        let similar = model.generate_embedding("Hi there, world.").unwrap();
+        let unrelated = model.generate_embedding("Quarterly revenue grew eight percent.").unwrap();
+        let dot = |a: &[f32], b: &[f32]| a.iter().zip(b).map(|(x, y)| x * y).sum::<f32>();
+        assert!(dot(&embedding, &similar) > dot(&embedding, &unrelated));
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_long_embedding_windows
+    #[test]
+    #[ignore]
+    fn test_long_embedding_windows() {
+        if !check_model_files() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+        let mut model = EmbeddingModel::new(&get_model_dir()).unwrap();
+
+        // Short text: a single window identical to the regular path
+        let (short, windows) = model.generate_embedding_long("a short sentence", 128).unwrap();
+        assert_eq!(windows, 1);
+        let single = model.generate_embedding("a short sentence").unwrap();
+        for (a, b) in short.iter().zip(&single) {
+            assert!((a - b).abs() < 1e-4);
+        }
+
+        // 1200 content tokens with 510-token windows stepping by 382
+        let long = "word ".repeat(1200);
+        let (embedding, windows) = model.generate_embedding_long(&long, 128).unwrap();
+        assert_eq!(windows, 3);
+        let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+        assert!((norm - 1.0).abs() < 1e-4);
+
+        assert!(model.generate_embedding_long(&long, 510).is_err());
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_batch_matches_single
+    #[test]
+    #[ignore]
+    fn test_batch_matches_single() {
+        if !check_model_files() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+        let mut model = EmbeddingModel::new(&get_model_dir()).unwrap();
+
+        // More than one sub-batch, with very different lengths so padding differs per row
+        let long = "word ".repeat(300);
+        let texts: Vec<String> = (0..EMBEDDING_BATCH_SIZE + 3)
+            .map(|i| if i % 7 == 0 { long.clone() } else { format!("transcript line number {}", i) })
+            .collect();
+        let refs: Vec<&str> = texts.iter().map(|t| t.as_str()).collect();
+
+        let batched = model.generate_embeddings(&refs).unwrap();
+        assert_eq!(batched.len(), texts.len());
+        for (text, batch_embedding) in texts.iter().zip(&batched) {
+            let single = model.generate_embedding(text).unwrap();
+            for (a, b) in single.iter().zip(batch_embedding) {
+                assert!((a - b).abs() < 1e-4, "batched embedding differs for {:?}", text);
+            }
+        }
+    }
+}
@@ -4,15 +4,20 @@
 #![allow(unexpected_cfgs)]
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ErrorStrategy, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 use parking_lot::Mutex;
 use std::sync::Arc;
 
 mod window;
 mod audio;
+mod audio_encode;
 mod parakeet;
 mod llm;
 mod embedding;
+mod model_error;
+mod download;
+mod settings;
 
 #[cfg(target_os = "macos")]
 mod macos;
@@ -27,6 +32,34 @@ pub use parakeet::*;
 pub use llm::*;
 pub use embedding::*;
 
+/// Lifecycle of a loadable engine (Parakeet, embeddings, LLM)
+#[derive(Debug, Clone)]
+pub(crate) enum EngineState {
+    Unloaded,
+    Loading,
+    Ready,
+    Error(String),
+}
+
+impl EngineState {
+    pub(crate) fn to_status(&self) -> EngineStatus {
+        let (state, error) = match self {
+            EngineState::Unloaded => ("unloaded", None),
+            EngineState::Loading => ("loading", None),
+            EngineState::Ready => ("ready", None),
+            EngineState::Error(e) => ("error", Some(e.clone())),
+        };
+        EngineStatus { state: state.to_string(), error }
+    }
+}
+
+/// Engine state: "unloaded" | "loading" | "ready" | "error" (with the last error message)
+#[napi(object)]
+pub struct EngineStatus {
+    pub state: String,
+    pub error: Option<String>,
+}
+
 /// Window information returned from native APIs
 #[napi(object)]
 pub struct WindowInfo {
@@ -35,44 +68,405 @@ pub struct WindowInfo {
     pub owner_name: String,
     pub title: String,
     pub bundle_id: Option<String>,
+    /// Heuristic key (pid + bundle id + normalized title) that survives window id changes
+    /// Not guaranteed unique across windows of the same app with identical titles
+    pub stable_key: String,
+    /// Known meeting app (Zoom, Teams, Webex...) or a browser showing a meeting URL
+    pub is_meeting_app: bool,
+    /// Minimized or otherwise not visible (off screen / fully transparent on macOS)
+    pub is_minimized: bool,
+    /// The window with keyboard focus in the frontmost app
+    pub is_focused: bool,
+    /// Stacking position in get_active_windows, 0 = topmost (overlays and always-on-top
+    /// windows included); -1 for windows looked up on their own
+    pub z_order: i32,
+}
+
+/// Display name, bundle id and icon of a running application
+#[napi(object)]
+pub struct AppInfo {
+    /// Localized app name (macOS) or executable name (Windows)
+    pub name: String,
+    pub bundle_id: Option<String>,
+    /// 64x64 PNG of the app icon, when one could be rendered
+    pub icon_png: Option<Buffer>,
+}
+
+/// Combined guess at whether a meeting is live, from detect_meeting_state
+#[napi(object)]
+pub struct MeetingState {
+    /// Confidence reached the meeting threshold (needs the mic plus a meeting app or URL)
+    pub active: bool,
+    /// Owner name of the meeting window, if one was found
+    pub app: Option<String>,
+    /// 0.0 - 1.0
+    pub confidence: f64,
+    /// Meeting URL when the meeting is in a browser tab
+    pub url: Option<String>,
+}
+
+/// Active tab of a browser window
+#[napi(object)]
+pub struct BrowserTabInfo {
+    pub url: String,
+    /// Active tab/page title, when the browser exposes one
+    pub title: Option<String>,
+}
+
+/// Currently playing media reported by the OS
+#[napi(object)]
+pub struct NowPlayingInfo {
+    pub app: Option<String>,
+    pub title: String,
+    pub artist: Option<String>,
 }
 
 /// Audio capture configuration
 #[napi(object)]
 pub struct AudioCaptureOptions {
+    /// System audio rate of the saved recording (default 48000)
+    /// macOS: 8000, 16000, 24000 or 48000; Windows: 8000 - 192000
     pub sample_rate: Option<u32>,
+    /// System audio channels, 1 or 2 (default 2)
     pub channels: Option<u32>,
+    /// Where to write the recording; its directory must exist and be writable
+    /// (default `<data dir>/ghost/recordings/ghost_recording_<timestamp>.wav`, owner-only)
     pub output_path: Option<String>,
     pub include_microphone: Option<bool>,
+    /// Also write `<output>_system.wav` and `<output>_mic.wav` as mono tracks at their native rates (macOS)
+    pub separate_tracks: Option<bool>,
+    /// Max streaming chunks (~100ms each) kept for get_audio_chunks before the oldest are dropped (default 200)
+    pub max_queued_chunks: Option<u32>,
+    /// Saved WAV layout: "stereo-split" (L=system, R=mic, default) or "mono-mix" (macOS)
+    pub wav_layout: Option<String>,
+    /// Microphone device id from list_input_devices (default input device if unset or missing)
+    pub input_device_id: Option<String>,
+    /// Saved WAV bit depth: 16 (PCM, default), 24 (PCM) or 32 (IEEE float)
+    pub wav_bit_depth: Option<u32>,
+    /// Main recording format: "wav" (default), "mp3" or "opus" (the latter two need the
+    /// matching cargo feature); the returned path gets the format's extension
+    pub output_format: Option<String>,
+    /// Keep a copy of the last N seconds of streaming chunks for flush_retained_chunks (default 0 = off)
+    pub retain_chunks_secs: Option<u32>,
+    /// Display id from list_displays to anchor the ScreenCaptureKit filter to (default: main display) (macOS)
+    /// Audio is display-agnostic; this only matters when the default display misbehaves
+    pub display_id: Option<u32>,
+    /// Write the recording to disk on stop (default true); false for streaming-only use,
+    /// in which case stop returns an empty path and output_path is never touched
+    pub save_wav: Option<bool>,
+    /// Cut leading and trailing silence longer than a few seconds from the saved recording
+    /// (default false); streaming chunks and separate tracks stay full length
+    pub trim_silence: Option<bool>,
+}
+
+/// A display ScreenCaptureKit can capture
+#[napi(object)]
+pub struct DisplayInfo {
+    /// CGDirectDisplayID
+    pub id: u32,
+    pub name: String,
+    pub is_main: bool,
+    /// Bounds in global display coordinates (points)
+    pub x: f64,
+    pub y: f64,
+    pub width: f64,
+    pub height: f64,
+}
+
+/// Audio input device
+#[napi(object)]
+pub struct AudioDeviceInfo {
+    /// Stable device UID
+    pub id: String,
+    pub name: String,
+    pub is_default: bool,
+}
+
+/// An application capturing from the microphone
+#[napi(object)]
+pub struct MicUser {
+    pub pid: i32,
+    pub app_name: String,
+    pub bundle_id: Option<String>,
+}
+
+/// Live format of the running capture (all zeros when not capturing)
+#[napi(object)]
+#[derive(Default)]
+pub struct CaptureFormat {
+    /// Native rate of the system audio stream (0 until the first buffer arrives)
+    pub system_sample_rate: u32,
+    pub system_channels: u32,
+    /// Native rate of the microphone (0 if the mic isn't being captured)
+    pub mic_sample_rate: u32,
+    /// Rate of the stereo PCM16 chunks returned by get_audio_chunks
+    pub stream_sample_rate: u32,
+}
+
+/// Files produced by a capture
+#[napi(object)]
+pub struct CaptureOutput {
+    /// Main recording (stereo L=system, R=mic, or mono mix depending on wav_layout)
+    pub path: String,
+    pub system_track_path: Option<String>,
+    pub mic_track_path: Option<String>,
+    /// Seconds cut from the start by trim_silence; subtract from capture-relative timestamps
+    pub trimmed_start_secs: f64,
+    /// Seconds cut from the end by trim_silence
+    pub trimmed_end_secs: f64,
+    /// Length of the saved recording (0 when nothing was saved)
+    pub duration_secs: f64,
 }
 
 // Global state for audio capture
 static AUDIO_ENGINE: Mutex<Option<AudioCaptureState>> = Mutex::new(None);
 
+// Set (under the AUDIO_ENGINE lock) while start_audio_capture is setting up, so a
+// second start can't slip in between the "already capturing" check and storing the state
+static CAPTURE_STARTING: std::sync::atomic::AtomicBool = std::sync::atomic::AtomicBool::new(false);
+
+/// Clears CAPTURE_STARTING when start_audio_capture returns, fails, or is dropped
+struct CaptureStartGuard;
+
+impl Drop for CaptureStartGuard {
+    fn drop(&mut self) {
+        CAPTURE_STARTING.store(false, std::sync::atomic::Ordering::SeqCst);
+    }
+}
+
+// JS callback fired whenever a capture ends, with (reason, output_path)
+static CAPTURE_STOPPED_CALLBACK: Mutex<Option<ThreadsafeFunction<(String, String), ErrorStrategy::Fatal>>> = Mutex::new(None);
+
+// JS callback fired with the new frontmost WindowInfo on app/window switches
+static ACTIVE_WINDOW_CALLBACK: Mutex<Option<ThreadsafeFunction<WindowInfo, ErrorStrategy::Fatal>>> = Mutex::new(None);
+
+// JS callback fired once screen recording permission is granted
+static SCREEN_RECORDING_GRANTED_CALLBACK: Mutex<Option<ThreadsafeFunction<(), ErrorStrategy::Fatal>>> = Mutex::new(None);
+
+// JS callback fired with the new state whenever microphone usage changes
+static MIC_WATCH_CALLBACK: Mutex<Option<ThreadsafeFunction<bool, ErrorStrategy::Fatal>>> = Mutex::new(None);
+
+/// Store a newly registered JS callback in its slot. The previous tsfn is leaked rather than
+/// dropped: releasing it crashes under Electron (see llm_chat_stream)
+pub(crate) fn replace_tsfn<T: 'static>(
+    slot: &Mutex<Option<ThreadsafeFunction<T, ErrorStrategy::Fatal>>>,
+    tsfn: ThreadsafeFunction<T, ErrorStrategy::Fatal>,
+) {
+    if let Some(previous) = slot.lock().replace(tsfn) {
+        std::mem::forget(previous);
+    }
+}
+
 struct AudioCaptureState {
     is_capturing: bool,
     start_time: std::time::Instant,
+    paused_at: Option<std::time::Instant>,
+    paused_total: std::time::Duration,
     output_path: String,
+    output_format: audio_encode::OutputFormat,
+    save_wav: bool,
     #[cfg(target_os = "macos")]
     stream_handle: Option<macos::audio::AudioStreamHandle>,
 }
 
-/// Get all visible windows on the system
+/// Get all windows on the system, minimized ones included with is_minimized set
+/// Sorted with the focused window first, then by stacking order (front to back), pid and window id
 #[napi]
 pub fn get_active_windows() -> Vec<WindowInfo> {
+    #[cfg(target_os = "macos")]
+    let mut windows = macos::window::get_windows();
+    
+    #[cfg(target_os = "windows")]
+    let mut windows = windows_impl::window::get_windows();
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let mut windows: Vec<WindowInfo> = vec![];
+    
+    window::sort_windows(&mut windows);
+    window::mark_meeting_browser_windows(&mut windows);
+    windows
+}
+
+/// Get the window the user is currently looking at (focused window of the frontmost app)
+#[napi]
+pub fn get_frontmost_window() -> Option<WindowInfo> {
+    #[cfg(target_os = "macos")]
+    let window = macos::window::get_frontmost_window();
+    
+    #[cfg(target_os = "windows")]
+    let window = windows_impl::window::get_frontmost_window();
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let window: Option<WindowInfo> = None;
+    
+    window.map(|mut window| {
+        window::mark_meeting_browser_windows(std::slice::from_mut(&mut window));
+        window
+    })
+}
+
+/// Fresh info for one window by the windowId from get_active_windows, or null if it has closed
+#[napi]
+pub fn get_window_by_id(window_id: i32) -> Option<WindowInfo> {
+    #[cfg(target_os = "macos")]
+    let window = macos::window::get_window_by_id(window_id);
+    
+    #[cfg(target_os = "windows")]
+    let window = windows_impl::window::get_window_by_id(window_id);
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let window: Option<WindowInfo> = {
+        let _ = window_id;
+        None
+    };
+    
+    window.map(|mut window| {
+        window::mark_meeting_browser_windows(std::slice::from_mut(&mut window));
+        window
+    })
+}
+
+/// Name, bundle id and icon for a pid from WindowInfo or get_microphone_users, or null if
+/// no such process is running
+#[napi]
+pub fn get_app_info(pid: i32) -> Option<AppInfo> {
     #[cfg(target_os = "macos")]
     {
-        macos::window::get_windows()
+        macos::window::get_app_info(pid)
     }
     
     #[cfg(target_os = "windows")]
     {
-        windows_impl::window::get_windows()
+        windows_impl::window::get_app_info(pid)
     }
     
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     {
-        vec![]
+        let _ = pid;
+        None
+    }
+}
+
+/// Get the most likely meeting window (Zoom, Teams, Webex, or a browser meeting tab)
+#[napi]
+pub fn get_active_meeting() -> Option<WindowInfo> {
+    window::pick_active_meeting(get_active_windows())
+}
+
+/// Decide whether a meeting is live from the mic being in use, a meeting app window
+/// and a meeting URL in a browser, in one call instead of stitching them together in JS
+#[napi]
+pub fn detect_meeting_state() -> MeetingState {
+    window::detect_meeting_state()
+}
+
+/// Replace the list of native meeting apps used for WindowInfo.isMeetingApp
+/// Entries are bundle ids on macOS and process names (e.g. "Zoom.exe") on Windows
+#[napi]
+pub fn set_meeting_app_bundles(bundles: Vec<String>) {
+    window::set_meeting_apps(bundles);
+}
+
+/// Call callback(windowInfo) whenever the user switches to another app or window
+/// Fires once immediately with the current frontmost window. On macOS, window
+/// switches within an app need accessibility permission; app switches don't
+#[napi]
+pub fn watch_active_window(callback: JsFunction) -> Result<()> {
+    let tsfn: ThreadsafeFunction<WindowInfo, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    
+    replace_tsfn(&ACTIVE_WINDOW_CALLBACK, tsfn);
+    
+    let handler: window::WindowChangeHandler = Arc::new(|info| {
+        if let Some(tsfn) = ACTIVE_WINDOW_CALLBACK.lock().as_ref() {
+            tsfn.call(info, ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    });
+    
+    #[cfg(target_os = "macos")]
+    let result = macos::window::watch_active_window(handler);
+    
+    #[cfg(target_os = "windows")]
+    let result = windows_impl::window::watch_active_window(handler);
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let result: std::result::Result<(), String> = {
+        let _ = handler;
+        Err("Window watching is not supported on this platform".to_string())
+    };
+    
+    result.map_err(Error::from_reason)
+}
+
+/// Stop the watch started by watch_active_window
+/// Returns false if no watch was active
+#[napi]
+pub fn unwatch_active_window() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::window::unwatch_active_window()
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::window::unwatch_active_window()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        false
+    }
+}
+
+/// Permission state, distinguishing "never asked" (prompt) from "denied" (send to Settings)
+#[napi(string_enum)]
+pub enum PermissionStatus {
+    Granted,
+    Denied,
+    NotDetermined,
+    /// Blocked by policy (e.g. MDM); the user can't change it
+    Restricted,
+}
+
+/// Accessibility permission status (macOS; always Granted elsewhere)
+#[napi]
+pub fn accessibility_permission_status() -> PermissionStatus {
+    #[cfg(target_os = "macos")]
+    {
+        macos::permissions::accessibility_status()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        PermissionStatus::Granted
+    }
+}
+
+/// Screen recording permission status (macOS; always Granted elsewhere)
+#[napi]
+pub fn screen_recording_permission_status() -> PermissionStatus {
+    #[cfg(target_os = "macos")]
+    {
+        macos::permissions::screen_recording_status()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        PermissionStatus::Granted
+    }
+}
+
+/// Microphone permission status (macOS; always Granted elsewhere)
+#[napi]
+pub fn microphone_permission_status() -> PermissionStatus {
+    #[cfg(target_os = "macos")]
+    {
+        macos::permissions::microphone_status()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        PermissionStatus::Granted
     }
 }
 
@@ -132,6 +526,67 @@ pub fn request_screen_recording_permission() -> bool {
     }
 }
 
+/// Check if microphone permission is granted (macOS)
+#[napi]
+pub fn check_microphone_permission() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::permissions::check_microphone()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        true
+    }
+}
+
+/// Request microphone permission (macOS) - shows the system prompt if not yet asked
+/// Resolves to whether access is granted
+#[napi]
+pub async fn request_microphone_permission() -> Result<bool> {
+    #[cfg(target_os = "macos")]
+    {
+        Ok(macos::permissions::request_microphone().await)
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        Ok(true)
+    }
+}
+
+/// Call callback() once screen recording permission is granted, then stop watching
+/// Use after request_screen_recording_permission sends the user to System Settings
+#[napi]
+pub fn watch_screen_recording_permission(callback: JsFunction) -> Result<()> {
+    let tsfn: ThreadsafeFunction<(), ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |_ctx| Ok(Vec::<()>::new()))?;
+    
+    replace_tsfn(&SCREEN_RECORDING_GRANTED_CALLBACK, tsfn);
+    
+    let on_granted = || {
+        if let Some(tsfn) = SCREEN_RECORDING_GRANTED_CALLBACK.lock().as_ref() {
+            tsfn.call((), ThreadsafeFunctionCallMode::NonBlocking);
+        }
+    };
+    
+    #[cfg(target_os = "macos")]
+    macos::permissions::watch_screen_recording(Box::new(on_granted));
+    
+    // Always granted elsewhere
+    #[cfg(not(target_os = "macos"))]
+    on_granted();
+    
+    Ok(())
+}
+
+/// Stop the watcher started by watch_screen_recording_permission
+#[napi]
+pub fn stop_watching_screen_recording_permission() {
+    #[cfg(target_os = "macos")]
+    macos::permissions::stop_watching_screen_recording();
+}
+
 /// Trigger ScreenCaptureKit to add app to Screen Recording permissions list
 /// This ensures the app appears in System Settings for the user to enable
 #[napi]
@@ -162,105 +617,500 @@ pub fn get_browser_url(pid: i32) -> Option<String> {
     }
 }
 
-/// Start capturing audio from a specific process
+/// Get the URLs of all of a browser's windows, not just the focused one, front to back and
+/// de-duplicated (requires accessibility permission). Finds meetings in background windows
 #[napi]
-pub async fn start_audio_capture(pid: i32, options: Option<AudioCaptureOptions>) -> Result<()> {
-    let opts = options.unwrap_or(AudioCaptureOptions {
-        sample_rate: Some(48000),
-        channels: Some(2),
-        output_path: None,
-        include_microphone: Some(true),
-    });
-    
-    let output_path = opts.output_path.unwrap_or_else(|| {
-        let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
-        format!("/tmp/ghost_recording_{}.wav", timestamp)
-    });
-    
-    // Check if already capturing
-    {
-        let state = AUDIO_ENGINE.lock();
-        if state.is_some() && state.as_ref().unwrap().is_capturing {
-            return Err(Error::from_reason("Already capturing audio"));
-        }
-    }
-    
+pub fn get_browser_urls(pid: i32) -> Vec<String> {
     #[cfg(target_os = "macos")]
     {
-        let stream_handle = macos::audio::start_capture(
-            pid,
-            opts.sample_rate.unwrap_or(48000),
-            opts.channels.unwrap_or(2),
-            &output_path,
-            opts.include_microphone.unwrap_or(true),
-        ).await.map_err(|e| Error::from_reason(format!("Failed to start capture: {}", e)))?;
-        
-        let mut state = AUDIO_ENGINE.lock();
-        *state = Some(AudioCaptureState {
-            is_capturing: true,
-            start_time: std::time::Instant::now(),
-            output_path,
-            stream_handle: Some(stream_handle),
-        });
+        macos::accessibility::get_browser_urls(pid)
     }
     
     #[cfg(target_os = "windows")]
     {
-        windows_impl::audio::start_capture(
-            pid,
-            opts.sample_rate.unwrap_or(48000),
-            opts.channels.unwrap_or(2),
-            &output_path,
-            opts.include_microphone.unwrap_or(true),
-        ).map_err(|e| Error::from_reason(format!("Failed to start capture: {}", e)))?;
-        
-        let mut state = AUDIO_ENGINE.lock();
-        *state = Some(AudioCaptureState {
-            is_capturing: true,
-            start_time: std::time::Instant::now(),
-            output_path,
-        });
+        windows_impl::accessibility::get_browser_urls(pid)
     }
     
     #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     {
         let _ = pid;
-        return Err(Error::from_reason("Unsupported platform"));
+        vec![]
     }
-    
-    Ok(())
 }
 
-/// Stop capturing audio and return the path to the recorded file
+/// Get the URL the user is looking at: the focused tab of the frontmost app, or null when that
+/// app isn't a known browser (requires accessibility permission)
 #[napi]
-pub async fn stop_audio_capture() -> Result<String> {
-    let capture_state = {
-        let mut state = AUDIO_ENGINE.lock();
-        state.take().ok_or_else(|| {
-            Error::from_reason("No active capture")
-        })?
-    };
+pub fn get_frontmost_browser_url() -> Option<String> {
+    #[cfg(target_os = "macos")]
+    let front = macos::window::get_frontmost_app_pid()
+        .map(|pid| (pid, macos::window::get_bundle_id_for_pid(pid), String::new()));
     
-    if !capture_state.is_capturing {
-        return Err(Error::from_reason("Not capturing"));
-    }
+    #[cfg(target_os = "windows")]
+    let front = windows_impl::window::get_frontmost_window()
+        .map(|window| (window.pid, window.bundle_id, window.owner_name));
     
-    #[cfg(target_os = "macos")]
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let front: Option<(i32, Option<String>, String)> = None;
+    
+    let (pid, bundle_id, owner_name) = front?;
+    if !window::is_browser_app(bundle_id.as_deref(), &owner_name) {
+        return None;
+    }
+    
+    get_browser_url(pid)
+}
+
+/// Get the URL and page title of a browser's focused window (requires accessibility permission)
+#[napi]
+pub fn get_browser_tab_info(pid: i32) -> Option<BrowserTabInfo> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::accessibility::get_browser_tab_info(pid)
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::accessibility::get_browser_tab_info(pid)
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let _ = pid;
+        None
+    }
+}
+
+/// Get the currently playing media (macOS MediaRemote)
+/// Returns null when nothing is playing or the API is unavailable on this OS version
+#[napi]
+pub fn get_now_playing() -> Option<NowPlayingInfo> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::now_playing::get_now_playing()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
+/// `<data dir>/ghost/recordings/ghost_recording_<timestamp>.wav`, creating the directory if needed
+/// Kept out of /tmp so other local users can't read meeting audio
+fn default_recording_path() -> Result<String> {
+    let dir = dirs::data_dir()
+        .unwrap_or_else(|| std::path::PathBuf::from("."))
+        .join("ghost")
+        .join("recordings");
+    std::fs::create_dir_all(&dir)
+        .map_err(|e| Error::from_reason(format!("Can't create recordings directory {}: {}", dir.display(), e)))?;
+    
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::PermissionsExt;
+        let _ = std::fs::set_permissions(&dir, std::fs::Permissions::from_mode(0o700));
+    }
+    
+    let timestamp = chrono::Local::now().format("%Y%m%d_%H%M%S");
+    Ok(dir.join(format!("ghost_recording_{}.wav", timestamp)).to_string_lossy().into_owned())
+}
+
+/// Reject options the platform can't honor before any stream is started
+/// The output path is only checked when the recording will be saved
+fn validate_capture_options(opts: &AudioCaptureOptions, output_path: &str) -> Result<()> {
+    if let Some(rate) = opts.sample_rate {
+        #[cfg(target_os = "macos")]
+        let (supported, expected) = (macos::audio::SUPPORTED_SAMPLE_RATES.contains(&rate), "8000, 16000, 24000 or 48000");
+        
+        // Windows resamples the recording to any rate
+        #[cfg(not(target_os = "macos"))]
+        let (supported, expected) = ((8000..=192000).contains(&rate), "8000 - 192000");
+        
+        if !supported {
+            return Err(Error::from_reason(format!("Unsupported sample_rate {}, expected {}", rate, expected)));
+        }
+    }
+    
+    if let Some(channels) = opts.channels {
+        if !(1..=2).contains(&channels) {
+            return Err(Error::from_reason(format!("Unsupported channels {}, expected 1 or 2", channels)));
+        }
+    }
+    
+    if !opts.save_wav.unwrap_or(true) {
+        return Ok(());
+    }
+    
+    let path = std::path::Path::new(output_path);
+    if output_path.trim().is_empty() || path.is_dir() {
+        return Err(Error::from_reason(format!("Invalid output_path '{}', expected a file path", output_path)));
+    }
+    let dir = match path.parent() {
+        Some(dir) if !dir.as_os_str().is_empty() => dir,
+        _ => std::path::Path::new("."),
+    };
+    if !dir.is_dir() {
+        return Err(Error::from_reason(format!("Output directory {} doesn't exist", dir.display())));
+    }
+    
+    // Only an actual open proves the directory is writable (permissions, read-only volumes, sandboxing)
+    let existed = path.exists();
+    std::fs::OpenOptions::new()
+        .append(true)
+        .create(true)
+        .open(path)
+        .map_err(|e| Error::from_reason(format!("Can't write to {}: {}", output_path, e)))?;
+    if !existed {
+        let _ = std::fs::remove_file(path);
+    }
+    Ok(())
+}
+
+/// Start capturing audio from a specific process (pid 0 = all system audio)
+/// Invalid options (unsupported rate or channels, unwritable output path) are rejected up front
+#[napi]
+pub async fn start_audio_capture(pid: i32, options: Option<AudioCaptureOptions>) -> Result<()> {
+    let opts = options.unwrap_or(AudioCaptureOptions {
+        sample_rate: Some(48000),
+        channels: Some(2),
+        output_path: None,
+        include_microphone: Some(true),
+        separate_tracks: None,
+        max_queued_chunks: None,
+        wav_layout: None,
+        input_device_id: None,
+        wav_bit_depth: None,
+        output_format: None,
+        retain_chunks_secs: None,
+        display_id: None,
+        save_wav: None,
+        trim_silence: None,
+    });
+    let save_wav = opts.save_wav.unwrap_or(true);
+    
+    let output_path = match opts.output_path.clone() {
+        Some(path) => path,
+        None if save_wav => default_recording_path()?,
+        None => String::new(),
+    };
+    validate_capture_options(&opts, &output_path)?;
+    
+    // Check if already capturing (or starting) and reserve the engine atomically
+    let _start_guard = {
+        let state = AUDIO_ENGINE.lock();
+        if state.is_some() && state.as_ref().unwrap().is_capturing {
+            return Err(Error::from_reason("Already capturing audio"));
+        }
+        if CAPTURE_STARTING.swap(true, std::sync::atomic::Ordering::SeqCst) {
+            return Err(Error::from_reason("Audio capture is already starting"));
+        }
+        CaptureStartGuard
+    };
+    
+    let sample_format = match opts.wav_bit_depth {
+        None => audio::WavSampleFormat::Pcm16,
+        Some(bits) => audio::WavSampleFormat::from_bit_depth(bits).ok_or_else(|| {
+            Error::from_reason(format!("Unsupported wav_bit_depth {}, expected 16, 24 or 32", bits))
+        })?,
+    };
+    
+    let output_format = match opts.output_format.as_deref() {
+        None => audio_encode::OutputFormat::Wav,
+        Some(value) => audio_encode::OutputFormat::parse(value).ok_or_else(|| {
+            Error::from_reason(format!("Unknown output_format '{}', expected \"wav\", \"mp3\" or \"opus\"", value))
+        })?,
+    };
+    if !output_format.is_available() {
+        return Err(Error::from_reason(format!(
+            "{} output is not available in this build (enable the \"{}\" cargo feature)",
+            output_format.extension(),
+            output_format.extension()
+        )));
+    }
+    
+    audio::set_chunk_retention(opts.retain_chunks_secs.unwrap_or(0));
+    audio::clear_mono_chunks();
+    
+    #[cfg(target_os = "macos")]
+    {
+        // A denied mic doesn't error in AVFoundation, it just records silence
+        if opts.include_microphone.unwrap_or(true)
+            && matches!(
+                macos::permissions::microphone_status(),
+                PermissionStatus::Denied | PermissionStatus::Restricted
+            )
+        {
+            return Err(Error::from_reason(
+                "Microphone permission denied - enable it in System Settings > Privacy & Security > Microphone, or pass includeMicrophone: false",
+            ));
+        }
+        
+        let wav_layout = match opts.wav_layout.as_deref() {
+            None => macos::audio::WavLayout::StereoSplit,
+            Some(value) => macos::audio::WavLayout::parse(value).ok_or_else(|| {
+                Error::from_reason(format!("Unknown wav_layout '{}', expected \"stereo-split\" or \"mono-mix\"", value))
+            })?,
+        };
+        
+        macos::audio::set_max_queued_chunks(
+            opts.max_queued_chunks.map(u64::from).unwrap_or(macos::audio::DEFAULT_MAX_QUEUED_CHUNKS),
+        );
+        
+        let stream_handle = macos::audio::start_capture(
+            pid,
+            opts.sample_rate.unwrap_or(48000),
+            opts.channels.unwrap_or(2),
+            &output_path,
+            opts.include_microphone.unwrap_or(true),
+            opts.separate_tracks.unwrap_or(false),
+            wav_layout,
+            sample_format,
+            opts.input_device_id.clone(),
+            opts.display_id,
+            save_wav,
+            opts.trim_silence.unwrap_or(false),
+        ).await.map_err(|e| Error::from_reason(format!("Failed to start capture: {}", e)))?;
+        
+        let mut state = AUDIO_ENGINE.lock();
+        *state = Some(AudioCaptureState {
+            is_capturing: true,
+            start_time: std::time::Instant::now(),
+            paused_at: None,
+            paused_total: std::time::Duration::ZERO,
+            output_path,
+            output_format,
+            save_wav,
+            stream_handle: Some(stream_handle),
+        });
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::start_capture(
+            pid,
+            opts.sample_rate.unwrap_or(48000),
+            opts.channels.unwrap_or(2),
+            &output_path,
+            opts.include_microphone.unwrap_or(true),
+            sample_format,
+            save_wav,
+            opts.trim_silence.unwrap_or(false),
+        ).map_err(|e| Error::from_reason(format!("Failed to start capture: {}", e)))?;
+        
+        let mut state = AUDIO_ENGINE.lock();
+        *state = Some(AudioCaptureState {
+            is_capturing: true,
+            start_time: std::time::Instant::now(),
+            paused_at: None,
+            paused_total: std::time::Duration::ZERO,
+            output_path,
+            output_format,
+            save_wav,
+        });
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        let _ = (pid, sample_format);
+        return Err(Error::from_reason("Unsupported platform"));
+    }
+    
+    Ok(())
+}
+
+/// Stop capturing audio and return the path to the recorded file
+/// Returns an empty string when the capture was started with save_wav: false
+#[napi]
+pub async fn stop_audio_capture() -> Result<String> {
+    stop_audio_capture_with_tracks().await.map(|output| output.path)
+}
+
+/// Stop capturing audio and return the stereo mix path plus any separate track paths
+#[napi]
+pub async fn stop_audio_capture_with_tracks() -> Result<CaptureOutput> {
+    let capture_state = {
+        let mut state = AUDIO_ENGINE.lock();
+        state.take().ok_or_else(|| {
+            Error::from_reason("No active capture")
+        })?
+    };
+    
+    if !capture_state.is_capturing {
+        return Err(Error::from_reason("Not capturing"));
+    }
+    
+    let mut output = CaptureOutput {
+        path: capture_state.output_path.clone(),
+        system_track_path: None,
+        mic_track_path: None,
+        trimmed_start_secs: 0.0,
+        trimmed_end_secs: 0.0,
+        duration_secs: 0.0,
+    };
+    
+    #[cfg(target_os = "macos")]
     if let Some(handle) = capture_state.stream_handle {
-        macos::audio::stop_capture(handle).await
-            .map_err(|e| Error::from_reason(format!("Failed to stop capture: {}", e)))?;
+        match macos::audio::stop_capture(handle).await {
+            Ok(files) => {
+                output.system_track_path = files.system_track_path;
+                output.mic_track_path = files.mic_track_path;
+                output.trimmed_start_secs = files.trim.leading_secs;
+                output.trimmed_end_secs = files.trim.trailing_secs;
+                output.duration_secs = files.trim.duration_secs;
+            }
+            Err(e) => {
+                notify_capture_stopped("error", &capture_state.output_path);
+                return Err(Error::from_reason(format!("Failed to stop capture: {}", e)));
+            }
+        }
     }
     
     #[cfg(target_os = "windows")]
+    match windows_impl::audio::stop_capture() {
+        Ok(trim) => {
+            output.trimmed_start_secs = trim.leading_secs;
+            output.trimmed_end_secs = trim.trailing_secs;
+            output.duration_secs = trim.duration_secs;
+        }
+        Err(e) => {
+            notify_capture_stopped("error", &capture_state.output_path);
+            return Err(Error::from_reason(format!("Failed to stop capture: {}", e)));
+        }
+    }
+    
+    if !capture_state.save_wav {
+        output.path = String::new();
+        notify_capture_stopped("user", &output.path);
+        return Ok(output);
+    }
+    
+    if capture_state.output_format != audio_encode::OutputFormat::Wav {
+        let wav_path = capture_state.output_path.clone();
+        let format = capture_state.output_format;
+        let encoded = tokio::task::spawn_blocking(move || audio_encode::encode_wav_file(&wav_path, format))
+            .await
+            .map_err(|e| Error::from_reason(format!("Encoding task failed: {}", e)))?;
+        match encoded {
+            Ok(path) => output.path = path,
+            Err(e) => {
+                // The WAV is kept, so the recording isn't lost
+                notify_capture_stopped("error", &capture_state.output_path);
+                return Err(Error::from_reason(format!("Failed to encode recording: {}", e)));
+            }
+        }
+    }
+    
+    notify_capture_stopped("user", &output.path);
+    Ok(output)
+}
+
+/// Register a callback invoked whenever a capture ends
+/// Called as callback(reason, outputPath) where reason is "user" after a normal stop, or
+/// "error" when stopping or encoding failed (outputPath is then the raw WAV, if any) or the
+/// stream stopped on its own. In that last case call stop_audio_capture to write what was
+/// recorded up to then
+#[napi]
+pub fn set_capture_stopped_callback(callback: JsFunction) -> Result<()> {
+    let tsfn: ThreadsafeFunction<(String, String), ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx| {
+            let (reason, output_path): (String, String) = ctx.value;
+            Ok(vec![reason, output_path])
+        })?;
+    
+    replace_tsfn(&CAPTURE_STOPPED_CALLBACK, tsfn);
+    
+    Ok(())
+}
+
+/// Report a capture whose stream stopped on its own (device or display gone, permission
+/// revoked). The engine stays reserved so stop_audio_capture can still write the recording
+pub(crate) fn notify_capture_interrupted() {
+    let output_path = AUDIO_ENGINE.lock().as_ref().map(|s| s.output_path.clone()).unwrap_or_default();
+    notify_capture_stopped("error", &output_path);
+}
+
+/// Notify JS that capture has ended
+pub(crate) fn notify_capture_stopped(reason: &str, output_path: &str) {
+    println!("[Audio] Capture stopped ({}): {}", reason, output_path);
+    
+    if let Some(tsfn) = CAPTURE_STOPPED_CALLBACK.lock().as_ref() {
+        tsfn.call(
+            (reason.to_string(), output_path.to_string()),
+            ThreadsafeFunctionCallMode::NonBlocking,
+        );
+    }
+}
+
+/// Concatenate WAV recordings into one file, inserting gaps_secs[i] of silence
+/// between part i and i+1. Parts are resampled to the first file's sample rate;
+/// all parts must have the same channel count
+#[napi]
+pub fn concat_wavs(paths: Vec<String>, output: String, gaps_secs: Vec<f64>) -> Result<()> {
+    audio::concat_wav_files(&paths, &output, &gaps_secs)
+        .map_err(|e| Error::from_reason(format!("Failed to concatenate recordings: {}", e)))
+}
+
+/// Pause capture without finalizing the recording
+/// Streams stay alive but no samples are recorded or streamed until resumed
+#[napi]
+pub fn pause_audio_capture() -> Result<()> {
+    let mut state = AUDIO_ENGINE.lock();
+    let capture = state.as_mut()
+        .filter(|s| s.is_capturing)
+        .ok_or_else(|| Error::from_reason("Not capturing"))?;
+    
+    if capture.paused_at.is_some() {
+        return Ok(());
+    }
+    
+    #[cfg(target_os = "macos")]
     {
-        windows_impl::audio::stop_capture()
-            .map_err(|e| Error::from_reason(format!("Failed to stop capture: {}", e)))?;
+        macos::audio::pause_capture();
     }
     
-    Ok(capture_state.output_path)
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::pause_capture();
+    }
+    
+    capture.paused_at = Some(std::time::Instant::now());
+    Ok(())
 }
 
-/// Get current audio level (0.0 - 1.0)
+/// Resume a paused capture
+#[napi]
+pub fn resume_audio_capture() -> Result<()> {
+    let mut state = AUDIO_ENGINE.lock();
+    let capture = state.as_mut()
+        .filter(|s| s.is_capturing)
+        .ok_or_else(|| Error::from_reason("Not capturing"))?;
+    
+    if let Some(paused_at) = capture.paused_at.take() {
+        capture.paused_total += paused_at.elapsed();
+        
+        #[cfg(target_os = "macos")]
+        {
+            macos::audio::resume_capture();
+        }
+        
+        #[cfg(target_os = "windows")]
+        {
+            windows_impl::audio::resume_capture();
+        }
+    }
+    
+    Ok(())
+}
+
+/// Check if capture is currently paused
+#[napi]
+pub fn is_capture_paused() -> bool {
+    let state = AUDIO_ENGINE.lock();
+    state.as_ref().map(|s| s.is_capturing && s.paused_at.is_some()).unwrap_or(false)
+}
+
+/// Get current audio level (0.0 - 1.0) - the louder of mic and system audio
 #[napi]
 pub fn get_audio_level() -> f64 {
     #[cfg(target_os = "macos")]
@@ -279,6 +1129,63 @@ pub fn get_audio_level() -> f64 {
     }
 }
 
+/// Get current microphone level (0.0 - 1.0)
+#[napi]
+pub fn get_mic_level() -> f64 {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::get_mic_level()
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::get_mic_level()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        0.0
+    }
+}
+
+/// Get current system audio level (0.0 - 1.0) - other meeting participants
+#[napi]
+pub fn get_system_level() -> f64 {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::get_system_level()
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::get_system_level()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        0.0
+    }
+}
+
+/// Sample rates and channel count of the running capture, as discovered from the live streams
+#[napi]
+pub fn get_capture_format() -> CaptureFormat {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::get_capture_format()
+    }
+    
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::get_capture_format()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        CaptureFormat::default()
+    }
+}
+
 /// Check if currently capturing
 #[napi]
 pub fn is_capturing() -> bool {
@@ -286,13 +1193,19 @@ pub fn is_capturing() -> bool {
     state.as_ref().map(|s| s.is_capturing).unwrap_or(false)
 }
 
-/// Get capture duration in milliseconds
+/// Get capture duration in milliseconds (excluding time spent paused)
 #[napi]
 pub fn get_capture_duration() -> i64 {
     let state = AUDIO_ENGINE.lock();
     state.as_ref()
         .filter(|s| s.is_capturing)
-        .map(|s| s.start_time.elapsed().as_millis() as i64)
+        .map(|s| {
+            let current_pause = s.paused_at.map(|p| p.elapsed()).unwrap_or_default();
+            s.start_time.elapsed()
+                .saturating_sub(s.paused_total)
+                .saturating_sub(current_pause)
+                .as_millis() as i64
+        })
         .unwrap_or(0)
 }
 
@@ -305,12 +1218,216 @@ pub fn is_microphone_in_use() -> bool {
         macos::mic_monitor::is_microphone_in_use()
     }
     
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::mic_monitor::is_microphone_in_use()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    {
+        false
+    }
+}
+
+/// List the applications currently using the microphone (macOS 14.2+)
+/// Lets the app tell a meeting apart from e.g. a voice memo
+#[napi]
+pub fn get_microphone_users() -> Vec<MicUser> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::mic_monitor::get_microphone_users()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        vec![]
+    }
+}
+
+/// Call callback(inUse) whenever the default microphone starts or stops being used
+/// Replaces polling isMicrophoneInUse(); rapid toggles are debounced (250ms)
+#[napi]
+pub fn watch_microphone(callback: JsFunction) -> Result<()> {
+    let tsfn: ThreadsafeFunction<bool, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    
+    replace_tsfn(&MIC_WATCH_CALLBACK, tsfn);
+    
+    #[cfg(target_os = "macos")]
+    {
+        macos::mic_monitor::watch_microphone(Arc::new(|in_use| {
+            if let Some(tsfn) = MIC_WATCH_CALLBACK.lock().as_ref() {
+                tsfn.call(in_use, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        }))
+        .map_err(Error::from_reason)
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        Err(Error::from_reason("Microphone watching is not supported on this platform"))
+    }
+}
+
+/// Stop the microphone watch started by watch_microphone
+/// Returns false if no watch was active
+#[napi]
+pub fn unwatch_microphone() -> bool {
+    #[cfg(target_os = "macos")]
+    {
+        macos::mic_monitor::unwatch_microphone()
+    }
+    
     #[cfg(not(target_os = "macos"))]
     {
         false
     }
 }
 
+/// Skip streaming chunks where both system and mic are below the VAD threshold
+#[napi]
+pub fn set_vad_enabled(enabled: bool) {
+    #[cfg(target_os = "macos")]
+    macos::audio::set_vad_enabled(enabled);
+    
+    #[cfg(not(target_os = "macos"))]
+    let _ = enabled;
+}
+
+/// Enable a ~80Hz high-pass filter on the microphone to remove DC offset and rumble (macOS)
+#[napi]
+pub fn set_mic_highpass_enabled(enabled: bool) {
+    #[cfg(target_os = "macos")]
+    macos::audio::set_mic_highpass_enabled(enabled);
+    
+    #[cfg(not(target_os = "macos"))]
+    let _ = enabled;
+}
+
+/// Normalize system and mic to the auto gain target level (with a limiter) when writing the
+/// final recording, replacing the fixed mic boost. Streaming chunks are unaffected. Off by default
+#[napi]
+pub fn set_auto_gain_enabled(enabled: bool) {
+    audio::set_auto_gain_enabled(enabled);
+}
+
+/// Set the auto gain target level in dBFS (-40 to -3, default -20)
+#[napi]
+pub fn set_auto_gain_target(dbfs: f64) -> Result<()> {
+    if !(-40.0..=-3.0).contains(&dbfs) {
+        return Err(Error::from_reason(format!("Auto gain target must be between -40 and -3 dBFS, got {}", dbfs)));
+    }
+    audio::set_auto_gain_target(dbfs as f32);
+    Ok(())
+}
+
+/// Set the streaming chunk length in ms (20 - 2000, default 100). Shorter chunks lower
+/// latency (e.g. 30ms VAD frames), longer ones cut per-chunk overhead (e.g. 1s batch uploads).
+/// Takes effect from the next chunk; max_queued_chunks still counts chunks, not time
+#[napi]
+pub fn set_chunk_duration_ms(ms: u32) -> Result<()> {
+    if !(audio::MIN_CHUNK_DURATION_MS..=audio::MAX_CHUNK_DURATION_MS).contains(&ms) {
+        return Err(Error::from_reason(format!(
+            "Chunk duration must be between {} and {} ms, got {}",
+            audio::MIN_CHUNK_DURATION_MS, audio::MAX_CHUNK_DURATION_MS, ms
+        )));
+    }
+    audio::set_chunk_duration_ms(ms);
+    Ok(())
+}
+
+/// Set the VAD RMS threshold (0.0 - 1.0, default 0.01)
+#[napi]
+pub fn set_vad_threshold(threshold: f64) {
+    #[cfg(target_os = "macos")]
+    macos::audio::set_vad_threshold(threshold);
+    
+    #[cfg(not(target_os = "macos"))]
+    let _ = threshold;
+}
+
+/// Fraction of streaming chunks in the current capture that were silence (0.0 - 1.0)
+#[napi]
+pub fn get_silence_ratio() -> f64 {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::get_silence_ratio()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        0.0
+    }
+}
+
+/// Number of streaming chunks dropped in the current capture because get_audio_chunks wasn't called often enough
+#[napi]
+pub fn get_dropped_chunk_count() -> i64 {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::get_dropped_chunk_count() as i64
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        0
+    }
+}
+
+/// Take the chunks retained during capture (see retain_chunks_secs) as one buffer of
+/// interleaved stereo 16kHz PCM16 (L=system, R=mic), e.g. for a local transcription pass with
+/// transcribe_audio_buffer(buffer, 16000, 2) when cloud streaming failed. Clears the buffer
+#[napi]
+pub fn flush_retained_chunks() -> Buffer {
+    audio::take_retained_chunks().into()
+}
+
+/// List displays available to ScreenCaptureKit, for the display_id capture option (macOS)
+/// Empty on headless Macs and other platforms
+#[napi]
+pub async fn list_displays() -> Result<Vec<DisplayInfo>> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio::list_displays()
+            .await
+            .map_err(|e| Error::from_reason(format!("Failed to list displays: {}", e)))
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        Ok(vec![])
+    }
+}
+
+/// List audio input devices (microphones)
+#[napi]
+pub fn list_input_devices() -> Vec<AudioDeviceInfo> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::audio_devices::list_input_devices()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        vec![]
+    }
+}
+
+/// Check if the default microphone is muted at the OS/hardware level
+/// Returns null if the device doesn't expose a mute control
+#[napi]
+pub fn is_microphone_muted() -> Option<bool> {
+    #[cfg(target_os = "macos")]
+    {
+        macos::mic_monitor::is_microphone_muted()
+    }
+    
+    #[cfg(not(target_os = "macos"))]
+    {
+        None
+    }
+}
+
 /// Get queued stereo audio chunks for streaming to Deepgram
 /// Returns Vec of stereo 16-bit PCM chunks (interleaved L=system, R=mic)
 /// Each chunk is ~100ms of audio at 16kHz
@@ -324,12 +1441,36 @@ pub fn get_audio_chunks() -> Vec<Buffer> {
             .collect()
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::get_audio_chunks()
+            .into_iter()
+            .map(|chunk| Buffer::from(chunk))
+            .collect()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     {
         vec![]
     }
 }
 
+/// Get queued mono 16-bit PCM chunks (~100ms at 16kHz) of one stream: "system", "mic" or
+/// "mix" (system + boosted mic). Each source has its own queue, filled alongside the stereo
+/// chunks of get_audio_chunks and bounded the same way. A queue only fills once its source
+/// has been asked for, so call this once before start_audio_capture to get every chunk
+#[napi]
+pub fn get_mono_chunks(source: String) -> Result<Vec<Buffer>> {
+    let source = audio::MonoSource::parse(&source).ok_or_else(|| {
+        Error::from_reason(format!("Unknown source '{}', expected \"system\", \"mic\" or \"mix\"", source))
+    })?;
+    
+    Ok(audio::take_mono_chunks(source)
+        .into_iter()
+        .map(Buffer::from)
+        .collect())
+}
+
 /// Check if there are audio chunks ready for streaming
 #[napi]
 pub fn has_audio_chunks() -> bool {
@@ -338,11 +1479,245 @@ pub fn has_audio_chunks() -> bool {
         macos::audio::has_audio_chunks()
     }
     
-    #[cfg(not(target_os = "macos"))]
+    #[cfg(target_os = "windows")]
+    {
+        windows_impl::audio::has_audio_chunks()
+    }
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
     {
         false
     }
 }
 
 // ============================================================================
+// Model Diagnostics
+// ============================================================================
+
+/// Estimated resident memory per loaded engine, in bytes (0 = not loaded)
+#[napi(object)]
+pub struct LoadedModelMemory {
+    pub parakeet_bytes: i64,
+    pub embedding_bytes: i64,
+    pub llm_bytes: i64,
+}
+
+/// ORT keeps its own copy of the weights plus arena and activation buffers on top
+const ONNX_RESIDENT_MULTIPLIER: f64 = 1.5;
+/// GGUF weights are mmapped; the KV cache and runtime buffers come on top
+const LLM_RESIDENT_MULTIPLIER: f64 = 1.2;
+
+/// Resident memory estimate behind both get_loaded_model_memory and get_model_resource_usage
+fn estimate_model_memory() -> LoadedModelMemory {
+    let estimate = |bytes: u64, multiplier: f64| (bytes as f64 * multiplier) as i64;
+    LoadedModelMemory {
+        parakeet_bytes: estimate(parakeet::loaded_memory_bytes(), ONNX_RESIDENT_MULTIPLIER),
+        embedding_bytes: estimate(embedding::loaded_memory_bytes(), ONNX_RESIDENT_MULTIPLIER),
+        llm_bytes: estimate(llm::loaded_memory_bytes(), LLM_RESIDENT_MULTIPLIER),
+    }
+}
+
+/// Get an estimate of how much RAM each loaded model is using
+/// ONNX Runtime doesn't expose per-session allocator stats, so this is the size of the
+/// weights each engine loaded times a runtime overhead multiplier (the same figures as
+/// get_model_resource_usage)
+#[napi]
+pub fn get_loaded_model_memory() -> LoadedModelMemory {
+    estimate_model_memory()
+}
+
+/// Loaded models, their estimated footprint, and the process's actual usage
+#[napi(object)]
+pub struct ModelResourceUsage {
+    pub parakeet_loaded: bool,
+    pub embedding_loaded: bool,
+    pub llm_loaded: bool,
+    /// Estimated resident bytes per model (0 = not loaded)
+    pub parakeet_bytes: i64,
+    pub embedding_bytes: i64,
+    pub llm_bytes: i64,
+    /// Sum of the per-model estimates
+    pub total_model_bytes: i64,
+    /// Measured resident memory of the whole process (null if unavailable)
+    pub process_resident_bytes: Option<i64>,
+    /// CPU seconds used by the process so far; diff two readings for a usage percentage
+    pub process_cpu_secs: Option<f64>,
+}
+
+/// Which models are loaded and roughly how much RAM each takes (weights × a runtime
+/// overhead multiplier), alongside the process's measured RSS and CPU time, so the UI
+/// can warn before loading another model on a memory-constrained machine
+#[napi]
+pub fn get_model_resource_usage() -> ModelResourceUsage {
+    let LoadedModelMemory { parakeet_bytes, embedding_bytes, llm_bytes } = estimate_model_memory();
+    
+    #[cfg(target_os = "macos")]
+    let (resident, cpu) = (macos::process_stats::resident_bytes(), macos::process_stats::cpu_time_secs());
+    
+    #[cfg(target_os = "windows")]
+    let (resident, cpu) = (windows_impl::process_stats::resident_bytes(), windows_impl::process_stats::cpu_time_secs());
+    
+    #[cfg(not(any(target_os = "macos", target_os = "windows")))]
+    let (resident, cpu): (Option<u64>, Option<f64>) = (None, None);
+    
+    ModelResourceUsage {
+        parakeet_loaded: parakeet::is_parakeet_ready(),
+        embedding_loaded: embedding::is_embedding_ready(),
+        llm_loaded: llm::is_llm_ready(),
+        parakeet_bytes,
+        embedding_bytes,
+        llm_bytes,
+        total_model_bytes: parakeet_bytes + embedding_bytes + llm_bytes,
+        process_resident_bytes: resident.map(|bytes| bytes as i64),
+        process_cpu_secs: cpu,
+    }
+}
 
+/// Trim a mirror URL and check it's http(s) with a host; trailing slashes are dropped
+fn parse_base_url(url: &str) -> Result<String> {
+    let url = url.trim().trim_end_matches('/');
+    let rest = url
+        .strip_prefix("https://")
+        .or_else(|| url.strip_prefix("http://"))
+        .ok_or_else(|| Error::from_reason(format!("Invalid base URL '{}', expected http:// or https://", url)))?;
+    let host = rest.split('/').next().unwrap_or_default();
+    if host.is_empty() || url.contains(char::is_whitespace) {
+        return Err(Error::from_reason(format!("Invalid base URL '{}'", url)));
+    }
+    Ok(url.to_string())
+}
+
+/// Download "parakeet" or "embedding" model files from a mirror (S3 bucket, corporate proxy)
+/// instead of Hugging Face. Files keep their Hugging Face paths relative to `url`, e.g.
+/// `<url>/encoder-model.int8.onnx`. Null or empty restores the default; not persisted
+#[napi]
+pub fn set_model_base_url(subsystem: String, url: Option<String>) -> Result<()> {
+    let url = match url.as_deref().map(str::trim) {
+        None | Some("") => None,
+        Some(value) => Some(parse_base_url(value)?),
+    };
+    
+    match subsystem.as_str() {
+        "parakeet" => parakeet::set_download_base_url(url)?,
+        "embedding" => embedding::set_download_base_url(url)?,
+        other => {
+            return Err(Error::from_reason(format!("Unknown subsystem '{}', expected \"parakeet\" or \"embedding\"", other)));
+        }
+    }
+    Ok(())
+}
+
+/// Base URL model files for "parakeet" or "embedding" are downloaded from
+#[napi]
+pub fn get_model_base_url(subsystem: String) -> Result<String> {
+    match subsystem.as_str() {
+        "parakeet" => Ok(parakeet::download_base_url()),
+        "embedding" => Ok(embedding::download_base_url()),
+        other => Err(Error::from_reason(format!("Unknown subsystem '{}', expected \"parakeet\" or \"embedding\"", other))),
+    }
+}
+
+/// Result of native_self_test; a false probe points at the broken subsystem
+#[napi(object)]
+pub struct SelfTestReport {
+    /// Version of this native module
+    pub version: String,
+    /// "macos", "windows", ...
+    pub platform: String,
+    pub arch: String,
+    /// Window enumeration ran without crashing
+    pub windows_ok: bool,
+    /// ONNX Runtime initialized (Parakeet and embeddings depend on it)
+    pub onnx_runtime_ok: bool,
+    /// A ScreenCaptureKit content filter could be created (macOS). False without probing when
+    /// Screen Recording permission isn't granted, since the probe could prompt for it.
+    /// Null where system audio isn't captured through ScreenCaptureKit
+    pub screen_capture_ok: Option<bool>,
+    /// The permission status APIs could be queried
+    pub permissions_ok: bool,
+    /// Why each failed probe failed
+    pub errors: Vec<String>,
+}
+
+/// Run a probe, turning a panic into an error so one broken subsystem can't take down the test
+fn run_probe(name: &str, errors: &mut Vec<String>, probe: impl FnOnce() -> std::result::Result<(), String>) -> bool {
+    match std::panic::catch_unwind(std::panic::AssertUnwindSafe(probe)) {
+        Ok(Ok(())) => true,
+        Ok(Err(e)) => {
+            errors.push(format!("{}: {}", name, e));
+            false
+        }
+        Err(_) => {
+            errors.push(format!("{}: panicked", name));
+            false
+        }
+    }
+}
+
+/// Probe each subsystem (windows, ONNX Runtime, ScreenCaptureKit, permissions) without
+/// starting anything, so support can tell "the native module is broken" from "a permission
+/// is missing" in one call
+#[napi]
+pub async fn native_self_test() -> SelfTestReport {
+    let mut errors = Vec::new();
+    
+    let windows_ok = run_probe("windows", &mut errors, || {
+        #[cfg(target_os = "macos")]
+        let _ = macos::window::get_windows();
+        
+        #[cfg(target_os = "windows")]
+        let _ = windows_impl::window::get_windows();
+        
+        Ok(())
+    });
+    
+    let onnx_runtime_ok = run_probe("onnx_runtime", &mut errors, || {
+        ort::session::Session::builder().map(|_| ()).map_err(|e| e.to_string())
+    });
+    
+    let permissions_ok = run_probe("permissions", &mut errors, || {
+        let _ = accessibility_permission_status();
+        let _ = screen_recording_permission_status();
+        let _ = microphone_permission_status();
+        Ok(())
+    });
+    
+    #[cfg(target_os = "macos")]
+    let screen_capture_ok = if matches!(screen_recording_permission_status(), PermissionStatus::Granted) {
+        let probe = tokio::task::spawn_blocking(|| {
+            let mut errors = Vec::new();
+            let ok = run_probe("screen_capture", &mut errors, || {
+                macos::audio::probe_content_filter().map_err(|e| e.to_string())
+            });
+            (ok, errors)
+        }).await;
+        
+        match probe {
+            Ok((ok, probe_errors)) => {
+                errors.extend(probe_errors);
+                Some(ok)
+            }
+            Err(e) => {
+                errors.push(format!("screen_capture: {}", e));
+                Some(false)
+            }
+        }
+    } else {
+        errors.push("screen_capture: not probed, Screen Recording permission isn't granted".to_string());
+        Some(false)
+    };
+    
+    #[cfg(not(target_os = "macos"))]
+    let screen_capture_ok = None;
+    
+    SelfTestReport {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        platform: std::env::consts::OS.to_string(),
+        arch: std::env::consts::ARCH.to_string(),
+        windows_ok,
+        onnx_runtime_ok,
+        screen_capture_ok,
+        permissions_ok,
+        errors,
+    }
+}
@@ -7,20 +7,34 @@ use napi::bindgen_prelude::*;
 use napi::threadsafe_function::{ThreadsafeFunction, ErrorStrategy, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 use parking_lot::Mutex;
+use std::collections::HashMap;
+use std::sync::atomic::{AtomicBool, AtomicU32, AtomicU64, Ordering};
+use std::path::PathBuf;
 use std::sync::Arc;
 use once_cell::sync::Lazy;
+use serde::{Deserialize, Serialize};
+use either::Either;
+
+use crate::{EngineState, EngineStatus};
+use crate::download;
+use crate::settings;
 
 // mistralrs imports
 use mistralrs::{
     GgufModelBuilder, TextMessageRole, TextMessages, Model,
-    RequestBuilder, Response, ChatCompletionChunkResponse, ChunkChoice, Delta,
+    RequestBuilder, Response, ChatCompletionChunkResponse, ChunkChoice, Delta, StopTokens,
+    CustomLogitsProcessor, DType, Tensor,
 };
 
-// Model configuration for Qwen2.5 3B Instruct (public, no auth required)
+// Default model: Qwen2.5 3B Instruct (public, no auth required)
 const GGUF_REPO: &str = "Qwen/Qwen2.5-3B-Instruct-GGUF";
 const GGUF_FILE: &str = "qwen2.5-3b-instruct-q4_k_m.gguf";
 const TOKENIZER_REPO: &str = "Qwen/Qwen2.5-3B-Instruct";
 const MODEL_SIZE_BYTES: u64 = 2_100_000_000; // ~2GB
+const MIN_MODEL_SIZE: u64 = 100_000_000; // Floor for a GGUF whose size was never recorded
+
+const DEFAULT_TEMPERATURE: f64 = 0.7;
+const MAX_INPUT_TOKENS: usize = 28_000; // Qwen2.5 has a 32K context; keep room for the reply
 
 // ============================================================================
 // Global State
@@ -28,12 +42,41 @@ const MODEL_SIZE_BYTES: u64 = 2_100_000_000; // ~2GB
 
 static LLM_STATE: Lazy<Mutex<Option<LlmEngine>>> = Lazy::new(|| Mutex::new(None));
 
+/// Model the engine loads (and whose cache the download/delete helpers inspect),
+/// restored from disk so a non-default model is still found after a restart
+static LLM_CONFIG: Lazy<Mutex<LlmConfig>> = Lazy::new(|| Mutex::new(load_config()));
+
+/// Device requested for the next load
+static LLM_DEVICE: Mutex<LlmDevice> = Mutex::new(LlmDevice::Auto);
+
+static LLM_ENGINE_STATE: Mutex<EngineState> = Mutex::new(EngineState::Unloaded);
+
 static LLM_INIT_PROGRESS: Mutex<LlmInitProgress> = Mutex::new(LlmInitProgress {
     is_loading: false,
     status: String::new(),
     error: None,
+    bytes_downloaded: 0,
+    total_bytes: 0,
+    percent: 0,
 });
 
+/// Cancellation flags for in-flight llm_chat_stream calls, keyed by stream id
+static LLM_STREAMS: Lazy<Mutex<HashMap<u32, Arc<AtomicBool>>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+static NEXT_STREAM_ID: Mutex<u32> = Mutex::new(1);
+
+/// Unload the model after this many seconds without requests (0 = never)
+static LLM_IDLE_TIMEOUT_SECS: AtomicU64 = AtomicU64::new(0);
+/// End of the last request (or load), for the idle timer
+static LLM_LAST_USED: Mutex<Option<std::time::Instant>> = Mutex::new(None);
+/// Requests currently using the model; the idle timer never unloads while this is non-zero
+static LLM_ACTIVE_REQUESTS: AtomicU32 = AtomicU32::new(0);
+/// Set when the idle timer unloaded the model, so the next request reloads it
+static LLM_IDLE_UNLOADED: AtomicBool = AtomicBool::new(false);
+static LLM_IDLE_WATCHER_STARTED: AtomicBool = AtomicBool::new(false);
+
+const IDLE_CHECK_INTERVAL: std::time::Duration = std::time::Duration::from_secs(5);
+
 // Tokio runtime for async operations
 static TOKIO_RUNTIME: Lazy<tokio::runtime::Runtime> = Lazy::new(|| {
     tokio::runtime::Builder::new_multi_thread()
@@ -54,6 +97,8 @@ pub struct LlmModelInfo {
     pub model_repo: String,
     pub model_file: String,
     pub estimated_size: i64,
+    /// "metal" or "cpu" once loaded
+    pub device: Option<String>,
 }
 
 #[napi(object)]
@@ -62,6 +107,10 @@ pub struct LlmInitProgress {
     pub is_loading: bool,
     pub status: String,
     pub error: Option<String>,
+    /// Download progress across the GGUF and tokenizer files (0 when already cached)
+    pub bytes_downloaded: i64,
+    pub total_bytes: i64,
+    pub percent: u32,
 }
 
 #[napi(object)]
@@ -83,8 +132,121 @@ pub struct LlmResponse {
     pub tokens_per_second: f64,
 }
 
+#[derive(Clone, Serialize, Deserialize)]
+struct LlmConfig {
+    repo: String,
+    file: String,
+    tokenizer_repo: String,
+    /// Size the hub reported for `file`, recorded on download; a cached copy of any other size
+    /// is truncated. None for models cached before sizes were recorded
+    #[serde(default)]
+    file_size: Option<u64>,
+}
+
+impl Default for LlmConfig {
+    fn default() -> Self {
+        Self {
+            repo: GGUF_REPO.to_string(),
+            file: GGUF_FILE.to_string(),
+            tokenizer_repo: TOKENIZER_REPO.to_string(),
+            file_size: None,
+        }
+    }
+}
+
+impl LlmConfig {
+    /// Same repo, file and tokenizer; the recorded size isn't part of a model's identity
+    fn same_model(&self, other: &LlmConfig) -> bool {
+        self.repo == other.repo && self.file == other.file && self.tokenizer_repo == other.tokenizer_repo
+    }
+    
+    fn is_default(&self) -> bool {
+        self.same_model(&Self::default())
+    }
+    
+    /// Whether a cached GGUF of `size` bytes is complete
+    fn is_complete_size(&self, size: u64) -> bool {
+        match self.file_size {
+            Some(expected) => size == expected,
+            None => size >= MIN_MODEL_SIZE,
+        }
+    }
+}
+
+/// Optional sampling controls; omitted fields keep mistral.rs defaults
+#[napi(object)]
+#[derive(Clone, Default)]
+pub struct LlmSamplingOptions {
+    /// Nucleus sampling: sample from the smallest token set whose probability reaches top_p
+    pub top_p: Option<f64>,
+    /// Sample from the k most likely tokens only
+    pub top_k: Option<u32>,
+    /// Same seed + prompt + temperature gives the same output
+    pub seed: Option<i64>,
+}
+
+#[derive(Clone, Copy, PartialEq)]
+enum LlmDevice {
+    Cpu,
+    Metal,
+    /// Metal on macOS, CPU elsewhere
+    Auto,
+}
+
+impl LlmDevice {
+    fn parse(device: &str) -> std::result::Result<Self, String> {
+        match device {
+            "cpu" => Ok(LlmDevice::Cpu),
+            "metal" => Ok(LlmDevice::Metal),
+            "auto" => Ok(LlmDevice::Auto),
+            other => Err(format!("Unknown device '{}', expected \"cpu\", \"metal\" or \"auto\"", other)),
+        }
+    }
+    
+    fn as_str(&self) -> &'static str {
+        match self {
+            LlmDevice::Cpu => "cpu",
+            LlmDevice::Metal => "metal",
+            LlmDevice::Auto => "auto",
+        }
+    }
+}
+
 struct LlmEngine {
     model: Arc<Model>,
+    config: LlmConfig,
+    /// Device actually in use (never Auto)
+    device: LlmDevice,
+    /// Size of the GGUF weights backing the model, used as a resident memory estimate
+    weights_bytes: u64,
+}
+
+// ============================================================================
+// Model Configuration
+// ============================================================================
+
+const CONFIG_FILE: &str = "llm-config.json";
+
+fn load_config() -> LlmConfig {
+    settings::load(CONFIG_FILE)
+}
+
+fn save_config(config: &LlmConfig) {
+    if let Err(e) = settings::save(CONFIG_FILE, config) {
+        println!("[LLM] ⚠️ Failed to save model config: {}", e);
+    }
+}
+
+fn current_config() -> LlmConfig {
+    LLM_CONFIG.lock().clone()
+}
+
+/// HuggingFace Hub cache directory for a repo (~/.cache/huggingface/hub/models--org--name)
+fn hub_model_dir(repo: &str) -> Option<PathBuf> {
+    dirs::home_dir().map(|home| {
+        home.join(".cache/huggingface/hub")
+            .join(format!("models--{}", repo.replace("/", "--")))
+    })
 }
 
 // ============================================================================
@@ -93,14 +255,25 @@ struct LlmEngine {
 
 #[napi]
 pub fn get_llm_model_info() -> LlmModelInfo {
-    let ready = LLM_STATE.lock().is_some();
+    let (ready, device) = match LLM_STATE.lock().as_ref() {
+        Some(engine) => (true, Some(engine.device.as_str().to_string())),
+        None => (false, None),
+    };
+    let config = current_config();
+    
+    let (model_name, estimated_size) = if config.is_default() {
+        ("Qwen2.5 3B Instruct (Q4_K_M)".to_string(), MODEL_SIZE_BYTES)
+    } else {
+        (config.file.trim_end_matches(".gguf").to_string(), cached_gguf_size(&config))
+    };
     
     LlmModelInfo {
         ready,
-        model_name: "Qwen2.5 3B Instruct (Q4_K_M)".to_string(),
-        model_repo: GGUF_REPO.to_string(),
-        model_file: GGUF_FILE.to_string(),
-        estimated_size: MODEL_SIZE_BYTES as i64,
+        model_name,
+        model_repo: config.repo,
+        model_file: config.file,
+        estimated_size: estimated_size as i64,
+        device,
     }
 }
 
@@ -114,23 +287,26 @@ pub fn is_llm_ready() -> bool {
     LLM_STATE.lock().is_some()
 }
 
+/// Detailed LLM state, distinguishing "never loaded" from "failed to load"
+#[napi]
+pub fn get_llm_state() -> EngineStatus {
+    LLM_ENGINE_STATE.lock().to_status()
+}
+
 /// Check if LLM model is downloaded (cached by HuggingFace Hub)
 #[napi]
 pub fn is_llm_downloaded() -> bool {
     // HuggingFace Hub caches models at ~/.cache/huggingface/hub/
     // The model directory name is based on the repo name with -- replacing /
-    let home = match dirs::home_dir() {
-        Some(h) => h,
+    let config = current_config();
+    let model_dir = match hub_model_dir(&config.repo) {
+        Some(dir) => dir,
         None => {
             println!("[LLM] Cannot determine home directory");
             return false;
         }
     };
     
-    let cache_dir = home.join(".cache/huggingface/hub");
-    let model_dir_name = format!("models--{}", GGUF_REPO.replace("/", "--"));
-    let model_dir = cache_dir.join(&model_dir_name);
-    
     println!("[LLM] Checking for model at: {}", model_dir.display());
     
     // Check if the snapshots directory exists and has content
@@ -140,22 +316,18 @@ pub fn is_llm_downloaded() -> bool {
         return false;
     }
     
-    // Check if any snapshot has the GGUF file with reasonable size
-    // The Q4_K_M model should be around 2GB
-    const MIN_MODEL_SIZE: u64 = 1_500_000_000; // At least 1.5GB
-    
+    // Check if any snapshot has the complete GGUF file
     if let Ok(entries) = std::fs::read_dir(&snapshots_dir) {
         for entry in entries.flatten() {
-            let gguf_path = entry.path().join(GGUF_FILE);
+            let gguf_path = entry.path().join(&config.file);
             if gguf_path.exists() {
-                // Verify file size is reasonable
                 if let Ok(metadata) = std::fs::metadata(&gguf_path) {
                     let size = metadata.len();
-                    if size >= MIN_MODEL_SIZE {
+                    if config.is_complete_size(size) {
                         println!("[LLM] ✅ Model found: {} ({:.2} GB)", gguf_path.display(), size as f64 / 1_000_000_000.0);
                         return true;
                     } else {
-                        println!("[LLM] ⚠️ Model file too small: {} bytes (expected >= {})", size, MIN_MODEL_SIZE);
+                        println!("[LLM] ⚠️ Model file incomplete: {} bytes (expected {:?}, at least {})", size, config.file_size, MIN_MODEL_SIZE);
                     }
                 }
             }
@@ -166,90 +338,200 @@ pub fn is_llm_downloaded() -> bool {
     false
 }
 
-/// Get download progress by checking HuggingFace cache for .part files
-#[napi]
-pub fn get_llm_download_progress() -> LlmDownloadProgress {
-    let home = match dirs::home_dir() {
-        Some(h) => h,
-        None => {
-            return LlmDownloadProgress {
-                is_downloading: false,
-                bytes_downloaded: 0,
-                total_bytes: MODEL_SIZE_BYTES as i64,
-                percent: 0,
-                current_file: String::new(),
-            };
-        }
+/// Size of the cached GGUF file, if any snapshot contains it
+fn cached_gguf_size(config: &LlmConfig) -> u64 {
+    let snapshots_dir = match hub_model_dir(&config.repo) {
+        Some(dir) => dir.join("snapshots"),
+        None => return 0,
     };
     
-    let cache_dir = home.join(".cache/huggingface/hub");
-    let model_dir_name = format!("models--{}", GGUF_REPO.replace("/", "--"));
-    let model_dir = cache_dir.join(&model_dir_name);
-    let blobs_dir = model_dir.join("blobs");
+    std::fs::read_dir(&snapshots_dir)
+        .ok()
+        .and_then(|entries| {
+            entries
+                .flatten()
+                .filter_map(|entry| std::fs::metadata(entry.path().join(&config.file)).ok())
+                .map(|metadata| metadata.len())
+                .max()
+        })
+        .unwrap_or(0)
+}
+
+/// Estimated resident memory of the loaded LLM (0 when not loaded)
+pub(crate) fn loaded_memory_bytes() -> u64 {
+    LLM_STATE.lock()
+        .as_ref()
+        .map(|engine| if engine.weights_bytes > 0 { engine.weights_bytes } else { MODEL_SIZE_BYTES })
+        .unwrap_or(0)
+}
+
+/// Download progress of the current init, or 100% when the model is already cached
+#[napi]
+pub fn get_llm_download_progress() -> LlmDownloadProgress {
+    let config = current_config();
     
     // Check if model is already downloaded
     if is_llm_downloaded() {
+        let size = cached_gguf_size(&config) as i64;
         return LlmDownloadProgress {
             is_downloading: false,
-            bytes_downloaded: MODEL_SIZE_BYTES as i64,
-            total_bytes: MODEL_SIZE_BYTES as i64,
+            bytes_downloaded: size,
+            total_bytes: size,
             percent: 100,
-            current_file: GGUF_FILE.to_string(),
+            current_file: config.file,
         };
     }
     
-    // Check for .part files (download in progress)
-    if blobs_dir.exists() {
-        if let Ok(entries) = std::fs::read_dir(&blobs_dir) {
-            for entry in entries.flatten() {
-                let path = entry.path();
-                let file_name = path.file_name().and_then(|n| n.to_str()).unwrap_or("");
-                
-                // Look for .part files (HuggingFace download in progress)
-                if file_name.ends_with(".part") {
-                    if let Ok(metadata) = std::fs::metadata(&path) {
-                        let downloaded_bytes = metadata.len() as i64;
-                        let total_bytes = MODEL_SIZE_BYTES as i64;
-                        let percent = if total_bytes > 0 {
-                            ((downloaded_bytes as f64 / total_bytes as f64) * 100.0) as u32
-                        } else {
-                            0
-                        };
-                        
-                        return LlmDownloadProgress {
-                            is_downloading: true,
-                            bytes_downloaded: downloaded_bytes,
-                            total_bytes,
-                            percent: percent.min(100),
-                            current_file: GGUF_FILE.to_string(),
-                        };
-                    }
-                }
-            }
+    let progress = LLM_INIT_PROGRESS.lock();
+    let is_downloading = progress.is_loading && progress.status.starts_with("Downloading");
+    LlmDownloadProgress {
+        is_downloading,
+        bytes_downloaded: progress.bytes_downloaded,
+        total_bytes: progress.total_bytes,
+        percent: progress.percent,
+        current_file: if is_downloading { config.file } else { String::new() },
+    }
+}
+
+// ============================================================================
+// Model Download
+// ============================================================================
+
+const HF_BASE_URL: &str = "https://huggingface.co";
+
+/// A file to place in the HuggingFace Hub cache, resolved against the repo's main branch
+struct HubFile {
+    repo: String,
+    name: String,
+    commit: String,
+    size: u64,
+}
+
+impl HubFile {
+    fn url(&self) -> String {
+        format!("{}/{}/resolve/{}/{}", HF_BASE_URL, self.repo, self.commit, self.name)
+    }
+    
+    /// snapshots/<commit>/<name>, where hf-hub (and so mistral.rs) looks for cached files
+    fn cache_path(&self) -> Option<PathBuf> {
+        hub_model_dir(&self.repo).map(|dir| dir.join("snapshots").join(&self.commit).join(&self.name))
+    }
+}
+
+/// Look up the commit and size of a file on main without downloading it.
+/// Returns None if the file doesn't exist in the repo
+fn resolve_hub_file(repo: &str, name: &str) -> std::result::Result<Option<HubFile>, String> {
+    // Redirects off: the resolve endpoint itself carries the repo commit and LFS size headers
+    let agent = ureq::AgentBuilder::new().redirects(0).build();
+    let url = format!("{}/{}/resolve/main/{}", HF_BASE_URL, repo, name);
+    
+    let response = match agent.head(&url).set("User-Agent", download::USER_AGENT).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(404, _)) => return Ok(None),
+        Err(e) => return Err(format!("Failed to resolve {}/{}: {}", repo, name, e)),
+    };
+    
+    let commit = response.header("x-repo-commit")
+        .ok_or_else(|| format!("No commit reported for {}/{}", repo, name))?
+        .to_string();
+    let size = response.header("x-linked-size")
+        .or_else(|| response.header("content-length"))
+        .and_then(|v| v.parse().ok())
+        .unwrap_or(0);
+    
+    Ok(Some(HubFile { repo: repo.to_string(), name: name.to_string(), commit, size }))
+}
+
+fn set_download_progress(bytes_downloaded: u64, total_bytes: u64) {
+    let mut progress = LLM_INIT_PROGRESS.lock();
+    progress.bytes_downloaded = bytes_downloaded as i64;
+    progress.total_bytes = total_bytes as i64;
+    progress.percent = if total_bytes > 0 {
+        ((bytes_downloaded as f64 / total_bytes as f64) * 100.0).min(100.0) as u32
+    } else {
+        0
+    };
+}
+
+/// Remember the hub's size for the configured GGUF so is_llm_downloaded can spot truncated copies
+fn record_gguf_size(config: &LlmConfig, size: u64) {
+    let mut current = LLM_CONFIG.lock();
+    if current.same_model(config) && current.file_size != Some(size) {
+        current.file_size = Some(size);
+        save_config(&current);
+    }
+}
+
+/// Fetch the GGUF and tokenizer files into the HuggingFace cache with byte-level
+/// progress, so mistral.rs finds them cached and only has to load
+fn download_to_hf_cache(config: &LlmConfig) -> std::result::Result<(), String> {
+    // (repo, file, required)
+    let wanted = [
+        (config.repo.as_str(), config.file.as_str(), true),
+        (config.tokenizer_repo.as_str(), "tokenizer.json", true),
+        (config.tokenizer_repo.as_str(), "tokenizer_config.json", false),
+    ];
+    
+    let mut files = Vec::new();
+    for (repo, name, required) in wanted {
+        match resolve_hub_file(repo, name)? {
+            Some(file) => files.push(file),
+            None if required => return Err(format!("{} not found in {}", name, repo)),
+            None => println!("[LLM] {} has no {}, skipping", repo, name),
         }
     }
     
-    // Check if init is in progress (from init progress state)
-    let init_progress = LLM_INIT_PROGRESS.lock();
-    if init_progress.is_loading && init_progress.status.contains("Downloading") {
-        // Download might be starting, return minimal progress
-        return LlmDownloadProgress {
-            is_downloading: true,
-            bytes_downloaded: 0,
-            total_bytes: MODEL_SIZE_BYTES as i64,
-            percent: 0,
-            current_file: GGUF_FILE.to_string(),
-        };
+    if let Some(gguf) = files.first().filter(|file| file.size > 0) {
+        record_gguf_size(config, gguf.size);
     }
     
-    // No download in progress
-    LlmDownloadProgress {
-        is_downloading: false,
-        bytes_downloaded: 0,
-        total_bytes: MODEL_SIZE_BYTES as i64,
-        percent: 0,
-        current_file: String::new(),
+    let total_bytes: u64 = files.iter().map(|f| f.size).sum();
+    let mut bytes_so_far: u64 = 0;
+    
+    for file in &files {
+        let dest = file.cache_path().ok_or("Cannot determine home directory")?;
+        
+        if dest.exists() {
+            let size = std::fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+            if file.size == 0 || size == file.size {
+                bytes_so_far += file.size;
+                set_download_progress(bytes_so_far, total_bytes);
+                continue;
+            }
+            println!("[LLM] ⚠️ Cached {} is {} of {} bytes, downloading again", file.name, size, file.size);
+            std::fs::remove_file(&dest).map_err(|e| format!("Failed to remove {}: {}", dest.display(), e))?;
+        }
+        
+        if let Some(parent) = dest.parent() {
+            std::fs::create_dir_all(parent).map_err(|e| format!("Failed to create {}: {}", parent.display(), e))?;
+        }
+        
+        LLM_INIT_PROGRESS.lock().status = format!("Downloading {}...", file.name);
+        let bytes_before = bytes_so_far;
+        download::download_with_retry(&mut download::Download {
+            url: &file.url(),
+            dest: &dest,
+            expected_size: Some(file.size).filter(|size| *size > 0),
+            log_prefix: "[LLM]",
+            is_cancelled: &|| false,
+            on_progress: &mut |file_bytes| {
+                bytes_so_far = bytes_before + file_bytes;
+                set_download_progress(bytes_so_far, total_bytes);
+            },
+        })?;
     }
+    
+    // Point main at the snapshot we filled so hf-hub resolves to it offline
+    for file in &files {
+        if let Some(repo_dir) = hub_model_dir(&file.repo) {
+            let refs_dir = repo_dir.join("refs");
+            std::fs::create_dir_all(&refs_dir).map_err(|e| e.to_string())?;
+            std::fs::write(refs_dir.join("main"), &file.commit).map_err(|e| e.to_string())?;
+        }
+    }
+    
+    set_download_progress(total_bytes, total_bytes);
+    Ok(())
 }
 
 // ============================================================================
@@ -260,6 +542,28 @@ pub fn get_llm_download_progress() -> LlmDownloadProgress {
 /// This is handled automatically by mistral.rs
 #[napi]
 pub fn init_llm() -> bool {
+    let config = LlmConfig::default();
+    init_llm_with_config(config.repo, config.file, config.tokenizer_repo)
+}
+
+/// Initialize the LLM from any GGUF repo/file, e.g. a smaller model on low-memory machines.
+/// The config is remembered, so download/delete checks keep targeting it after a restart.
+/// A different model that is already loaded is unloaded first
+#[napi]
+pub fn init_llm_with_config(repo: String, file: String, tokenizer_repo: String) -> bool {
+    start_init(LlmConfig { repo, file, tokenizer_repo, file_size: None }, *LLM_DEVICE.lock())
+}
+
+/// Initialize the configured LLM on "cpu", "metal" or "auto" (Metal on macOS).
+/// If Metal fails to initialize the model loads on CPU and the init status says so.
+/// A model already loaded on a different device is reloaded
+#[napi]
+pub fn init_llm_with_device(device: String) -> Result<bool> {
+    let device = LlmDevice::parse(&device).map_err(Error::from_reason)?;
+    Ok(start_init(current_config(), device))
+}
+
+fn start_init(config: LlmConfig, device: LlmDevice) -> bool {
     // Check if already loading
     {
         let progress = LLM_INIT_PROGRESS.lock();
@@ -270,31 +574,97 @@ pub fn init_llm() -> bool {
     
     // Check if already loaded
     {
-        if LLM_STATE.lock().is_some() {
-            return true;
+        let mut state = LLM_STATE.lock();
+        match state.as_ref() {
+            Some(engine) if engine.config.same_model(&config) && (device == LlmDevice::Auto || engine.device == device) => return true,
+            Some(_) => {
+                println!("[LLM] Switching model or device, unloading current one");
+                *state = None;
+                *LLM_ENGINE_STATE.lock() = EngineState::Unloaded;
+            }
+            None => {}
         }
     }
     
+    // Keep the size recorded for this model, the caller only names it
+    let config = {
+        let mut current = LLM_CONFIG.lock();
+        let file_size = if current.same_model(&config) { current.file_size } else { None };
+        *current = LlmConfig { file_size, ..config };
+        current.clone()
+    };
+    *LLM_DEVICE.lock() = device;
+    save_config(&config);
+    
     // Start loading in background
     {
         let mut progress = LLM_INIT_PROGRESS.lock();
         progress.is_loading = true;
         progress.status = "Starting model download/load...".to_string();
         progress.error = None;
+        *LLM_ENGINE_STATE.lock() = EngineState::Loading;
     }
     
-    std::thread::spawn(|| {
-        do_init_llm();
+    std::thread::spawn(move || {
+        do_init_llm(config, device);
     });
     
     true
 }
 
-fn do_init_llm() {
-    println!("[LLM] Initializing Qwen2.5 3B...");
-    println!("[LLM] Repo: {}", GGUF_REPO);
-    println!("[LLM] File: {}", GGUF_FILE);
-    println!("[LLM] Tokenizer: {}", TOKENIZER_REPO);
+/// Download (if needed) and load a GGUF model
+async fn build_model(config: &LlmConfig, force_cpu: bool) -> std::result::Result<Model, String> {
+    // GgufModelBuilder automatically downloads from HuggingFace
+    let mut builder = GgufModelBuilder::new(
+        &config.repo,
+        vec![config.file.clone()],
+    )
+    .with_tok_model_id(&config.tokenizer_repo)
+    .with_logging();
+    
+    if force_cpu {
+        builder = builder.with_force_cpu();
+    }
+    
+    builder
+        .build()
+        .await
+        .map_err(|e| format!("Model build error: {}", e))
+}
+
+/// Load on the requested device. Metal failures fall back to CPU, returning
+/// the warning to show alongside the device actually used
+fn load_model(config: &LlmConfig, device: LlmDevice) -> std::result::Result<(Model, LlmDevice, Option<String>), String> {
+    let want_metal = match device {
+        LlmDevice::Cpu => false,
+        LlmDevice::Metal | LlmDevice::Auto => cfg!(target_os = "macos"),
+    };
+    
+    let mut warning = None;
+    if want_metal {
+        println!("[LLM] Loading on Metal...");
+        match TOKIO_RUNTIME.block_on(build_model(config, false)) {
+            Ok(model) => return Ok((model, LlmDevice::Metal, None)),
+            Err(e) => {
+                println!("[LLM] ⚠️ Metal init failed, falling back to CPU: {}", e);
+                warning = Some(format!("Metal unavailable ({}), running on CPU", e));
+            }
+        }
+    } else if device == LlmDevice::Metal {
+        println!("[LLM] ⚠️ Metal requested but not available on this platform, using CPU");
+        warning = Some("Metal is only available on macOS, running on CPU".to_string());
+    }
+    
+    println!("[LLM] Loading on CPU...");
+    let model = TOKIO_RUNTIME.block_on(build_model(config, true))?;
+    Ok((model, LlmDevice::Cpu, warning))
+}
+
+fn do_init_llm(config: LlmConfig, device: LlmDevice) {
+    println!("[LLM] Initializing...");
+    println!("[LLM] Repo: {}", config.repo);
+    println!("[LLM] File: {}", config.file);
+    println!("[LLM] Tokenizer: {}", config.tokenizer_repo);
     
     // Ensure HuggingFace cache directory exists (important for fresh installs from DMG)
     if let Some(home) = dirs::home_dir() {
@@ -309,36 +679,41 @@ fn do_init_llm() {
     {
         let mut progress = LLM_INIT_PROGRESS.lock();
         progress.status = "Downloading model from HuggingFace (if not cached)...".to_string();
+        progress.bytes_downloaded = 0;
+        progress.total_bytes = 0;
+        progress.percent = 0;
     }
     
-    let result = TOKIO_RUNTIME.block_on(async {
-        // GgufModelBuilder automatically downloads from HuggingFace
-        let model = GgufModelBuilder::new(
-            GGUF_REPO,
-            vec![GGUF_FILE.to_string()],
-        )
-        .with_tok_model_id(TOKENIZER_REPO)
-        .with_logging()
-        .build()
-        .await
-        .map_err(|e| format!("Model build error: {}", e))?;
-        
-        Ok::<_, String>(model)
-    });
+    let result = download_to_hf_cache(&config)
+        .map_err(|e| format!("Download error: {}", e))
+        .and_then(|_| {
+            LLM_INIT_PROGRESS.lock().status = "Loading model...".to_string();
+            load_model(&config, device)
+        });
     
     match result {
-        Ok(model) => {
+        Ok((model, device, warning)) => {
+            let weights_bytes = cached_gguf_size(&config);
             let mut state = LLM_STATE.lock();
-            *state = Some(LlmEngine { model: Arc::new(model) });
+            *state = Some(LlmEngine { model: Arc::new(model), config, device, weights_bytes });
+            
+            *LLM_ENGINE_STATE.lock() = EngineState::Ready;
+            LLM_IDLE_UNLOADED.store(false, Ordering::SeqCst);
+            touch_llm();
             
             let mut progress = LLM_INIT_PROGRESS.lock();
             progress.is_loading = false;
-            progress.status = "Model ready".to_string();
+            progress.status = match warning {
+                Some(warning) => format!("Model ready (⚠️ {})", warning),
+                None => "Model ready".to_string(),
+            };
             progress.error = None;
             
-            println!("[LLM] ✅ Model initialized successfully");
+            println!("[LLM] ✅ Model initialized successfully on {}", device.as_str());
         }
         Err(e) => {
+            *LLM_ENGINE_STATE.lock() = EngineState::Error(e.clone());
+            
             let mut progress = LLM_INIT_PROGRESS.lock();
             progress.is_loading = false;
             progress.status = "Failed".to_string();
@@ -349,10 +724,11 @@ fn do_init_llm() {
     }
 }
 
-/// Synchronous init that blocks until model is ready
+/// Synchronous init that blocks until model is ready (uses the last configured model)
 #[napi]
 pub fn init_llm_sync() -> Result<bool> {
-    println!("[LLM] Initializing Qwen2.5 3B (sync)...");
+    let config = current_config();
+    println!("[LLM] Initializing {} (sync)...", config.file);
     
     // Check if already loaded
     {
@@ -361,56 +737,144 @@ pub fn init_llm_sync() -> Result<bool> {
         }
     }
     
-    let result = TOKIO_RUNTIME.block_on(async {
-        let model = GgufModelBuilder::new(
-            GGUF_REPO,
-            vec![GGUF_FILE.to_string()],
-        )
-        .with_tok_model_id(TOKENIZER_REPO)
-        .with_logging()
-        .build()
-        .await
-        .map_err(|e| format!("Model build error: {}", e))?;
-        
-        Ok::<_, String>(model)
-    });
+    *LLM_ENGINE_STATE.lock() = EngineState::Loading;
+    
+    let result = download_to_hf_cache(&config)
+        .map_err(|e| format!("Download error: {}", e))
+        .and_then(|_| load_model(&config, *LLM_DEVICE.lock()));
     
     match result {
-        Ok(model) => {
+        Ok((model, device, _warning)) => {
+            let weights_bytes = cached_gguf_size(&config);
             let mut state = LLM_STATE.lock();
-            *state = Some(LlmEngine { model: Arc::new(model) });
-            println!("[LLM] ✅ Model initialized successfully");
+            *state = Some(LlmEngine { model: Arc::new(model), config, device, weights_bytes });
+            *LLM_ENGINE_STATE.lock() = EngineState::Ready;
+            LLM_IDLE_UNLOADED.store(false, Ordering::SeqCst);
+            touch_llm();
+            println!("[LLM] ✅ Model initialized successfully on {}", device.as_str());
             Ok(true)
         }
         Err(e) => {
             println!("[LLM] ❌ Init failed: {}", e);
+            *LLM_ENGINE_STATE.lock() = EngineState::Error(e.clone());
             Err(Error::from_reason(e))
         }
     }
 }
 
+/// Free the model's memory but keep the downloaded files; init_llm loads it again
+#[napi]
+pub fn unload_llm() {
+    shutdown_llm();
+}
+
 #[napi]
 pub fn shutdown_llm() {
     let mut state = LLM_STATE.lock();
     *state = None;
+    *LLM_ENGINE_STATE.lock() = EngineState::Unloaded;
+    LLM_IDLE_UNLOADED.store(false, Ordering::SeqCst);
     println!("[LLM] Shutdown complete");
 }
 
-/// Delete the downloaded LLM model from HuggingFace cache
+// ============================================================================
+// Idle Unloading
+// ============================================================================
+
+/// Unload the model after `secs` seconds without llm_generate/llm_chat/llm_chat_stream
+/// calls to free its memory (0 disables, the default). The next request starts reloading it in
+/// the background and fails with a [RELOADING] error; retry once get_llm_state() is "ready".
+/// get_llm_init_progress reports the unload and the reload
 #[napi]
-pub fn delete_llm_model() -> Result<bool> {
-    // First shutdown the model if it's loaded
-    {
+pub fn set_llm_idle_timeout(secs: u32) {
+    LLM_IDLE_TIMEOUT_SECS.store(secs as u64, Ordering::SeqCst);
+    touch_llm();
+    
+    if secs > 0 && !LLM_IDLE_WATCHER_STARTED.swap(true, Ordering::SeqCst) {
+        std::thread::spawn(run_idle_watcher);
+    }
+    println!("[LLM] Idle timeout: {}", if secs == 0 { "off".to_string() } else { format!("{}s", secs) });
+}
+
+fn touch_llm() {
+    *LLM_LAST_USED.lock() = Some(std::time::Instant::now());
+}
+
+fn run_idle_watcher() {
+    loop {
+        std::thread::sleep(IDLE_CHECK_INTERVAL);
+        
+        let timeout = LLM_IDLE_TIMEOUT_SECS.load(Ordering::SeqCst);
+        if timeout == 0 || LLM_INIT_PROGRESS.lock().is_loading {
+            continue;
+        }
+        
+        // Checked under the state lock, which acquire_model also holds while registering a request
         let mut state = LLM_STATE.lock();
+        if state.is_none() || LLM_ACTIVE_REQUESTS.load(Ordering::SeqCst) > 0 {
+            continue;
+        }
+        let idle = LLM_LAST_USED.lock().map(|at| at.elapsed()).unwrap_or_default();
+        if idle < std::time::Duration::from_secs(timeout) {
+            continue;
+        }
+        
         *state = None;
+        drop(state);
+        *LLM_ENGINE_STATE.lock() = EngineState::Unloaded;
+        LLM_IDLE_UNLOADED.store(true, Ordering::SeqCst);
+        LLM_INIT_PROGRESS.lock().status = format!("Unloaded after {}s idle (reloads on next request)", timeout);
+        println!("[LLM] Unloaded after {}s idle to free memory", idle.as_secs());
+    }
+}
+
+/// Marks the model as in use; the idle countdown restarts when the request finishes
+struct LlmActivity;
+
+impl LlmActivity {
+    fn begin() -> Self {
+        LLM_ACTIVE_REQUESTS.fetch_add(1, Ordering::SeqCst);
+        LlmActivity
+    }
+}
+
+impl Drop for LlmActivity {
+    fn drop(&mut self) {
+        touch_llm();
+        LLM_ACTIVE_REQUESTS.fetch_sub(1, Ordering::SeqCst);
+    }
+}
+
+/// The loaded model for a request. If the idle timer unloaded it, a background reload is
+/// started and the request fails with [RELOADING] instead of blocking the JS thread for the load
+fn acquire_model() -> Result<(Arc<Model>, LlmDevice, LlmActivity)> {
+    if LLM_STATE.lock().is_none() && LLM_IDLE_UNLOADED.load(Ordering::SeqCst) {
+        if start_init(current_config(), *LLM_DEVICE.lock()) {
+            println!("[LLM] Reloading model unloaded by the idle timer");
+        }
+        return Err(Error::from_reason(
+            "[RELOADING] LLM is reloading after an idle unload, retry once get_llm_state() reports \"ready\"",
+        ));
     }
     
-    let home = dirs::home_dir()
-        .ok_or_else(|| Error::from_reason("Cannot determine home directory"))?;
+    let state = LLM_STATE.lock();
+    let engine = state.as_ref()
+        .ok_or_else(|| Error::from_reason("LLM not initialized. Call init_llm() first."))?;
+    Ok((engine.model.clone(), engine.device, LlmActivity::begin()))
+}
+
+/// Delete the configured LLM model from HuggingFace cache, unloading it first unless
+/// `keep_loaded` is true. A kept model works until unloaded (the weights are mmapped, so on
+/// Windows the delete may fail while it's loaded); with an idle timeout set, the next
+/// idle unload is final since there's nothing left to reload from
+#[napi]
+pub fn delete_llm_model(keep_loaded: Option<bool>) -> Result<bool> {
+    if !keep_loaded.unwrap_or(false) {
+        shutdown_llm();
+    }
     
-    let cache_dir = home.join(".cache/huggingface/hub");
-    let model_dir_name = format!("models--{}", GGUF_REPO.replace("/", "--"));
-    let model_dir = cache_dir.join(&model_dir_name);
+    let model_dir = hub_model_dir(&current_config().repo)
+        .ok_or_else(|| Error::from_reason("Cannot determine home directory"))?;
     
     println!("[LLM] Deleting model at: {}", model_dir.display());
     
@@ -425,34 +889,278 @@ pub fn delete_llm_model() -> Result<bool> {
     }
 }
 
+// ============================================================================
+// Request Building
+// ============================================================================
+
+/// Convert a JSON messages array ([{"role": ..., "content": ...}]) into chat messages
+fn parse_messages(messages: Vec<serde_json::Value>) -> TextMessages {
+    let mut text_messages = TextMessages::new();
+    
+    for msg in messages {
+        let role_str = msg.get("role")
+            .and_then(|r| r.as_str())
+            .unwrap_or("user");
+        let content = msg.get("content")
+            .and_then(|c| c.as_str())
+            .unwrap_or("");
+        
+        let role = match role_str {
+            "system" => TextMessageRole::System,
+            "assistant" => TextMessageRole::Assistant,
+            _ => TextMessageRole::User,
+        };
+        
+        text_messages = text_messages.add_message(role, content);
+    }
+    
+    text_messages
+}
+
+/// Apply sampling options to a request. Temperature 0 selects greedy decoding,
+/// which gives the same output for the same input every time
+fn build_request(
+    messages: TextMessages,
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+    stop: &[String],
+    sampling: &LlmSamplingOptions,
+) -> RequestBuilder {
+    let mut request = RequestBuilder::from(messages);
+    
+    let temperature = temperature.unwrap_or(DEFAULT_TEMPERATURE);
+    let top_k = sampling.top_k.map(|k| k as usize);
+    request = if temperature <= 0.0 {
+        request.set_deterministic_sampler()
+    } else if let Some(seed) = sampling.seed {
+        // mistral.rs draws from one engine-wide RNG, so seeded requests pick the
+        // token themselves and leave the engine's greedy sampler a single choice
+        request
+            .set_deterministic_sampler()
+            .with_logits_processor(Arc::new(SeededSampler {
+                state: Mutex::new(seed as u64),
+                temperature,
+                top_k,
+                top_p: sampling.top_p,
+            }))
+    } else {
+        let mut request = request.set_sampler_temperature(temperature);
+        if let Some(top_k) = top_k {
+            request = request.set_sampler_topk(top_k);
+        }
+        if let Some(top_p) = sampling.top_p {
+            request = request.set_sampler_topp(top_p);
+        }
+        request
+    };
+    
+    if let Some(max_tokens) = max_tokens {
+        request = request.set_sampler_max_len(max_tokens as usize);
+    }
+    
+    if !stop.is_empty() {
+        request = request.set_sampler_stop_toks(StopTokens::Seqs(stop.to_vec()));
+    }
+    
+    request
+}
+
+/// Temperature/top-k/top-p sampling driven by a per-request seeded RNG
+struct SeededSampler {
+    state: Mutex<u64>,
+    temperature: f64,
+    top_k: Option<usize>,
+    top_p: Option<f64>,
+}
+
+impl CustomLogitsProcessor for SeededSampler {
+    fn apply(&self, logits: &Tensor, _context: &[u32]) -> mistralrs::Result<Tensor> {
+        let values = logits.flatten_all()?.to_dtype(DType::F32)?.to_vec1::<f32>()?;
+        let chosen = sample_token(&values, self.temperature, self.top_k, self.top_p, &mut self.state.lock());
+        
+        // Mask everything but the chosen token
+        let masked: Vec<f32> = values.iter()
+            .enumerate()
+            .map(|(i, &v)| if i == chosen { v } else { f32::NEG_INFINITY })
+            .collect();
+        Tensor::from_vec(masked, logits.shape(), logits.device())?.to_dtype(logits.dtype())
+    }
+}
+
+/// SplitMix64 step, returning a uniform value in [0, 1)
+fn next_random(state: &mut u64) -> f64 {
+    *state = state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+    let mut z = *state;
+    z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+    z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+    z ^= z >> 31;
+    (z >> 11) as f64 / (1u64 << 53) as f64
+}
+
+/// Pick a token index from logits with temperature, then top-k, then top-p filtering
+fn sample_token(logits: &[f32], temperature: f64, top_k: Option<usize>, top_p: Option<f64>, state: &mut u64) -> usize {
+    let mut candidates: Vec<usize> = (0..logits.len()).filter(|&i| logits[i].is_finite()).collect();
+    if candidates.is_empty() {
+        return 0;
+    }
+    candidates.sort_by(|&a, &b| logits[b].total_cmp(&logits[a]));
+    if let Some(k) = top_k {
+        candidates.truncate(k.max(1));
+    }
+    
+    let max_logit = logits[candidates[0]] as f64;
+    let mut probs: Vec<f64> = candidates.iter()
+        .map(|&i| ((logits[i] as f64 - max_logit) / temperature).exp())
+        .collect();
+    let total: f64 = probs.iter().sum();
+    for p in &mut probs {
+        *p /= total;
+    }
+    
+    if let Some(top_p) = top_p {
+        let mut cumulative = 0.0;
+        let keep = probs.iter()
+            .position(|&p| {
+                cumulative += p;
+                cumulative >= top_p
+            })
+            .map(|i| i + 1)
+            .unwrap_or(probs.len());
+        probs.truncate(keep);
+    }
+    
+    let mut target = next_random(state) * probs.iter().sum::<f64>();
+    for (i, p) in probs.iter().enumerate() {
+        target -= p;
+        if target <= 0.0 {
+            return candidates[i];
+        }
+    }
+    candidates[probs.len() - 1]
+}
+
+/// Cut text at the earliest stop sequence, if any occurs
+fn truncate_at_stop(text: &mut String, stop: &[String]) {
+    if let Some(pos) = stop.iter().filter(|s| !s.is_empty()).filter_map(|s| text.find(s.as_str())).min() {
+        text.truncate(pos);
+    }
+}
+
+/// Finds stop sequences in streamed text, including ones split across chunks,
+/// by holding back any tail that could be the start of a stop sequence
+struct StopMatcher {
+    stop: Vec<String>,
+    pending: String,
+}
+
+impl StopMatcher {
+    fn new(stop: Vec<String>) -> Self {
+        Self {
+            stop: stop.into_iter().filter(|s| !s.is_empty()).collect(),
+            pending: String::new(),
+        }
+    }
+    
+    /// Add a chunk; returns the text that is safe to emit and whether a stop sequence was hit
+    fn push(&mut self, chunk: &str) -> (String, bool) {
+        self.pending.push_str(chunk);
+        
+        let before_len = self.pending.len();
+        truncate_at_stop(&mut self.pending, &self.stop);
+        if self.pending.len() < before_len {
+            return (std::mem::take(&mut self.pending), true);
+        }
+        
+        let hold = self.stop.iter()
+            .map(|s| partial_stop_len(&self.pending, s))
+            .max()
+            .unwrap_or(0);
+        let emit: String = self.pending.drain(..self.pending.len() - hold).collect();
+        (emit, false)
+    }
+    
+    /// Text held back at the end of the stream
+    fn flush(&mut self) -> String {
+        std::mem::take(&mut self.pending)
+    }
+}
+
+/// Length of the longest suffix of `text` that is a proper prefix of `stop`
+fn partial_stop_len(text: &str, stop: &str) -> usize {
+    (1..stop.len())
+        .rev()
+        .find(|&len| stop.is_char_boundary(len) && text.ends_with(&stop[..len]))
+        .unwrap_or(0)
+}
+
+/// Count tokens with the loaded model's tokenizer. Messages are rendered through
+/// the chat template (including the assistant prompt), raw text is counted as-is
+fn count_tokens(model: &Model, input: Either<TextMessages, String>) -> std::result::Result<usize, String> {
+    let add_generation_prompt = input.is_left();
+    TOKIO_RUNTIME.block_on(model.tokenize(input, None, false, add_generation_prompt, None))
+        .map(|tokens| tokens.len())
+        .map_err(|e| format!("Tokenize error: {}", e))
+}
+
+/// Reject prompts that would overflow the context window before generating anything
+fn check_input_length(model: &Model, messages: &TextMessages) -> Result<()> {
+    let tokens = count_tokens(model, Either::Left(messages.clone()))
+        .map_err(Error::from_reason)?;
+    if tokens > MAX_INPUT_TOKENS {
+        println!("[LLM] ❌ Input too long: {} tokens", tokens);
+        return Err(Error::from_reason(format!(
+            "Input too long: {} tokens (max {})", tokens, MAX_INPUT_TOKENS
+        )));
+    }
+    Ok(())
+}
+
 // ============================================================================
 // NAPI Exports - Inference
 // ============================================================================
 
-/// Generate text completion using the local LLM
+/// Number of tokens the loaded model's tokenizer produces for `text`
 #[napi]
-pub fn llm_generate(prompt: String, _max_tokens: Option<u32>, _temperature: Option<f64>) -> Result<LlmResponse> {
-    let state = LLM_STATE.lock();
+pub fn llm_count_tokens(text: String) -> Result<u32> {
+    let (model, _device, _activity) = acquire_model()?;
     
-    let engine = state.as_ref()
-        .ok_or_else(|| Error::from_reason("LLM not initialized. Call init_llm() first."))?;
+    count_tokens(&model, Either::Right(text))
+        .map(|tokens| tokens as u32)
+        .map_err(Error::from_reason)
+}
+
+/// Generate text completion using the local LLM
+/// Temperature defaults to 0.7; 0 gives deterministic (greedy) output
+/// Output ends before the first of the `stop` sequences, if given
+#[napi]
+pub fn llm_generate(
+    prompt: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+    stop: Option<Vec<String>>,
+    sampling: Option<LlmSamplingOptions>,
+) -> Result<LlmResponse> {
+    let (model, device, _activity) = acquire_model()?;
     
     println!("[LLM] Generate called with prompt length: {}", prompt.len());
+    let device = device.as_str();
+    let stop = stop.unwrap_or_default();
     
-    let model = engine.model.clone();
-    drop(state); // Release lock before async operation
+    let messages = TextMessages::new()
+        .add_message(TextMessageRole::User, &prompt);
+    check_input_length(&model, &messages)?;
     
     let result = TOKIO_RUNTIME.block_on(async {
-        let messages = TextMessages::new()
-            .add_message(TextMessageRole::User, &prompt);
+        let request = build_request(messages, max_tokens, temperature, &stop, &sampling.unwrap_or_default());
         
-        let response = model.send_chat_request(messages).await
+        let response = model.send_chat_request(request).await
             .map_err(|e| format!("Generation error: {}", e))?;
         
-        let text = response.choices.get(0)
+        let mut text = response.choices.get(0)
             .and_then(|c| c.message.content.as_ref())
             .map(|s| s.to_string())
             .unwrap_or_default();
+        truncate_at_stop(&mut text, &stop);
         
         Ok::<_, String>(LlmResponse {
             text,
@@ -464,8 +1172,8 @@ pub fn llm_generate(prompt: String, _max_tokens: Option<u32>, _temperature: Opti
     
     match result {
         Ok(response) => {
-            println!("[LLM] ✅ Generated {} tokens at {:.1} tok/s", 
-                response.completion_tokens, response.tokens_per_second);
+            println!("[LLM] ✅ Generated {} tokens at {:.1} tok/s ({})", 
+                response.completion_tokens, response.tokens_per_second, device);
             Ok(response)
         }
         Err(e) => {
@@ -477,49 +1185,39 @@ pub fn llm_generate(prompt: String, _max_tokens: Option<u32>, _temperature: Opti
 
 /// Chat completion - takes messages array and returns response
 /// Messages format: [{"role": "system", "content": "..."}, {"role": "user", "content": "..."}]
+/// Output ends before the first of the `stop` sequences, if given
 #[napi]
-pub fn llm_chat(messages_json: String, _max_tokens: Option<u32>, _temperature: Option<f64>) -> Result<LlmResponse> {
-    let state = LLM_STATE.lock();
-    
-    let engine = state.as_ref()
-        .ok_or_else(|| Error::from_reason("LLM not initialized. Call init_llm() first."))?;
-    
+pub fn llm_chat(
+    messages_json: String,
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+    stop: Option<Vec<String>>,
+    sampling: Option<LlmSamplingOptions>,
+) -> Result<LlmResponse> {
     // Parse messages JSON
     let messages: Vec<serde_json::Value> = serde_json::from_str(&messages_json)
         .map_err(|e| Error::from_reason(format!("Invalid JSON: {}", e)))?;
     
+    let (model, device, _activity) = acquire_model()?;
+    
     println!("[LLM] Chat called with {} messages", messages.len());
+    let device = device.as_str();
+    let stop = stop.unwrap_or_default();
     
-    let model = engine.model.clone();
-    drop(state); // Release lock before async operation
+    let messages = parse_messages(messages);
+    check_input_length(&model, &messages)?;
     
     let result = TOKIO_RUNTIME.block_on(async {
-        let mut text_messages = TextMessages::new();
+        let request = build_request(messages, max_tokens, temperature, &stop, &sampling.unwrap_or_default());
         
-        for msg in messages {
-            let role_str = msg.get("role")
-                .and_then(|r| r.as_str())
-                .unwrap_or("user");
-            let content = msg.get("content")
-                .and_then(|c| c.as_str())
-                .unwrap_or("");
-            
-            let role = match role_str {
-                "system" => TextMessageRole::System,
-                "assistant" => TextMessageRole::Assistant,
-                _ => TextMessageRole::User,
-            };
-            
-            text_messages = text_messages.add_message(role, content);
-        }
-        
-        let response = model.send_chat_request(text_messages).await
+        let response = model.send_chat_request(request).await
             .map_err(|e| format!("Chat error: {}", e))?;
         
-        let text = response.choices.get(0)
+        let mut text = response.choices.get(0)
             .and_then(|c| c.message.content.as_ref())
             .map(|s| s.to_string())
             .unwrap_or_default();
+        truncate_at_stop(&mut text, &stop);
         
         Ok::<_, String>(LlmResponse {
             text,
@@ -531,8 +1229,8 @@ pub fn llm_chat(messages_json: String, _max_tokens: Option<u32>, _temperature: O
     
     match result {
         Ok(response) => {
-            println!("[LLM] ✅ Chat response: {} tokens at {:.1} tok/s", 
-                response.completion_tokens, response.tokens_per_second);
+            println!("[LLM] ✅ Chat response: {} tokens at {:.1} tok/s ({})", 
+                response.completion_tokens, response.tokens_per_second, device);
             Ok(response)
         }
         Err(e) => {
@@ -544,21 +1242,37 @@ pub fn llm_chat(messages_json: String, _max_tokens: Option<u32>, _temperature: O
 
 /// Stream chat completion - returns chunks as they're generated
 /// This is useful for showing real-time responses
+/// Returns a stream id that can be passed to cancel_llm_stream
+/// max_tokens and temperature work as in llm_chat
+/// Streaming ends before the first of the `stop` sequences, if given
 #[napi]
-pub fn llm_chat_stream(messages_json: String, callback: JsFunction) -> Result<()> {
-    let state = LLM_STATE.lock();
-    
-    let engine = state.as_ref()
-        .ok_or_else(|| Error::from_reason("LLM not initialized. Call init_llm() first."))?;
-    
+pub fn llm_chat_stream(
+    messages_json: String,
+    callback: JsFunction,
+    max_tokens: Option<u32>,
+    temperature: Option<f64>,
+    stop: Option<Vec<String>>,
+    sampling: Option<LlmSamplingOptions>,
+) -> Result<u32> {
     // Parse messages JSON
     let messages: Vec<serde_json::Value> = serde_json::from_str(&messages_json)
         .map_err(|e| Error::from_reason(format!("Invalid JSON: {}", e)))?;
     
+    let (model, _device, activity) = acquire_model()?;
+    
     println!("[LLM] Stream chat called with {} messages", messages.len());
     
-    let model = engine.model.clone();
-    drop(state);
+    let messages = parse_messages(messages);
+    check_input_length(&model, &messages)?;
+    
+    let stream_id = {
+        let mut next = NEXT_STREAM_ID.lock();
+        let id = *next;
+        *next = next.wrapping_add(1).max(1);
+        id
+    };
+    let cancelled = Arc::new(AtomicBool::new(false));
+    LLM_STREAMS.lock().insert(stream_id, cancelled.clone());
     
     // Create threadsafe function for callback
     let tsfn: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
@@ -568,42 +1282,48 @@ pub fn llm_chat_stream(messages_json: String, callback: JsFunction) -> Result<()
     
     std::thread::spawn(move || {
         let result = TOKIO_RUNTIME.block_on(async {
-            let mut text_messages = TextMessages::new();
-            
-            for msg in messages {
-                let role_str = msg.get("role")
-                    .and_then(|r| r.as_str())
-                    .unwrap_or("user");
-                let content = msg.get("content")
-                    .and_then(|c| c.as_str())
-                    .unwrap_or("");
-                
-                let role = match role_str {
-                    "system" => TextMessageRole::System,
-                    "assistant" => TextMessageRole::Assistant,
-                    _ => TextMessageRole::User,
-                };
-                
-                text_messages = text_messages.add_message(role, content);
-            }
-            
-            let request = RequestBuilder::from(text_messages);
+            // Stop sequences are matched locally so they're caught across chunk boundaries
+            let request = build_request(messages, max_tokens, temperature, &[], &sampling.unwrap_or_default());
+            let mut stop_matcher = StopMatcher::new(stop.unwrap_or_default());
             
             match model.stream_chat_request(request).await {
                 Ok(mut stream) => {
                     while let Some(chunk) = stream.next().await {
+                        if cancelled.load(Ordering::SeqCst) {
+                            break;
+                        }
                         if let Response::Chunk(ChatCompletionChunkResponse { choices, .. }) = chunk {
                             if let Some(ChunkChoice {
                                 delta: Delta { content: Some(content), .. },
                                 ..
                             }) = choices.first()
                             {
-                                tsfn.call(content.clone(), ThreadsafeFunctionCallMode::NonBlocking);
+                                let (text, stopped) = stop_matcher.push(content);
+                                if !text.is_empty() {
+                                    tsfn.call(text, ThreadsafeFunctionCallMode::NonBlocking);
+                                }
+                                if stopped {
+                                    break;
+                                }
                             }
                         }
                     }
+                    // Drop the stream before signalling so the model is free for the next request
+                    drop(stream);
+                    
+                    // Text held back as a possible stop-sequence prefix that never completed
+                    let tail = stop_matcher.flush();
+                    if !tail.is_empty() && !cancelled.load(Ordering::SeqCst) {
+                        tsfn.call(tail, ThreadsafeFunctionCallMode::NonBlocking);
+                    }
+                    
                     // Signal completion
-                    tsfn.call("[DONE]".to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+                    if cancelled.load(Ordering::SeqCst) {
+                        println!("[LLM] Stream {} cancelled", stream_id);
+                        tsfn.call("[CANCELLED]".to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+                    } else {
+                        tsfn.call("[DONE]".to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+                    }
                     Ok(())
                 }
                 Err(e) => {
@@ -619,10 +1339,163 @@ pub fn llm_chat_stream(messages_json: String, callback: JsFunction) -> Result<()
         // but prevents the crash. This is a known issue with napi-rs + Electron.
         std::mem::forget(tsfn);
         
+        LLM_STREAMS.lock().remove(&stream_id);
+        drop(activity);
+        
         if let Err(e) = result {
             println!("[LLM] Stream error: {}", e);
         }
     });
     
-    Ok(())
+    Ok(stream_id)
+}
+
+/// Stop an llm_chat_stream early; its callback receives "[CANCELLED]" instead of "[DONE]"
+/// Returns false if the stream already finished or the id is unknown
+#[napi]
+pub fn cancel_llm_stream(stream_id: u32) -> bool {
+    match LLM_STREAMS.lock().get(&stream_id) {
+        Some(cancelled) => {
+            cancelled.store(true, Ordering::SeqCst);
+            true
+        }
+        None => false,
+    }
+}
+
+// ============================================================================
+// NAPI Exports - Embeddings
+// ============================================================================
+
+/// Embed `text` with the loaded LLM (pooled over the prompt, L2-normalized), so chat-only
+/// setups can do semantic search without also loading the MiniLM embedding model
+///
+/// The vector has the LLM's hidden size (2048 for Qwen2.5 3B), not MiniLM's 384, and lives in
+/// a different space: never compare or index these against generate_embedding vectors.
+/// Fails with "Model does not support embeddings" if the loaded model's architecture can't
+/// produce them
+#[napi]
+pub fn llm_embed(text: String) -> Result<Vec<f64>> {
+    let (model, _device, _activity) = acquire_model()?;
+    
+    let embedding = TOKIO_RUNTIME.block_on(model.generate_embedding(text)).map_err(|e| e.to_string());
+    normalized_embedding(embedding).map_err(Error::from_reason)
+}
+
+/// L2-normalize the LLM's pooled output; an error or an empty vector means the architecture
+/// has no embedding support
+fn normalized_embedding(embedding: std::result::Result<Vec<f32>, String>) -> std::result::Result<Vec<f64>, String> {
+    let embedding = embedding.map_err(|e| format!("Model does not support embeddings: {}", e))?;
+    if embedding.is_empty() {
+        return Err("Model does not support embeddings: empty output".to_string());
+    }
+    
+    let norm: f32 = embedding.iter().map(|x| x * x).sum::<f32>().sqrt();
+    Ok(embedding.iter()
+        .map(|&v| if norm > 0.0 { (v / norm) as f64 } else { v as f64 })
+        .collect())
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn run_matcher(stop: &[&str], chunks: &[&str]) -> (String, bool) {
+        let mut matcher = StopMatcher::new(stop.iter().map(|s| s.to_string()).collect());
+        let mut out = String::new();
+        for chunk in chunks {
+            let (text, stopped) = matcher.push(chunk);
+            out.push_str(&text);
+            if stopped {
+                return (out, true);
+            }
+        }
+        out.push_str(&matcher.flush());
+        (out, false)
+    }
+
+    #[test]
+    fn test_embedding_support_errors_are_explicit() {
+        let unsupported = normalized_embedding(Err("not implemented for this architecture".to_string())).unwrap_err();
+        assert!(unsupported.starts_with("Model does not support embeddings"));
+        assert!(normalized_embedding(Ok(Vec::new())).unwrap_err().starts_with("Model does not support embeddings"));
+
+        let embedding = normalized_embedding(Ok(vec![3.0, 4.0])).unwrap();
+        assert!((embedding[0] - 0.6).abs() < 1e-6);
+        assert!((embedding[1] - 0.8).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_stop_sequence_split_across_chunks() {
+        assert_eq!(run_matcher(&["</answer>"], &["Paris</ans", "wer> and more"]), ("Paris".to_string(), true));
+        assert_eq!(run_matcher(&["\n\n"], &["one\n", "\ntwo"]), ("one".to_string(), true));
+    }
+
+    #[test]
+    fn test_partial_stop_prefix_is_flushed() {
+        // "</a" looked like the start of the stop sequence but never completed
+        assert_eq!(run_matcher(&["</answer>"], &["x </a", "b> y"]), ("x </ab> y".to_string(), false));
+        assert_eq!(run_matcher(&["END"], &["the EN"]), ("the EN".to_string(), false));
+    }
+
+    #[test]
+    fn test_recorded_size_must_match_exactly() {
+        let recorded = LlmConfig { file_size: Some(1_900_000_000), ..LlmConfig::default() };
+        assert!(recorded.is_complete_size(1_900_000_000));
+        assert!(!recorded.is_complete_size(650_000_000));
+        // Without a recorded size only the floor applies
+        assert!(LlmConfig::default().is_complete_size(650_000_000));
+        assert!(!LlmConfig::default().is_complete_size(1_000));
+        assert!(recorded.same_model(&LlmConfig::default()));
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_zero_temperature_is_deterministic
+    #[test]
+    #[ignore]
+    fn test_zero_temperature_is_deterministic() {
+        if !is_llm_downloaded() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+        init_llm_sync().unwrap();
+
+        let prompt = "Name three colors.".to_string();
+        let first = llm_generate(prompt.clone(), Some(32), Some(0.0), None, None).unwrap();
+        let second = llm_generate(prompt, Some(32), Some(0.0), None, None).unwrap();
+        assert_eq!(first.text, second.text);
+        assert!(first.completion_tokens <= 32);
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_fixed_seed_is_reproducible
+    #[test]
+    #[ignore]
+    fn test_fixed_seed_is_reproducible() {
+        if !is_llm_downloaded() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+        init_llm_sync().unwrap();
+
+        let sampling = LlmSamplingOptions { top_p: Some(0.9), top_k: Some(40), seed: Some(42) };
+        let prompt = "Write a one-line poem about rain.".to_string();
+        let first = llm_generate(prompt.clone(), Some(32), Some(1.0), None, Some(sampling.clone())).unwrap();
+        let second = llm_generate(prompt, Some(32), Some(1.0), None, Some(sampling)).unwrap();
+        assert_eq!(first.text, second.text);
+    }
+
+    #[test]
+    fn test_seeded_sampling_repeats() {
+        let logits = [1.0f32, 2.0, 0.5, 1.5, f32::NEG_INFINITY];
+        let draw = |seed: u64| {
+            let mut state = seed;
+            (0..20).map(|_| sample_token(&logits, 1.0, None, None, &mut state)).collect::<Vec<_>>()
+        };
+        assert_eq!(draw(7), draw(7));
+        assert!(draw(7).iter().all(|&i| i < 4));
+
+        // top_k = 1 and a tiny top_p both reduce to argmax
+        let mut state = 7;
+        assert_eq!(sample_token(&logits, 1.0, Some(1), None, &mut state), 1);
+        assert_eq!(sample_token(&logits, 1.0, None, Some(0.01), &mut state), 1);
+    }
 }
@@ -1,6 +1,7 @@
 //! macOS accessibility API for reading UI elements
 
-use core_foundation::base::TCFType;
+use crate::BrowserTabInfo;
+use core_foundation::base::{CFType, TCFType};
 use core_foundation::string::CFString;
 use std::ffi::c_void;
 use std::ptr::null_mut;
@@ -19,8 +20,86 @@ extern "C" {
     fn CFRelease(cf: *const c_void);
 }
 
+// ============================================================================
+// Per-browser address bar strategies
+// ============================================================================
+
+/// How to recognize the address bar in a particular browser's AX tree
+struct UrlBarStrategy {
+    bundle_ids: &'static [&'static str],
+    /// Candidate element roles
+    roles: &'static [&'static str],
+    /// Lowercase substrings matched against AXIdentifier, AXDescription,
+    /// AXRoleDescription and AXPlaceholderValue
+    hints: &'static [&'static str],
+    /// Recursion cap for the tree walk
+    max_depth: u32,
+}
+
+const DEFAULT_MAX_DEPTH: u32 = 15;
+
+/// Used for unknown browsers - the original generic walk
+const GENERIC_STRATEGY: UrlBarStrategy = UrlBarStrategy {
+    bundle_ids: &[],
+    roles: &["AXTextField", "AXComboBox"],
+    hints: &["url", "address", "omnibox"],
+    max_depth: DEFAULT_MAX_DEPTH,
+};
+
+const BROWSER_STRATEGIES: &[UrlBarStrategy] = &[
+    // Safari: WEB_BROWSER_ADDRESS_AND_SEARCH_FIELD
+    UrlBarStrategy {
+        bundle_ids: &["com.apple.Safari", "com.apple.SafariTechnologyPreview"],
+        roles: &["AXTextField"],
+        hints: &["address", "url"],
+        max_depth: DEFAULT_MAX_DEPTH,
+    },
+    // Chromium omnibox: "Address and search bar"
+    UrlBarStrategy {
+        bundle_ids: &[
+            "com.google.Chrome",
+            "com.google.Chrome.canary",
+            "com.microsoft.edgemac",
+            "com.brave.Browser",
+            "com.vivaldi.Vivaldi",
+            "com.operasoftware.Opera",
+        ],
+        roles: &["AXTextField", "AXComboBox"],
+        hints: &["omnibox", "address", "url"],
+        max_depth: DEFAULT_MAX_DEPTH,
+    },
+    // Firefox: urlbar with a "Search or enter address" placeholder, nested deeper than Chromium
+    UrlBarStrategy {
+        bundle_ids: &["org.mozilla.firefox", "org.mozilla.firefoxdeveloperedition", "org.mozilla.nightly"],
+        roles: &["AXTextField", "AXComboBox"],
+        hints: &["search or enter address", "urlbar", "address"],
+        max_depth: 25,
+    },
+    // Arc: the URL lives in the sidebar command bar rather than a toolbar field
+    UrlBarStrategy {
+        bundle_ids: &["company.thebrowser.Browser"],
+        roles: &["AXTextField", "AXStaticText"],
+        hints: &["commandbar", "url", "address"],
+        max_depth: 20,
+    },
+];
+
+fn strategy_for_bundle(bundle_id: Option<&str>) -> &'static UrlBarStrategy {
+    bundle_id
+        .and_then(|id| BROWSER_STRATEGIES.iter().find(|s| s.bundle_ids.contains(&id)))
+        .unwrap_or(&GENERIC_STRATEGY)
+}
+
 /// Get the URL from a browser window using accessibility APIs
 pub fn get_browser_url(pid: i32) -> Option<String> {
+    get_browser_tab_info(pid).map(|tab| tab.url)
+}
+
+/// Get the URL and active tab title from a browser's focused window in one AX pass
+/// The focused window's AXTitle usually holds the active tab title
+pub fn get_browser_tab_info(pid: i32) -> Option<BrowserTabInfo> {
+    let strategy = strategy_for_bundle(super::window::get_bundle_id_for_pid(pid).as_deref());
+    
     unsafe {
         // Create accessibility element for the application
         let app = AXUIElementCreateApplication(pid);
@@ -43,24 +122,103 @@ pub fn get_browser_url(pid: i32) -> Option<String> {
         }
         
         // Try to find URL bar by traversing the accessibility tree
-        // Limit depth to 15 levels to prevent stack overflow
-        let url = find_url_element(focused_window, 0, 15);
+        let url = find_url_element(focused_window, strategy, 0);
+        let title = url
+            .as_ref()
+            .and_then(|_| copy_string_attribute(focused_window, "AXTitle"))
+            .filter(|t| !t.is_empty());
         
         CFRelease(app as *const c_void);
         if !focused_window.is_null() {
             CFRelease(focused_window as *const c_void);
         }
         
-        url
+        url.map(|url| BrowserTabInfo { url, title })
     }
 }
 
+/// Get the URLs of all of a browser's windows (not just the focused one), front to back,
+/// de-duplicated; windows without a readable address bar are skipped
+pub fn get_browser_urls(pid: i32) -> Vec<String> {
+    let strategy = strategy_for_bundle(super::window::get_bundle_id_for_pid(pid).as_deref());
+    let mut urls: Vec<String> = Vec::new();
+    
+    unsafe {
+        let app = AXUIElementCreateApplication(pid);
+        if app.is_null() {
+            return urls;
+        }
+        
+        let mut windows: *const c_void = null_mut() as *const c_void;
+        let attr_windows = CFString::new("AXWindows");
+        let result = AXUIElementCopyAttributeValue(
+            app,
+            attr_windows.as_concrete_TypeRef() as CFStringRef,
+            &mut windows as *mut _ as *mut *const c_void,
+        );
+        
+        if result == 0 && !windows.is_null() {
+            let windows_array: core_foundation::array::CFArray<CFType> =
+                core_foundation::array::CFArray::wrap_under_create_rule(windows as *const _);
+            
+            for i in 0..windows_array.len() {
+                let Some(window) = windows_array.get(i) else { continue };
+                let window_element = window.as_CFTypeRef() as AXUIElementRef;
+                if let Some(url) = find_url_element(window_element, strategy, 0) {
+                    if !url.is_empty() && !urls.contains(&url) {
+                        urls.push(url);
+                    }
+                }
+            }
+        }
+        
+        CFRelease(app as *const c_void);
+    }
+    
+    urls
+}
+
+/// Copy a string-valued attribute, or None if missing or not a string
+unsafe fn copy_string_attribute(element: AXUIElementRef, attribute: &str) -> Option<String> {
+    let mut value: *const c_void = null_mut() as *const c_void;
+    let attr = CFString::new(attribute);
+    let result = AXUIElementCopyAttributeValue(
+        element,
+        attr.as_concrete_TypeRef() as CFStringRef,
+        &mut value as *mut _ as *mut *const c_void,
+    );
+    
+    if result != 0 || value.is_null() {
+        return None;
+    }
+    
+    CFType::wrap_under_create_rule(value as *const _)
+        .downcast_into::<CFString>()
+        .map(|s| s.to_string())
+}
+
+/// Whether an element looks like the address bar under the given strategy
+unsafe fn is_url_bar(element: AXUIElementRef, strategy: &UrlBarStrategy) -> bool {
+    let role = match copy_string_attribute(element, "AXRole") {
+        Some(role) => role,
+        None => return false,
+    };
+    if !strategy.roles.contains(&role.as_str()) {
+        return false;
+    }
+    
+    ["AXIdentifier", "AXDescription", "AXRoleDescription", "AXPlaceholderValue"]
+        .iter()
+        .filter_map(|attr| copy_string_attribute(element, attr))
+        .map(|text| text.to_lowercase())
+        .any(|text| strategy.hints.iter().any(|hint| text.contains(hint)))
+}
+
 /// Navigate the accessibility tree to find URL element
-/// depth: current recursion depth
-/// max_depth: maximum allowed depth to prevent stack overflow
-unsafe fn find_url_element(element: AXUIElementRef, depth: u32, max_depth: u32) -> Option<String> {
+/// depth: current recursion depth, capped by the strategy's max_depth
+unsafe fn find_url_element(element: AXUIElementRef, strategy: &UrlBarStrategy, depth: u32) -> Option<String> {
     // Prevent stack overflow by limiting recursion depth
-    if depth >= max_depth {
+    if depth >= strategy.max_depth {
         return None;
     }
     
@@ -77,80 +235,21 @@ unsafe fn find_url_element(element: AXUIElementRef, depth: u32, max_depth: u32)
         return None;
     }
     
-    let children_array: core_foundation::array::CFArray<core_foundation::base::CFType> = 
-        core_foundation::array::CFArray::wrap_under_get_rule(children as *const _);
+    let children_array: core_foundation::array::CFArray<CFType> =
+        core_foundation::array::CFArray::wrap_under_create_rule(children as *const _);
     
     for i in 0..children_array.len() {
         if let Some(child) = children_array.get(i) {
             let child_element = child.as_CFTypeRef() as AXUIElementRef;
             
-            // Check role
-            let mut role: *const c_void = null_mut() as *const c_void;
-            let attr_role = CFString::new("AXRole");
-            let _ = AXUIElementCopyAttributeValue(
-                child_element,
-                attr_role.as_concrete_TypeRef() as CFStringRef,
-                &mut role as *mut _ as *mut *const c_void,
-            );
-            
-            if !role.is_null() {
-                let role_str: CFString = CFString::wrap_under_get_rule(role as *const _);
-                let role_string = role_str.to_string();
-                
-                // Check for text field (address bar)
-                if role_string == "AXTextField" || role_string == "AXComboBox" {
-                    // Check if this looks like a URL bar
-                    let mut identifier: *const c_void = null_mut() as *const c_void;
-                    let attr_id = CFString::new("AXIdentifier");
-                    let _ = AXUIElementCopyAttributeValue(
-                        child_element,
-                        attr_id.as_concrete_TypeRef() as CFStringRef,
-                        &mut identifier as *mut _ as *mut *const c_void,
-                    );
-                    
-                    let is_url_bar = if !identifier.is_null() {
-                        let id_str: CFString = CFString::wrap_under_get_rule(identifier as *const _);
-                        let id_string = id_str.to_string().to_lowercase();
-                        id_string.contains("url") || id_string.contains("address") || id_string.contains("omnibox")
-                    } else {
-                        // Check description as fallback
-                        let mut desc: *const c_void = null_mut() as *const c_void;
-                        let attr_desc = CFString::new("AXDescription");
-                        let _ = AXUIElementCopyAttributeValue(
-                            child_element,
-                            attr_desc.as_concrete_TypeRef() as CFStringRef,
-                            &mut desc as *mut _ as *mut *const c_void,
-                        );
-                        
-                        if !desc.is_null() {
-                            let desc_str: CFString = CFString::wrap_under_get_rule(desc as *const _);
-                            let desc_string = desc_str.to_string().to_lowercase();
-                            desc_string.contains("url") || desc_string.contains("address")
-                        } else {
-                            false
-                        }
-                    };
-                    
-                    if is_url_bar {
-                        // Get the value (URL)
-                        let mut value: *const c_void = null_mut() as *const c_void;
-                        let attr_value = CFString::new("AXValue");
-                        let _ = AXUIElementCopyAttributeValue(
-                            child_element,
-                            attr_value.as_concrete_TypeRef() as CFStringRef,
-                            &mut value as *mut _ as *mut *const c_void,
-                        );
-                        
-                        if !value.is_null() {
-                            let value_str: CFString = CFString::wrap_under_get_rule(value as *const _);
-                            return Some(value_str.to_string());
-                        }
-                    }
+            if is_url_bar(child_element, strategy) {
+                if let Some(url) = copy_string_attribute(child_element, "AXValue").filter(|v| !v.is_empty()) {
+                    return Some(url);
                 }
             }
             
             // Recursively search children (increment depth)
-            if let Some(url) = find_url_element(child_element, depth + 1, max_depth) {
+            if let Some(url) = find_url_element(child_element, strategy, depth + 1) {
                 return Some(url);
             }
         }
@@ -11,15 +11,16 @@
 //! audio BEFORE it's routed to the output device, making it work regardless of
 //! whether the user is using Bluetooth headphones, wired headphones, or speakers.
 
-use crate::audio::{AudioError, WavHeader};
+use crate::audio::{apply_auto_gain, chunk_samples, create_private_file, queue_mono_chunks, soft_limit, resample_stream, resample_stream_to_16k, retain_chunk, trim_silence, try_resample_audio, AudioError, HighPassFilter, SilenceTrim, StreamResampler, WavHeader, WavSampleFormat, TARGET_SAMPLE_RATE};
+use crate::{CaptureFormat, DisplayInfo};
+use core_graphics::display::CGDisplay;
 use cocoa::base::{id, nil, BOOL, NO, YES};
 use objc::declare::ClassDecl;
 use objc::runtime::{Class, Object, Sel};
 use objc::{class, msg_send, sel, sel_impl};
-use parking_lot::Mutex;
+use parking_lot::{Mutex, RwLock};
 use std::collections::VecDeque;
 use std::ffi::c_void;
-use std::fs::File;
 use std::io::Write;
 use std::ptr::null_mut;
 use std::sync::atomic::{AtomicBool, AtomicPtr, AtomicU64, Ordering};
@@ -47,8 +48,19 @@ extern "C" {
     fn CMAudioFormatDescriptionGetStreamBasicDescription(
         desc: id,
     ) -> *const AudioStreamBasicDescription;
+    fn AudioUnitSetProperty(
+        unit: *mut c_void,
+        property_id: u32,
+        scope: u32,
+        element: u32,
+        data: *const c_void,
+        data_size: u32,
+    ) -> i32;
 }
 
+const AUDIO_OUTPUT_UNIT_PROPERTY_CURRENT_DEVICE: u32 = 2000;
+const AUDIO_UNIT_SCOPE_GLOBAL: u32 = 0;
+
 extern "C" {
     fn dispatch_get_global_queue(identifier: i64, flags: u64) -> id;
     fn dispatch_semaphore_create(value: i64) -> *mut c_void;
@@ -79,12 +91,24 @@ struct AudioStreamBasicDescription {
 // Global State
 // ============================================================================
 
-static CURRENT_LEVEL: Mutex<f64> = Mutex::new(0.0);
+static SYSTEM_LEVEL: Mutex<f64> = Mutex::new(0.0);
+static MIC_LEVEL: Mutex<f64> = Mutex::new(0.0);
 static IS_CAPTURING: AtomicBool = AtomicBool::new(false);
+/// Stream stays alive, samples are dropped. Callbacks hold the read lock while they append,
+/// so a pause can't land between their check and their writes to the buffers
+static IS_PAUSED: RwLock<bool> = RwLock::new(false);
 static SAMPLE_RATE: AtomicU64 = AtomicU64::new(48000);
+static MIC_SAMPLE_RATE: AtomicU64 = AtomicU64::new(48000);
 static CHANNELS: AtomicU64 = AtomicU64::new(2);
+static SYSTEM_FORMAT_KNOWN: AtomicBool = AtomicBool::new(false);  // Set once SAMPLE_RATE/CHANNELS come from a real buffer
+// Format of SYSTEM_AUDIO_DATA: the first buffer's format, kept for the whole recording so a
+// mid-stream device switch doesn't mix rates in the WAV
+static WAV_SAMPLE_RATE: AtomicU64 = AtomicU64::new(48000);
+static WAV_CHANNELS: AtomicU64 = AtomicU64::new(2);
 
 // Separate buffers for system and mic audio (for WAV saving)
+/// Off for streaming-only captures (save_wav false), which keep no raw audio for a WAV
+static SAVE_WAV: AtomicBool = AtomicBool::new(true);
 static SYSTEM_AUDIO_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());  // System audio (float32)
 static MIC_AUDIO_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());     // Microphone audio (float32)
 
@@ -92,29 +116,91 @@ static MIC_AUDIO_DATA: Mutex<Vec<u8>> = Mutex::new(Vec::new());     // Microphon
 // Each chunk is already formatted as stereo 16-bit PCM (L=system, R=mic)
 static AUDIO_CHUNK_QUEUE: Mutex<VecDeque<Vec<u8>>> = Mutex::new(VecDeque::new());
 
+// Queue cap so a stalled consumer can't grow memory unbounded (~100ms per chunk)
+pub const DEFAULT_MAX_QUEUED_CHUNKS: u64 = 200;
+static MAX_QUEUED_CHUNKS: AtomicU64 = AtomicU64::new(DEFAULT_MAX_QUEUED_CHUNKS);
+static DROPPED_CHUNKS: AtomicU64 = AtomicU64::new(0);
+
+// Voice activity gate: chunks where both channels are below the RMS threshold are silent
+static VAD_ENABLED: AtomicBool = AtomicBool::new(false);
+static VAD_THRESHOLD: Mutex<f64> = Mutex::new(0.01);
+static TOTAL_CHUNKS: AtomicU64 = AtomicU64::new(0);
+static SILENT_CHUNKS: AtomicU64 = AtomicU64::new(0);
+
 // Intermediate buffers for building stereo chunks
 static SYSTEM_BUFFER: Mutex<Vec<f32>> = Mutex::new(Vec::new());
 static MIC_BUFFER: Mutex<Vec<f32>> = Mutex::new(Vec::new());
 
+// Per-stream sinc resamplers (capture rate -> 16kHz), kept across callbacks
+static SYSTEM_RESAMPLER: Mutex<Option<StreamResampler>> = Mutex::new(None);
+static MIC_RESAMPLER: Mutex<Option<StreamResampler>> = Mutex::new(None);
+// Converts system audio back to WAV_SAMPLE_RATE after a format change
+static SYSTEM_WAV_RESAMPLER: Mutex<Option<StreamResampler>> = Mutex::new(None);
+
+// Optional DC/rumble filter on the mic, applied before the level meter, WAV and stream
+static MIC_HIGHPASS_ENABLED: AtomicBool = AtomicBool::new(false);
+static MIC_HIGHPASS: Mutex<Option<HighPassFilter>> = Mutex::new(None);
+
 static ACTIVE_STREAM: AtomicPtr<Object> = AtomicPtr::new(null_mut());
 static ACTIVE_DELEGATE: AtomicPtr<Object> = AtomicPtr::new(null_mut());
 static MIC_ENGINE: AtomicPtr<Object> = AtomicPtr::new(null_mut());
 
 // Shared state for callbacks
-static CB_CONTENT: AtomicPtr<Object> = AtomicPtr::new(null_mut());
-static CB_ERROR: AtomicBool = AtomicBool::new(false);
 static CB_START_OK: AtomicBool = AtomicBool::new(false);
 
 
 pub struct AudioStreamHandle {
     pub output_path: String,
+    pub separate_tracks: bool,
+    pub wav_layout: WavLayout,
+    pub sample_format: WavSampleFormat,
+    /// false = streaming only, nothing is written on stop
+    pub save_wav: bool,
+    /// Cut long leading/trailing silence from the main WAV (separate tracks stay whole)
+    pub trim_silence: bool,
+}
+
+/// Channel layout of the WAV written on stop
+#[derive(Clone, Copy, Debug, PartialEq)]
+pub enum WavLayout {
+    /// 2 channels: Left = system, Right = mic
+    StereoSplit,
+    /// 1 channel: system + mic summed
+    MonoMix,
+}
+
+impl WavLayout {
+    pub fn parse(value: &str) -> Option<Self> {
+        match value {
+            "stereo-split" => Some(WavLayout::StereoSplit),
+            "mono-mix" => Some(WavLayout::MonoMix),
+            _ => None,
+        }
+    }
+}
+
+/// Files written when a capture stops
+pub struct CaptureFiles {
+    pub system_track_path: Option<String>,
+    pub mic_track_path: Option<String>,
+    /// Silence cut from the main WAV and its resulting length
+    pub trim: SilenceTrim,
 }
 
 unsafe impl Send for AudioStreamHandle {}
 unsafe impl Sync for AudioStreamHandle {}
 
+/// Combined level: the louder of system and mic
 pub fn get_current_level() -> f64 {
-    *CURRENT_LEVEL.lock()
+    get_system_level().max(get_mic_level())
+}
+
+pub fn get_system_level() -> f64 {
+    *SYSTEM_LEVEL.lock()
+}
+
+pub fn get_mic_level() -> f64 {
+    *MIC_LEVEL.lock()
 }
 
 /// Get queued stereo audio chunks for streaming to Deepgram
@@ -129,6 +215,51 @@ pub fn has_audio_chunks() -> bool {
     !AUDIO_CHUNK_QUEUE.lock().is_empty()
 }
 
+/// Only queue chunks with voice activity on at least one channel
+pub fn set_vad_enabled(enabled: bool) {
+    VAD_ENABLED.store(enabled, Ordering::SeqCst);
+}
+
+/// High-pass the mic at ~80Hz to remove DC offset and rumble
+pub fn set_mic_highpass_enabled(enabled: bool) {
+    MIC_HIGHPASS_ENABLED.store(enabled, Ordering::SeqCst);
+    if !enabled {
+        // Start from fresh state if re-enabled later
+        *MIC_HIGHPASS.lock() = None;
+    }
+}
+
+/// RMS level (0.0 - 1.0) below which a channel counts as silent
+pub fn set_vad_threshold(threshold: f64) {
+    *VAD_THRESHOLD.lock() = threshold.max(0.0);
+}
+
+/// Fraction of chunks in the current capture that were silent on both channels
+/// Tracked whether or not the VAD gate is enabled
+pub fn get_silence_ratio() -> f64 {
+    let total = TOTAL_CHUNKS.load(Ordering::SeqCst);
+    if total == 0 {
+        return 0.0;
+    }
+    SILENT_CHUNKS.load(Ordering::SeqCst) as f64 / total as f64
+}
+
+fn rms(samples: &[f32]) -> f64 {
+    if samples.is_empty() { return 0.0; }
+    let sq: f64 = samples.iter().map(|s| (*s as f64).powi(2)).sum();
+    (sq / samples.len() as f64).sqrt()
+}
+
+/// Set how many chunks may be queued before the oldest are dropped
+pub fn set_max_queued_chunks(max: u64) {
+    MAX_QUEUED_CHUNKS.store(max.max(1), Ordering::SeqCst);
+}
+
+/// Number of chunks dropped since capture started because nobody drained the queue
+pub fn get_dropped_chunk_count() -> u64 {
+    DROPPED_CHUNKS.load(Ordering::SeqCst)
+}
+
 // ============================================================================
 // Stereo Chunk Builder
 // ============================================================================
@@ -152,13 +283,24 @@ fn build_stereo_chunks() {
             system.len(), mic.len());
     }
     
-    // Target ~100ms chunks at 16kHz = 1600 samples per channel
-    let chunk_size = 1600;
+    // Read once under both locks so L/R stay aligned if the duration changes mid-stream
+    let chunk_size = chunk_samples();
     
     // Process when either buffer has enough data
     while system.len() >= chunk_size || mic.len() >= chunk_size {
         let samples_to_process = chunk_size.min(system.len().max(mic.len()));
         
+        // Voice activity: silent only if both channels are under the threshold
+        let threshold = *VAD_THRESHOLD.lock();
+        let system_rms = rms(&system[..samples_to_process.min(system.len())]);
+        let mic_rms = rms(&mic[..samples_to_process.min(mic.len())]) * MIC_GAIN as f64;
+        let is_silent = system_rms < threshold && mic_rms < threshold;
+        TOTAL_CHUNKS.fetch_add(1, Ordering::SeqCst);
+        if is_silent {
+            SILENT_CHUNKS.fetch_add(1, Ordering::SeqCst);
+        }
+        let skip_chunk = is_silent && VAD_ENABLED.load(Ordering::SeqCst);
+        
         // Build STEREO 16-bit PCM: [L0, R0, L1, R1, ...]
         // Left = System audio (other participants)
         // Right = Mic audio (you)
@@ -170,14 +312,27 @@ fn build_stereo_chunks() {
             let left_i16 = (left_sample.clamp(-1.0, 1.0) * 32767.0) as i16;
             stereo_chunk.extend_from_slice(&left_i16.to_le_bytes());
             
-            // Right channel = Mic audio (your voice) - boost slightly
-            let right_sample = if i < mic.len() { mic[i] * 1.5 } else { 0.0 };
+            // Right channel = Mic audio (your voice), boosted like the saved mix
+            let right_sample = if i < mic.len() { mic[i] * MIC_GAIN } else { 0.0 };
             let right_i16 = (right_sample.clamp(-1.0, 1.0) * 32767.0) as i16;
             stereo_chunk.extend_from_slice(&right_i16.to_le_bytes());
         }
         
-        // Queue the chunk
-        AUDIO_CHUNK_QUEUE.lock().push_back(stereo_chunk);
+        // Queue the chunk, dropping the oldest if the consumer has fallen behind
+        if !skip_chunk {
+            let max = MAX_QUEUED_CHUNKS.load(Ordering::SeqCst) as usize;
+            queue_mono_chunks(&system, &mic, samples_to_process, MIC_GAIN, max);
+            retain_chunk(&stereo_chunk);
+            let mut queue = AUDIO_CHUNK_QUEUE.lock();
+            queue.push_back(stereo_chunk);
+            while queue.len() > max {
+                queue.pop_front();
+                let dropped = DROPPED_CHUNKS.fetch_add(1, Ordering::SeqCst) + 1;
+                if dropped == 1 || dropped % 100 == 0 {
+                    println!("[Audio] ⚠️ Chunk queue full ({}), dropped {} chunks so far", max, dropped);
+                }
+            }
+        }
         
         // Remove processed samples
         if samples_to_process <= system.len() {
@@ -209,12 +364,33 @@ fn get_delegate_class() -> *const Class {
                 sel!(stream:didOutputSampleBuffer:ofType:),
                 on_system_audio as extern "C" fn(&Object, Sel, id, id, i64),
             );
+            // SCStreamDelegate, so a stream that dies on its own is reported
+            decl.add_method(
+                sel!(stream:didStopWithError:),
+                on_stream_stopped as extern "C" fn(&Object, Sel, id, id),
+            );
             CLS = decl.register();
         });
         CLS
     }
 }
 
+/// The stream stopped without stop_capture (display or app gone, permission revoked...)
+#[allow(deprecated)]
+extern "C" fn on_stream_stopped(_: &Object, _: Sel, _: id, error: id) {
+    // stop_capture clears IS_CAPTURING before stopping the stream
+    if !IS_CAPTURING.load(Ordering::SeqCst) {
+        return;
+    }
+    
+    let reason = unsafe {
+        let description: id = if error.is_null() { nil } else { msg_send![error, localizedDescription] };
+        super::window::nsstring_to_string(description)
+    };
+    println!("[Audio] ❌ System audio stream stopped: {}", reason.as_deref().unwrap_or("unknown error"));
+    crate::notify_capture_interrupted();
+}
+
 static SYSTEM_CALLBACK_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
 
 #[allow(deprecated)]
@@ -237,30 +413,48 @@ extern "C" fn on_system_audio(_: &Object, _: Sel, _: id, sample: id, typ: i64) {
         if !fmt.is_null() {
             let asbd = CMAudioFormatDescriptionGetStreamBasicDescription(fmt);
             if !asbd.is_null() {
-                SAMPLE_RATE.store((*asbd).sample_rate as u64, Ordering::SeqCst);
-                CHANNELS.store((*asbd).channels_per_frame as u64, Ordering::SeqCst);
+                let rate = (*asbd).sample_rate as u64;
+                let channels = (*asbd).channels_per_frame as u64;
+                if !SYSTEM_FORMAT_KNOWN.swap(true, Ordering::SeqCst) {
+                    WAV_SAMPLE_RATE.store(rate, Ordering::SeqCst);
+                    WAV_CHANNELS.store(channels, Ordering::SeqCst);
+                } else {
+                    let previous_rate = SAMPLE_RATE.load(Ordering::SeqCst);
+                    let previous_channels = CHANNELS.load(Ordering::SeqCst);
+                    if rate != previous_rate || channels != previous_channels {
+                        // The stream config pins 48kHz stereo, but SCK can still follow the output
+                        // device (e.g. AirPods connecting). The 16kHz resampler flushes itself on the
+                        // rate change and the WAV data is converted back to the original format
+                        println!("[Audio] ⚠️ System audio format changed mid-stream: {}Hz/{}ch -> {}Hz/{}ch",
+                            previous_rate, previous_channels, rate, channels);
+                    }
+                }
+                SAMPLE_RATE.store(rate, Ordering::SeqCst);
+                CHANNELS.store(channels, Ordering::SeqCst);
             }
         }
 
         // Calculate level for UI feedback
-        *CURRENT_LEVEL.lock() = calc_level(&data);
+        *SYSTEM_LEVEL.lock() = calc_level(&data);
 
-        if IS_CAPTURING.load(Ordering::SeqCst) {
+        let paused = IS_PAUSED.read();
+        if IS_CAPTURING.load(Ordering::SeqCst) && !*paused {
             // Store raw data for WAV file
-            SYSTEM_AUDIO_DATA.lock().extend_from_slice(&data);
+            if SAVE_WAV.load(Ordering::SeqCst) {
+                append_system_wav_data(&data);
+            }
             
             // Convert float32 to f32 samples and add to buffer for real-time streaming
             // System audio is stereo (2 channels), we'll take left channel or mix
             let channels = CHANNELS.load(Ordering::SeqCst) as usize;
             let source_rate = SAMPLE_RATE.load(Ordering::SeqCst) as f64;
-            let target_rate = 16000.0; // Deepgram expects 16kHz
             
             let float_samples: Vec<f32> = data
                 .chunks_exact(4)
                 .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
                 .collect();
             
-            // Mix stereo to mono and resample to 16kHz
+            // Mix stereo to mono and resample to 16kHz (Deepgram expects 16kHz)
             let mono_samples: Vec<f32> = if channels == 2 {
                 float_samples.chunks(2)
                     .map(|pair| (pair[0] + pair.get(1).unwrap_or(&0.0)) / 2.0)
@@ -269,20 +463,7 @@ extern "C" fn on_system_audio(_: &Object, _: Sel, _: id, sample: id, typ: i64) {
                 float_samples
             };
             
-            // Simple resampling (linear interpolation)
-            let resample_ratio = target_rate / source_rate;
-            let output_len = (mono_samples.len() as f64 * resample_ratio) as usize;
-            let mut resampled: Vec<f32> = Vec::with_capacity(output_len);
-            
-            for i in 0..output_len {
-                let src_pos = i as f64 / resample_ratio;
-                let src_idx = src_pos as usize;
-                let frac = src_pos - src_idx as f64;
-                
-                let s0 = mono_samples.get(src_idx).copied().unwrap_or(0.0);
-                let s1 = mono_samples.get(src_idx + 1).copied().unwrap_or(s0);
-                resampled.push(s0 + (s1 - s0) * frac as f32);
-            }
+            let resampled = resample_stream_to_16k(&mut SYSTEM_RESAMPLER.lock(), &mono_samples, source_rate);
             
             SYSTEM_BUFFER.lock().extend(resampled);
             
@@ -301,12 +482,53 @@ extern "C" fn on_system_audio(_: &Object, _: Sel, _: id, sample: id, typ: i64) {
     }
 }
 
+/// Append a system buffer to the WAV data, converting it to WAV_SAMPLE_RATE/WAV_CHANNELS
+/// if the capture format has drifted since the recording started. System audio is always
+/// downmixed to mono when the WAV is written, so converting via mono loses nothing
+fn append_system_wav_data(data: &[u8]) {
+    let rate = SAMPLE_RATE.load(Ordering::SeqCst);
+    let channels = CHANNELS.load(Ordering::SeqCst);
+    let wav_rate = WAV_SAMPLE_RATE.load(Ordering::SeqCst);
+    let wav_channels = WAV_CHANNELS.load(Ordering::SeqCst);
+    
+    let mut resampler = SYSTEM_WAV_RESAMPLER.lock();
+    if rate != wav_rate {
+        let mono = downmix_to_mono(&f32_from_bytes(data), channels as u16);
+        append_converted_system_data(&resample_stream(&mut resampler, &mono, rate as f64, wav_rate as f64), wav_channels);
+        return;
+    }
+    
+    // Back on the original rate: emit the converter's queued tail first
+    if let Some(mut previous) = resampler.take() {
+        append_converted_system_data(&previous.flush(), wav_channels);
+    }
+    if channels == wav_channels {
+        SYSTEM_AUDIO_DATA.lock().extend_from_slice(data);
+    } else {
+        append_converted_system_data(&downmix_to_mono(&f32_from_bytes(data), channels as u16), wav_channels);
+    }
+}
+
+/// Store mono samples as float32 frames with `wav_channels` copies of each sample
+fn append_converted_system_data(mono: &[f32], wav_channels: u64) {
+    let mut wav_data = SYSTEM_AUDIO_DATA.lock();
+    for sample in mono {
+        for _ in 0..wav_channels {
+            wav_data.extend_from_slice(&sample.to_le_bytes());
+        }
+    }
+}
+
 fn calc_level(data: &[u8]) -> f64 {
     if data.len() < 4 { return 0.0; }
     let samples: Vec<f32> = data
         .chunks_exact(4)
         .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
         .collect();
+    calc_level_f32(&samples)
+}
+
+fn calc_level_f32(samples: &[f32]) -> f64 {
     if samples.is_empty() { return 0.0; }
     let sq: f64 = samples.iter().map(|s| (*s as f64).powi(2)).sum();
     ((sq / samples.len() as f64).sqrt() * 2.0).min(1.0)
@@ -317,7 +539,7 @@ fn calc_level(data: &[u8]) -> f64 {
 // ============================================================================
 
 #[allow(deprecated)]
-unsafe fn start_microphone_capture() -> Result<(), AudioError> {
+unsafe fn start_microphone_capture(device_uid: Option<&str>) -> Result<(), AudioError> {
     println!("[Audio] Starting microphone capture...");
     
     // Create AVAudioEngine
@@ -333,6 +555,11 @@ unsafe fn start_microphone_capture() -> Result<(), AudioError> {
         return Err(AudioError::StreamCreationFailed("No input node".into()));
     }
     
+    // Point the input node at the requested device (must happen before reading its format)
+    if let Some(uid) = device_uid {
+        select_input_device(input_node, uid);
+    }
+    
     // DISABLED Voice Processing - it causes speaker volume to dip on macOS
     // We don't need echo cancellation because we capture system audio and mic separately
     // on different channels, and Deepgram processes them independently
@@ -359,13 +586,15 @@ unsafe fn start_microphone_capture() -> Result<(), AudioError> {
     // Install tap on input node to receive audio
     let buffer_size: u32 = 4096;
     let mic_sample_rate = sample_rate;
+    MIC_SAMPLE_RATE.store(sample_rate as u64, Ordering::SeqCst);
     
     // Counter for mic callbacks
     static MIC_CALLBACK_COUNT: std::sync::atomic::AtomicU64 = std::sync::atomic::AtomicU64::new(0);
     
     // Create the tap block
     let tap_block = block::ConcreteBlock::new(move |buffer: id, _when: id| {
-        if !IS_CAPTURING.load(Ordering::SeqCst) { return; }
+        let paused = IS_PAUSED.read();
+        if !IS_CAPTURING.load(Ordering::SeqCst) || *paused { return; }
         
         // Get float channel data
         let float_data: *const *const f32 = msg_send![buffer, floatChannelData];
@@ -376,30 +605,32 @@ unsafe fn start_microphone_capture() -> Result<(), AudioError> {
         
         // Copy mono channel (or first channel of stereo)
         let channel_data = *float_data;
-        let samples = std::slice::from_raw_parts(channel_data, frame_length as usize);
+        let raw = std::slice::from_raw_parts(channel_data, frame_length as usize);
         
-        // Store raw for WAV file
-        let bytes: Vec<u8> = samples.iter()
-            .flat_map(|s| s.to_le_bytes())
-            .collect();
-        MIC_AUDIO_DATA.lock().extend_from_slice(&bytes);
+        let filtered;
+        let samples = if MIC_HIGHPASS_ENABLED.load(Ordering::SeqCst) {
+            filtered = MIC_HIGHPASS.lock()
+                .get_or_insert_with(|| HighPassFilter::new(HighPassFilter::DEFAULT_CUTOFF_HZ, mic_sample_rate as f32))
+                .process(raw);
+            &filtered[..]
+        } else {
+            raw
+        };
         
-        // Resample to 16kHz for Deepgram streaming
-        let target_rate = 16000.0;
-        let resample_ratio = target_rate / mic_sample_rate;
-        let output_len = (samples.len() as f64 * resample_ratio) as usize;
-        let mut resampled: Vec<f32> = Vec::with_capacity(output_len);
+        // Level from the native-rate samples so the meter reacts before chunks are built
+        *MIC_LEVEL.lock() = calc_level_f32(samples);
         
-        for i in 0..output_len {
-            let src_pos = i as f64 / resample_ratio;
-            let src_idx = src_pos as usize;
-            let frac = src_pos - src_idx as f64;
-            
-            let s0 = samples.get(src_idx).copied().unwrap_or(0.0);
-            let s1 = samples.get(src_idx + 1).copied().unwrap_or(s0);
-            resampled.push(s0 + (s1 - s0) * frac as f32);
+        // Store raw for WAV file
+        if SAVE_WAV.load(Ordering::SeqCst) {
+            let bytes: Vec<u8> = samples.iter()
+                .flat_map(|s| s.to_le_bytes())
+                .collect();
+            MIC_AUDIO_DATA.lock().extend_from_slice(&bytes);
         }
         
+        // Resample to 16kHz for Deepgram streaming
+        let resampled = resample_stream_to_16k(&mut MIC_RESAMPLER.lock(), samples, mic_sample_rate);
+        
         MIC_BUFFER.lock().extend(resampled);
         
         // Build audio chunks periodically (important: this ensures mic audio gets processed
@@ -433,6 +664,39 @@ unsafe fn start_microphone_capture() -> Result<(), AudioError> {
     Ok(())
 }
 
+/// Route the engine's input node to a specific device, leaving the default in place on failure
+#[allow(deprecated)]
+unsafe fn select_input_device(input_node: id, uid: &str) {
+    let device_id = match super::audio_devices::find_input_device(uid) {
+        Some(id) => id,
+        None => {
+            println!("[Audio] ⚠️ Input device '{}' not found, using default", uid);
+            return;
+        }
+    };
+    
+    let audio_unit: *mut c_void = msg_send![input_node, audioUnit];
+    if audio_unit.is_null() {
+        println!("[Audio] ⚠️ Input node has no audio unit, using default device");
+        return;
+    }
+    
+    let status = AudioUnitSetProperty(
+        audio_unit,
+        AUDIO_OUTPUT_UNIT_PROPERTY_CURRENT_DEVICE,
+        AUDIO_UNIT_SCOPE_GLOBAL,
+        0,
+        &device_id as *const u32 as *const c_void,
+        std::mem::size_of::<u32>() as u32,
+    );
+    
+    if status == 0 {
+        println!("[Audio] Using input device '{}'", uid);
+    } else {
+        println!("[Audio] ⚠️ Failed to select input device '{}' ({}), using default", uid, status);
+    }
+}
+
 #[allow(deprecated)]
 unsafe fn stop_microphone_capture() {
     let engine = MIC_ENGINE.swap(null_mut(), Ordering::SeqCst) as id;
@@ -451,18 +715,74 @@ unsafe fn stop_microphone_capture() {
 // Main API
 // ============================================================================
 
+/// Live stream formats; zeros when not capturing
+pub fn get_capture_format() -> CaptureFormat {
+    if !IS_CAPTURING.load(Ordering::SeqCst) {
+        return CaptureFormat::default();
+    }
+    
+    let (system_sample_rate, system_channels) = if SYSTEM_FORMAT_KNOWN.load(Ordering::SeqCst) {
+        (SAMPLE_RATE.load(Ordering::SeqCst) as u32, CHANNELS.load(Ordering::SeqCst) as u32)
+    } else {
+        (0, 0)
+    };
+    let mic_sample_rate = if MIC_ENGINE.load(Ordering::SeqCst).is_null() {
+        0
+    } else {
+        MIC_SAMPLE_RATE.load(Ordering::SeqCst) as u32
+    };
+    
+    CaptureFormat {
+        system_sample_rate,
+        system_channels,
+        mic_sample_rate,
+        stream_sample_rate: TARGET_SAMPLE_RATE,
+    }
+}
+
+/// Pause capture: streams keep running but callbacks stop appending samples
+pub fn pause_capture() {
+    let mut paused = IS_PAUSED.write();
+    *paused = true;
+    
+    // Flush whole chunks, then drop the partial remainder of both channels so
+    // L/R restart aligned on resume instead of one side being zero-padded
+    build_stereo_chunks();
+    SYSTEM_BUFFER.lock().clear();
+    MIC_BUFFER.lock().clear();
+    drop(paused);
+    println!("[Audio] Capture paused");
+}
+
+/// Resume a paused capture
+pub fn resume_capture() {
+    let mut paused = IS_PAUSED.write();
+    // Drop resampler filter state from before the pause
+    *SYSTEM_RESAMPLER.lock() = None;
+    *MIC_RESAMPLER.lock() = None;
+    *paused = false;
+    println!("[Audio] Capture resumed");
+}
+
 /// Start capturing system audio + microphone
 /// System audio is captured via Core Audio Process Tap (works with Bluetooth!)
 /// Microphone is captured via AVFoundation
 #[allow(deprecated)]
 pub async fn start_capture(
-    _pid: i32, // No longer used - we capture all system audio
-    _sr: u32,
-    _ch: u32,
+    pid: i32, // 0 = capture all system audio
+    sample_rate: u32,
+    channels: u32,
     output_path: &str,
     include_mic: bool,
+    separate_tracks: bool,
+    wav_layout: WavLayout,
+    sample_format: WavSampleFormat,
+    input_device_id: Option<String>,
+    display_id: Option<u32>,
+    save_wav: bool,
+    trim_silence: bool,
 ) -> Result<AudioStreamHandle, AudioError> {
-    println!("[Audio] Starting capture (ScreenCaptureKit, mic={})", include_mic);
+    println!("[Audio] Starting capture (ScreenCaptureKit, pid={}, {}Hz, {}ch, mic={})", pid, sample_rate, channels, include_mic);
 
     // Clear previous data
     SYSTEM_AUDIO_DATA.lock().clear();
@@ -470,10 +790,18 @@ pub async fn start_capture(
     SYSTEM_BUFFER.lock().clear();
     MIC_BUFFER.lock().clear();
     AUDIO_CHUNK_QUEUE.lock().clear();
+    DROPPED_CHUNKS.store(0, Ordering::SeqCst);
+    TOTAL_CHUNKS.store(0, Ordering::SeqCst);
+    SILENT_CHUNKS.store(0, Ordering::SeqCst);
+    *SYSTEM_RESAMPLER.lock() = None;
+    *MIC_RESAMPLER.lock() = None;
+    *SYSTEM_WAV_RESAMPLER.lock() = None;
+    *MIC_HIGHPASS.lock() = None;
     SYSTEM_CALLBACK_COUNT.store(0, Ordering::SeqCst);
+    SYSTEM_FORMAT_KNOWN.store(false, Ordering::SeqCst);
+    SAVE_WAV.store(save_wav, Ordering::SeqCst);
+    *IS_PAUSED.write() = false;
     IS_CAPTURING.store(true, Ordering::SeqCst);
-    CB_CONTENT.store(null_mut(), Ordering::SeqCst);
-    CB_ERROR.store(false, Ordering::SeqCst);
 
     let path = output_path.to_string();
     let capture_mic = include_mic;
@@ -482,11 +810,11 @@ pub async fn start_capture(
     // Note: ScreenCaptureKit captures audio BEFORE Bluetooth encoding,
     // so it works with both regular speakers and Bluetooth headphones!
     let result = tokio::task::spawn_blocking(move || unsafe { 
-        setup_system_audio_capture()?;
+        setup_system_audio_capture(pid, display_id, sample_rate, channels)?;
         
         // Start microphone capture if requested
         if capture_mic {
-            if let Err(e) = start_microphone_capture() {
+            if let Err(e) = start_microphone_capture(input_device_id.as_deref()) {
                 eprintln!("[Audio] Warning: Failed to start mic capture: {}", e);
                 // Continue anyway - we'll still capture system audio
             }
@@ -498,7 +826,7 @@ pub async fn start_capture(
     match result {
         Ok(Ok(())) => {
             println!("[Audio] Capture started successfully");
-            Ok(AudioStreamHandle { output_path: path })
+            Ok(AudioStreamHandle { output_path: path, separate_tracks, wav_layout, sample_format, save_wav, trim_silence })
         }
         Ok(Err(e)) => {
             IS_CAPTURING.store(false, Ordering::SeqCst);
@@ -511,82 +839,42 @@ pub async fn start_capture(
     }
 }
 
-/// Setup ScreenCaptureKit to capture ALL system audio (loopback)
+/// Rates SCStreamConfiguration.sampleRate accepts
+pub const SUPPORTED_SAMPLE_RATES: &[u32] = &[8000, 16000, 24000, 48000];
+
+/// Setup ScreenCaptureKit to capture system audio (loopback)
+/// With a non-zero pid only that application's audio is captured, otherwise ALL applications
 /// Note: ScreenCaptureKit captures audio BEFORE Bluetooth encoding,
 /// so it works with both regular speakers and Bluetooth headphones!
 #[allow(deprecated)]
-unsafe fn setup_system_audio_capture() -> Result<(), AudioError> {
-    let sem = dispatch_semaphore_create(0);
-    
-    println!("[Audio] Getting shareable content for system audio...");
-
-    let sem_ptr = sem as usize;
-    let block1 = block::ConcreteBlock::new(move |content: id, error: id| {
-        if error.is_null() && !content.is_null() {
-            let _: () = msg_send![content, retain];
-            CB_CONTENT.store(content as *mut _, Ordering::SeqCst);
-        } else {
-            CB_ERROR.store(true, Ordering::SeqCst);
-        }
-        dispatch_semaphore_signal(sem_ptr as *mut c_void);
-    });
-    let block1 = block1.copy();
-
-    let _: () = msg_send![
-        class!(SCShareableContent),
-        getShareableContentWithCompletionHandler: &*block1
-    ];
-
-    let timeout = dispatch_time(DISPATCH_TIME_NOW, 30 * NSEC_PER_SEC);
-    let result = dispatch_semaphore_wait(sem, timeout);
-    
-    if result != 0 {
-        return Err(AudioError::StreamCreationFailed("Timeout".into()));
-    }
-
-    if CB_ERROR.load(Ordering::SeqCst) {
-        return Err(AudioError::PermissionDenied);
-    }
+unsafe fn setup_system_audio_capture(pid: i32, display_id: Option<u32>, sample_rate: u32, channels: u32) -> Result<(), AudioError> {
+    let content = fetch_shareable_content()?;
 
-    let content = CB_CONTENT.load(Ordering::SeqCst) as id;
-    if content.is_null() {
-        return Err(AudioError::PermissionDenied);
-    }
-
-    // Get display
-    let displays: id = msg_send![content, displays];
-    let dcount: usize = msg_send![displays, count];
-    if dcount == 0 {
-        return Err(AudioError::StreamCreationFailed("No display".into()));
-    }
-    let display: id = msg_send![displays, objectAtIndex: 0usize];
-
-    // Create filter to capture ALL applications (system audio loopback)
-    println!("[Audio] Setting up system audio loopback (all applications)");
-    let all_apps: id = msg_send![content, applications];
-    let filter: id = msg_send![class!(SCContentFilter), alloc];
-    let empty_windows: id = msg_send![class!(NSArray), array];
-    let filter: id = msg_send![filter, initWithDisplay:display includingApplications:all_apps exceptingWindows:empty_windows];
+    let filter = match create_content_filter(content, pid, display_id) {
+        Some(filter) => filter,
+        None => return Err(AudioError::NoDisplay),
+    };
 
     // Config - audio only, minimal video
     let cfg: id = msg_send![class!(SCStreamConfiguration), new];
     let _: () = msg_send![cfg, setCapturesAudio: YES];
     let _: () = msg_send![cfg, setExcludesCurrentProcessAudio: YES]; // Don't capture our own app
-    let _: () = msg_send![cfg, setSampleRate: 48000i64];
-    let _: () = msg_send![cfg, setChannelCount: 2i64];
+    let _: () = msg_send![cfg, setSampleRate: sample_rate as i64];
+    let _: () = msg_send![cfg, setChannelCount: channels as i64];
     let _: () = msg_send![cfg, setWidth: 2usize];  // Minimal video
     let _: () = msg_send![cfg, setHeight: 2usize];
     let _: () = msg_send![cfg, setShowsCursor: NO];
 
-    // Create stream
+    // Create stream; the delegate receives both the audio and stream errors
+    let del: id = msg_send![get_delegate_class(), new];
     let stream: id = msg_send![class!(SCStream), alloc];
-    let stream: id = msg_send![stream, initWithFilter:filter configuration:cfg delegate:nil];
+    let stream: id = msg_send![stream, initWithFilter:filter configuration:cfg delegate:del];
     if stream.is_null() {
+        let _: () = msg_send![del, release];
         return Err(AudioError::StreamCreationFailed("Stream failed".into()));
     }
 
     // Add audio output
-    let del: id = msg_send![get_delegate_class(), new];
     let q: id = dispatch_get_global_queue(QOS_CLASS_USER_INITIATED, 0);
 
     let mut err: id = nil;
@@ -620,10 +908,220 @@ unsafe fn setup_system_audio_capture() -> Result<(), AudioError> {
     Ok(())
 }
 
+/// Fetch SCShareableContent (retained), waiting up to 30s for ScreenCaptureKit
+/// Fails with PermissionDenied if Screen Recording access was refused
+/// Each call gets its own channel, so concurrent fetches can't see each other's result
+#[allow(deprecated)]
+unsafe fn fetch_shareable_content() -> Result<id, AudioError> {
+    println!("[Audio] Getting shareable content...");
+
+    // The content pointer crosses threads as usize; None means ScreenCaptureKit refused
+    let (tx, rx) = std::sync::mpsc::sync_channel::<Option<usize>>(1);
+    let block1 = block::ConcreteBlock::new(move |content: id, error: id| {
+        if error.is_null() && !content.is_null() {
+            let _: () = msg_send![content, retain];
+            // Nobody is waiting any more after a timeout, so don't leak the content
+            if tx.send(Some(content as usize)).is_err() {
+                let _: () = msg_send![content, release];
+            }
+        } else {
+            let _ = tx.send(None);
+        }
+    });
+    let block1 = block1.copy();
+
+    let _: () = msg_send![
+        class!(SCShareableContent),
+        getShareableContentWithCompletionHandler: &*block1
+    ];
+
+    match rx.recv_timeout(std::time::Duration::from_secs(30)) {
+        Ok(Some(content)) => Ok(content as id),
+        Ok(None) => Err(AudioError::PermissionDenied),
+        Err(_) => Err(AudioError::StreamCreationFailed("Timeout".into())),
+    }
+}
+
+/// List the displays ScreenCaptureKit can capture (empty on headless Macs)
+pub async fn list_displays() -> Result<Vec<DisplayInfo>, AudioError> {
+    tokio::task::spawn_blocking(|| unsafe {
+        let content = fetch_shareable_content()?;
+        let displays: id = msg_send![content, displays];
+        let count: usize = msg_send![displays, count];
+        let main_id = CGDisplay::main().id;
+        
+        let list = (0..count)
+            .map(|i| {
+                let display: id = msg_send![displays, objectAtIndex: i];
+                let display_id: u32 = msg_send![display, displayID];
+                let bounds = CGDisplay::new(display_id).bounds();
+                DisplayInfo {
+                    id: display_id,
+                    name: display_name(display_id),
+                    is_main: display_id == main_id,
+                    x: bounds.origin.x,
+                    y: bounds.origin.y,
+                    width: bounds.size.width,
+                    height: bounds.size.height,
+                }
+            })
+            .collect();
+        
+        let _: () = msg_send![content, release];
+        Ok(list)
+    })
+    .await
+    .map_err(|e| AudioError::StreamCreationFailed(format!("Task error: {}", e)))?
+}
+
+/// Build (but don't start) an all-applications content filter, to check ScreenCaptureKit works
+/// Needs Screen Recording permission, like a real capture, and can prompt for it when missing.
+/// Blocks on the shareable content fetch, so call it from a blocking worker
+pub fn probe_content_filter() -> Result<(), AudioError> {
+    unsafe {
+        let content = fetch_shareable_content()?;
+        let filter = create_content_filter(content, 0, None);
+        let _: () = msg_send![content, release];
+        
+        match filter {
+            Some(filter) => {
+                let _: () = msg_send![filter, release];
+                Ok(())
+            }
+            None => Err(AudioError::NoDisplay),
+        }
+    }
+}
+
+/// Name of a display from CoreGraphics. NSScreen has the localized names, but AppKit
+/// must only be used on the main thread and this runs on a blocking worker
+fn display_name(display_id: u32) -> String {
+    if CGDisplay::new(display_id).is_builtin() {
+        "Built-in Display".to_string()
+    } else {
+        format!("Display {}", display_id)
+    }
+}
+
+/// Pick the display for the content filter: the pinned `display_id` if it's connected,
+/// else the main display, else the first one (index 0 isn't necessarily the main display)
+#[allow(deprecated)]
+unsafe fn select_display(displays: id, count: usize, display_id: Option<u32>) -> id {
+    let display_at = |i: usize| -> (id, u32) {
+        let display: id = msg_send![displays, objectAtIndex: i];
+        let number: u32 = msg_send![display, displayID];
+        (display, number)
+    };
+    let find = |wanted: u32| (0..count).map(display_at).find(|&(_, number)| number == wanted).map(|(display, _)| display);
+    
+    if let Some(wanted) = display_id {
+        if let Some(display) = find(wanted) {
+            println!("[Audio] Using pinned display {}", wanted);
+            return display;
+        }
+        println!("[Audio] ⚠️ Display {} isn't connected, using the main display", wanted);
+    }
+    
+    find(CGDisplay::main().id).unwrap_or_else(|| display_at(0).0)
+}
+
+/// Build the SCContentFilter for system audio
+///
+/// Normally this is a display filter including the target application (or all applications).
+/// Headless Macs, SSH sessions and closed lids report no displays; since only audio is needed,
+/// fall back to a desktop-independent window filter, which captures that window's app audio.
+/// Returns None if there is neither a display nor a window
+#[allow(deprecated)]
+unsafe fn create_content_filter(content: id, pid: i32, display_id: Option<u32>) -> Option<id> {
+    let displays: id = msg_send![content, displays];
+    let dcount: usize = msg_send![displays, count];
+    
+    if dcount == 0 {
+        println!("[Audio] ⚠️ No display available, trying a window-based filter");
+        let window = fallback_window(content, pid)?;
+        let filter: id = msg_send![class!(SCContentFilter), alloc];
+        let filter: id = msg_send![filter, initWithDesktopIndependentWindow: window];
+        return if filter.is_null() { None } else { Some(filter) };
+    }
+    let display = select_display(displays, dcount, display_id);
+
+    // Create filter for the target application, or ALL applications (system audio loopback)
+    let all_apps: id = msg_send![content, applications];
+    let apps = if pid > 0 {
+        let matching = applications_with_pid(all_apps, pid);
+        let count: usize = msg_send![matching, count];
+        if count > 0 {
+            println!("[Audio] Setting up system audio capture for PID {}", pid);
+            matching
+        } else {
+            println!("[Audio] ⚠️ No shareable application with PID {}, falling back to all applications", pid);
+            all_apps
+        }
+    } else {
+        println!("[Audio] Setting up system audio loopback (all applications)");
+        all_apps
+    };
+    let filter: id = msg_send![class!(SCContentFilter), alloc];
+    let empty_windows: id = msg_send![class!(NSArray), array];
+    let filter: id = msg_send![filter, initWithDisplay:display includingApplications:apps exceptingWindows:empty_windows];
+    Some(filter)
+}
+
+/// A shareable window to anchor a display-less filter: the target app's, or the frontmost
+/// app's when no pid was given. None rather than an arbitrary window of some other app
+#[allow(deprecated)]
+unsafe fn fallback_window(content: id, pid: i32) -> Option<id> {
+    let target = if pid > 0 {
+        pid
+    } else {
+        match super::window::get_frontmost_app_pid() {
+            Some(frontmost) => frontmost,
+            None => {
+                println!("[Audio] ❌ No frontmost application to capture audio from");
+                return None;
+            }
+        }
+    };
+    
+    let windows: id = msg_send![content, windows];
+    let count: usize = msg_send![windows, count];
+    for i in 0..count {
+        let window: id = msg_send![windows, objectAtIndex: i];
+        let app: id = msg_send![window, owningApplication];
+        if app.is_null() {
+            continue;
+        }
+        let app_pid: i32 = msg_send![app, processIdentifier];
+        if app_pid == target {
+            println!("[Audio] Capturing audio via a window of PID {} (limited to its application)", target);
+            return Some(window);
+        }
+    }
+    
+    println!("[Audio] ❌ PID {} has no shareable window to capture audio from", target);
+    None
+}
+
+/// Filter an NSArray<SCRunningApplication> down to the apps whose processIdentifier matches pid
 #[allow(deprecated)]
-pub async fn stop_capture(handle: AudioStreamHandle) -> Result<(), AudioError> {
+unsafe fn applications_with_pid(apps: id, pid: i32) -> id {
+    let matching: id = msg_send![class!(NSMutableArray), array];
+    let count: usize = msg_send![apps, count];
+    for i in 0..count {
+        let app: id = msg_send![apps, objectAtIndex: i];
+        let app_pid: i32 = msg_send![app, processIdentifier];
+        if app_pid == pid {
+            let _: () = msg_send![matching, addObject: app];
+        }
+    }
+    matching
+}
+
+#[allow(deprecated)]
+pub async fn stop_capture(handle: AudioStreamHandle) -> Result<CaptureFiles, AudioError> {
     println!("[Audio] Stopping capture");
     IS_CAPTURING.store(false, Ordering::SeqCst);
+    *IS_PAUSED.write() = false;
 
     tokio::time::sleep(std::time::Duration::from_millis(200)).await;
 
@@ -656,70 +1154,204 @@ pub async fn stop_capture(handle: AudioStreamHandle) -> Result<(), AudioError> {
     build_stereo_chunks();
 
     // Get audio data for WAV file
+    if let Some(mut resampler) = SYSTEM_WAV_RESAMPLER.lock().take() {
+        append_converted_system_data(&resampler.flush(), WAV_CHANNELS.load(Ordering::SeqCst));
+    }
     let system_data = std::mem::take(&mut *SYSTEM_AUDIO_DATA.lock());
     let mic_data = std::mem::take(&mut *MIC_AUDIO_DATA.lock());
-    let rate = SAMPLE_RATE.load(Ordering::SeqCst) as u32;
-    let channels = CHANNELS.load(Ordering::SeqCst) as u16;
+    let rate = WAV_SAMPLE_RATE.load(Ordering::SeqCst) as u32;
+    let channels = WAV_CHANNELS.load(Ordering::SeqCst) as u16;
+    let mic_rate = MIC_SAMPLE_RATE.load(Ordering::SeqCst) as u32;
 
     println!("[Audio] System audio: {} bytes, Mic audio: {} bytes", system_data.len(), mic_data.len());
 
-    // Mix audio and save as WAV (stereo: L=system, R=mic)
-    let stereo = create_stereo_wav(&system_data, &mic_data, channels);
-    println!("[Audio] Stereo WAV: {} samples", stereo.len() / 4); // 2 bytes * 2 channels
-    
-    write_wav(&handle.output_path, &stereo, rate, 2)?; // Always stereo output
-    Ok(())
+    let mut files = CaptureFiles { system_track_path: None, mic_track_path: None, trim: SilenceTrim::default() };
+    if !handle.save_wav {
+        println!("[Audio] Streaming-only capture, no WAV written");
+        return Ok(files);
+    }
+
+    let format = handle.sample_format;
+    match handle.wav_layout {
+        WavLayout::StereoSplit => {
+            // Mix audio and save as WAV (stereo: L=system, R=mic)
+            let (stereo, trim) = create_stereo_wav(&system_data, &mic_data, channels, mic_rate, format, rate, handle.trim_silence);
+            println!("[Audio] Stereo WAV: {} bytes ({}-bit)", stereo.len(), format.bits_per_sample());
+            write_wav(&handle.output_path, &stereo, rate, 2, format)?;
+            files.trim = trim;
+        }
+        WavLayout::MonoMix => {
+            let (mono, trim) = create_mono_mix_wav(&system_data, &mic_data, channels, mic_rate, format, rate, handle.trim_silence);
+            println!("[Audio] Mono WAV: {} bytes ({}-bit)", mono.len(), format.bits_per_sample());
+            write_wav(&handle.output_path, &mono, rate, 1, format)?;
+            files.trim = trim;
+        }
+    }
+
+    if handle.separate_tracks {
+        // Raw mono tracks at their native rates (no mic boost) for external post-processing
+        if !system_data.is_empty() {
+            let path = track_path(&handle.output_path, "system");
+            let mono = downmix_to_mono(&f32_from_bytes(&system_data), channels);
+            write_wav(&path, &format.encode(&mono), rate, 1, format)?;
+            files.system_track_path = Some(path);
+        }
+        if !mic_data.is_empty() {
+            let path = track_path(&handle.output_path, "mic");
+            write_wav(&path, &format.encode(&f32_from_bytes(&mic_data)), mic_rate, 1, format)?;
+            files.mic_track_path = Some(path);
+        }
+    }
+
+    Ok(files)
 }
 
-/// Create stereo WAV data: Left = system audio, Right = mic audio
-fn create_stereo_wav(system_data: &[u8], mic_data: &[u8], system_channels: u16) -> Vec<u8> {
-    // Convert system audio from float32 to samples
-    let system_samples: Vec<f32> = system_data
-        .chunks_exact(4)
+/// `<output>_<track>.wav` next to the stereo mix
+fn track_path(output_path: &str, track: &str) -> String {
+    let base = output_path.strip_suffix(".wav").unwrap_or(output_path);
+    format!("{}_{}.wav", base, track)
+}
+
+fn f32_from_bytes(data: &[u8]) -> Vec<f32> {
+    data.chunks_exact(4)
         .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect();
-    
-    // Mix system stereo to mono if needed
-    let system_mono: Vec<f32> = if system_channels == 2 {
-        system_samples.chunks(2)
+        .collect()
+}
+
+fn downmix_to_mono(samples: &[f32], channels: u16) -> Vec<f32> {
+    if channels == 2 {
+        samples.chunks(2)
             .map(|pair| (pair[0] + pair.get(1).unwrap_or(&0.0)) / 2.0)
             .collect()
     } else {
-        system_samples
-    };
+        samples.to_vec()
+    }
+}
+
+/// Mic samples resampled to the mix rate. The mic records at its native rate whatever
+/// sample_rate the capture asked ScreenCaptureKit for, so the two can differ
+fn mic_samples_at(mic_data: &[u8], mic_rate: u32, rate: u32) -> Vec<f32> {
+    let samples = f32_from_bytes(mic_data);
+    match try_resample_audio(&samples, mic_rate, rate) {
+        Ok(resampled) => resampled,
+        Err(e) => {
+            println!("[Audio] ⚠️ Mic resampling failed, mixing at {}Hz: {}", mic_rate, e);
+            samples
+        }
+    }
+}
+
+// Mic is quieter than system playback, boost it in the saved mix
+const MIC_GAIN: f32 = 1.5;
+
+/// Leading/trailing silence trimmed if requested, otherwise just the length
+fn trim_or_measure(system: &mut Vec<f32>, mic: &mut Vec<f32>, rate: u32, trim: bool) -> SilenceTrim {
+    if trim {
+        let result = trim_silence(system, mic, rate);
+        println!("[Audio] Trimmed silence: {:.1}s leading, {:.1}s trailing", result.leading_secs, result.trailing_secs);
+        result
+    } else {
+        SilenceTrim::untrimmed(system.len().max(mic.len()), rate)
+    }
+}
+
+/// Create stereo WAV data: Left = system audio, Right = mic audio
+fn create_stereo_wav(
+    system_data: &[u8],
+    mic_data: &[u8],
+    system_channels: u16,
+    mic_rate: u32,
+    format: WavSampleFormat,
+    rate: u32,
+    trim: bool,
+) -> (Vec<u8>, SilenceTrim) {
+    // Convert system audio from float32 and mix stereo to mono if needed
+    let mut system_mono = downmix_to_mono(&f32_from_bytes(system_data), system_channels);
     
-    // Convert mic audio from float32 to samples (already mono)
-    let mic_samples: Vec<f32> = mic_data
-        .chunks_exact(4)
-        .map(|c| f32::from_le_bytes([c[0], c[1], c[2], c[3]]))
-        .collect();
+    // Convert mic audio from float32 to samples (already mono) at the system rate
+    let mut mic_samples = mic_samples_at(mic_data, mic_rate, rate);
+    let trimmed = trim_or_measure(&mut system_mono, &mut mic_samples, rate, trim);
+    
+    // Auto gain levels both channels itself, replacing the fixed mic boost
+    let auto_gain = apply_auto_gain(&mut system_mono, &mut mic_samples);
+    let mic_gain = if auto_gain { 1.0 } else { MIC_GAIN };
     
     let max_len = system_mono.len().max(mic_samples.len());
     
     // Create interleaved stereo: [L0, R0, L1, R1, ...]
-    let mut stereo: Vec<u8> = Vec::with_capacity(max_len * 4); // 2 bytes * 2 channels
+    let mut stereo: Vec<f32> = Vec::with_capacity(max_len * 2);
     
     for i in 0..max_len {
         // Left = System
-        let left = system_mono.get(i).copied().unwrap_or(0.0);
-        let left_i16 = (left.clamp(-1.0, 1.0) * 32767.0) as i16;
-        stereo.extend_from_slice(&left_i16.to_le_bytes());
+        stereo.push(system_mono.get(i).copied().unwrap_or(0.0));
         
         // Right = Mic (boosted)
-        let right = mic_samples.get(i).copied().unwrap_or(0.0) * 1.5;
-        let right_i16 = (right.clamp(-1.0, 1.0) * 32767.0) as i16;
-        stereo.extend_from_slice(&right_i16.to_le_bytes());
+        stereo.push(mic_samples.get(i).copied().unwrap_or(0.0) * mic_gain);
     }
     
-    stereo
+    if auto_gain {
+        stereo.iter_mut().for_each(|s| *s = soft_limit(*s));
+    }
+    
+    (format.encode(&stereo), trimmed)
 }
 
-fn write_wav(path: &str, pcm: &[u8], rate: u32, channels: u16) -> Result<(), AudioError> {
-    let mut f = File::create(path).map_err(|e| AudioError::WriteError(e.to_string()))?;
-    f.write_all(&WavHeader::new(rate, channels, 16).write_header(pcm.len() as u32))
+/// Create mono WAV data: system audio + boosted mic summed into one channel
+fn create_mono_mix_wav(
+    system_data: &[u8],
+    mic_data: &[u8],
+    system_channels: u16,
+    mic_rate: u32,
+    format: WavSampleFormat,
+    rate: u32,
+    trim: bool,
+) -> (Vec<u8>, SilenceTrim) {
+    let mut system_mono = downmix_to_mono(&f32_from_bytes(system_data), system_channels);
+    let mut mic_samples = mic_samples_at(mic_data, mic_rate, rate);
+    let trimmed = trim_or_measure(&mut system_mono, &mut mic_samples, rate, trim);
+    
+    let auto_gain = apply_auto_gain(&mut system_mono, &mut mic_samples);
+    let mic_gain = if auto_gain { 1.0 } else { MIC_GAIN };
+    
+    let max_len = system_mono.len().max(mic_samples.len());
+    let mixed: Vec<f32> = (0..max_len)
+        .map(|i| {
+            let sample = system_mono.get(i).copied().unwrap_or(0.0)
+                + mic_samples.get(i).copied().unwrap_or(0.0) * mic_gain;
+            if auto_gain { soft_limit(sample) } else { sample }
+        })
+        .collect();
+    
+    (format.encode(&mixed), trimmed)
+}
+
+fn write_wav(path: &str, pcm: &[u8], rate: u32, channels: u16, format: WavSampleFormat) -> Result<(), AudioError> {
+    let mut f = create_private_file(path).map_err(|e| AudioError::WriteError(e.to_string()))?;
+    f.write_all(&WavHeader::with_format(rate, channels, format).write_header(pcm.len() as u32))
         .map_err(|e| AudioError::WriteError(e.to_string()))?;
     f.write_all(pcm)
         .map_err(|e| AudioError::WriteError(e.to_string()))?;
-    println!("[Audio] Wrote stereo WAV: {} ({} bytes)", path, pcm.len());
+    println!("[Audio] Wrote {} WAV: {} ({} bytes)", if channels == 2 { "stereo" } else { "mono" }, path, pcm.len());
     Ok(())
 }
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_mixes_resample_mic_to_the_system_rate() {
+        // 1s of system audio at 16kHz mono and 1s of mic at its native 48kHz
+        let to_bytes = |samples: Vec<f32>| samples.iter().flat_map(|s| s.to_le_bytes()).collect::<Vec<u8>>();
+        let system = to_bytes((0..16000).map(|i| (i as f32 * 0.05).sin() * 0.5).collect());
+        let mic = to_bytes((0..48000).map(|i| (i as f32 * 0.02).sin() * 0.5).collect());
+
+        let (stereo, trim) = create_stereo_wav(&system, &mic, 1, 48000, WavSampleFormat::Pcm16, 16000, false);
+        let frames = stereo.len() / 4;
+        assert!((frames as i64 - 16000).abs() <= 160, "stereo mix is {} frames", frames);
+        assert!((trim.duration_secs - 1.0).abs() < 0.01);
+
+        let (mono, _) = create_mono_mix_wav(&system, &mic, 1, 48000, WavSampleFormat::Pcm16, 16000, false);
+        assert_eq!(mono.len() / 2, frames);
+    }
+}
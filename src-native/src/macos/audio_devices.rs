@@ -0,0 +1,161 @@
+//! Enumerate CoreAudio input devices
+//!
+//! Devices are identified by their CoreAudio UID string, which is stable across
+//! reboots and reconnects (unlike the numeric AudioObjectID).
+
+use super::mic_monitor::AudioObjectPropertyAddress;
+use crate::AudioDeviceInfo;
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use std::os::raw::c_void;
+
+type AudioObjectID = u32;
+type OSStatus = i32;
+
+const AUDIO_HARDWARE_PROPERTY_DEVICES: u32 = 0x64657623; // 'dev#'
+const AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE: u32 = 0x64496E20; // 'dIn '
+const AUDIO_DEVICE_PROPERTY_DEVICE_UID: u32 = 0x75696420; // 'uid '
+const AUDIO_DEVICE_PROPERTY_STREAMS: u32 = 0x73746D23; // 'stm#'
+const AUDIO_OBJECT_PROPERTY_NAME: u32 = 0x6C6E616D; // 'lnam'
+const AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL: u32 = 0x676C6F62; // 'glob'
+const AUDIO_OBJECT_PROPERTY_SCOPE_INPUT: u32 = 0x696E7074; // 'inpt'
+const AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN: u32 = 0;
+const AUDIO_OBJECT_SYSTEM_OBJECT: AudioObjectID = 1;
+
+#[link(name = "CoreAudio", kind = "framework")]
+extern "C" {
+    fn AudioObjectGetPropertyDataSize(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: *mut u32,
+    ) -> OSStatus;
+
+    fn AudioObjectGetPropertyData(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: *mut u32,
+        data: *mut c_void,
+    ) -> OSStatus;
+}
+
+fn address(selector: u32, scope: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        selector,
+        scope,
+        element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    }
+}
+
+/// List all devices that have at least one input stream
+pub fn list_input_devices() -> Vec<AudioDeviceInfo> {
+    let default_id = default_input_device_id();
+
+    all_device_ids()
+        .into_iter()
+        .filter(|&device| has_input_streams(device))
+        .filter_map(|device| {
+            let id = device_string(device, AUDIO_DEVICE_PROPERTY_DEVICE_UID)?;
+            let name = device_string(device, AUDIO_OBJECT_PROPERTY_NAME).unwrap_or_else(|| id.clone());
+            Some(AudioDeviceInfo {
+                id,
+                name,
+                is_default: Some(device) == default_id,
+            })
+        })
+        .collect()
+}
+
+/// Resolve a device UID (as returned by list_input_devices) to a CoreAudio object id
+/// Returns None if the device is gone or has no inputs
+pub fn find_input_device(uid: &str) -> Option<u32> {
+    all_device_ids()
+        .into_iter()
+        .filter(|&device| has_input_streams(device))
+        .find(|&device| device_string(device, AUDIO_DEVICE_PROPERTY_DEVICE_UID).as_deref() == Some(uid))
+}
+
+fn all_device_ids() -> Vec<AudioObjectID> {
+    unsafe {
+        let addr = address(AUDIO_HARDWARE_PROPERTY_DEVICES, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+
+        let mut size: u32 = 0;
+        if AudioObjectGetPropertyDataSize(AUDIO_OBJECT_SYSTEM_OBJECT, &addr, 0, std::ptr::null(), &mut size) != 0 {
+            println!("[AudioDevices] Failed to get device list size");
+            return Vec::new();
+        }
+
+        let count = size as usize / std::mem::size_of::<AudioObjectID>();
+        let mut devices: Vec<AudioObjectID> = vec![0; count];
+        let status = AudioObjectGetPropertyData(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &addr,
+            0,
+            std::ptr::null(),
+            &mut size,
+            devices.as_mut_ptr() as *mut c_void,
+        );
+
+        if status != 0 {
+            println!("[AudioDevices] Failed to get device list: {}", status);
+            return Vec::new();
+        }
+
+        devices.truncate(size as usize / std::mem::size_of::<AudioObjectID>());
+        devices
+    }
+}
+
+fn default_input_device_id() -> Option<AudioObjectID> {
+    unsafe {
+        let addr = address(AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+        let mut device: AudioObjectID = 0;
+        let mut size = std::mem::size_of::<AudioObjectID>() as u32;
+
+        let status = AudioObjectGetPropertyData(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &addr,
+            0,
+            std::ptr::null(),
+            &mut size,
+            &mut device as *mut _ as *mut c_void,
+        );
+
+        if status != 0 || device == 0 { None } else { Some(device) }
+    }
+}
+
+fn has_input_streams(device: AudioObjectID) -> bool {
+    unsafe {
+        let addr = address(AUDIO_DEVICE_PROPERTY_STREAMS, AUDIO_OBJECT_PROPERTY_SCOPE_INPUT);
+        let mut size: u32 = 0;
+        AudioObjectGetPropertyDataSize(device, &addr, 0, std::ptr::null(), &mut size) == 0 && size > 0
+    }
+}
+
+/// Read a CFString-valued device property (the caller owns the returned string)
+fn device_string(device: AudioObjectID, selector: u32) -> Option<String> {
+    unsafe {
+        let addr = address(selector, AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL);
+        let mut value: CFStringRef = std::ptr::null();
+        let mut size = std::mem::size_of::<CFStringRef>() as u32;
+
+        let status = AudioObjectGetPropertyData(
+            device,
+            &addr,
+            0,
+            std::ptr::null(),
+            &mut size,
+            &mut value as *mut _ as *mut c_void,
+        );
+
+        if status != 0 || value.is_null() {
+            return None;
+        }
+
+        Some(CFString::wrap_under_create_rule(value).to_string())
+    }
+}
@@ -1,7 +1,14 @@
 //! Monitor microphone usage to detect when a meeting ends
 //! Uses CoreAudio to check if input device is being used
 
+use crate::MicUser;
+use core_foundation::base::TCFType;
+use core_foundation::string::{CFString, CFStringRef};
+use parking_lot::Mutex;
 use std::os::raw::c_void;
+use std::sync::atomic::{AtomicU64, Ordering};
+use std::sync::Arc;
+use std::time::Duration;
 
 // CoreAudio types and constants
 #[repr(C)]
@@ -18,6 +25,12 @@ type OSStatus = i32;
 // Audio property selectors - using FourCC codes
 const AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE: u32 = 0x64496E20; // 'dIn '
 const AUDIO_DEVICE_PROPERTY_DEVICE_IS_RUNNING: u32 = 0x676F696E; // 'goin' - device is running
+const AUDIO_DEVICE_PROPERTY_MUTE: u32 = 0x6D757465; // 'mute'
+// Process objects (macOS 14.2+)
+const AUDIO_HARDWARE_PROPERTY_PROCESS_OBJECT_LIST: u32 = 0x70727323; // 'prs#'
+const AUDIO_PROCESS_PROPERTY_PID: u32 = 0x70706964; // 'ppid'
+const AUDIO_PROCESS_PROPERTY_BUNDLE_ID: u32 = 0x70626964; // 'pbid'
+const AUDIO_PROCESS_PROPERTY_IS_RUNNING_INPUT: u32 = 0x70697269; // 'piri'
 const AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL: u32 = 0x676C6F62; // 'glob'
 const AUDIO_OBJECT_PROPERTY_SCOPE_INPUT: u32 = 0x696E7074; // 'inpt'
 const AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN: u32 = 0;
@@ -25,6 +38,14 @@ const AUDIO_OBJECT_SYSTEM_OBJECT: AudioObjectID = 1;
 
 #[link(name = "CoreAudio", kind = "framework")]
 extern "C" {
+    fn AudioObjectGetPropertyDataSize(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        qualifier_data_size: u32,
+        qualifier_data: *const c_void,
+        data_size: *mut u32,
+    ) -> OSStatus;
+
     fn AudioObjectGetPropertyData(
         object_id: AudioObjectID,
         address: *const AudioObjectPropertyAddress,
@@ -33,12 +54,37 @@ extern "C" {
         data_size: *mut u32,
         data: *mut c_void,
     ) -> OSStatus;
+
+    fn AudioObjectHasProperty(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+    ) -> u8;
+
+    fn AudioObjectAddPropertyListener(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus;
+
+    fn AudioObjectRemovePropertyListener(
+        object_id: AudioObjectID,
+        address: *const AudioObjectPropertyAddress,
+        listener: AudioObjectPropertyListenerProc,
+        client_data: *mut c_void,
+    ) -> OSStatus;
 }
 
-/// Check if the default microphone is currently being used by any process
-pub fn is_microphone_in_use() -> bool {
+type AudioObjectPropertyListenerProc = unsafe extern "C" fn(
+    object_id: AudioObjectID,
+    number_addresses: u32,
+    addresses: *const AudioObjectPropertyAddress,
+    client_data: *mut c_void,
+) -> OSStatus;
+
+/// Get the default input device, or None if CoreAudio can't tell us
+fn default_input_device() -> Option<AudioObjectID> {
     unsafe {
-        // Get the default input device
         let address = AudioObjectPropertyAddress {
             selector: AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE,
             scope: AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
@@ -59,10 +105,22 @@ pub fn is_microphone_in_use() -> bool {
 
         if status != 0 || device_id == 0 {
             println!("[Ghost MicMonitor] Failed to get default input device: {}", status);
-            // On error, assume mic is in use to avoid false positives
-            return true;
+            return None;
         }
 
+        Some(device_id)
+    }
+}
+
+/// Check if the default microphone is currently being used by any process
+pub fn is_microphone_in_use() -> bool {
+    // On error, assume mic is in use to avoid false positives
+    let device_id = match default_input_device() {
+        Some(id) => id,
+        None => return true,
+    };
+
+    unsafe {
         // Check if the device is running (using input scope for microphone)
         let running_address = AudioObjectPropertyAddress {
             selector: AUDIO_DEVICE_PROPERTY_DEVICE_IS_RUNNING,
@@ -92,6 +150,340 @@ pub fn is_microphone_in_use() -> bool {
     }
 }
 
+/// Check if the default microphone is muted at the OS/hardware level
+/// Returns None if the device doesn't support the mute property
+pub fn is_microphone_muted() -> Option<bool> {
+    let device_id = default_input_device()?;
+
+    unsafe {
+        let mute_address = AudioObjectPropertyAddress {
+            selector: AUDIO_DEVICE_PROPERTY_MUTE,
+            scope: AUDIO_OBJECT_PROPERTY_SCOPE_INPUT,
+            element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+        };
+
+        if AudioObjectHasProperty(device_id, &mute_address) == 0 {
+            return None;
+        }
+
+        let mut is_muted: u32 = 0;
+        let mut muted_size = std::mem::size_of::<u32>() as u32;
+
+        let status = AudioObjectGetPropertyData(
+            device_id,
+            &mute_address,
+            0,
+            std::ptr::null(),
+            &mut muted_size,
+            &mut is_muted as *mut _ as *mut c_void,
+        );
+
+        if status != 0 {
+            println!("[Ghost MicMonitor] Failed to read mute state: {} (0x{:08X})", status, status as u32);
+            return None;
+        }
+
+        Some(is_muted != 0)
+    }
+}
+
+fn global_address(selector: u32) -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        selector,
+        scope: AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+        element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    }
+}
+
+/// List CoreAudio process objects, or None if this macOS predates them (< 14.2)
+fn audio_process_objects() -> Option<Vec<AudioObjectID>> {
+    unsafe {
+        let address = global_address(AUDIO_HARDWARE_PROPERTY_PROCESS_OBJECT_LIST);
+        if AudioObjectHasProperty(AUDIO_OBJECT_SYSTEM_OBJECT, &address) == 0 {
+            return None;
+        }
+
+        let mut size: u32 = 0;
+        if AudioObjectGetPropertyDataSize(AUDIO_OBJECT_SYSTEM_OBJECT, &address, 0, std::ptr::null(), &mut size) != 0 {
+            println!("[Ghost MicMonitor] Failed to get process list size");
+            return Some(Vec::new());
+        }
+
+        let mut processes: Vec<AudioObjectID> = vec![0; size as usize / std::mem::size_of::<AudioObjectID>()];
+        let status = AudioObjectGetPropertyData(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &address,
+            0,
+            std::ptr::null(),
+            &mut size,
+            processes.as_mut_ptr() as *mut c_void,
+        );
+
+        if status != 0 {
+            println!("[Ghost MicMonitor] Failed to get process list: {}", status);
+            return Some(Vec::new());
+        }
+
+        processes.truncate(size as usize / std::mem::size_of::<AudioObjectID>());
+        Some(processes)
+    }
+}
+
+fn process_u32(process: AudioObjectID, selector: u32) -> Option<u32> {
+    unsafe {
+        let address = global_address(selector);
+        let mut value: u32 = 0;
+        let mut size = std::mem::size_of::<u32>() as u32;
+
+        let status = AudioObjectGetPropertyData(
+            process,
+            &address,
+            0,
+            std::ptr::null(),
+            &mut size,
+            &mut value as *mut _ as *mut c_void,
+        );
+
+        (status == 0).then_some(value)
+    }
+}
+
+fn process_bundle_id(process: AudioObjectID) -> Option<String> {
+    unsafe {
+        let address = global_address(AUDIO_PROCESS_PROPERTY_BUNDLE_ID);
+        let mut value: CFStringRef = std::ptr::null();
+        let mut size = std::mem::size_of::<CFStringRef>() as u32;
+
+        let status = AudioObjectGetPropertyData(
+            process,
+            &address,
+            0,
+            std::ptr::null(),
+            &mut size,
+            &mut value as *mut _ as *mut c_void,
+        );
+
+        if status != 0 || value.is_null() {
+            return None;
+        }
+
+        let bundle_id = CFString::wrap_under_create_rule(value).to_string();
+        (!bundle_id.is_empty()).then_some(bundle_id)
+    }
+}
+
+/// List the applications currently capturing from an audio input
+/// Uses CoreAudio process objects (macOS 14.2+); returns an empty list on older systems
+pub fn get_microphone_users() -> Vec<MicUser> {
+    let processes = match audio_process_objects() {
+        Some(processes) => processes,
+        None => {
+            println!("[Ghost MicMonitor] Audio process list not supported on this macOS version");
+            return Vec::new();
+        }
+    };
+
+    let mut users: Vec<MicUser> = Vec::new();
+
+    for process in processes {
+        if process_u32(process, AUDIO_PROCESS_PROPERTY_IS_RUNNING_INPUT).unwrap_or(0) == 0 {
+            continue;
+        }
+
+        let pid = match process_u32(process, AUDIO_PROCESS_PROPERTY_PID) {
+            Some(pid) => pid as i32,
+            None => continue,
+        };
+
+        if users.iter().any(|u| u.pid == pid) {
+            continue;
+        }
+
+        // Helper processes (e.g. a browser's audio service) aren't NSRunningApplications,
+        // so fall back to the bundle id CoreAudio recorded for the input session
+        let bundle_id = super::window::get_bundle_id_for_pid(pid).or_else(|| process_bundle_id(process));
+        let app_name = super::window::get_app_name_for_pid(pid)
+            .or_else(|| bundle_id.clone())
+            .unwrap_or_else(|| format!("pid {}", pid));
+
+        users.push(MicUser { pid, app_name, bundle_id });
+    }
+
+    users
+}
+
+// ============================================================================
+// Change notifications
+// ============================================================================
+
+/// Quiet period before reporting a change, so device reconfiguration doesn't spam events
+const WATCH_DEBOUNCE_MS: u64 = 250;
+
+pub type MicChangeHandler = Arc<dyn Fn(bool) + Send + Sync>;
+
+struct MicWatch {
+    /// Input device the running listener is attached to
+    device: Option<AudioObjectID>,
+    handler: MicChangeHandler,
+    last_state: bool,
+}
+
+static MIC_WATCH: Mutex<Option<MicWatch>> = Mutex::new(None);
+
+// Bumped on every event; a debounced check only fires if no newer event arrived
+static WATCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+fn device_running_address() -> AudioObjectPropertyAddress {
+    AudioObjectPropertyAddress {
+        selector: AUDIO_DEVICE_PROPERTY_DEVICE_IS_RUNNING,
+        scope: AUDIO_OBJECT_PROPERTY_SCOPE_INPUT,
+        element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    }
+}
+
+unsafe extern "C" fn on_device_running_changed(
+    _object_id: AudioObjectID,
+    _number_addresses: u32,
+    _addresses: *const AudioObjectPropertyAddress,
+    _client_data: *mut c_void,
+) -> OSStatus {
+    schedule_check();
+    0
+}
+
+unsafe extern "C" fn on_default_input_changed(
+    _object_id: AudioObjectID,
+    _number_addresses: u32,
+    _addresses: *const AudioObjectPropertyAddress,
+    _client_data: *mut c_void,
+) -> OSStatus {
+    if let Some(watch) = MIC_WATCH.lock().as_mut() {
+        if let Some(device) = watch.device.take() {
+            remove_device_listener(device);
+        }
+        watch.device = default_input_device().filter(|&device| add_device_listener(device));
+    }
+    schedule_check();
+    0
+}
+
+fn add_device_listener(device: AudioObjectID) -> bool {
+    let address = device_running_address();
+    let status = unsafe {
+        AudioObjectAddPropertyListener(device, &address, on_device_running_changed, std::ptr::null_mut())
+    };
+    if status != 0 {
+        println!("[Ghost MicMonitor] Failed to add running listener: {}", status);
+    }
+    status == 0
+}
+
+fn remove_device_listener(device: AudioObjectID) {
+    let address = device_running_address();
+    unsafe {
+        AudioObjectRemovePropertyListener(device, &address, on_device_running_changed, std::ptr::null_mut());
+    }
+}
+
+/// Re-check the mic after the debounce window and report it if it changed
+fn schedule_check() {
+    let generation = WATCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+
+    std::thread::spawn(move || {
+        std::thread::sleep(Duration::from_millis(WATCH_DEBOUNCE_MS));
+        if WATCH_GENERATION.load(Ordering::SeqCst) != generation {
+            return;
+        }
+
+        let in_use = is_microphone_in_use();
+        let handler = {
+            let mut guard = MIC_WATCH.lock();
+            let watch = match guard.as_mut() {
+                Some(watch) => watch,
+                None => return,
+            };
+            if watch.last_state == in_use {
+                return;
+            }
+            watch.last_state = in_use;
+            watch.handler.clone()
+        };
+
+        println!("[Ghost MicMonitor] Microphone {}", if in_use { "in use" } else { "released" });
+        handler(in_use);
+    });
+}
+
+/// Install CoreAudio listeners that call handler(in_use) whenever the default
+/// microphone starts or stops running. Replaces any existing watch
+pub fn watch_microphone(handler: MicChangeHandler) -> Result<(), String> {
+    unwatch_microphone();
+
+    let default_address = AudioObjectPropertyAddress {
+        selector: AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE,
+        scope: AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+        element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    };
+
+    let mut guard = MIC_WATCH.lock();
+
+    // Follow the default device so switching mics doesn't silently stop events
+    let status = unsafe {
+        AudioObjectAddPropertyListener(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &default_address,
+            on_default_input_changed,
+            std::ptr::null_mut(),
+        )
+    };
+    if status != 0 {
+        return Err(format!("Failed to watch default input device: {}", status));
+    }
+
+    let device = default_input_device().filter(|&device| add_device_listener(device));
+    *guard = Some(MicWatch {
+        device,
+        handler,
+        last_state: is_microphone_in_use(),
+    });
+
+    println!("[Ghost MicMonitor] ✅ Watching microphone usage");
+    Ok(())
+}
+
+/// Remove the listeners installed by watch_microphone
+/// Returns false if no watch was active
+pub fn unwatch_microphone() -> bool {
+    // Invalidate any pending debounced check
+    WATCH_GENERATION.fetch_add(1, Ordering::SeqCst);
+
+    let watch = match MIC_WATCH.lock().take() {
+        Some(watch) => watch,
+        None => return false,
+    };
+
+    let default_address = AudioObjectPropertyAddress {
+        selector: AUDIO_HARDWARE_PROPERTY_DEFAULT_INPUT_DEVICE,
+        scope: AUDIO_OBJECT_PROPERTY_SCOPE_GLOBAL,
+        element: AUDIO_OBJECT_PROPERTY_ELEMENT_MAIN,
+    };
+
+    unsafe {
+        AudioObjectRemovePropertyListener(
+            AUDIO_OBJECT_SYSTEM_OBJECT,
+            &default_address,
+            on_default_input_changed,
+            std::ptr::null_mut(),
+        );
+    }
+    if let Some(device) = watch.device {
+        remove_device_listener(device);
+    }
+
+    println!("[Ghost MicMonitor] Stopped watching microphone usage");
+    true
+}
+
 #[cfg(test)]
 mod tests {
     use super::*;
@@ -4,5 +4,8 @@ pub mod window;
 pub mod permissions;
 pub mod accessibility;
 pub mod audio;
+pub mod audio_devices;
 pub mod mic_monitor;
+pub mod now_playing;
+pub mod process_stats;
 
@@ -0,0 +1,167 @@
+//! Read the system "Now Playing" metadata via the private MediaRemote framework
+//!
+//! MediaRemote is loaded at runtime with dlopen because it is a private framework
+//! and may be missing or restricted (e.g. entitlement checks on newer macOS).
+//! Every failure path simply reports that nothing is playing.
+
+use crate::NowPlayingInfo;
+use cocoa::base::{id, nil};
+use core_foundation::base::{CFType, TCFType};
+use core_foundation::dictionary::CFDictionary;
+use core_foundation::string::CFString;
+use objc::{class, msg_send, sel, sel_impl};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::ffi::{c_void, CStr};
+use std::os::raw::{c_char, c_int};
+use std::sync::Arc;
+
+extern "C" {
+    fn dlopen(filename: *const c_char, flag: c_int) -> *mut c_void;
+    fn dlsym(handle: *mut c_void, symbol: *const c_char) -> *mut c_void;
+    fn dispatch_get_global_queue(identifier: i64, flags: u64) -> id;
+    fn dispatch_semaphore_create(value: i64) -> *mut c_void;
+    fn dispatch_semaphore_signal(dsema: *mut c_void) -> i64;
+    fn dispatch_semaphore_wait(dsema: *mut c_void, timeout: u64) -> i64;
+    fn dispatch_time(when: u64, delta: i64) -> u64;
+}
+
+const RTLD_LAZY: c_int = 0x1;
+const QOS_CLASS_USER_INITIATED: i64 = 0x19;
+const DISPATCH_TIME_NOW: u64 = 0;
+const CALLBACK_TIMEOUT_NS: i64 = 2_000_000_000;
+
+const MEDIA_REMOTE_PATH: &[u8] = b"/System/Library/PrivateFrameworks/MediaRemote.framework/MediaRemote\0";
+
+type GetNowPlayingInfoFn = unsafe extern "C" fn(queue: id, block: &block::Block<(*const c_void,), ()>);
+type GetNowPlayingPidFn = unsafe extern "C" fn(queue: id, block: &block::Block<(c_int,), ()>);
+
+struct MediaRemote {
+    get_info: GetNowPlayingInfoFn,
+    get_pid: Option<GetNowPlayingPidFn>,
+}
+
+static MEDIA_REMOTE: Lazy<Option<MediaRemote>> = Lazy::new(|| unsafe {
+    let handle = dlopen(MEDIA_REMOTE_PATH.as_ptr() as *const c_char, RTLD_LAZY);
+    if handle.is_null() {
+        println!("[NowPlaying] MediaRemote framework not available");
+        return None;
+    }
+
+    let get_info = dlsym(handle, b"MRMediaRemoteGetNowPlayingInfo\0".as_ptr() as *const c_char);
+    if get_info.is_null() {
+        println!("[NowPlaying] MRMediaRemoteGetNowPlayingInfo not found");
+        return None;
+    }
+
+    let get_pid = dlsym(handle, b"MRMediaRemoteGetNowPlayingApplicationPID\0".as_ptr() as *const c_char);
+
+    Some(MediaRemote {
+        get_info: std::mem::transmute::<*mut c_void, GetNowPlayingInfoFn>(get_info),
+        get_pid: if get_pid.is_null() {
+            None
+        } else {
+            Some(std::mem::transmute::<*mut c_void, GetNowPlayingPidFn>(get_pid))
+        },
+    })
+});
+
+/// Get the currently playing media, or None if nothing is playing / API unavailable
+#[allow(deprecated)]
+pub fn get_now_playing() -> Option<NowPlayingInfo> {
+    let media_remote = MEDIA_REMOTE.as_ref()?;
+
+    let (title, artist) = unsafe { fetch_title_and_artist(media_remote.get_info)? };
+    let app = media_remote.get_pid
+        .and_then(|get_pid| unsafe { fetch_app_pid(get_pid) })
+        .and_then(app_name_for_pid);
+
+    Some(NowPlayingInfo { app, title, artist })
+}
+
+unsafe fn fetch_title_and_artist(get_info: GetNowPlayingInfoFn) -> Option<(String, Option<String>)> {
+    let result: Arc<Mutex<Option<(String, Option<String>)>>> = Arc::new(Mutex::new(None));
+    let sem = dispatch_semaphore_create(0);
+    let sem_ptr = sem as usize;
+
+    let result_cb = result.clone();
+    let block = block::ConcreteBlock::new(move |info: *const c_void| {
+        if !info.is_null() {
+            let dict: CFDictionary<CFString, CFType> = CFDictionary::wrap_under_get_rule(info as *const _);
+            let title = dict_string(&dict, "kMRMediaRemoteNowPlayingInfoTitle");
+            let artist = dict_string(&dict, "kMRMediaRemoteNowPlayingInfoArtist");
+            if let Some(title) = title.filter(|t| !t.is_empty()) {
+                *result_cb.lock() = Some((title, artist));
+            }
+        }
+        dispatch_semaphore_signal(sem_ptr as *mut c_void);
+    });
+    let block = block.copy();
+
+    let queue = dispatch_get_global_queue(QOS_CLASS_USER_INITIATED, 0);
+    get_info(queue, &*block);
+
+    let timeout = dispatch_time(DISPATCH_TIME_NOW, CALLBACK_TIMEOUT_NS);
+    if dispatch_semaphore_wait(sem, timeout) != 0 {
+        println!("[NowPlaying] Timed out waiting for now playing info");
+        return None;
+    }
+
+    let info = result.lock().take();
+    info
+}
+
+unsafe fn fetch_app_pid(get_pid: GetNowPlayingPidFn) -> Option<i32> {
+    let result: Arc<Mutex<Option<i32>>> = Arc::new(Mutex::new(None));
+    let sem = dispatch_semaphore_create(0);
+    let sem_ptr = sem as usize;
+
+    let result_cb = result.clone();
+    let block = block::ConcreteBlock::new(move |pid: c_int| {
+        if pid > 0 {
+            *result_cb.lock() = Some(pid);
+        }
+        dispatch_semaphore_signal(sem_ptr as *mut c_void);
+    });
+    let block = block.copy();
+
+    let queue = dispatch_get_global_queue(QOS_CLASS_USER_INITIATED, 0);
+    get_pid(queue, &*block);
+
+    let timeout = dispatch_time(DISPATCH_TIME_NOW, CALLBACK_TIMEOUT_NS);
+    if dispatch_semaphore_wait(sem, timeout) != 0 {
+        return None;
+    }
+
+    let pid = result.lock().take();
+    pid
+}
+
+fn dict_string(dict: &CFDictionary<CFString, CFType>, key: &str) -> Option<String> {
+    dict.find(&CFString::new(key))
+        .and_then(|value| value.downcast::<CFString>())
+        .map(|s| s.to_string())
+}
+
+/// Get the localized application name for a process ID using NSRunningApplication
+#[allow(deprecated)]
+fn app_name_for_pid(pid: i32) -> Option<String> {
+    unsafe {
+        let app: id = msg_send![class!(NSRunningApplication), runningApplicationWithProcessIdentifier: pid];
+        if app == nil {
+            return None;
+        }
+
+        let name: id = msg_send![app, localizedName];
+        if name == nil {
+            return None;
+        }
+
+        let c_str: *const i8 = msg_send![name, UTF8String];
+        if c_str.is_null() {
+            return None;
+        }
+
+        Some(CStr::from_ptr(c_str).to_string_lossy().to_string())
+    }
+}
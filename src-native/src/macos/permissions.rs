@@ -1,5 +1,6 @@
 //! macOS permission checking and requesting
 
+use crate::PermissionStatus;
 use cocoa::base::{id, nil};
 use core_foundation::base::TCFType;
 use core_foundation::boolean::CFBoolean;
@@ -7,7 +8,8 @@ use core_foundation::dictionary::CFDictionary;
 use core_foundation::runloop::{kCFRunLoopDefaultMode, CFRunLoopRunInMode};
 use core_foundation::string::CFString;
 use objc::{class, msg_send, sel, sel_impl};
-use std::sync::atomic::{AtomicBool, Ordering};
+use std::path::PathBuf;
+use std::sync::atomic::{AtomicBool, AtomicU64, Ordering};
 use std::time::{Duration, Instant};
 
 #[link(name = "ApplicationServices", kind = "framework")]
@@ -18,6 +20,11 @@ extern "C" {
 #[link(name = "ScreenCaptureKit", kind = "framework")]
 extern "C" {}
 
+#[link(name = "AVFoundation", kind = "framework")]
+extern "C" {
+    static AVMediaTypeAudio: id;
+}
+
 /// Check if accessibility permission is granted
 pub fn check_accessibility() -> bool {
     unsafe {
@@ -33,6 +40,8 @@ pub fn check_accessibility() -> bool {
 
 /// Request accessibility permission (opens System Settings)
 pub fn request_accessibility() -> bool {
+    mark_prompted(ACCESSIBILITY_PROMPT);
+    
     unsafe {
         let key = CFString::new("AXTrustedCheckOptionPrompt");
         let options = CFDictionary::from_CFType_pairs(&[(
@@ -104,6 +113,8 @@ static SCK_OK: AtomicBool = AtomicBool::new(false);
 /// This will add the app to the Screen Recording list and prompt the user
 #[allow(deprecated)]
 pub fn request_screen_recording() -> bool {
+    mark_prompted(SCREEN_RECORDING_PROMPT);
+    
     // First try the basic CG request
     unsafe {
         CGRequestScreenCaptureAccess();
@@ -145,6 +156,146 @@ pub fn trigger_screen_capture_kit_permission() {
     }
 }
 
+// ============================================================================
+// Tri-state status
+// ============================================================================
+
+// Accessibility and screen recording have no "not determined" API, so we remember
+// whether we've ever prompted: not granted + prompted means the user declined
+const ACCESSIBILITY_PROMPT: &str = "accessibility";
+const SCREEN_RECORDING_PROMPT: &str = "screen-recording";
+
+fn prompt_marker(permission: &str) -> PathBuf {
+    let app_data = dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ghost");
+    std::fs::create_dir_all(&app_data).ok();
+    app_data.join(format!(".prompted-{}", permission))
+}
+
+fn mark_prompted(permission: &str) {
+    if let Err(e) = std::fs::write(prompt_marker(permission), b"") {
+        println!("[Permissions] ⚠️ Failed to record {} prompt: {}", permission, e);
+    }
+}
+
+fn was_prompted(permission: &str) -> bool {
+    prompt_marker(permission).exists()
+}
+
+fn status_from_prompt(granted: bool, permission: &str) -> PermissionStatus {
+    if granted {
+        PermissionStatus::Granted
+    } else if was_prompted(permission) {
+        PermissionStatus::Denied
+    } else {
+        PermissionStatus::NotDetermined
+    }
+}
+
+pub fn accessibility_status() -> PermissionStatus {
+    status_from_prompt(check_accessibility(), ACCESSIBILITY_PROMPT)
+}
+
+pub fn screen_recording_status() -> PermissionStatus {
+    status_from_prompt(unsafe { CGPreflightScreenCaptureAccess() }, SCREEN_RECORDING_PROMPT)
+}
+
+/// Microphone status straight from AVCaptureDevice authorizationStatusForMediaType:
+#[allow(deprecated)]
+pub fn microphone_status() -> PermissionStatus {
+    // AVAuthorizationStatus: 0 = NotDetermined, 1 = Restricted, 2 = Denied, 3 = Authorized
+    let status: i64 = unsafe { msg_send![class!(AVCaptureDevice), authorizationStatusForMediaType: AVMediaTypeAudio] };
+    
+    match status {
+        3 => PermissionStatus::Granted,
+        2 => PermissionStatus::Denied,
+        1 => PermissionStatus::Restricted,
+        _ => PermissionStatus::NotDetermined,
+    }
+}
+
+// ============================================================================
+// Screen recording grant watcher
+// ============================================================================
+
+// macOS has no notification for TCC changes, so poll with backoff
+const WATCH_INITIAL_INTERVAL_MS: u64 = 500;
+const WATCH_MAX_INTERVAL_MS: u64 = 5000;
+
+// Bumped to cancel the running watcher
+static SCREEN_WATCH_GENERATION: AtomicU64 = AtomicU64::new(0);
+
+/// Call on_granted once screen recording is granted, then stop
+/// Replaces any running watcher
+pub fn watch_screen_recording(on_granted: Box<dyn FnOnce() + Send>) {
+    let generation = SCREEN_WATCH_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    
+    std::thread::spawn(move || {
+        let mut interval = WATCH_INITIAL_INTERVAL_MS;
+        
+        loop {
+            if SCREEN_WATCH_GENERATION.load(Ordering::SeqCst) != generation {
+                return;
+            }
+            
+            if unsafe { CGPreflightScreenCaptureAccess() } {
+                println!("[Permissions] ✅ Screen recording granted");
+                on_granted();
+                return;
+            }
+            
+            std::thread::sleep(Duration::from_millis(interval));
+            interval = (interval * 3 / 2).min(WATCH_MAX_INTERVAL_MS);
+        }
+    });
+}
+
+/// Stop the watcher started by watch_screen_recording without firing it
+pub fn stop_watching_screen_recording() {
+    SCREEN_WATCH_GENERATION.fetch_add(1, Ordering::SeqCst);
+}
+
+// ============================================================================
+// Microphone
+// ============================================================================
+
+/// Check if microphone permission is granted
+pub fn check_microphone() -> bool {
+    matches!(microphone_status(), PermissionStatus::Granted)
+}
+
+/// Request microphone permission, resolving once the user answers the prompt
+/// Resolves immediately if the user already decided
+#[allow(deprecated)]
+pub async fn request_microphone() -> bool {
+    match microphone_status() {
+        PermissionStatus::Granted => return true,
+        PermissionStatus::Denied | PermissionStatus::Restricted => return false,
+        PermissionStatus::NotDetermined => {}
+    }
+    
+    let (tx, rx) = tokio::sync::oneshot::channel::<bool>();
+    let tx = parking_lot::Mutex::new(Some(tx));
+    
+    unsafe {
+        let block = block::ConcreteBlock::new(move |granted: objc::runtime::BOOL| {
+            if let Some(tx) = tx.lock().take() {
+                let _ = tx.send(granted != objc::runtime::NO);
+            }
+        });
+        let block = block.copy();
+        
+        let _: () = msg_send![
+            class!(AVCaptureDevice),
+            requestAccessForMediaType: AVMediaTypeAudio
+            completionHandler: &*block
+        ];
+    }
+    
+    rx.await.unwrap_or(false)
+}
+
 #[link(name = "CoreGraphics", kind = "framework")]
 extern "C" {
     fn CGPreflightScreenCaptureAccess() -> bool;
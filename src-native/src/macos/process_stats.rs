@@ -0,0 +1,73 @@
+//! Resident memory and CPU time of this process
+//! Uses Mach task_info, the same source Activity Monitor and ps read
+
+type KernReturn = i32;
+type MachPort = u32;
+
+#[repr(C)]
+#[derive(Debug, Default, Clone, Copy)]
+struct TimeValue {
+    seconds: i32,
+    microseconds: i32,
+}
+
+impl TimeValue {
+    fn as_secs(&self) -> f64 {
+        self.seconds as f64 + self.microseconds as f64 / 1_000_000.0
+    }
+}
+
+/// mach_task_basic_info; its times only cover threads that have exited
+#[repr(C)]
+#[derive(Debug, Default)]
+struct MachTaskBasicInfo {
+    virtual_size: u64,
+    resident_size: u64,
+    resident_size_max: u64,
+    user_time: TimeValue,
+    system_time: TimeValue,
+    policy: i32,
+    suspend_count: i32,
+}
+
+/// task_thread_times_info: times of the live threads
+#[repr(C)]
+#[derive(Debug, Default)]
+struct TaskThreadTimesInfo {
+    user_time: TimeValue,
+    system_time: TimeValue,
+}
+
+const MACH_TASK_BASIC_INFO: i32 = 20;
+const TASK_THREAD_TIMES_INFO: i32 = 3;
+const KERN_SUCCESS: KernReturn = 0;
+
+extern "C" {
+    static mach_task_self_: MachPort;
+    fn task_info(task: MachPort, flavor: i32, info: *mut i32, count: *mut u32) -> KernReturn;
+}
+
+/// Query one task_info flavor into `T` (count is in 32-bit words)
+fn query<T: Default>(flavor: i32) -> Option<T> {
+    let mut info = T::default();
+    let mut count = (std::mem::size_of::<T>() / std::mem::size_of::<i32>()) as u32;
+    let status = unsafe {
+        task_info(mach_task_self_, flavor, &mut info as *mut T as *mut i32, &mut count)
+    };
+    (status == KERN_SUCCESS).then_some(info)
+}
+
+/// Resident set size in bytes
+pub fn resident_bytes() -> Option<u64> {
+    query::<MachTaskBasicInfo>(MACH_TASK_BASIC_INFO).map(|info| info.resident_size)
+}
+
+/// User + system CPU seconds used by the process so far (live and exited threads)
+pub fn cpu_time_secs() -> Option<f64> {
+    let exited = query::<MachTaskBasicInfo>(MACH_TASK_BASIC_INFO)?;
+    let live = query::<TaskThreadTimesInfo>(TASK_THREAD_TIMES_INFO)?;
+    Some(
+        exited.user_time.as_secs() + exited.system_time.as_secs()
+            + live.user_time.as_secs() + live.system_time.as_secs(),
+    )
+}
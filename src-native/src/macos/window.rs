@@ -1,22 +1,64 @@
 //! macOS window enumeration using CoreGraphics
 
 use crate::WindowInfo;
+use crate::window::{is_meeting_app, report_active_window, set_active_window_handler, stable_window_key, WindowChangeHandler};
 use core_foundation::array::CFArray;
 use core_foundation::base::{CFType, TCFType, CFTypeRef};
 use core_foundation::boolean::CFBoolean;
 use core_foundation::dictionary::CFDictionary;
 use core_foundation::number::CFNumber;
 use core_foundation::string::CFString;
-use core_graphics::display::{CGWindowListCopyWindowInfo, kCGWindowListOptionOnScreenOnly, kCGNullWindowID};
+use core_graphics::display::{CGWindowListCopyWindowInfo, kCGWindowListExcludeDesktopElements, kCGWindowListOptionAll, kCGWindowListOptionIncludingWindow, kCGWindowListOptionOnScreenOnly, kCGNullWindowID};
+use parking_lot::Mutex;
+use std::ffi::c_void;
 
-/// Get all visible windows on macOS
+type AXObserverRef = *mut c_void;
+type AXUIElementRef = *mut c_void;
+type CFStringRef = *const c_void;
+type CFRunLoopRef = *mut c_void;
+type CFRunLoopSourceRef = *mut c_void;
+
+type AXObserverCallback = unsafe extern "C" fn(
+    observer: AXObserverRef,
+    element: AXUIElementRef,
+    notification: CFStringRef,
+    refcon: *mut c_void,
+);
+
+#[link(name = "ApplicationServices", kind = "framework")]
+extern "C" {
+    fn AXUIElementCreateApplication(pid: i32) -> AXUIElementRef;
+    fn AXObserverCreate(pid: i32, callback: AXObserverCallback, observer: *mut AXObserverRef) -> i32;
+    fn AXObserverAddNotification(
+        observer: AXObserverRef,
+        element: AXUIElementRef,
+        notification: CFStringRef,
+        refcon: *mut c_void,
+    ) -> i32;
+    fn AXObserverGetRunLoopSource(observer: AXObserverRef) -> CFRunLoopSourceRef;
+    fn CFRunLoopGetMain() -> CFRunLoopRef;
+    fn CFRunLoopAddSource(run_loop: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+    fn CFRunLoopRemoveSource(run_loop: CFRunLoopRef, source: CFRunLoopSourceRef, mode: CFStringRef);
+    fn CFRelease(cf: *const c_void);
+    static kCFRunLoopDefaultMode: CFStringRef;
+}
+
+#[link(name = "AppKit", kind = "framework")]
+extern "C" {
+    static NSWorkspaceDidActivateApplicationNotification: cocoa::base::id;
+}
+
+/// Get all windows on macOS, minimized ones included with is_minimized set
 pub fn get_windows() -> Vec<WindowInfo> {
     let mut windows = Vec::new();
+    let frontmost_pid = get_frontmost_app_pid();
+    let key_layer = CFString::new("kCGWindowLayer");
+    let mut focus_found = false;
     
     unsafe {
-        // Get window list from CGWindowListCopyWindowInfo
+        // OnScreenOnly would leave out minimized windows, so ask for all of them
         let window_list = CGWindowListCopyWindowInfo(
-            kCGWindowListOptionOnScreenOnly,
+            kCGWindowListOptionAll | kCGWindowListExcludeDesktopElements,
             kCGNullWindowID,
         );
         
@@ -28,9 +70,21 @@ pub fn get_windows() -> Vec<WindowInfo> {
         
         for i in 0..array.len() {
             if let Some(dict) = array.get(i) {
-                if let Some(info) = parse_window_dict(&dict) {
+                if let Some(mut info) = parse_window_dict(&dict) {
+                    let is_normal = dict_i32(&dict, &key_layer) == Some(0);
+                    // Off-screen overlays and helper panels aren't windows anyone minimized
+                    if info.is_minimized && !is_normal {
+                        continue;
+                    }
+                    
                     // Filter out desktop elements and menu bar
                     if !info.owner_name.is_empty() && info.window_id > 0 {
+                        // Front to back order: the frontmost app's first normal window has focus
+                        if !focus_found && Some(info.pid) == frontmost_pid && is_normal && !info.is_minimized {
+                            info.is_focused = true;
+                            focus_found = true;
+                        }
+                        info.z_order = windows.len() as i32;
                         windows.push(info);
                     }
                 }
@@ -41,6 +95,99 @@ pub fn get_windows() -> Vec<WindowInfo> {
     windows
 }
 
+/// Get the focused window: the frontmost normal (layer 0) window of the frontmost app
+pub fn get_frontmost_window() -> Option<WindowInfo> {
+    let pid = get_frontmost_app_pid()?;
+    let key_pid = CFString::new("kCGWindowOwnerPID");
+    let key_layer = CFString::new("kCGWindowLayer");
+    
+    unsafe {
+        let window_list = CGWindowListCopyWindowInfo(
+            kCGWindowListOptionOnScreenOnly,
+            kCGNullWindowID,
+        );
+        
+        if window_list.is_null() {
+            return None;
+        }
+        
+        let array: CFArray<CFDictionary<CFString, CFType>> = CFArray::wrap_under_get_rule(window_list as *const _);
+        
+        // The list is ordered front to back, so the first match is the focused window
+        for i in 0..array.len() {
+            let dict = match array.get(i) {
+                Some(dict) => dict,
+                None => continue,
+            };
+            
+            if dict_i32(&dict, &key_pid) != Some(pid) || dict_i32(&dict, &key_layer) != Some(0) {
+                continue;
+            }
+            
+            if let Some(mut info) = parse_window_dict(&dict) {
+                info.is_focused = true;
+                return Some(info);
+            }
+        }
+    }
+    
+    None
+}
+
+/// Fetch a single window by its CGWindowID, without enumerating the rest
+pub fn get_window_by_id(window_id: i32) -> Option<WindowInfo> {
+    if window_id <= 0 {
+        return None;
+    }
+    
+    unsafe {
+        let window_list = CGWindowListCopyWindowInfo(
+            kCGWindowListOptionIncludingWindow,
+            window_id as u32,
+        );
+        
+        if window_list.is_null() {
+            return None;
+        }
+        
+        // Empty once the window has closed
+        let array: CFArray<CFDictionary<CFString, CFType>> = CFArray::wrap_under_get_rule(window_list as *const _);
+        
+        let mut info = (0..array.len())
+            .filter_map(|i| array.get(i))
+            .filter_map(|dict| parse_window_dict(&dict))
+            .find(|info| info.window_id == window_id)?;
+        info.is_focused = get_frontmost_window().map(|w| w.window_id) == Some(window_id);
+        Some(info)
+    }
+}
+
+fn dict_i32(dict: &CFDictionary<CFString, CFType>, key: &CFString) -> Option<i32> {
+    dict.find(key).and_then(|v| {
+        let num_ref = v.as_CFTypeRef();
+        let num: CFNumber = unsafe { CFNumber::wrap_under_get_rule(num_ref as *const _) };
+        num.to_i32()
+    })
+}
+
+/// Get the pid of the active application using NSWorkspace
+#[allow(deprecated)]
+pub(crate) fn get_frontmost_app_pid() -> Option<i32> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+    
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let app: id = msg_send![workspace, frontmostApplication];
+        if app == nil {
+            return None;
+        }
+        
+        let pid: i32 = msg_send![app, processIdentifier];
+        Some(pid)
+    }
+}
+
 fn parse_window_dict(dict: &CFDictionary<CFString, CFType>) -> Option<WindowInfo> {
     let key_pid = CFString::new("kCGWindowOwnerPID");
     let key_window_id = CFString::new("kCGWindowNumber");
@@ -48,16 +195,21 @@ fn parse_window_dict(dict: &CFDictionary<CFString, CFType>) -> Option<WindowInfo
     let key_name = CFString::new("kCGWindowName");
     let key_layer = CFString::new("kCGWindowLayer");
     let key_on_screen = CFString::new("kCGWindowIsOnscreen");
+    let key_alpha = CFString::new("kCGWindowAlpha");
     
-    // Check if window is on screen
-    if let Some(on_screen_val) = dict.find(&key_on_screen) {
+    // CoreGraphics omits kCGWindowIsOnscreen for minimized (or hidden) windows, which only
+    // show up in an OptionAll or by-id query. Fully transparent windows count too, nothing of them is visible
+    let is_on_screen = dict.find(&key_on_screen).map(|on_screen_val| {
         let on_screen_ref = on_screen_val.as_CFTypeRef();
         let on_screen: CFBoolean = unsafe { CFBoolean::wrap_under_get_rule(on_screen_ref as *const _) };
-        let is_on_screen: bool = on_screen.into();
-        if !is_on_screen {
-            return None;
-        }
-    }
+        bool::from(on_screen)
+    }).unwrap_or(false);
+    let alpha = dict.find(&key_alpha).and_then(|v| {
+        let num_ref = v.as_CFTypeRef();
+        let num: CFNumber = unsafe { CFNumber::wrap_under_get_rule(num_ref as *const _) };
+        num.to_f64()
+    }).unwrap_or(1.0);
+    let is_minimized = !is_on_screen || alpha <= 0.0;
     
     // Get layer - allow layer 0 (normal windows) and layer 3 (PiP/overlays)
     // Skip layer < 0 (system UI) and layer > 10 (desktop elements)
@@ -97,6 +249,8 @@ fn parse_window_dict(dict: &CFDictionary<CFString, CFType>) -> Option<WindowInfo
     
     // Get bundle ID from running application
     let bundle_id = get_bundle_id_for_pid(pid);
+    let stable_key = stable_window_key(pid, bundle_id.as_deref(), &owner_name, &title);
+    let is_meeting_app = is_meeting_app(bundle_id.as_deref(), &owner_name);
     
     Some(WindowInfo {
         pid,
@@ -104,12 +258,17 @@ fn parse_window_dict(dict: &CFDictionary<CFString, CFType>) -> Option<WindowInfo
         owner_name,
         title,
         bundle_id,
+        stable_key,
+        is_meeting_app,
+        is_minimized,
+        is_focused: false,
+        z_order: -1,
     })
 }
 
 /// Get bundle identifier for a process ID using NSWorkspace
 #[allow(deprecated)]
-fn get_bundle_id_for_pid(pid: i32) -> Option<String> {
+pub(crate) fn get_bundle_id_for_pid(pid: i32) -> Option<String> {
     use cocoa::base::{id, nil};
     use objc::{class, msg_send, sel, sel_impl};
     use std::ffi::CStr;
@@ -137,3 +296,292 @@ fn get_bundle_id_for_pid(pid: i32) -> Option<String> {
     
     None
 }
+
+/// Get the localized app name for a process ID using NSWorkspace
+#[allow(deprecated)]
+pub(crate) fn get_app_name_for_pid(pid: i32) -> Option<String> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+    use std::ffi::CStr;
+    
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let running_apps: id = msg_send![workspace, runningApplications];
+        let count: usize = msg_send![running_apps, count];
+        
+        for i in 0..count {
+            let app: id = msg_send![running_apps, objectAtIndex: i];
+            let app_pid: i32 = msg_send![app, processIdentifier];
+            
+            if app_pid == pid {
+                let name: id = msg_send![app, localizedName];
+                if name != nil {
+                    let c_str: *const i8 = msg_send![name, UTF8String];
+                    if !c_str.is_null() {
+                        return Some(CStr::from_ptr(c_str).to_string_lossy().to_string());
+                    }
+                }
+            }
+        }
+    }
+    
+    None
+}
+
+/// Edge length of the PNG rendered by get_app_info (32pt at 2x)
+const APP_ICON_SIZE: f64 = 64.0;
+
+/// Name, bundle id and icon of the running app with this pid (NSRunningApplication)
+#[allow(deprecated)]
+pub fn get_app_info(pid: i32) -> Option<crate::AppInfo> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+    
+    unsafe {
+        let app: id = msg_send![class!(NSRunningApplication), runningApplicationWithProcessIdentifier: pid];
+        if app == nil {
+            return None;
+        }
+        
+        let name = nsstring_to_string(msg_send![app, localizedName])
+            .or_else(|| get_app_name_for_pid(pid))
+            .unwrap_or_default();
+        let bundle_id = nsstring_to_string(msg_send![app, bundleIdentifier]);
+        let icon: id = msg_send![app, icon];
+        let icon_png = render_icon_png(icon).map(napi::bindgen_prelude::Buffer::from);
+        
+        Some(crate::AppInfo { name, bundle_id, icon_png })
+    }
+}
+
+#[allow(deprecated)]
+pub(crate) unsafe fn nsstring_to_string(value: cocoa::base::id) -> Option<String> {
+    use objc::{msg_send, sel, sel_impl};
+    
+    if value.is_null() {
+        return None;
+    }
+    let c_str: *const i8 = msg_send![value, UTF8String];
+    if c_str.is_null() {
+        return None;
+    }
+    Some(std::ffi::CStr::from_ptr(c_str).to_string_lossy().to_string())
+}
+
+/// Draw an NSImage into an APP_ICON_SIZE bitmap and encode it as PNG
+#[allow(deprecated)]
+unsafe fn render_icon_png(image: cocoa::base::id) -> Option<Vec<u8>> {
+    use cocoa::base::{id, nil, NO, YES};
+    use cocoa::foundation::{NSPoint, NSRect, NSSize, NSString};
+    use objc::{class, msg_send, sel, sel_impl};
+    
+    if image == nil {
+        return None;
+    }
+    
+    let pixels = APP_ICON_SIZE as i64;
+    let color_space = NSString::alloc(nil).init_str("NSDeviceRGBColorSpace");
+    let rep: id = msg_send![class!(NSBitmapImageRep), alloc];
+    let rep: id = msg_send![rep,
+        initWithBitmapDataPlanes: std::ptr::null_mut::<*mut u8>()
+        pixelsWide: pixels
+        pixelsHigh: pixels
+        bitsPerSample: 8i64
+        samplesPerPixel: 4i64
+        hasAlpha: YES
+        isPlanar: NO
+        colorSpaceName: color_space
+        bytesPerRow: 0i64
+        bitsPerPixel: 0i64];
+    let _: () = msg_send![color_space, release];
+    if rep == nil {
+        return None;
+    }
+    
+    let context: id = msg_send![class!(NSGraphicsContext), graphicsContextWithBitmapImageRep: rep];
+    if context == nil {
+        let _: () = msg_send![rep, release];
+        return None;
+    }
+    let _: () = msg_send![class!(NSGraphicsContext), saveGraphicsState];
+    let _: () = msg_send![class!(NSGraphicsContext), setCurrentContext: context];
+    let rect = NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(APP_ICON_SIZE, APP_ICON_SIZE));
+    let zero = NSRect::new(NSPoint::new(0.0, 0.0), NSSize::new(0.0, 0.0));
+    // NSCompositingOperationSourceOver = 2
+    let _: () = msg_send![image, drawInRect: rect fromRect: zero operation: 2u64 fraction: 1.0f64];
+    let _: () = msg_send![class!(NSGraphicsContext), restoreGraphicsState];
+    
+    // NSBitmapImageFileTypePNG = 4
+    let properties: id = msg_send![class!(NSDictionary), dictionary];
+    let data: id = msg_send![rep, representationUsingType: 4u64 properties: properties];
+    let png = if data == nil {
+        None
+    } else {
+        let bytes: *const u8 = msg_send![data, bytes];
+        let len: usize = msg_send![data, length];
+        (!bytes.is_null() && len > 0).then(|| std::slice::from_raw_parts(bytes, len).to_vec())
+    };
+    let _: () = msg_send![rep, release];
+    png
+}
+
+// ============================================================================
+// Focus-change watching
+// ============================================================================
+
+struct FocusWatch {
+    /// Token returned by NSNotificationCenter for the app activation observer
+    activation_observer: usize,
+    /// AXObserver on the frontmost app for window focus changes (needs accessibility permission)
+    focus_observer: Option<usize>,
+}
+
+static FOCUS_WATCH: Mutex<Option<FocusWatch>> = Mutex::new(None);
+
+/// Report the frontmost window whenever the user switches apps or windows
+/// Replaces any existing watch
+#[allow(deprecated)]
+pub fn watch_active_window(handler: WindowChangeHandler) -> Result<(), String> {
+    use cocoa::base::{id, nil};
+    use objc::{class, msg_send, sel, sel_impl};
+    
+    unwatch_active_window();
+    set_active_window_handler(Some(handler));
+    
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let center: id = msg_send![workspace, notificationCenter];
+        
+        let block = block::ConcreteBlock::new(move |_notification: id| {
+            on_app_activated();
+        });
+        let block = block.copy();
+        
+        let token: id = msg_send![center,
+            addObserverForName: NSWorkspaceDidActivateApplicationNotification
+            object: nil
+            queue: nil
+            usingBlock: &*block];
+        
+        if token == nil {
+            set_active_window_handler(None);
+            return Err("Failed to observe app activation".to_string());
+        }
+        let _: id = msg_send![token, retain];
+        
+        let focus_observer = get_frontmost_app_pid().and_then(|pid| attach_focus_observer(pid));
+        *FOCUS_WATCH.lock() = Some(FocusWatch {
+            activation_observer: token as usize,
+            focus_observer,
+        });
+    }
+    
+    println!("[Window] ✅ Watching active window");
+    if let Some(info) = get_frontmost_window() {
+        report_active_window(info);
+    }
+    Ok(())
+}
+
+/// Stop the watch started by watch_active_window
+/// Returns false if no watch was active
+#[allow(deprecated)]
+pub fn unwatch_active_window() -> bool {
+    use cocoa::base::id;
+    use objc::{class, msg_send, sel, sel_impl};
+    
+    set_active_window_handler(None);
+    
+    let watch = match FOCUS_WATCH.lock().take() {
+        Some(watch) => watch,
+        None => return false,
+    };
+    
+    unsafe {
+        let workspace: id = msg_send![class!(NSWorkspace), sharedWorkspace];
+        let center: id = msg_send![workspace, notificationCenter];
+        let token = watch.activation_observer as id;
+        let _: () = msg_send![center, removeObserver: token];
+        let _: () = msg_send![token, release];
+        
+        if let Some(observer) = watch.focus_observer {
+            detach_focus_observer(observer);
+        }
+    }
+    
+    println!("[Window] Stopped watching active window");
+    true
+}
+
+/// Move the window focus observer to the newly activated app and report its window
+fn on_app_activated() {
+    let pid = get_frontmost_app_pid();
+    
+    {
+        let mut guard = FOCUS_WATCH.lock();
+        let watch = match guard.as_mut() {
+            Some(watch) => watch,
+            None => return,
+        };
+        
+        unsafe {
+            if let Some(observer) = watch.focus_observer.take() {
+                detach_focus_observer(observer);
+            }
+            watch.focus_observer = pid.and_then(|pid| attach_focus_observer(pid));
+        }
+    }
+    
+    if let Some(info) = get_frontmost_window() {
+        report_active_window(info);
+    }
+}
+
+unsafe extern "C" fn on_focused_window_changed(
+    _observer: AXObserverRef,
+    _element: AXUIElementRef,
+    _notification: CFStringRef,
+    _refcon: *mut c_void,
+) {
+    if let Some(info) = get_frontmost_window() {
+        report_active_window(info);
+    }
+}
+
+/// Observe AXFocusedWindowChanged on an app, delivered on the main run loop
+/// Returns None without accessibility permission; app switches are still reported
+unsafe fn attach_focus_observer(pid: i32) -> Option<usize> {
+    let mut observer: AXObserverRef = std::ptr::null_mut();
+    if AXObserverCreate(pid, on_focused_window_changed, &mut observer) != 0 || observer.is_null() {
+        return None;
+    }
+    
+    let app = AXUIElementCreateApplication(pid);
+    if app.is_null() {
+        CFRelease(observer as *const c_void);
+        return None;
+    }
+    
+    let notification = CFString::new("AXFocusedWindowChanged");
+    let status = AXObserverAddNotification(
+        observer,
+        app,
+        notification.as_concrete_TypeRef() as CFStringRef,
+        std::ptr::null_mut(),
+    );
+    CFRelease(app as *const c_void);
+    
+    if status != 0 {
+        CFRelease(observer as *const c_void);
+        return None;
+    }
+    
+    CFRunLoopAddSource(CFRunLoopGetMain(), AXObserverGetRunLoopSource(observer), kCFRunLoopDefaultMode);
+    Some(observer as usize)
+}
+
+unsafe fn detach_focus_observer(observer: usize) {
+    let observer = observer as AXObserverRef;
+    CFRunLoopRemoveSource(CFRunLoopGetMain(), AXObserverGetRunLoopSource(observer), kCFRunLoopDefaultMode);
+    CFRelease(observer as *const c_void);
+}
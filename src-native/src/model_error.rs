@@ -0,0 +1,72 @@
+//! Error type shared by the local model engines (Parakeet, embeddings)
+
+use napi::{Error, Status};
+
+/// Model engine error types
+///
+/// Crosses into JS as an Error whose message starts with a stable code, e.g.
+/// "[NOT_DOWNLOADED] Model not downloaded", so the UI can branch on the code
+/// instead of matching message text
+#[derive(Debug, thiserror::Error)]
+pub enum ModelError {
+    #[error("Model not downloaded")]
+    NotDownloaded,
+    
+    #[error("{0} not initialized")]
+    NotInitialized(&'static str),
+    
+    #[error("Corrupt model: {0}")]
+    CorruptModel(String),
+    
+    #[error("Failed to load model: {0}")]
+    LoadFailed(String),
+    
+    #[error("Inference failed: {0}")]
+    InferenceFailed(String),
+    
+    #[error("Unsupported audio format: {0}")]
+    UnsupportedAudioFormat(String),
+    
+    #[error("Download failed: {0}")]
+    DownloadFailed(String),
+    
+    #[error("Invalid input: {0}")]
+    InvalidInput(String),
+    
+    /// Decode hit the max_duration_ms deadline, from APIs that can't return a partial transcript
+    #[error("Transcription stopped at the max_duration_ms deadline")]
+    Timeout,
+    
+    #[error("I/O error: {0}")]
+    Io(String),
+}
+
+impl ModelError {
+    /// Stable identifier for JS, independent of the message wording
+    pub fn code(&self) -> &'static str {
+        match self {
+            ModelError::NotDownloaded => "NOT_DOWNLOADED",
+            ModelError::NotInitialized(_) => "NOT_INITIALIZED",
+            ModelError::CorruptModel(_) => "CORRUPT_MODEL",
+            ModelError::LoadFailed(_) => "LOAD_FAILED",
+            ModelError::InferenceFailed(_) => "INFERENCE_FAILED",
+            ModelError::UnsupportedAudioFormat(_) => "UNSUPPORTED_AUDIO_FORMAT",
+            ModelError::DownloadFailed(_) => "DOWNLOAD_FAILED",
+            ModelError::InvalidInput(_) => "INVALID_INPUT",
+            ModelError::Timeout => "TIMEOUT",
+            ModelError::Io(_) => "IO_ERROR",
+        }
+    }
+    
+    /// "[CODE] message", as seen by JS (also used for "[ERROR] ..." callback sentinels)
+    pub fn reason(&self) -> String {
+        format!("[{}] {}", self.code(), self)
+    }
+}
+
+impl From<ModelError> for Error {
+    fn from(e: ModelError) -> Self {
+        Error::new(Status::GenericFailure, e.reason())
+    }
+}
+
@@ -4,22 +4,33 @@
 //! using direct ONNX Runtime for optimal performance and text quality.
 
 use napi::bindgen_prelude::*;
+use napi::threadsafe_function::{ThreadsafeFunction, ErrorStrategy, ThreadsafeFunctionCallMode};
 use napi_derive::napi;
 use parking_lot::Mutex;
 use std::path::PathBuf;
-use std::io::{Read, Write};
+use std::io::Read;
+use std::collections::{HashMap, HashSet};
+use std::sync::atomic::{AtomicU32, AtomicU64, Ordering};
 
 // ONNX Runtime implementation
 use ndarray::{Array, Array1, Array2, Array3, ArrayD, ArrayViewD, IxDyn};
 use once_cell::sync::Lazy;
 use ort::execution_providers::CPUExecutionProvider;
+#[cfg(target_os = "macos")]
+use ort::execution_providers::CoreMLExecutionProvider;
 use ort::inputs;
 use ort::session::builder::GraphOptimizationLevel;
 use ort::session::Session;
 use ort::value::TensorRef;
-use regex::Regex;
+use sha2::{Digest, Sha256};
 use std::fs;
 
+use crate::{EngineState, EngineStatus};
+use crate::download;
+use crate::settings::{self, ModelDirConfig};
+use crate::model_error::ModelError;
+use crate::audio::{resample_stream_to_16k, try_resample_audio, AudioError, StreamResampler, WavAudio};
+
 // ============================================================================
 // Parakeet Model - Direct ONNX Runtime Implementation
 // ============================================================================
@@ -29,24 +40,100 @@ type DecoderState = (Array3<f32>, Array3<f32>);
 const SUBSAMPLING_FACTOR: usize = 8;
 const WINDOW_SIZE: f32 = 0.01;
 const MAX_TOKENS_PER_STEP: usize = 10;
+const SAMPLE_RATE: usize = 16000;
+const MIN_ENCODER_WINDOW_SECS: f32 = 1.0;
+/// Longest buffer transcribe_audio_buffer* accepts; longer recordings should go through transcribe_wav_file
+const MAX_BUFFER_SECS: usize = 30 * 60;
+
+/// Decode constants, adjustable with configure_parakeet for other model exports
+/// Snapshotted into the model by init_parakeet
+#[derive(Debug, Clone, Copy)]
+struct DecodeConfig {
+    /// Non-blank tokens emitted per encoder frame before forcing a step; lower curbs repetition loops
+    max_tokens_per_step: usize,
+    /// Feature frames per encoder frame
+    subsampling_factor: usize,
+    /// Feature hop in seconds
+    window_size: f32,
+}
+
+impl DecodeConfig {
+    const DEFAULT: Self = Self {
+        max_tokens_per_step: MAX_TOKENS_PER_STEP,
+        subsampling_factor: SUBSAMPLING_FACTOR,
+        window_size: WINDOW_SIZE,
+    };
+    
+    /// Seconds per encoder frame (10ms hop * 8 = 80ms by default)
+    fn frame_secs(&self) -> f32 {
+        self.window_size * self.subsampling_factor as f32
+    }
+    
+    fn samples_per_frame(&self) -> usize {
+        (self.window_size * SAMPLE_RATE as f32).round() as usize * self.subsampling_factor
+    }
+}
+
+static DECODE_CONFIG: Mutex<DecodeConfig> = Mutex::new(DecodeConfig::DEFAULT);
 
-// Regex for decoding SentencePiece tokens
-static DECODE_SPACE_RE: Lazy<Option<Regex>> =
-    Lazy::new(|| Regex::new(r"\A\s|\s\B|(\s)\b").ok());
+// Streaming: frames of already-decoded audio re-encoded as left context, and trailing
+// frames held back until more audio arrives (they lack right context and are unstable)
+const STREAM_LEFT_CONTEXT_FRAMES: usize = 25;   // 2s
+const STREAM_RIGHT_CONTEXT_FRAMES: usize = 4;   // 320ms
+/// Frames of overlap on each side of an encoder window when long audio is windowed
+const WINDOW_CONTEXT_FRAMES: usize = 12;   // ~1s
+/// A streamed segment with no sentence end is finalized anyway once it is this long
+const STREAM_MAX_SEGMENT_SECS: f32 = 20.0;
 
-#[derive(Debug, Clone)]
+/// Punctuation that attaches to the preceding word, so a word-start space before it is dropped
+const CLOSING_PUNCTUATION: &[char] = &['.', ',', '!', '?', ';', ':', ')', ']', '}', '»', '”', '’', '…', '%', '·', '。', '、', '，', '！', '？'];
+
+#[derive(Debug, Clone, Default)]
 pub struct TimestampedResult {
     pub text: String,
     pub timestamps: Vec<f32>,
     pub tokens: Vec<String>,
+    /// Softmax probability of each chosen token, index-aligned with `tokens`
+    pub confidences: Vec<f32>,
+    /// Decoding stopped at the deadline; the fields hold what was decoded before it
+    pub timed_out: bool,
 }
 
-// Use String for internal errors, convert to napi::Error at boundaries
-type ModelResult<T> = std::result::Result<T, String>;
+// Typed internal errors, converted to a coded napi::Error at boundaries
+type ModelResult<T> = std::result::Result<T, ModelError>;
+
+fn ort_err(e: ort::Error) -> ModelError { ModelError::InferenceFailed(e.to_string()) }
+fn load_err(e: ort::Error) -> ModelError { ModelError::LoadFailed(e.to_string()) }
+fn io_err(e: std::io::Error) -> ModelError { ModelError::Io(e.to_string()) }
+fn shape_err(e: ndarray::ShapeError) -> ModelError { ModelError::InferenceFailed(e.to_string()) }
+fn missing_output(name: &str) -> ModelError { ModelError::InferenceFailed(format!("{} not found", name)) }
+
+/// Incremental transcription state carried between transcribe_chunk calls
+struct ParakeetStream {
+    /// 16kHz samples starting at `buffer_start_frame` (left context + undecoded audio)
+    buffer: Vec<f32>,
+    buffer_start_frame: usize,
+    /// First encoder frame (stream timeline) not yet decoded
+    next_frame: usize,
+    decoder_state: DecoderState,
+    /// Every token emitted so far; the decoder conditions on the last one
+    tokens: Vec<i32>,
+    resampler: Option<StreamResampler>,
+    /// 16kHz samples from `history_start_frame` on: the segment not yet finalized,
+    /// its left context and whatever arrived after it
+    history: Vec<f32>,
+    history_start_frame: usize,
+    /// First frame of the segment not yet finalized
+    segment_start_frame: usize,
+}
 
-fn ort_err(e: ort::Error) -> String { e.to_string() }
-fn io_err(e: std::io::Error) -> String { e.to_string() }
-fn shape_err(e: ndarray::ShapeError) -> String { e.to_string() }
+/// Output of one streaming step
+struct StreamStep {
+    /// Tokens decoded by this step, timed from limited context
+    interim: TimestampedResult,
+    /// The span finalized by this step, re-decoded with the audio around it
+    finalized: Option<TimestampedResult>,
+}
 
 /// ParakeetModel - direct ONNX Runtime implementation
 struct ParakeetModel {
@@ -56,20 +143,48 @@ struct ParakeetModel {
     vocab: Vec<String>,
     blank_idx: i32,
     vocab_size: usize,
+    /// Language prompt tokens (`<|en|>` style) present in the vocabulary, keyed by language code
+    language_tokens: HashMap<String, i32>,
+    /// Combined size of the loaded ONNX files, used as a resident memory estimate
+    weights_bytes: u64,
+    /// Max seconds of audio per encoder call (None = encode the whole buffer at once)
+    max_encoder_window_secs: Option<f32>,
+    /// Wall-clock limit for one transcription in ms (None = no limit)
+    max_duration_ms: Option<u32>,
+    /// Deadline of the transcription in progress, checked in the decode loop
+    decode_deadline: Option<std::time::Instant>,
+    decode: DecodeConfig,
 }
 
 impl ParakeetModel {
-    fn new(model_dir: &PathBuf, quantized: bool) -> ModelResult<Self> {
-        let encoder = Self::init_session(model_dir, "encoder-model", None, quantized)?;
-        let decoder_joint = Self::init_session(model_dir, "decoder_joint-model", None, quantized)?;
-        let preprocessor = Self::init_session(model_dir, "nemo128", None, false)?;
+    fn new(
+        model_dir: &PathBuf,
+        quantized: bool,
+        max_encoder_window_secs: Option<f32>,
+        max_duration_ms: Option<u32>,
+        use_coreml: bool,
+        threads: usize,
+    ) -> ModelResult<Self> {
+        // CoreML runs int8 graphs poorly (most ops fall back to CPU), prefer the fp32 encoder when present
+        let encoder_quantized = quantized && !(use_coreml && model_dir.join("encoder-model.onnx").exists());
+        let (encoder, encoder_bytes) = Self::init_session(model_dir, "encoder-model", Some(threads), encoder_quantized, use_coreml)?;
+        let (decoder_joint, decoder_bytes) = Self::init_session(model_dir, "decoder_joint-model", Some(threads), quantized, false)?;
+        let (preprocessor, preprocessor_bytes) = Self::init_session(model_dir, "nemo128", Some(threads), false, false)?;
 
         let (vocab, blank_idx) = Self::load_vocab(model_dir)?;
         let vocab_size = vocab.len();
+        let language_tokens: HashMap<String, i32> = get_parakeet_languages()
+            .into_iter()
+            .filter_map(|lang| {
+                let token = format!("<|{}|>", lang);
+                let id = vocab.iter().position(|t| *t == token)?;
+                Some((lang, id as i32))
+            })
+            .collect();
 
         println!(
-            "[Parakeet] Loaded vocabulary with {} tokens, blank_idx={}",
-            vocab_size, blank_idx
+            "[Parakeet] Loaded vocabulary with {} tokens, blank_idx={}, threads={}",
+            vocab_size, blank_idx, threads
         );
 
         Ok(Self {
@@ -79,6 +194,12 @@ impl ParakeetModel {
             vocab,
             blank_idx,
             vocab_size,
+            language_tokens,
+            weights_bytes: encoder_bytes + decoder_bytes + preprocessor_bytes,
+            max_encoder_window_secs,
+            max_duration_ms,
+            decode_deadline: None,
+            decode: *DECODE_CONFIG.lock(),
         })
     }
 
@@ -87,9 +208,8 @@ impl ParakeetModel {
         model_name: &str,
         intra_threads: Option<usize>,
         try_quantized: bool,
-    ) -> ModelResult<Session> {
-        let providers = vec![CPUExecutionProvider::default().build()];
-
+        use_coreml: bool,
+    ) -> ModelResult<(Session, u64)> {
         let model_filename = if try_quantized {
             let quantized_name = format!("{}.int8.onnx", model_name);
             let quantized_path = model_dir.join(&quantized_name);
@@ -107,17 +227,23 @@ impl ParakeetModel {
             regular_name
         };
 
-        let mut builder = Session::builder().map_err(ort_err)?
-            .with_optimization_level(GraphOptimizationLevel::Level3).map_err(ort_err)?
-            .with_execution_providers(providers).map_err(ort_err)?
-            .with_parallel_execution(true).map_err(ort_err)?;
-
-        if let Some(threads) = intra_threads {
-            builder = builder.with_intra_threads(threads).map_err(ort_err)?
-                .with_inter_threads(threads).map_err(ort_err)?;
-        }
+        let model_path = model_dir.join(&model_filename);
 
-        let session = builder.commit_from_file(model_dir.join(&model_filename)).map_err(ort_err)?;
+        let session = if use_coreml {
+            match Self::build_session(&model_path, intra_threads, true) {
+                Ok(session) => {
+                    println!("[Parakeet] '{}' using CoreMLExecutionProvider", model_filename);
+                    session
+                }
+                Err(e) => {
+                    println!("[Parakeet] ⚠️ CoreML unavailable for '{}' ({}), falling back to CPU", model_filename, e);
+                    Self::build_session(&model_path, intra_threads, false)?
+                }
+            }
+        } else {
+            Self::build_session(&model_path, intra_threads, false)?
+        };
+        let file_bytes = fs::metadata(&model_path).map(|m| m.len()).unwrap_or(0);
 
         for input in &session.inputs {
             println!(
@@ -126,7 +252,30 @@ impl ParakeetModel {
             );
         }
 
-        Ok(session)
+        Ok((session, file_bytes))
+    }
+
+    fn build_session(model_path: &PathBuf, intra_threads: Option<usize>, coreml: bool) -> ModelResult<Session> {
+        let mut providers = Vec::new();
+        if coreml {
+            #[cfg(target_os = "macos")]
+            providers.push(CoreMLExecutionProvider::default().build().error_on_failure());
+            #[cfg(not(target_os = "macos"))]
+            return Err(ModelError::LoadFailed("CoreML is only available on macOS".to_string()));
+        }
+        providers.push(CPUExecutionProvider::default().build());
+
+        let mut builder = Session::builder().map_err(load_err)?
+            .with_optimization_level(GraphOptimizationLevel::Level3).map_err(load_err)?
+            .with_execution_providers(providers).map_err(load_err)?
+            .with_parallel_execution(true).map_err(load_err)?;
+
+        if let Some(threads) = intra_threads {
+            builder = builder.with_intra_threads(threads).map_err(load_err)?
+                .with_inter_threads(threads).map_err(load_err)?;
+        }
+
+        builder.commit_from_file(model_path).map_err(load_err)
     }
 
     fn load_vocab(model_dir: &PathBuf) -> ModelResult<(Vec<String>, i32)> {
@@ -157,7 +306,8 @@ impl ParakeetModel {
             vocab[id] = token.replace('\u{2581}', " ");
         }
 
-        let blank_idx = blank_idx.ok_or_else(|| "Missing <blk> token in vocabulary".to_string())? as i32;
+        let blank_idx = blank_idx
+            .ok_or_else(|| ModelError::CorruptModel("Missing <blk> token in vocabulary".to_string()))? as i32;
 
         Ok((vocab, blank_idx))
     }
@@ -173,9 +323,9 @@ impl ParakeetModel {
         ];
         let outputs = self.preprocessor.run(inputs).map_err(ort_err)?;
 
-        let features = outputs.get("features").ok_or("features not found")?
+        let features = outputs.get("features").ok_or_else(|| missing_output("features"))?
             .try_extract_array().map_err(ort_err)?;
-        let features_lens = outputs.get("features_lens").ok_or("features_lens not found")?
+        let features_lens = outputs.get("features_lens").ok_or_else(|| missing_output("features_lens"))?
             .try_extract_array().map_err(ort_err)?;
 
         Ok((features.to_owned(), features_lens.to_owned()))
@@ -192,9 +342,9 @@ impl ParakeetModel {
         ];
         let outputs = self.encoder.run(inputs).map_err(ort_err)?;
 
-        let encoder_output = outputs.get("outputs").ok_or("outputs not found")?
+        let encoder_output = outputs.get("outputs").ok_or_else(|| missing_output("outputs"))?
             .try_extract_array().map_err(ort_err)?;
-        let encoded_lengths = outputs.get("encoded_lengths").ok_or("encoded_lengths not found")?
+        let encoded_lengths = outputs.get("encoded_lengths").ok_or_else(|| missing_output("encoded_lengths"))?
             .try_extract_array().map_err(ort_err)?;
 
         let encoder_output = encoder_output.permuted_axes(IxDyn(&[0, 2, 1]));
@@ -205,17 +355,15 @@ impl ParakeetModel {
     fn create_decoder_state(&self) -> ModelResult<DecoderState> {
         let inputs = &self.decoder_joint.inputs;
 
-        let state1_shape = inputs.iter()
-            .find(|input| input.name == "input_states_1")
-            .ok_or("input_states_1 not found")?
-            .input_type.tensor_shape()
-            .ok_or("Failed to get input_states_1 shape")?;
+        let state_shape = |name: &str| {
+            inputs.iter()
+                .find(|input| input.name == name)
+                .and_then(|input| input.input_type.tensor_shape())
+                .ok_or_else(|| ModelError::CorruptModel(format!("Decoder has no {} input shape", name)))
+        };
 
-        let state2_shape = inputs.iter()
-            .find(|input| input.name == "input_states_2")
-            .ok_or("input_states_2 not found")?
-            .input_type.tensor_shape()
-            .ok_or("Failed to get input_states_2 shape")?;
+        let state1_shape = state_shape("input_states_1")?;
+        let state2_shape = state_shape("input_states_2")?;
 
         let state1 = Array::zeros((state1_shape[0] as usize, 1, state1_shape[2] as usize));
         let state2 = Array::zeros((state2_shape[0] as usize, 1, state2_shape[2] as usize));
@@ -247,11 +395,11 @@ impl ParakeetModel {
 
         let outputs = self.decoder_joint.run(inputs).map_err(ort_err)?;
 
-        let logits = outputs.get("outputs").ok_or("outputs not found")?
+        let logits = outputs.get("outputs").ok_or_else(|| missing_output("outputs"))?
             .try_extract_array().map_err(ort_err)?;
-        let state1 = outputs.get("output_states_1").ok_or("output_states_1 not found")?
+        let state1 = outputs.get("output_states_1").ok_or_else(|| missing_output("output_states_1"))?
             .try_extract_array().map_err(ort_err)?;
-        let state2 = outputs.get("output_states_2").ok_or("output_states_2 not found")?
+        let state2 = outputs.get("output_states_2").ok_or_else(|| missing_output("output_states_2"))?
             .try_extract_array().map_err(ort_err)?;
 
         let logits = logits.remove_axis(ndarray::Axis(0));
@@ -261,42 +409,154 @@ impl ParakeetModel {
         Ok((logits.to_owned(), (state1_3d, state2_3d)))
     }
 
+    /// `prompt_token` (e.g. a language token) seeds the decoder and is stripped from the output
     fn recognize_batch(
         &mut self,
         waveforms: &ArrayViewD<f32>,
         waveforms_len: &ArrayViewD<i64>,
+        prompt_token: Option<i32>,
     ) -> ModelResult<Vec<TimestampedResult>> {
+        if let Some(window_samples) = self.max_window_samples() {
+            let longest = waveforms_len.iter().copied().max().unwrap_or(0) as usize;
+            if longest > window_samples {
+                return self.recognize_windowed(waveforms, waveforms_len, window_samples, prompt_token);
+            }
+        }
+
         let (features, features_lens) = self.preprocess(waveforms, waveforms_len)?;
         let (encoder_out, encoder_out_lens) = self.encode(&features.view(), &features_lens.view())?;
 
         let mut results = Vec::new();
         for (encodings, &encodings_len) in encoder_out.outer_iter().zip(encoder_out_lens.iter()) {
-            let (tokens, timestamps) = self.decode_sequence(&encodings.view(), encodings_len as usize)?;
-            let result = self.decode_tokens(tokens, timestamps);
+            let mut state = self.create_decoder_state()?;
+            let mut tokens: Vec<i32> = prompt_token.into_iter().collect();
+            let mut timestamps = Vec::new();
+            let mut confidences = Vec::new();
+            let completed = self.decode_sequence(
+                &encodings.view(), encodings_len as usize, 0,
+                &mut state, &mut tokens, &mut timestamps, &mut confidences,
+            )?;
+            if prompt_token.is_some() {
+                tokens.remove(0);
+            }
+            let mut result = self.decode_tokens(tokens, timestamps, confidences);
+            result.timed_out = !completed;
+            results.push(result);
+        }
+
+        Ok(results)
+    }
+
+    fn max_window_samples(&self) -> Option<usize> {
+        self.max_encoder_window_secs
+            .map(|secs| (secs.max(MIN_ENCODER_WINDOW_SECS) * SAMPLE_RATE as f32) as usize)
+    }
+
+    /// Encode at most `window_samples` per encoder call, carrying decoder state across
+    /// windows so per-call latency stays bounded regardless of input length
+    /// Windows start on encoder frame boundaries and overlap their neighbours by up to
+    /// WINDOW_CONTEXT_FRAMES on each side; each frame is decoded once, from the window where
+    /// it has context on both sides, so words at a cut are neither clipped nor repeated
+    fn recognize_windowed(
+        &mut self,
+        waveforms: &ArrayViewD<f32>,
+        waveforms_len: &ArrayViewD<i64>,
+        window_samples: usize,
+        prompt_token: Option<i32>,
+    ) -> ModelResult<Vec<TimestampedResult>> {
+        let samples_per_frame = self.decode.samples_per_frame();
+        let window_frames = (window_samples / samples_per_frame).max(1);
+        let context_frames = (window_frames / 4).min(WINDOW_CONTEXT_FRAMES);
+        let core_frames = window_frames - 2 * context_frames;
+        let mut results = Vec::new();
+
+        for (waveform, &len) in waveforms.outer_iter().zip(waveforms_len.iter()) {
+            let len = len as usize;
+            let total_frames = len.div_ceil(samples_per_frame);
+            let mut state = self.create_decoder_state()?;
+            let mut tokens: Vec<i32> = prompt_token.into_iter().collect();
+            let mut timestamps = Vec::new();
+            let mut confidences = Vec::new();
+
+            let mut completed = true;
+            let mut core_start = 0;
+            while completed && core_start < total_frames {
+                let mut core_end = (core_start + core_frames).min(total_frames);
+                let window_start = core_start.saturating_sub(context_frames);
+                let window_end = (core_end + context_frames).min(total_frames);
+                let last_window = window_end == total_frames;
+                if last_window {
+                    core_end = total_frames;
+                }
+                let (sample_start, sample_end) = (window_start * samples_per_frame, (window_end * samples_per_frame).min(len));
+
+                let window = waveform.slice(ndarray::s![sample_start..sample_end]).to_owned()
+                    .insert_axis(ndarray::Axis(0))
+                    .into_dyn();
+                let window_len = Array1::from_vec(vec![(sample_end - sample_start) as i64]).into_dyn();
+
+                let (features, features_lens) = self.preprocess(&window.view(), &window_len.view())?;
+                let (encoder_out, encoder_out_lens) = self.encode(&features.view(), &features_lens.view())?;
+
+                let encodings = encoder_out.index_axis(ndarray::Axis(0), 0);
+                let encodings_len = encoder_out_lens.iter().next().copied().unwrap_or(0) as usize;
+                // The last window also takes any trailing frame the encoder rounded up to
+                let decode_start = (core_start - window_start).min(encodings_len);
+                let decode_end = if last_window {
+                    encodings_len
+                } else {
+                    (core_end - window_start).min(encodings_len)
+                };
+                let core = encodings.slice(ndarray::s![decode_start..decode_end, ..]).into_dyn();
+                completed = self.decode_sequence(
+                    &core, decode_end - decode_start, core_start,
+                    &mut state, &mut tokens, &mut timestamps, &mut confidences,
+                )?;
+
+                core_start = core_end;
+            }
+
+            if prompt_token.is_some() {
+                tokens.remove(0);
+            }
+            let mut result = self.decode_tokens(tokens, timestamps, confidences);
+            result.timed_out = !completed;
             results.push(result);
         }
 
         Ok(results)
     }
 
+    /// Greedy TDT decode of one encoder output, continuing from `prev_state`/`tokens`
+    /// Timestamps are shifted by `frame_offset` so windows share one timeline
+    /// Returns false if `decode_deadline` passed first; the tokens decoded so far are kept
     fn decode_sequence(
         &mut self,
         encodings: &ArrayViewD<f32>,
         encodings_len: usize,
-    ) -> ModelResult<(Vec<i32>, Vec<usize>)> {
-        let mut prev_state = self.create_decoder_state()?;
-        let mut tokens = Vec::new();
-        let mut timestamps = Vec::new();
+        frame_offset: usize,
+        prev_state: &mut DecoderState,
+        tokens: &mut Vec<i32>,
+        timestamps: &mut Vec<usize>,
+        confidences: &mut Vec<f32>,
+    ) -> ModelResult<bool> {
+        let tokens_before = tokens.len();
 
         let mut t = 0;
         let mut emitted_tokens = 0;
 
         while t < encodings_len {
+            if self.decode_deadline.is_some_and(|deadline| std::time::Instant::now() >= deadline) {
+                println!("[Parakeet] ⚠️ Decode deadline hit at timestep {}/{}", t, encodings_len);
+                return Ok(false);
+            }
+
             let encoder_step = encodings.slice(ndarray::s![t, ..]);
             let encoder_step_dyn = encoder_step.to_owned().into_dyn();
-            let (probs, new_state) = self.decode_step(&tokens, &prev_state, &encoder_step_dyn.view())?;
+            let (probs, new_state) = self.decode_step(tokens, prev_state, &encoder_step_dyn.view())?;
 
-            let vocab_logits_slice = probs.as_slice().ok_or("Failed to get logits slice")?;
+            let vocab_logits_slice = probs.as_slice()
+                .ok_or_else(|| ModelError::InferenceFailed("Failed to get logits slice".to_string()))?;
 
             let vocab_logits = if probs.len() > self.vocab_size {
                 &vocab_logits_slice[..self.vocab_size]
@@ -304,54 +564,178 @@ impl ParakeetModel {
                 vocab_logits_slice
             };
 
-            let token = vocab_logits.iter().enumerate()
+            let (token, max_logit) = vocab_logits.iter().enumerate()
                 .max_by(|(_, a), (_, b)| a.partial_cmp(b).unwrap_or(std::cmp::Ordering::Equal))
-                .map(|(idx, _)| idx as i32)
-                .unwrap_or(self.blank_idx);
+                .map(|(idx, &logit)| (idx as i32, logit))
+                .unwrap_or((self.blank_idx, 0.0));
 
             if token != self.blank_idx {
-                prev_state = new_state;
+                // Softmax probability of the argmax: 1 / sum(exp(l - max))
+                let denom: f32 = vocab_logits.iter().map(|&l| (l - max_logit).exp()).sum();
+                *prev_state = new_state;
                 tokens.push(token);
-                timestamps.push(t);
+                timestamps.push(frame_offset + t);
+                confidences.push(if denom > 0.0 { 1.0 / denom } else { 0.0 });
                 emitted_tokens += 1;
             }
 
-            if token == self.blank_idx || emitted_tokens == MAX_TOKENS_PER_STEP {
+            if token == self.blank_idx || emitted_tokens >= self.decode.max_tokens_per_step {
                 t += 1;
                 emitted_tokens = 0;
             }
         }
 
-        if tokens.is_empty() {
+        if tokens.len() == tokens_before {
             println!("[Parakeet] No tokens decoded for {} timesteps - audio may be silence", encodings_len);
         }
 
-        Ok((tokens, timestamps))
+        Ok(true)
     }
 
-    fn decode_tokens(&self, ids: Vec<i32>, timestamps: Vec<usize>) -> TimestampedResult {
-        let tokens: Vec<String> = ids.iter()
-            .filter_map(|&id| {
-                let idx = id as usize;
-                if idx < self.vocab.len() { Some(self.vocab[idx].clone()) } else { None }
-            })
-            .collect();
+    fn decode_tokens(&self, ids: Vec<i32>, timestamps: Vec<usize>, confidences: Vec<f32>) -> TimestampedResult {
+        // Filter unknown ids together with their timestamp/confidence to keep indices aligned
+        let mut tokens = Vec::with_capacity(ids.len());
+        let mut float_timestamps = Vec::with_capacity(ids.len());
+        let mut token_confidences = Vec::with_capacity(ids.len());
+        for ((id, t), confidence) in ids.into_iter().zip(timestamps).zip(confidences) {
+            let Some(token) = self.vocab.get(id as usize) else { continue };
+            tokens.push(token.clone());
+            float_timestamps.push(self.decode.frame_secs() * t as f32);
+            token_confidences.push(confidence);
+        }
+
+        TimestampedResult {
+            text: join_tokens(&tokens),
+            timestamps: float_timestamps,
+            tokens,
+            confidences: token_confidences,
+            timed_out: false,
+        }
+    }
+
+    fn create_stream(&self) -> ModelResult<ParakeetStream> {
+        Ok(ParakeetStream {
+            buffer: Vec::new(),
+            buffer_start_frame: 0,
+            next_frame: 0,
+            decoder_state: self.create_decoder_state()?,
+            tokens: Vec::new(),
+            resampler: None,
+            history: Vec::new(),
+            history_start_frame: 0,
+            segment_start_frame: 0,
+        })
+    }
 
-        // Regex-based text cleanup
-        let text = match &*DECODE_SPACE_RE {
-            Some(regex) => regex
-                .replace_all(&tokens.join(""), |caps: &regex::Captures| {
-                    if caps.get(1).is_some() { " " } else { "" }
-                })
-                .to_string(),
-            None => tokens.join(""),
+    /// Feed 16kHz samples to a stream, returning the newly decoded (interim) tokens
+    /// When a sentence ends, or the stream does, the segment it closes is re-decoded as a
+    /// whole so its timings no longer depend on where the chunks happened to be cut
+    fn transcribe_stream(
+        &mut self,
+        stream: &mut ParakeetStream,
+        samples: &[f32],
+        finalize: bool,
+    ) -> ModelResult<StreamStep> {
+        stream.history.extend_from_slice(samples);
+        let interim = self.decode_stream(stream, samples, finalize)?;
+        
+        let max_segment_frames = (STREAM_MAX_SEGMENT_SECS / self.decode.frame_secs()) as usize;
+        let sentence_end = interim.tokens.iter()
+            .zip(&interim.timestamps)
+            .filter(|(token, _)| token.ends_with('.') || token.ends_with('?') || token.ends_with('!'))
+            .map(|(_, &t)| (t / self.decode.frame_secs()).round() as usize + 1)
+            .last();
+        let finalize_to = if finalize || stream.next_frame - stream.segment_start_frame >= max_segment_frames {
+            Some(stream.next_frame)
+        } else {
+            sentence_end
         };
+        
+        let finalized = match finalize_to {
+            Some(end_frame) if end_frame > stream.segment_start_frame => Some(self.realign_stream_segment(stream, end_frame)?),
+            _ => None,
+        };
+        Ok(StreamStep { interim, finalized })
+    }
 
-        let float_timestamps: Vec<f32> = timestamps.iter()
-            .map(|&t| WINDOW_SIZE * SUBSAMPLING_FACTOR as f32 * t as f32)
-            .collect();
+    /// Re-decode the stream's open segment up to `end_frame` from its retained audio, which
+    /// includes left context and anything received after the segment, then close it there
+    fn realign_stream_segment(&mut self, stream: &mut ParakeetStream, end_frame: usize) -> ModelResult<TimestampedResult> {
+        let samples_per_frame = self.decode.samples_per_frame();
+        let frame_secs = self.decode.frame_secs();
+        let result = if stream.history.len() >= samples_per_frame {
+            let decoded = self.transcribe_samples_without_deadline(stream.history.clone())?;
+            tokens_in_frames(decoded, frame_secs, stream.history_start_frame, stream.segment_start_frame..end_frame)
+        } else {
+            TimestampedResult::default()
+        };
+        
+        stream.segment_start_frame = end_frame;
+        let keep_from = end_frame.saturating_sub(STREAM_LEFT_CONTEXT_FRAMES).max(stream.history_start_frame);
+        let drop_samples = ((keep_from - stream.history_start_frame) * samples_per_frame).min(stream.history.len());
+        stream.history.drain(..drop_samples);
+        stream.history_start_frame = keep_from;
+        
+        Ok(result)
+    }
+
+    /// Decode only the frames of a stream that are now final
+    /// The buffer is re-encoded with left context so boundary words see their surroundings,
+    /// but each encoder frame is decoded exactly once, so nothing is dropped or duplicated
+    fn decode_stream(
+        &mut self,
+        stream: &mut ParakeetStream,
+        samples: &[f32],
+        finalize: bool,
+    ) -> ModelResult<TimestampedResult> {
+        stream.buffer.extend_from_slice(samples);
+
+        if stream.buffer.is_empty() {
+            return Ok(TimestampedResult::default());
+        }
+
+        let waveform = Array2::from_shape_vec((1, stream.buffer.len()), stream.buffer.clone())
+            .map_err(shape_err)?
+            .into_dyn();
+        let waveform_len = Array1::from_vec(vec![stream.buffer.len() as i64]).into_dyn();
+
+        let (features, features_lens) = self.preprocess(&waveform.view(), &waveform_len.view())?;
+        let (encoder_out, encoder_out_lens) = self.encode(&features.view(), &features_lens.view())?;
+        let encoded_frames = encoder_out_lens.iter().next().copied().unwrap_or(0) as usize;
 
-        TimestampedResult { text, timestamps: float_timestamps, tokens }
+        let holdback = if finalize { 0 } else { STREAM_RIGHT_CONTEXT_FRAMES };
+        let decode_start = stream.next_frame - stream.buffer_start_frame;
+        let decode_end = encoded_frames.saturating_sub(holdback);
+        if decode_end <= decode_start {
+            return Ok(TimestampedResult::default());
+        }
+
+        let encodings = encoder_out.index_axis(ndarray::Axis(0), 0);
+        let final_frames = encodings.slice(ndarray::s![decode_start..decode_end, ..]).into_dyn();
+
+        let tokens_before = stream.tokens.len();
+        let mut timestamps = Vec::new();
+        let mut confidences = Vec::new();
+        // Streaming chunks are short, so no deadline is set and this always completes
+        self.decode_sequence(
+            &final_frames, decode_end - decode_start, stream.next_frame,
+            &mut stream.decoder_state, &mut stream.tokens, &mut timestamps, &mut confidences,
+        )?;
+        let new_tokens = stream.tokens[tokens_before..].to_vec();
+
+        // Keep only the left context behind the decode point
+        stream.next_frame = stream.buffer_start_frame + decode_end;
+        let keep_from = stream.next_frame.saturating_sub(STREAM_LEFT_CONTEXT_FRAMES).max(stream.buffer_start_frame);
+        let drop_samples = ((keep_from - stream.buffer_start_frame) * self.decode.samples_per_frame()).min(stream.buffer.len());
+        stream.buffer.drain(..drop_samples);
+        stream.buffer_start_frame = keep_from;
+
+        let mut result = self.decode_tokens(new_tokens, timestamps, confidences);
+        // decode_tokens trims the leading word boundary; restore it between chunks
+        if tokens_before > 0 && result.tokens.first().map(|t| t.starts_with(' ')).unwrap_or(false) {
+            result.text.insert(0, ' ');
+        }
+        Ok(result)
     }
 
     fn transcribe_samples(&mut self, samples: Vec<f32>) -> ModelResult<String> {
@@ -360,25 +744,113 @@ impl ParakeetModel {
     }
 
     fn transcribe_samples_with_timestamps(&mut self, samples: Vec<f32>) -> ModelResult<TimestampedResult> {
+        self.transcribe_samples_with_language(samples, None)
+    }
+
+    /// Transcribe ignoring max_duration_ms, for files where the whole transcript is wanted
+    fn transcribe_samples_without_deadline(&mut self, samples: Vec<f32>) -> ModelResult<TimestampedResult> {
+        let max_duration_ms = self.max_duration_ms.take();
+        let result = self.transcribe_samples_with_timestamps(samples);
+        self.max_duration_ms = max_duration_ms;
+        result
+    }
+
+    /// Transcribe with an optional language hint; it is fed to the decoder as a prompt
+    /// token when the vocabulary has one for the language, and ignored otherwise
+    fn transcribe_samples_with_language(&mut self, samples: Vec<f32>, language: Option<&str>) -> ModelResult<TimestampedResult> {
+        let prompt_token = language.and_then(|lang| self.language_tokens.get(lang).copied());
+        if let (Some(lang), None) = (language, prompt_token) {
+            println!("[Parakeet] Model has no prompt token for '{}', decoding without it", lang);
+        }
+
         let batch_size = 1;
         let samples_len = samples.len();
 
         let waveforms = Array2::from_shape_vec((batch_size, samples_len), samples).map_err(shape_err)?.into_dyn();
         let waveforms_lens = Array1::from_vec(vec![samples_len as i64]).into_dyn();
 
-        let results = self.recognize_batch(&waveforms.view(), &waveforms_lens.view())?;
+        self.decode_deadline = self.max_duration_ms
+            .map(|ms| std::time::Instant::now() + std::time::Duration::from_millis(ms as u64));
+        let results = self.recognize_batch(&waveforms.view(), &waveforms_lens.view(), prompt_token);
+        self.decode_deadline = None;
+        let results = results?;
 
-        let result = results.into_iter().next().ok_or("No transcription result")?;
+        let result = results.into_iter().next()
+            .ok_or_else(|| ModelError::InferenceFailed("No transcription result".to_string()))?;
 
         Ok(result)
     }
 }
 
+/// Join SentencePiece tokens (with ▁ already mapped to spaces) into clean text
+///
+/// Only the ▁-derived word-start spaces are turned into separators: one space per word
+/// start, none at the beginning or before closing punctuation. Unlike word-boundary
+/// regexes this doesn't depend on what counts as a word character, so Cyrillic, Greek
+/// and other scripts come out the same as Latin
+fn join_tokens(tokens: &[String]) -> String {
+    let mut text = String::new();
+    let mut word_start = false;
+    
+    for token in tokens {
+        let content = token.trim_start();
+        if content.len() != token.len() {
+            word_start = true;
+        }
+        let Some(first) = content.chars().next() else {
+            continue;
+        };
+        
+        if word_start && !text.is_empty() && !CLOSING_PUNCTUATION.contains(&first) {
+            text.push(' ');
+        }
+        text.push_str(content);
+        word_start = false;
+    }
+    text
+}
+
+/// Keep the tokens of `result` whose frame falls in `frames`, moving them onto the stream timeline
+/// `result` was decoded from audio starting at stream frame `offset_frame`
+fn tokens_in_frames(result: TimestampedResult, frame_secs: f32, offset_frame: usize, frames: std::ops::Range<usize>) -> TimestampedResult {
+    let offset_secs = offset_frame as f32 * frame_secs;
+    let mut tokens = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut confidences = Vec::new();
+    for ((token, t), confidence) in result.tokens.into_iter().zip(result.timestamps).zip(result.confidences) {
+        let frame = offset_frame + (t / frame_secs).round() as usize;
+        if frames.contains(&frame) {
+            tokens.push(token);
+            timestamps.push(t + offset_secs);
+            confidences.push(confidence);
+        }
+    }
+
+    TimestampedResult { text: join_tokens(&tokens), timestamps, tokens, confidences, timed_out: result.timed_out }
+}
+
+/// Drop tokens whose confidence is below `min_confidence`, rebuilding the text
+fn retain_confident(result: TimestampedResult, min_confidence: f32) -> TimestampedResult {
+    let mut tokens = Vec::new();
+    let mut timestamps = Vec::new();
+    let mut confidences = Vec::new();
+    for ((token, t), confidence) in result.tokens.into_iter().zip(result.timestamps).zip(result.confidences) {
+        if confidence >= min_confidence {
+            tokens.push(token);
+            timestamps.push(t);
+            confidences.push(confidence);
+        }
+    }
+
+    TimestampedResult { text: join_tokens(&tokens), timestamps, tokens, confidences, timed_out: result.timed_out }
+}
+
 // ============================================================================
 // Global State and NAPI Exports
 // ============================================================================
 
 static PARAKEET_STATE: Mutex<Option<ParakeetModel>> = Mutex::new(None);
+static PARAKEET_ENGINE_STATE: Mutex<EngineState> = Mutex::new(EngineState::Unloaded);
 
 static DOWNLOAD_PROGRESS: Mutex<DownloadProgress> = Mutex::new(DownloadProgress {
     is_downloading: false,
@@ -391,6 +863,14 @@ static DOWNLOAD_PROGRESS: Mutex<DownloadProgress> = Mutex::new(DownloadProgress
     error: None,
 });
 
+/// Bumped to cancel the running download; each download thread remembers the value it started with
+static DOWNLOAD_GENERATION: AtomicU64 = AtomicU64::new(0);
+static DOWNLOAD_THREAD: Mutex<Option<std::thread::JoinHandle<()>>> = Mutex::new(None);
+
+/// Directory set with set_parakeet_model_dir (None = app data), restored from disk
+static MODEL_DIR_OVERRIDE: Lazy<Mutex<Option<PathBuf>>> =
+    Lazy::new(|| Mutex::new(settings::load::<ModelDirConfig>(CONFIG_FILE).model_dir));
+
 #[napi(object)]
 pub struct ParakeetModelInfo {
     pub downloaded: bool,
@@ -400,7 +880,7 @@ pub struct ParakeetModelInfo {
 }
 
 #[napi(object)]
-#[derive(Clone)]
+#[derive(Clone, Default)]
 pub struct DownloadProgress {
     pub is_downloading: bool,
     pub current_file: String,
@@ -412,25 +892,168 @@ pub struct DownloadProgress {
     pub error: Option<String>,
 }
 
+const MODEL_DIR_NAME: &str = "parakeet-tdt-v3";
+const CONFIG_FILE: &str = "parakeet-config.json";
+
 fn get_model_dir() -> PathBuf {
-    let app_data = dirs::data_dir()
-        .unwrap_or_else(|| PathBuf::from("."))
-        .join("ghost")
-        .join("models")
-        .join("parakeet-tdt-v3");
-    std::fs::create_dir_all(&app_data).ok();
-    app_data
+    let base = MODEL_DIR_OVERRIDE.lock().clone().unwrap_or_else(|| {
+        dirs::data_dir()
+            .unwrap_or_else(|| PathBuf::from("."))
+            .join("ghost")
+            .join("models")
+    });
+    let model_dir = base.join(MODEL_DIR_NAME);
+    std::fs::create_dir_all(&model_dir).ok();
+    model_dir
+}
+
+/// Keep the model under `path` (in a parakeet-tdt-v3 subfolder) instead of the app data
+/// directory, e.g. on an external disk; an empty path restores the default. Persisted across
+/// restarts. Existing files aren't moved, and an already loaded model stays loaded
+#[napi]
+pub fn set_parakeet_model_dir(path: String) -> Result<()> {
+    if DOWNLOAD_PROGRESS.lock().is_downloading {
+        return Err(ModelError::InvalidInput("Can't change the model directory while a download is running".into()).into());
+    }
+    
+    settings::set_model_dir(CONFIG_FILE, MODEL_DIR_NAME, &MODEL_DIR_OVERRIDE, &path)?;
+    println!("[Parakeet] Model directory: {}", get_model_dir().display());
+    Ok(())
 }
 
+/// Directory set with set_parakeet_model_dir, or null when using the default
+#[napi]
+pub fn get_parakeet_model_dir() -> Option<String> {
+    MODEL_DIR_OVERRIDE.lock().as_ref().map(|dir| dir.to_string_lossy().to_string())
+}
+
+const DEFAULT_BASE_URL: &str = "https://huggingface.co/istupakov/parakeet-tdt-0.6b-v3-onnx/resolve/main";
+
+/// Mirror set with set_model_base_url (None = Hugging Face)
+static BASE_URL_OVERRIDE: Mutex<Option<String>> = Mutex::new(None);
+
+/// Fetch the model files from a mirror with the same file names; None restores Hugging Face
+pub(crate) fn set_download_base_url(url: Option<String>) -> ModelResult<()> {
+    if DOWNLOAD_PROGRESS.lock().is_downloading {
+        return Err(ModelError::InvalidInput("Can't change the download URL while a download is running".into()));
+    }
+    
+    *BASE_URL_OVERRIDE.lock() = url;
+    println!("[Parakeet] Download base URL: {}", download_base_url());
+    Ok(())
+}
+
+/// Base URL the model file names are appended to
+pub(crate) fn download_base_url() -> String {
+    BASE_URL_OVERRIDE.lock().clone().unwrap_or_else(|| DEFAULT_BASE_URL.to_string())
+}
+
+/// A file of the model export and the SHA-256 it must hash to
+struct ModelFile {
+    name: &'static str,
+    /// Approximate size, for download progress
+    size: u64,
+    /// Hugging Face LFS sha256; a mirror has to serve byte-identical files
+    sha256: Option<&'static str>,
+}
+
+/// Pinned hashes take precedence over anything a server reports, but none are pinned yet:
+/// DEFAULT_BASE_URL follows `main`, so there is no fixed revision to take them from. Until
+/// there is, LFS files are checked against the X-Linked-Etag Hugging Face reports for them,
+/// also when a mirror serves the bytes; non-LFS files (vocab.txt) only against the checksum
+/// recorded when they were downloaded
+const MODEL_FILES: [ModelFile; 4] = [
+    ModelFile { name: "encoder-model.int8.onnx", size: 652_000_000, sha256: None },
+    ModelFile { name: "decoder_joint-model.int8.onnx", size: 18_200_000, sha256: None },
+    ModelFile { name: "nemo128.onnx", size: 140_000, sha256: None },
+    ModelFile { name: "vocab.txt", size: 93_900, sha256: None },
+];
+
+/// A file only counts as downloaded once its checksum has been recorded,
+/// so half-finished downloads from a previous run are never treated as complete
 fn check_model_files() -> bool {
     let model_dir = get_model_dir();
-    let required = [
-        "encoder-model.int8.onnx",
-        "decoder_joint-model.int8.onnx",
-        "nemo128.onnx",
-        "vocab.txt"
-    ];
-    required.iter().all(|f| model_dir.join(f).exists())
+    MODEL_FILES.iter().all(|file| {
+        let path = model_dir.join(file.name);
+        path.exists() && (checksum_path(&path).exists() || adopt_legacy_file(&path, file))
+    })
+}
+
+/// Record the checksum of a file downloaded before checksums were kept, instead of fetching
+/// it again. It has to match the pinned hash; without one, the old size check applies
+fn adopt_legacy_file(path: &PathBuf, file: &ModelFile) -> bool {
+    let size = fs::metadata(path).map(|m| m.len()).unwrap_or(0);
+    if file.sha256.is_none() && size <= file.size / 2 {
+        return false;
+    }
+    
+    let actual = match sha256_file(path) {
+        Ok(actual) => actual,
+        Err(_) => return false,
+    };
+    if file.sha256.is_some_and(|pinned| pinned != actual) {
+        println!("[Parakeet] ⚠️ {} doesn't match its pinned checksum", file.name);
+        return false;
+    }
+    
+    println!("[Parakeet] Recorded checksum of previously downloaded {}", file.name);
+    fs::write(checksum_path(path), &actual).is_ok()
+}
+
+/// SHA-256 Hugging Face reports for an LFS file. Only the resolve endpoint's redirect carries
+/// X-Linked-Etag, so redirects are not followed; None for non-LFS files or when unreachable
+fn reported_sha256(url: &str) -> Option<String> {
+    let agent = ureq::AgentBuilder::new().redirects(0).build();
+    let response = match agent.head(url).set("User-Agent", download::USER_AGENT).call() {
+        Ok(response) => response,
+        Err(ureq::Error::Status(_, response)) => response,
+        Err(_) => return None,
+    };
+    response.header("x-linked-etag")
+        .map(|etag| etag.trim_matches('"').to_lowercase())
+        .filter(|etag| etag.len() == 64 && etag.chars().all(|c| c.is_ascii_hexdigit()))
+}
+
+/// `<file>.sha256` sidecar holding the verified hash of a downloaded file
+fn checksum_path(path: &PathBuf) -> PathBuf {
+    let mut name = path.file_name().unwrap_or_default().to_os_string();
+    name.push(".sha256");
+    path.with_file_name(name)
+}
+
+fn sha256_file(path: &PathBuf) -> std::result::Result<String, String> {
+    let mut file = fs::File::open(path).map_err(|e| format!("Failed to open {:?}: {}", path, e))?;
+    let mut hasher = Sha256::new();
+    let mut buffer = [0u8; 65536];
+    loop {
+        let n = file.read(&mut buffer).map_err(|e| format!("Failed to read {:?}: {}", path, e))?;
+        if n == 0 {
+            break;
+        }
+        hasher.update(&buffer[..n]);
+    }
+    Ok(hasher.finalize().iter().map(|b| format!("{:02x}", b)).collect())
+}
+
+/// Check a model file against its pinned checksum, or the one recorded at download
+fn verify_model_file(path: &PathBuf, file: &ModelFile) -> std::result::Result<(), String> {
+    let expected = match file.sha256 {
+        Some(pinned) => pinned.to_string(),
+        None => fs::read_to_string(checksum_path(path))
+            .map(|recorded| recorded.trim().to_string())
+            .map_err(|_| format!("{} has no recorded checksum (incomplete download)", file.name))?,
+    };
+    let actual = sha256_file(path)?;
+    if actual != expected {
+        return Err(format!("{} is corrupted (sha256 {} != {})", file.name, actual, expected));
+    }
+    Ok(())
+}
+
+/// Delete a model file and its checksum so the next download fetches it again
+fn remove_model_file(path: &PathBuf) {
+    let _ = fs::remove_file(path);
+    let _ = fs::remove_file(checksum_path(path));
 }
 
 #[napi]
@@ -484,112 +1107,95 @@ pub fn get_parakeet_download_progress() -> DownloadProgress {
     DOWNLOAD_PROGRESS.lock().clone()
 }
 
-fn download_file_with_progress(
-    url: &str, 
-    dest: &PathBuf, 
-    file_index: usize,
-    total_files: usize,
-    expected_size: u64,
-    total_expected: u64,
-    bytes_so_far: &mut u64,
-) -> std::result::Result<(), String> {
-    let filename = dest.file_name().unwrap_or_default().to_string_lossy().to_string();
-    
-    {
-        let mut progress = DOWNLOAD_PROGRESS.lock();
-        progress.current_file = filename.clone();
-        progress.current_file_index = file_index as u32;
-        progress.total_files = total_files as u32;
-    }
-    
-    println!("[Parakeet] Downloading {} -> {:?}", url, dest);
-    
-    let response = ureq::get(url)
-        .set("User-Agent", "Mozilla/5.0 ghost-app/1.0")
-        .call()
-        .map_err(|e| format!("HTTP request failed: {:?}", e))?;
-    
-    if response.status() != 200 {
-        return Err(format!("HTTP {}: {}", response.status(), response.status_text()));
-    }
-    
-    let mut file = std::fs::File::create(dest)
-        .map_err(|e| format!("Failed to create file: {:?}", e))?;
-    
-    let mut reader = response.into_reader();
-    let mut buffer = [0u8; 65536];
-    let mut file_downloaded: u64 = 0;
-    
-    loop {
-        let bytes_read = reader.read(&mut buffer)
-            .map_err(|e| format!("Failed to read: {:?}", e))?;
-        
-        if bytes_read == 0 {
-            break;
-        }
-        
-        file.write_all(&buffer[..bytes_read])
-            .map_err(|e| format!("Failed to write: {:?}", e))?;
-        
-        file_downloaded += bytes_read as u64;
-        *bytes_so_far += bytes_read as u64;
-        
-        if file_downloaded % (1024 * 1024) < 65536 {
-            let mut progress = DOWNLOAD_PROGRESS.lock();
-            progress.bytes_downloaded = *bytes_so_far as i64;
-            progress.total_bytes = total_expected as i64;
-            progress.percent = ((*bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
-        }
-    }
-    
-    {
-        let mut progress = DOWNLOAD_PROGRESS.lock();
-        progress.bytes_downloaded = *bytes_so_far as i64;
-        progress.percent = ((*bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
-    }
-    
-    println!("[Parakeet] ✓ Downloaded {} ({} bytes)", filename, file_downloaded);
-    Ok(())
+fn is_download_cancelled(generation: u64) -> bool {
+    DOWNLOAD_GENERATION.load(Ordering::SeqCst) != generation
 }
 
-fn do_download() {
+fn do_download(generation: u64) {
     println!("[Parakeet] Starting model download...");
     
     let model_dir = get_model_dir();
-    let base_url = "https://huggingface.co/istupakov/parakeet-tdt-0.6b-v3-onnx/resolve/main";
+    let base_url = download_base_url();
     
-    let files: Vec<(&str, String, u64)> = vec![
-        ("encoder-model.int8.onnx", format!("{}/encoder-model.int8.onnx", base_url), 652_000_000),
-        ("decoder_joint-model.int8.onnx", format!("{}/decoder_joint-model.int8.onnx", base_url), 18_200_000),
-        ("nemo128.onnx", format!("{}/nemo128.onnx", base_url), 140_000),
-        ("vocab.txt", format!("{}/vocab.txt", base_url), 93_900),
-    ];
-    
-    let total_expected: u64 = files.iter().map(|(_, _, s)| s).sum();
-    let total_files = files.len();
+    let total_expected: u64 = MODEL_FILES.iter().map(|file| file.size).sum();
+    let total_files = MODEL_FILES.len();
     let mut bytes_so_far: u64 = 0;
     
-    for (index, (filename, url, expected_size)) in files.iter().enumerate() {
+    for (index, file) in MODEL_FILES.iter().enumerate() {
+        if is_download_cancelled(generation) {
+            return;
+        }
+        
+        let filename = file.name;
+        let url = format!("{}/{}", base_url, filename);
         let dest = model_dir.join(filename);
         
-        if dest.exists() {
-            let size = std::fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
-            if size > (*expected_size / 2) {
-                println!("[Parakeet] {} already exists, skipping", filename);
-                bytes_so_far += size;
+        if dest.exists() && (checksum_path(&dest).exists() || adopt_legacy_file(&dest, file)) {
+            match verify_model_file(&dest, file) {
+                Ok(()) => {
+                    println!("[Parakeet] {} already downloaded and verified, skipping", filename);
+                    bytes_so_far += std::fs::metadata(&dest).map(|m| m.len()).unwrap_or(0);
+                    let mut progress = DOWNLOAD_PROGRESS.lock();
+                    progress.bytes_downloaded = bytes_so_far as i64;
+                    progress.percent = ((bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
+                    continue;
+                }
+                Err(e) => {
+                    println!("[Parakeet] ⚠️ {}, re-downloading", e);
+                    remove_model_file(&dest);
+                }
+            }
+        } else if dest.exists() {
+            println!("[Parakeet] ⚠️ {} can't be verified, re-downloading", filename);
+            remove_model_file(&dest);
+        }
+        
+        // Ask Hugging Face even when downloading from a mirror, which reports no hash of its own
+        let expected_sha256 = file.sha256.map(str::to_string)
+            .or_else(|| reported_sha256(&format!("{}/{}", DEFAULT_BASE_URL, file.name)));
+        {
+            let mut progress = DOWNLOAD_PROGRESS.lock();
+            progress.current_file = filename.to_string();
+            progress.current_file_index = index as u32;
+            progress.total_files = total_files as u32;
+        }
+        
+        let bytes_before = bytes_so_far;
+        let result = download::download_with_retry(&mut download::Download {
+            url: &url,
+            dest: &dest,
+            expected_size: None,
+            log_prefix: "[Parakeet]",
+            is_cancelled: &|| is_download_cancelled(generation),
+            on_progress: &mut |file_bytes| {
+                bytes_so_far = bytes_before + file_bytes;
                 let mut progress = DOWNLOAD_PROGRESS.lock();
                 progress.bytes_downloaded = bytes_so_far as i64;
+                progress.total_bytes = total_expected as i64;
                 progress.percent = ((bytes_so_far as f64 / total_expected as f64) * 100.0).min(99.0) as u32;
-                continue;
+            },
+        }).and_then(|_| {
+            let actual = sha256_file(&dest)?;
+            match &expected_sha256 {
+                Some(expected) if actual != *expected => {
+                    return Err(format!("Checksum mismatch (expected {}, got {})", expected, actual));
+                }
+                Some(_) => {}
+                None => println!("[Parakeet] ⚠️ No checksum known for {}, recording {}", filename, actual),
             }
-        }
+            fs::write(checksum_path(&dest), &actual).map_err(|e| format!("Failed to record checksum: {}", e))
+        });
         
-        if let Err(e) = download_file_with_progress(
-            &url, &dest, index, total_files, *expected_size, total_expected, &mut bytes_so_far
-        ) {
+        if let Err(e) = result {
+            remove_model_file(&dest);
+            if is_download_cancelled(generation) {
+                // cancel_parakeet_download already reset the progress
+                println!("[Parakeet] Download of {} cancelled", filename);
+                return;
+            }
             let mut progress = DOWNLOAD_PROGRESS.lock();
             progress.is_downloading = false;
-            progress.error = Some(format!("Failed to download {}: {}", filename, e));
+            progress.error = Some(format!("Failed to download {}: {}. Please retry the download (it will resume where it stopped).", filename, e));
             return;
         }
     }
@@ -627,41 +1233,217 @@ pub fn download_parakeet_model() -> bool {
         };
     }
     
-    std::thread::spawn(|| { do_download(); });
+    let generation = DOWNLOAD_GENERATION.fetch_add(1, Ordering::SeqCst) + 1;
+    let mut thread = DOWNLOAD_THREAD.lock();
+    let previous = thread.take();
+    *thread = Some(std::thread::spawn(move || {
+        // A cancelled download may still be cleaning up its .part file
+        if let Some(previous) = previous {
+            let _ = previous.join();
+        }
+        do_download(generation);
+    }));
+    true
+}
+
+/// Stop the running model download, deleting the partial file and resetting progress.
+/// Returns false if no download was in progress
+#[napi]
+pub fn cancel_parakeet_download() -> bool {
+    let mut progress = DOWNLOAD_PROGRESS.lock();
+    if !progress.is_downloading {
+        return false;
+    }
+    
+    DOWNLOAD_GENERATION.fetch_add(1, Ordering::SeqCst);
+    *progress = DownloadProgress::default();
+    println!("[Parakeet] Download cancelled");
     true
 }
 
+/// Decoder tuning for configure_parakeet; unset fields keep their current value
+#[napi(object)]
+pub struct ParakeetDecodeConfig {
+    /// Max non-blank tokens per encoder frame (1 - 50, default 10)
+    /// Lower values stop noisy audio from emitting the same token over and over
+    pub max_tokens_per_step: Option<u32>,
+    /// Encoder subsampling of the model export (1 - 16, default 8)
+    pub subsampling_factor: Option<u32>,
+    /// Preprocessor hop in seconds (0.005 - 0.05, default 0.01)
+    pub window_size_secs: Option<f64>,
+}
+
+/// Adjust decode constants for experimenting with other model exports
+/// Applies from the next init_parakeet; fields are validated together, so nothing changes on error
+#[napi]
+pub fn configure_parakeet(config: ParakeetDecodeConfig) -> Result<()> {
+    let mut decode = *DECODE_CONFIG.lock();
+    
+    if let Some(max_tokens) = config.max_tokens_per_step {
+        if !(1..=50).contains(&max_tokens) {
+            return Err(ModelError::InvalidInput(format!("max_tokens_per_step must be 1 - 50, got {}", max_tokens)).into());
+        }
+        decode.max_tokens_per_step = max_tokens as usize;
+    }
+    if let Some(factor) = config.subsampling_factor {
+        if !(1..=16).contains(&factor) {
+            return Err(ModelError::InvalidInput(format!("subsampling_factor must be 1 - 16, got {}", factor)).into());
+        }
+        decode.subsampling_factor = factor as usize;
+    }
+    if let Some(window) = config.window_size_secs {
+        if !(0.005..=0.05).contains(&window) {
+            return Err(ModelError::InvalidInput(format!("window_size_secs must be 0.005 - 0.05, got {}", window)).into());
+        }
+        decode.window_size = window as f32;
+    }
+    
+    *DECODE_CONFIG.lock() = decode;
+    println!("[Parakeet] Decode config: {:?}", decode);
+    if PARAKEET_STATE.lock().is_some() {
+        println!("[Parakeet] ⚠️ Model already loaded, call init_parakeet again to apply");
+    }
+    Ok(())
+}
+
+/// Options for loading the Parakeet model
+#[napi(object)]
+pub struct ParakeetInitOptions {
+    /// Max seconds of audio per encoder call; bounds latency for streaming (min 1s)
+    pub max_encoder_window_secs: Option<f64>,
+    /// Run the encoder on the CoreML execution provider (Apple Silicon), falling back to CPU
+    /// Defaults to the PARAKEET_USE_COREML=1 environment flag
+    pub use_coreml: Option<bool>,
+    /// ORT intra/inter-op threads per session (default: half the CPU cores, at least 1)
+    /// More threads transcribe faster but compete with the audio capture threads,
+    /// which can cause dropouts on laptops while recording
+    pub threads: Option<u32>,
+    /// Run warmup_parakeet right after loading, so the first real transcription doesn't stall
+    pub warmup: Option<bool>,
+    /// Stop decoding after this many ms and return what was transcribed so far, with
+    /// `timed_out` set (unset or 0 = no limit). transcribe_wav_file always runs to the end
+    pub max_duration_ms: Option<u32>,
+}
+
+fn default_thread_count() -> usize {
+    std::thread::available_parallelism()
+        .map(|n| n.get() / 2)
+        .unwrap_or(1)
+        .max(1)
+}
+
+fn coreml_env_flag() -> bool {
+    std::env::var("PARAKEET_USE_COREML")
+        .map(|v| v == "1" || v.eq_ignore_ascii_case("true"))
+        .unwrap_or(false)
+}
+
 #[napi]
-pub fn init_parakeet() -> Result<bool> {
+pub fn init_parakeet(options: Option<ParakeetInitOptions>) -> Result<bool> {
     println!("[Parakeet] Initializing model...");
     
     let model_dir = get_model_dir();
     
     if !check_model_files() {
-        return Err(Error::from_reason("Model not downloaded"));
+        *PARAKEET_ENGINE_STATE.lock() = EngineState::Error(ModelError::NotDownloaded.to_string());
+        return Err(ModelError::NotDownloaded.into());
     }
     
     println!("[Parakeet] Loading from: {:?}", model_dir);
+    *PARAKEET_ENGINE_STATE.lock() = EngineState::Loading;
+    
+    // Catch truncated/corrupted files before ORT fails with a cryptic error
+    for file in &MODEL_FILES {
+        let path = model_dir.join(file.name);
+        if let Err(e) = verify_model_file(&path, file) {
+            remove_model_file(&path);
+            let error = ModelError::CorruptModel(format!("{}. The file was deleted, please download the model again.", e));
+            println!("[Parakeet] ❌ {}", error);
+            *PARAKEET_ENGINE_STATE.lock() = EngineState::Error(error.to_string());
+            return Err(error.into());
+        }
+    }
     
-    match ParakeetModel::new(&model_dir, true) {
+    let max_encoder_window_secs = options.as_ref()
+        .and_then(|o| o.max_encoder_window_secs)
+        .map(|secs| secs as f32);
+    let use_coreml = options.as_ref()
+        .and_then(|o| o.use_coreml)
+        .unwrap_or_else(coreml_env_flag);
+    let threads = options.as_ref()
+        .and_then(|o| o.threads)
+        .map(|t| (t as usize).max(1))
+        .unwrap_or_else(default_thread_count);
+    let warmup = options.as_ref()
+        .and_then(|o| o.warmup)
+        .unwrap_or(false);
+    let max_duration_ms = options.as_ref()
+        .and_then(|o| o.max_duration_ms)
+        .filter(|&ms| ms > 0);
+    
+    match ParakeetModel::new(&model_dir, true, max_encoder_window_secs, max_duration_ms, use_coreml, threads) {
         Ok(model) => {
-            let mut state = PARAKEET_STATE.lock();
-            *state = Some(model);
+            *PARAKEET_STATE.lock() = Some(model);
+            *PARAKEET_ENGINE_STATE.lock() = EngineState::Ready;
             println!("[Parakeet] ✅ Model initialized successfully");
+            
+            // A failed warmup isn't fatal, the first transcription will just be slow
+            if warmup {
+                if let Err(e) = warmup_parakeet() {
+                    println!("[Parakeet] ⚠️ Warmup failed: {}", e);
+                }
+            }
             Ok(true)
         }
         Err(e) => {
-            println!("[Parakeet] ❌ Init failed: {:?}", e);
-            Err(Error::from_reason(format!("Init failed: {:?}", e)))
+            println!("[Parakeet] ❌ Init failed: {}", e);
+            *PARAKEET_ENGINE_STATE.lock() = EngineState::Error(e.to_string());
+            Err(e.into())
         }
     }
 }
 
+/// Seconds of silence pushed through the model by warmup_parakeet
+const WARMUP_SECS: f32 = 0.5;
+
+/// Run a short silent clip through preprocess → encode → decode so ORT allocates and
+/// optimizes its graphs now rather than on the first caption
+/// Returns the warmup time in ms, or null if the model isn't loaded
+#[napi]
+pub fn warmup_parakeet() -> Result<Option<i64>> {
+    let mut state = PARAKEET_STATE.lock();
+    let Some(model) = state.as_mut() else {
+        return Ok(None);
+    };
+    
+    let started = std::time::Instant::now();
+    let silence = vec![0.0f32; (WARMUP_SECS * 16000.0) as usize];
+    model.transcribe_samples_with_language(silence, None)?;
+    let elapsed_ms = started.elapsed().as_millis() as i64;
+    
+    println!("[Parakeet] ✅ Warmup took {}ms", elapsed_ms);
+    Ok(Some(elapsed_ms))
+}
+
 #[napi]
 pub fn is_parakeet_ready() -> bool {
     PARAKEET_STATE.lock().is_some()
 }
 
+/// Detailed Parakeet state, distinguishing "never loaded" from "failed to load"
+#[napi]
+pub fn get_parakeet_state() -> EngineStatus {
+    PARAKEET_ENGINE_STATE.lock().to_status()
+}
+
+/// Estimated resident memory of the loaded Parakeet sessions (0 when not loaded)
+pub(crate) fn loaded_memory_bytes() -> u64 {
+    PARAKEET_STATE.lock()
+        .as_ref()
+        .map(|model| model.weights_bytes)
+        .unwrap_or(0)
+}
+
 /// A segment of transcribed text with its timestamp
 #[napi(object)]
 #[derive(Clone)]
@@ -669,6 +1451,8 @@ pub struct TranscriptSegment {
     pub text: String,
     pub start_time: f64,  // Seconds from start of audio chunk
     pub end_time: f64,    // Seconds from start of audio chunk
+    pub speaker: Option<String>,  // Set by apply_diarization
+    pub confidence: Option<f64>,  // Mean token confidence (0.0 - 1.0)
 }
 
 /// Result containing segments with timestamps
@@ -677,66 +1461,788 @@ pub struct TranscriptSegment {
 pub struct TranscriptWithTimestamps {
     pub segments: Vec<TranscriptSegment>,
     pub full_text: String,
+    /// Language hint the transcript was produced with (None = auto)
+    pub language: Option<String>,
+    /// Decoder output before normalization (only set when normalize_text was requested)
+    pub raw_text: Option<String>,
+    /// True when max_duration_ms stopped decoding early; the transcript covers only the audio before that
+    pub timed_out: Option<bool>,
 }
 
+/// `channels` (default 1) is the number of interleaved channels, mixed to mono. It used to be
+/// ignored and every buffer read as mono, so callers passing it for mono audio must drop it.
+/// Fails with TIMEOUT if max_duration_ms cut decoding short; the timestamped variants
+/// return the partial transcript instead
 #[napi]
-pub fn transcribe_audio_buffer(audio_data: Buffer, sample_rate: Option<u32>, _channels: Option<u32>) -> Result<String> {
-    let result = transcribe_audio_buffer_with_timestamps(audio_data, sample_rate, _channels)?;
+pub fn transcribe_audio_buffer(audio_data: Buffer, sample_rate: Option<u32>, channels: Option<u32>) -> Result<String> {
+    let options = TranscribeOptions { sample_rate, channels, ..Default::default() };
+    let result = transcribe_audio_buffer_with_timestamps(audio_data, Some(options))?;
+    if result.timed_out == Some(true) {
+        return Err(ModelError::Timeout.into());
+    }
     Ok(result.full_text)
 }
 
-/// Transcribe audio and return segments with timestamps
+/// Options for transcribe_audio_buffer_with_timestamps
+#[napi(object)]
+#[derive(Default)]
+pub struct TranscribeOptions {
+    /// Rate of the PCM16 input (default 16000)
+    pub sample_rate: Option<u32>,
+    /// Interleaved channels of the input (default 1), mixed to mono, e.g. from flush_retained_chunks
+    pub channels: Option<u32>,
+    /// Tokens with confidence below this (0.0 - 1.0) are dropped
+    pub min_confidence: Option<f64>,
+    /// Segments break every this many seconds (default 2.5)
+    pub segment_interval_secs: Option<f64>,
+    /// Also break segments after tokens ending in . ? or ! (default true)
+    pub split_on_sentence: Option<bool>,
+    /// A code from get_parakeet_languages; "auto" (or unset) lets the model detect it
+    pub language: Option<String>,
+    /// Tidy punctuation spacing and sentence casing; the decoder output is kept in `rawText`
+    pub normalize_text: Option<bool>,
+}
+
+/// Transcribe PCM16 audio and return segments with timestamps
 #[napi]
-pub fn transcribe_audio_buffer_with_timestamps(audio_data: Buffer, sample_rate: Option<u32>, _channels: Option<u32>) -> Result<TranscriptWithTimestamps> {
-    let mut state = PARAKEET_STATE.lock();
+pub fn transcribe_audio_buffer_with_timestamps(audio_data: Buffer, options: Option<TranscribeOptions>) -> Result<TranscriptWithTimestamps> {
+    let options = options.unwrap_or_default();
+    let segment_options = SegmentOptions {
+        min_confidence: options.min_confidence,
+        segment_interval_secs: options.segment_interval_secs,
+        split_on_sentence: options.split_on_sentence,
+        language: validate_language(options.language)?,
+        normalize_text: options.normalize_text.unwrap_or(false),
+    };
+    let channels = options.channels.unwrap_or(1).max(1) as usize;
+    transcribe_pcm16(audio_data.as_ref(), options.sample_rate.unwrap_or(16000), channels, &segment_options)
+}
+
+/// Same as transcribe_audio_buffer_with_timestamps, but inference runs on a blocking
+/// worker thread so the JS event loop stays responsive; resolves with the transcript
+#[napi]
+pub async fn transcribe_audio_buffer_async(audio_data: Buffer, sample_rate: Option<u32>) -> Result<TranscriptWithTimestamps> {
+    let audio_bytes = audio_data.to_vec();
+    drop(audio_data);
     
-    let model = state.as_mut()
-        .ok_or_else(|| Error::from_reason("Parakeet not initialized"))?;
+    tokio::task::spawn_blocking(move || {
+        transcribe_pcm16(&audio_bytes, sample_rate.unwrap_or(16000), 1, &SegmentOptions::default())
+    })
+    .await
+    .map_err(|e| Error::from(ModelError::InferenceFailed(format!("Transcription task failed: {}", e))))?
+}
+
+/// A word with the time span of its SentencePiece pieces
+#[napi(object)]
+#[derive(Clone)]
+pub struct WordTiming {
+    pub word: String,
+    /// Seconds from the start of the buffer: first piece's timestamp
+    pub start_time: f64,
+    /// Last piece's timestamp plus one encoder frame
+    pub end_time: f64,
+    /// Mean confidence of the word's pieces (0.0 - 1.0)
+    pub confidence: Option<f64>,
+}
+
+/// Transcribe mono PCM16 and return per-word timings (for click-to-seek)
+/// Fails with TIMEOUT if max_duration_ms cut decoding short
+#[napi]
+pub fn transcribe_audio_buffer_words(audio_data: Buffer, sample_rate: Option<u32>) -> Result<Vec<WordTiming>> {
+    let samples_16k = pcm16_to_16k_mono(audio_data.as_ref(), sample_rate.unwrap_or(16000), 1)?;
     
-    let audio_bytes = audio_data.as_ref();
-    let source_rate = sample_rate.unwrap_or(16000);
+    let (result, frame_secs) = {
+        let mut state = PARAKEET_STATE.lock();
+        let model = state.as_mut()
+            .ok_or(ModelError::NotInitialized("Parakeet"))?;
+        (model.transcribe_samples_with_timestamps(samples_16k)?, model.decode.frame_secs())
+    };
+    
+    if result.timed_out {
+        return Err(ModelError::Timeout.into());
+    }
+    
+    let words = words_from_tokens(&result, frame_secs);
+    println!("[Parakeet] ✅ {} words", words.len());
+    Ok(words)
+}
+
+/// Merge pieces into words: a piece starting with a space (the mapped ▁) opens a new word,
+/// anything else (subwords, punctuation) continues the current one. A bare "▁" piece opens
+/// a word whose text comes entirely from the pieces after it
+fn words_from_tokens(result: &TimestampedResult, frame_secs: f32) -> Vec<WordTiming> {
+    let mut words = Vec::new();
+    let mut text = String::new();
+    let mut start = 0.0f32;
+    let mut end = 0.0f32;
+    let mut confidences: Vec<f32> = Vec::new();
+    
+    let mut flush = |text: &mut String, start: f32, end: f32, confidences: &mut Vec<f32>| {
+        let word = text.trim();
+        if !word.is_empty() {
+            words.push(WordTiming {
+                word: word.to_string(),
+                start_time: start as f64,
+                end_time: (end + frame_secs) as f64,
+                confidence: mean_confidence(confidences),
+            });
+        }
+        text.clear();
+        confidences.clear();
+    };
+    
+    for (i, (token, &timestamp)) in result.tokens.iter().zip(&result.timestamps).enumerate() {
+        if token.starts_with(' ') || text.trim().is_empty() {
+            if !text.trim().is_empty() {
+                flush(&mut text, start, end, &mut confidences);
+            }
+            if text.is_empty() {
+                start = timestamp;
+            }
+        }
+        text.push_str(token);
+        end = timestamp;
+        confidences.extend(result.confidences.get(i).copied());
+    }
+    flush(&mut text, start, end, &mut confidences);
+    
+    words
+}
+
+/// Optional knobs for decoding and grouping tokens into segments
+#[derive(Default)]
+struct SegmentOptions {
+    min_confidence: Option<f64>,
+    segment_interval_secs: Option<f64>,
+    split_on_sentence: Option<bool>,
+    /// Validated language code (None = auto)
+    language: Option<String>,
+    /// Run normalize_transcript_text over the full text and segments
+    normalize_text: bool,
+}
+
+/// Normalize a language hint: None/"auto" mean auto-detect, anything else must be supported
+fn validate_language(language: Option<String>) -> ModelResult<Option<String>> {
+    let Some(language) = language.map(|l| l.trim().to_lowercase()) else {
+        return Ok(None);
+    };
+    if language == "auto" {
+        return Ok(None);
+    }
     
-    println!("[Parakeet] Processing {} bytes at {}Hz", audio_bytes.len(), source_rate);
+    let supported = get_parakeet_languages();
+    if !supported.contains(&language) {
+        return Err(ModelError::InvalidInput(format!(
+            "Unsupported language '{}', expected \"auto\" or one of: {}",
+            language,
+            supported.join(", ")
+        )));
+    }
+    Ok(Some(language))
+}
+
+/// Punctuation the tokenizer may leave a space in front of, which belongs on the previous word.
+/// French typography keeps a space before ; : ! and ?
+fn attaches_to_previous(c: char, language: Option<&str>) -> bool {
+    match c {
+        ',' | '.' | '…' | ')' | ']' | '%' => true,
+        ';' | ':' | '!' | '?' => language != Some("fr"),
+        _ => false,
+    }
+}
+
+/// Whether `text` finishes a sentence (closing quotes/brackets after the mark are ignored).
+/// Greek uses ; as its question mark
+fn ends_sentence(text: &str, language: Option<&str>) -> bool {
+    let last = text.trim_end().trim_end_matches(['"', '\'', '»', '”', '’', ')', ']']).chars().last();
+    match last {
+        Some('.' | '?' | '!' | '…') => true,
+        Some(';') => language == Some("el"),
+        _ => false,
+    }
+}
+
+/// Words common in French and rare in the other supported languages
+const FRENCH_MARKERS: &[&str] = &[
+    "les", "des", "est", "je", "vous", "nous", "pas", "c'est", "ça", "avec",
+    "dans", "très", "mais", "oui", "cette", "sont", "j'ai", "aussi", "être",
+];
+
+/// Guess the language of decoded text, for the languages whose punctuation rules differ
+/// (French, Greek); None means the default rules apply
+fn detect_language(text: &str) -> Option<&'static str> {
+    let letters = text.chars().filter(|c| c.is_alphabetic()).count();
+    let greek = text.chars()
+        .filter(|c| matches!(*c, '\u{0370}'..='\u{03FF}' | '\u{1F00}'..='\u{1FFF}'))
+        .count();
+    if letters > 0 && greek * 2 > letters {
+        return Some("el");
+    }
     
-    // Convert bytes to f32 samples
+    let words: Vec<String> = text.split_whitespace()
+        .map(|w| w.trim_matches(|c: char| !c.is_alphanumeric() && c != '\'').to_lowercase())
+        .collect();
+    let french = words.iter().filter(|w| FRENCH_MARKERS.contains(&w.as_str())).count();
+    if french >= 2 && french * 10 >= words.len() {
+        return Some("fr");
+    }
+    None
+}
+
+/// Collapse repeated whitespace, glue punctuation onto the preceding word and capitalize
+/// sentence starts. Characters without case (digits, uncased scripts) are left as-is, and a
+/// sentence starting with a digit isn't capitalized further in
+fn normalize_transcript_text(text: &str, language: Option<&str>, capitalize_start: bool) -> String {
+    let mut out = String::with_capacity(text.len());
+    let mut capitalize_next = capitalize_start;
+    
+    for word in text.split_whitespace() {
+        let glued = word.chars().next().is_some_and(|c| attaches_to_previous(c, language));
+        if !out.is_empty() && !glued {
+            out.push(' ');
+        }
+        
+        for c in word.chars() {
+            // Skip leading punctuation such as ¿ or an opening quote
+            if capitalize_next && c.is_alphanumeric() {
+                capitalize_next = false;
+                if c.is_alphabetic() {
+                    out.extend(c.to_uppercase());
+                    continue;
+                }
+            }
+            out.push(c);
+        }
+        
+        // Only a mark at the end of a word ends a sentence, so 3.5 or example.com stay intact
+        if ends_sentence(word, language) {
+            capitalize_next = true;
+        }
+    }
+    
+    out
+}
+
+/// Normalize the full text and every segment, carrying sentence state across segment
+/// boundaries; returns the raw decoder text. Without a language hint the rules follow
+/// the language detected in the text
+fn normalize_transcript(transcript: &mut TranscriptWithTimestamps) {
+    let language = transcript.language.clone();
+    let language = language.as_deref().or_else(|| detect_language(&transcript.full_text));
+    
+    let raw = std::mem::take(&mut transcript.full_text);
+    transcript.full_text = normalize_transcript_text(&raw, language, true);
+    transcript.raw_text = Some(raw);
+    
+    let mut capitalize = true;
+    for segment in &mut transcript.segments {
+        segment.text = normalize_transcript_text(&segment.text, language, capitalize);
+        capitalize = ends_sentence(&segment.text, language);
+    }
+}
+
+/// Interleaved PCM16 at any rate → mono 16kHz samples, rejecting buffers over MAX_BUFFER_SECS
+fn pcm16_to_16k_mono(audio_bytes: &[u8], source_rate: u32, channels: usize) -> Result<Vec<f32>> {
+    println!("[Parakeet] Processing {} bytes at {}Hz ({}ch)", audio_bytes.len(), source_rate, channels);
+    
+    let frames = audio_bytes.len() / (2 * channels);
+    if frames > MAX_BUFFER_SECS * source_rate.max(1) as usize {
+        return Err(ModelError::InvalidInput(format!(
+            "Audio buffer is {:.0}s long, the limit is {}s; use transcribe_wav_file for long recordings",
+            frames as f64 / source_rate.max(1) as f64,
+            MAX_BUFFER_SECS
+        )).into());
+    }
+    
+    // Convert bytes to f32 samples, averaging each frame down to mono
     let samples: Vec<f32> = audio_bytes
-        .chunks_exact(2)
-        .map(|chunk| {
-            let sample = i16::from_le_bytes([chunk[0], chunk[1]]);
-            sample as f32 / 32768.0
+        .chunks_exact(2 * channels)
+        .map(|frame| {
+            let sum: f32 = frame
+                .chunks_exact(2)
+                .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+                .sum();
+            sum / channels as f32
         })
         .collect();
     
     // Resample to 16kHz if needed
-    let samples_16k = if source_rate != 16000 {
+    Ok(if source_rate != 16000 {
         resample_audio(&samples, source_rate, 16000)
     } else {
         samples
-    };
+    })
+}
+
+/// Transcribe interleaved 16-bit PCM, holding the model lock only for inference
+fn transcribe_pcm16(audio_bytes: &[u8], source_rate: u32, channels: usize, options: &SegmentOptions) -> Result<TranscriptWithTimestamps> {
+    let samples_16k = pcm16_to_16k_mono(audio_bytes, source_rate, channels)?;
     
     println!("[Parakeet] Transcribing {} samples at 16kHz", samples_16k.len());
     
-    match model.transcribe_samples_with_timestamps(samples_16k) {
+    let result = {
+        let mut state = PARAKEET_STATE.lock();
+        let model = state.as_mut()
+            .ok_or(ModelError::NotInitialized("Parakeet"))?;
+        model.transcribe_samples_with_language(samples_16k, options.language.as_deref())
+    };
+    
+    match result {
         Ok(result) => {
+            let result = match options.min_confidence {
+                Some(min) => retain_confident(result, min as f32),
+                None => result,
+            };
+            
             // Group tokens into segments (every ~2-3 seconds or by sentence)
-            let segments = create_segments(&result);
+            let builder = SegmentBuilder::with_options(
+                options.segment_interval_secs.map(|secs| secs as f32).unwrap_or(SegmentBuilder::SEGMENT_INTERVAL),
+                options.split_on_sentence.unwrap_or(true),
+            );
+            let segments = create_segments_with(&result, builder);
             
             println!("[Parakeet] ✅ Result: {} chars, {} segments", result.text.len(), segments.len());
             
-            Ok(TranscriptWithTimestamps {
+            let mut transcript = TranscriptWithTimestamps {
                 segments,
                 full_text: result.text,
+                language: options.language.clone(),
+                raw_text: None,
+                timed_out: Some(result.timed_out),
+            };
+            if options.normalize_text {
+                normalize_transcript(&mut transcript);
+            }
+            Ok(transcript)
+        }
+        Err(e) => {
+            println!("[Parakeet] ❌ Transcription failed: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Transcribe a WAV file (16-bit PCM or 32-bit float, any rate, channels mixed to mono)
+#[napi]
+pub fn transcribe_wav_file(path: String) -> Result<TranscriptWithTimestamps> {
+    let wav = WavAudio::read(&path).map_err(|e| match e {
+        AudioError::InvalidFormat(detail) => ModelError::UnsupportedAudioFormat(detail),
+        other => ModelError::Io(other.to_string()),
+    })?;
+    
+    let channels = wav.channels as usize;
+    let mono: Vec<f32> = wav.samples
+        .chunks_exact(channels)
+        .map(|frame| frame.iter().map(|&s| s as f32 / 32768.0).sum::<f32>() / channels as f32)
+        .collect();
+    
+    println!("[Parakeet] Transcribing {}: {} frames at {}Hz, {} channels", path, mono.len(), wav.sample_rate, channels);
+    
+    let samples_16k = if wav.sample_rate != 16000 {
+        resample_audio(&mono, wav.sample_rate, 16000)
+    } else {
+        mono
+    };
+    
+    let mut state = PARAKEET_STATE.lock();
+    let model = state.as_mut()
+        .ok_or(ModelError::NotInitialized("Parakeet"))?;
+    
+    match model.transcribe_samples_without_deadline(samples_16k) {
+        Ok(result) => Ok(TranscriptWithTimestamps {
+            segments: create_segments(&result),
+            full_text: result.text,
+            language: None,
+            raw_text: None,
+            timed_out: Some(false),
+        }),
+        Err(e) => {
+            println!("[Parakeet] ❌ Transcription failed: {}", e);
+            Err(e.into())
+        }
+    }
+}
+
+/// Transcribe a long buffer on a background thread, calling back with each segment as it completes
+/// Called as callback(json) with a JSON TranscriptSegment, then "[DONE]" (or "[ERROR] message")
+/// Audio is decoded about one segment interval at a time and the model is only locked per
+/// step, so other transcriptions aren't shut out for the length of the buffer
+#[napi]
+pub fn transcribe_audio_buffer_streaming(audio_data: Buffer, sample_rate: Option<u32>, callback: JsFunction) -> Result<()> {
+    if PARAKEET_STATE.lock().is_none() {
+        return Err(ModelError::NotInitialized("Parakeet").into());
+    }
+    
+    let source_rate = sample_rate.unwrap_or(16000);
+    let samples: Vec<f32> = audio_data.as_ref()
+        .chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+        .collect();
+    
+    let tsfn: ThreadsafeFunction<String, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx| {
+            Ok(vec![ctx.value])
+        })?;
+    
+    std::thread::spawn(move || {
+        let samples_16k = if source_rate != 16000 {
+            resample_audio(&samples, source_rate, 16000)
+        } else {
+            samples
+        };
+        
+        let emit = |segment: &TranscriptSegment| {
+            let json = serde_json::json!({
+                "text": segment.text,
+                "startTime": segment.start_time,
+                "endTime": segment.end_time,
+                "confidence": segment.confidence,
+            });
+            tsfn.call(json.to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+        };
+        
+        let result = (|| -> ModelResult<usize> {
+            let mut stream = PARAKEET_STATE.lock().as_ref()
+                .ok_or(ModelError::NotInitialized("Parakeet"))?
+                .create_stream()?;
+            let mut builder = SegmentBuilder::new();
+            let mut emitted = 0;
+            
+            let step_samples = (SegmentBuilder::SEGMENT_INTERVAL * SAMPLE_RATE as f32) as usize;
+            let chunks: Vec<&[f32]> = samples_16k.chunks(step_samples).collect();
+            for (i, chunk) in chunks.iter().enumerate() {
+                let finalize = i == chunks.len() - 1;
+                let partial = {
+                    let mut state = PARAKEET_STATE.lock();
+                    let model = state.as_mut().ok_or(ModelError::NotInitialized("Parakeet"))?;
+                    model.decode_stream(&mut stream, chunk, finalize)?
+                };
+                for (j, (token, &timestamp)) in partial.tokens.iter().zip(partial.timestamps.iter()).enumerate() {
+                    for segment in builder.push(token, timestamp, partial.confidences.get(j).copied()) {
+                        emit(&segment);
+                        emitted += 1;
+                    }
+                }
+            }
+            
+            if let Some(segment) = builder.finish() {
+                emit(&segment);
+                emitted += 1;
+            }
+            Ok(emitted)
+        })();
+        
+        match result {
+            Ok(count) => {
+                println!("[Parakeet] ✅ Streamed {} segments", count);
+                tsfn.call("[DONE]".to_string(), ThreadsafeFunctionCallMode::NonBlocking);
+            }
+            Err(e) => {
+                println!("[Parakeet] ❌ Streaming transcription failed: {}", e);
+                tsfn.call(format!("[ERROR] {}", e.reason()), ThreadsafeFunctionCallMode::NonBlocking);
+            }
+        }
+        
+        // Never drop the tsfn - releasing it crashes under Electron (see llm_chat_stream)
+        std::mem::forget(tsfn);
+    });
+    
+    Ok(())
+}
+
+/// Per-speaker transcripts of a stereo capture (L=system "them", R=mic "me")
+#[napi(object)]
+pub struct StereoTranscript {
+    pub me: TranscriptWithTimestamps,
+    pub them: TranscriptWithTimestamps,
+    /// Segments from both tracks sorted by start_time, with speaker "me"/"them"
+    pub merged: Vec<TranscriptSegment>,
+    /// Time spent transcribing each channel, in milliseconds
+    pub me_ms: i64,
+    pub them_ms: i64,
+}
+
+/// Transcribe interleaved stereo 16-bit PCM (L=system, R=mic) one channel at a time
+/// The passes run sequentially so CPU use matches a single mono transcription
+#[napi]
+pub fn transcribe_stereo_buffer(audio_data: Buffer, sample_rate: Option<u32>) -> Result<StereoTranscript> {
+    let mut state = PARAKEET_STATE.lock();
+    
+    let model = state.as_mut()
+        .ok_or(ModelError::NotInitialized("Parakeet"))?;
+    
+    let source_rate = sample_rate.unwrap_or(16000);
+    let mut system = Vec::new();
+    let mut mic = Vec::new();
+    for frame in audio_data.as_ref().chunks_exact(4) {
+        system.push(i16::from_le_bytes([frame[0], frame[1]]) as f32 / 32768.0);
+        mic.push(i16::from_le_bytes([frame[2], frame[3]]) as f32 / 32768.0);
+    }
+    
+    println!("[Parakeet] Transcribing stereo: {} frames at {}Hz", system.len(), source_rate);
+    
+    let mut transcribe_channel = |samples: Vec<f32>, speaker: &str| -> Result<(TranscriptWithTimestamps, i64)> {
+        let started = std::time::Instant::now();
+        let samples_16k = if source_rate != 16000 {
+            resample_audio(&samples, source_rate, 16000)
+        } else {
+            samples
+        };
+        
+        let result = model.transcribe_samples_with_timestamps(samples_16k).map_err(|e| {
+            println!("[Parakeet] ❌ Transcription failed ({}): {}", speaker, e);
+            Error::from(e)
+        })?;
+        let segments = create_segments(&result).into_iter()
+            .map(|segment| TranscriptSegment { speaker: Some(speaker.to_string()), ..segment })
+            .collect();
+        
+        let transcript = TranscriptWithTimestamps {
+            segments,
+            full_text: result.text,
+            language: None,
+            raw_text: None,
+            timed_out: Some(result.timed_out),
+        };
+        Ok((transcript, started.elapsed().as_millis() as i64))
+    };
+    
+    let (them, them_ms) = transcribe_channel(system, "them")?;
+    let (me, me_ms) = transcribe_channel(mic, "me")?;
+    
+    let mut merged: Vec<TranscriptSegment> = them.segments.iter().chain(me.segments.iter()).cloned().collect();
+    merged.sort_by(|a, b| a.start_time.partial_cmp(&b.start_time).unwrap_or(std::cmp::Ordering::Equal));
+    
+    println!("[Parakeet] ✅ Stereo result: them {}ms, me {}ms, {} segments", them_ms, me_ms, merged.len());
+    
+    Ok(StereoTranscript { me, them, merged, me_ms, them_ms })
+}
+
+// ============================================================================
+// Transcription Queue
+// ============================================================================
+
+struct TranscriptionJob {
+    id: u32,
+    audio_bytes: Vec<u8>,
+    sample_rate: u32,
+}
+
+/// Outcome of a queued transcription
+#[napi(object)]
+pub struct TranscriptionJobResult {
+    pub id: u32,
+    /// "pending", "done" or "error"
+    pub status: String,
+    pub transcript: Option<TranscriptWithTimestamps>,
+    pub error: Option<String>,
+}
+
+static NEXT_JOB_ID: AtomicU32 = AtomicU32::new(1);
+
+/// Sender to the worker thread, started on first enqueue
+static QUEUE_SENDER: Mutex<Option<std::sync::mpsc::Sender<TranscriptionJob>>> = Mutex::new(None);
+
+/// Ids submitted but not finished yet
+static PENDING_JOBS: Lazy<Mutex<HashSet<u32>>> = Lazy::new(|| Mutex::new(HashSet::new()));
+
+/// Finished results waiting for poll_transcription_result (only kept while no callback is set)
+static JOB_RESULTS: Lazy<Mutex<HashMap<u32, TranscriptionJobResult>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+// JS callback fired with each TranscriptionJobResult as jobs finish
+static JOB_RESULT_CALLBACK: Mutex<Option<ThreadsafeFunction<TranscriptionJobResult, ErrorStrategy::Fatal>>> = Mutex::new(None);
+
+/// Worker: runs jobs one at a time in submission order
+fn run_transcription_queue(jobs: std::sync::mpsc::Receiver<TranscriptionJob>) {
+    for job in jobs {
+        let result = match transcribe_pcm16(&job.audio_bytes, job.sample_rate, 1, &SegmentOptions::default()) {
+            Ok(transcript) => TranscriptionJobResult {
+                id: job.id,
+                status: "done".to_string(),
+                transcript: Some(transcript),
+                error: None,
+            },
+            Err(e) => TranscriptionJobResult {
+                id: job.id,
+                status: "error".to_string(),
+                transcript: None,
+                error: Some(e.reason.clone()),
+            },
+        };
+        
+        // Hold PENDING_JOBS while publishing so a poll never sees the job in neither place
+        let mut pending = PENDING_JOBS.lock();
+        pending.remove(&job.id);
+        match JOB_RESULT_CALLBACK.lock().as_ref() {
+            Some(tsfn) => {
+                tsfn.call(result, ThreadsafeFunctionCallMode::NonBlocking);
+            }
+            None => {
+                JOB_RESULTS.lock().insert(job.id, result);
+            }
+        }
+    }
+}
+
+/// Queue a 16-bit mono PCM buffer for transcription on the background worker
+/// Jobs run in submission order; returns the job id for poll_transcription_result
+#[napi]
+pub fn enqueue_transcription(audio_data: Buffer, sample_rate: Option<u32>) -> Result<u32> {
+    if PARAKEET_STATE.lock().is_none() {
+        return Err(ModelError::NotInitialized("Parakeet").into());
+    }
+    
+    let id = NEXT_JOB_ID.fetch_add(1, Ordering::SeqCst);
+    let job = TranscriptionJob {
+        id,
+        audio_bytes: audio_data.to_vec(),
+        sample_rate: sample_rate.unwrap_or(16000),
+    };
+    
+    PENDING_JOBS.lock().insert(id);
+    
+    let mut sender = QUEUE_SENDER.lock();
+    let sender = sender.get_or_insert_with(|| {
+        let (tx, rx) = std::sync::mpsc::channel();
+        std::thread::spawn(move || run_transcription_queue(rx));
+        println!("[Parakeet] Transcription queue worker started");
+        tx
+    });
+    
+    if sender.send(job).is_err() {
+        PENDING_JOBS.lock().remove(&id);
+        return Err(Error::from_reason("Transcription queue worker has stopped"));
+    }
+    
+    Ok(id)
+}
+
+/// Result of a queued job: status "pending" while queued or running, then the finished
+/// result (returned once, then forgotten). Returns null for unknown or already-polled ids
+#[napi]
+pub fn poll_transcription_result(id: u32) -> Option<TranscriptionJobResult> {
+    let pending = PENDING_JOBS.lock();
+    if pending.contains(&id) {
+        return Some(TranscriptionJobResult {
+            id,
+            status: "pending".to_string(),
+            transcript: None,
+            error: None,
+        });
+    }
+    drop(pending);
+    
+    JOB_RESULTS.lock().remove(&id)
+}
+
+/// Number of queued or running transcription jobs
+#[napi]
+pub fn get_transcription_queue_length() -> u32 {
+    PENDING_JOBS.lock().len() as u32
+}
+
+/// Register a callback invoked with each TranscriptionJobResult as queued jobs finish
+/// While a callback is registered, results are delivered only to it (not kept for polling)
+#[napi]
+pub fn set_transcription_result_callback(callback: JsFunction) -> Result<()> {
+    let tsfn: ThreadsafeFunction<TranscriptionJobResult, ErrorStrategy::Fatal> = callback
+        .create_threadsafe_function(0, |ctx| Ok(vec![ctx.value]))?;
+    
+    crate::replace_tsfn(&JOB_RESULT_CALLBACK, tsfn);
+    
+    Ok(())
+}
+
+// ============================================================================
+// Streaming Transcription
+// ============================================================================
+
+static PARAKEET_STREAM: Mutex<Option<ParakeetStream>> = Mutex::new(None);
+
+/// What one call into the live transcription stream produced
+#[napi(object)]
+pub struct StreamTranscript {
+    /// Segments decoded from this chunk, timed from limited context (interim, for captions)
+    pub segments: Vec<TranscriptSegment>,
+    pub full_text: String,
+    /// Interim per-word timings of this chunk
+    pub words: Vec<WordTiming>,
+    /// Segments finalized by this call, at a sentence end or the end of the stream. They are
+    /// re-decoded with the audio around them, so their timings replace the interim ones
+    pub finalized_segments: Vec<TranscriptSegment>,
+    pub finalized_words: Vec<WordTiming>,
+}
+
+fn run_stream(samples: &[f32], source_rate: u32, finalize: bool) -> Result<StreamTranscript> {
+    let mut state = PARAKEET_STATE.lock();
+    let model = state.as_mut()
+        .ok_or(ModelError::NotInitialized("Parakeet"))?;
+    
+    let mut stream_slot = PARAKEET_STREAM.lock();
+    if stream_slot.is_none() {
+        *stream_slot = Some(model.create_stream()?);
+    }
+    let stream = stream_slot.as_mut().unwrap();
+    
+    let samples_16k = if source_rate as usize != SAMPLE_RATE {
+        resample_stream_to_16k(&mut stream.resampler, samples, source_rate as f64)
+    } else {
+        samples.to_vec()
+    };
+    
+    let frame_secs = model.decode.frame_secs();
+    match model.transcribe_stream(stream, &samples_16k, finalize) {
+        Ok(StreamStep { interim, finalized }) => {
+            let finalized = finalized.unwrap_or_default();
+            Ok(StreamTranscript {
+                segments: create_segments(&interim),
+                words: words_from_tokens(&interim, frame_secs),
+                full_text: interim.text,
+                finalized_segments: create_segments(&finalized),
+                finalized_words: words_from_tokens(&finalized, frame_secs),
             })
         }
         Err(e) => {
-            println!("[Parakeet] ❌ Transcription failed: {:?}", e);
-            Err(Error::from_reason(format!("Transcription failed: {:?}", e)))
+            println!("[Parakeet] ❌ Stream transcription failed: {}", e);
+            Err(e.into())
         }
     }
 }
 
+/// Feed a chunk (16-bit mono PCM) to the live transcription stream
+/// Returns only tokens decoded from this chunk, plus any segment it finalized;
+/// timestamps are seconds since the stream started
+#[napi]
+pub fn transcribe_chunk(audio_data: Buffer, sample_rate: Option<u32>) -> Result<StreamTranscript> {
+    let samples: Vec<f32> = audio_data.as_ref()
+        .chunks_exact(2)
+        .map(|chunk| i16::from_le_bytes([chunk[0], chunk[1]]) as f32 / 32768.0)
+        .collect();
+    
+    run_stream(&samples, sample_rate.unwrap_or(16000), false)
+}
+
+/// Decode any audio still held back at the end of the stream and finalize the last segment,
+/// then reset it
+#[napi]
+pub fn finish_transcribe_stream() -> Result<StreamTranscript> {
+    let result = run_stream(&[], SAMPLE_RATE as u32, true);
+    *PARAKEET_STREAM.lock() = None;
+    result
+}
+
+/// Discard the live transcription stream without decoding pending audio
+#[napi]
+pub fn reset_transcribe_stream() {
+    *PARAKEET_STREAM.lock() = None;
+}
+
 /// Create segments from timestamped tokens, grouping by ~2-3 second intervals or sentence boundaries
 fn create_segments(result: &TimestampedResult) -> Vec<TranscriptSegment> {
+    create_segments_with(result, SegmentBuilder::new())
+}
+
+fn create_segments_with(result: &TimestampedResult, mut builder: SegmentBuilder) -> Vec<TranscriptSegment> {
     if result.tokens.is_empty() || result.timestamps.is_empty() {
         // Return single segment with full text if no timestamps
         if !result.text.is_empty() {
@@ -744,99 +2250,237 @@ fn create_segments(result: &TimestampedResult) -> Vec<TranscriptSegment> {
                 text: result.text.clone(),
                 start_time: 0.0,
                 end_time: 0.0,
+                speaker: None,
+                confidence: None,
             }];
         }
         return vec![];
     }
     
-    let mut segments = Vec::new();
-    let mut current_tokens: Vec<String> = Vec::new();
-    let mut segment_start_time: Option<f32> = None;
-    let mut last_time: f32 = 0.0;
+    let mut segments: Vec<TranscriptSegment> = result.tokens.iter()
+        .zip(result.timestamps.iter())
+        .enumerate()
+        .flat_map(|(i, (token, &timestamp))| builder.push(token, timestamp, result.confidences.get(i).copied()))
+        .collect();
+    segments.extend(builder.finish());
     
+    segments
+}
+
+/// Groups tokens into segments as they arrive, so segments can be emitted progressively
+struct SegmentBuilder {
+    interval_secs: f32,
+    split_on_sentence: bool,
+    current_tokens: Vec<String>,
+    current_confidences: Vec<f32>,
+    segment_start_time: Option<f32>,
+    last_time: f32,
+}
+
+impl SegmentBuilder {
     const SEGMENT_INTERVAL: f32 = 2.5; // Create new segment every ~2.5 seconds
     
-    for (i, (token, &timestamp)) in result.tokens.iter().zip(result.timestamps.iter()).enumerate() {
-        if segment_start_time.is_none() {
-            segment_start_time = Some(timestamp);
+    fn new() -> Self {
+        Self::with_options(Self::SEGMENT_INTERVAL, true)
+    }
+    
+    fn with_options(interval_secs: f32, split_on_sentence: bool) -> Self {
+        Self {
+            interval_secs: interval_secs.max(0.0),
+            split_on_sentence,
+            current_tokens: Vec::new(),
+            current_confidences: Vec::new(),
+            segment_start_time: None,
+            last_time: 0.0,
         }
+    }
+    
+    /// Add a token; returns any segments it closes
+    /// Interval breaks only happen where a new word starts, so tiny intervals never split words
+    fn push(&mut self, token: &str, timestamp: f32, confidence: Option<f32>) -> Vec<TranscriptSegment> {
+        let mut closed = Vec::new();
         
-        current_tokens.push(token.clone());
-        last_time = timestamp;
+        let starts_word = token.starts_with(' ');
+        let interval_elapsed = self.segment_start_time
+            .map(|start| timestamp - start >= self.interval_secs)
+            .unwrap_or(false);
+        if interval_elapsed && starts_word {
+            closed.extend(self.finish());
+        }
         
-        // Check if we should create a new segment
-        let time_since_start = timestamp - segment_start_time.unwrap_or(0.0);
-        let is_sentence_end = token.ends_with('.') || token.ends_with('?') || token.ends_with('!');
-        let is_last_token = i == result.tokens.len() - 1;
+        if self.segment_start_time.is_none() {
+            self.segment_start_time = Some(timestamp);
+        }
         
-        if time_since_start >= SEGMENT_INTERVAL || is_sentence_end || is_last_token {
-            // Create segment from accumulated tokens
-            let segment_text = current_tokens.join("").trim().to_string();
-            
-            if !segment_text.is_empty() {
-                segments.push(TranscriptSegment {
-                    text: segment_text,
-                    start_time: segment_start_time.unwrap_or(0.0) as f64,
-                    end_time: last_time as f64,
-                });
-            }
-            
-            // Reset for next segment
-            current_tokens.clear();
-            segment_start_time = None;
+        self.current_tokens.push(token.to_string());
+        self.current_confidences.extend(confidence);
+        self.last_time = timestamp;
+        
+        let is_sentence_end = token.ends_with('.') || token.ends_with('?') || token.ends_with('!');
+        if self.split_on_sentence && is_sentence_end {
+            closed.extend(self.finish());
         }
+        
+        closed
     }
     
-    // Handle any remaining tokens
-    if !current_tokens.is_empty() {
-        let segment_text = current_tokens.join("").trim().to_string();
-        if !segment_text.is_empty() {
-            segments.push(TranscriptSegment {
+    /// Flush accumulated tokens into a segment (None if they are only whitespace)
+    fn finish(&mut self) -> Option<TranscriptSegment> {
+        let segment_text = self.current_tokens.join("").trim().to_string();
+        let segment = if segment_text.is_empty() {
+            None
+        } else {
+            Some(TranscriptSegment {
                 text: segment_text,
-                start_time: segment_start_time.unwrap_or(0.0) as f64,
-                end_time: last_time as f64,
-            });
-        }
+                start_time: self.segment_start_time.unwrap_or(0.0) as f64,
+                end_time: self.last_time as f64,
+                speaker: None,
+                confidence: mean_confidence(&self.current_confidences),
+            })
+        };
+        
+        // Reset for next segment
+        self.current_tokens.clear();
+        self.current_confidences.clear();
+        self.segment_start_time = None;
+        segment
+    }
+}
+
+fn mean_confidence(confidences: &[f32]) -> Option<f64> {
+    if confidences.is_empty() {
+        return None;
     }
+    Some(confidences.iter().map(|&c| c as f64).sum::<f64>() / confidences.len() as f64)
+}
+
+/// A speaker turn from an external diarization tool
+#[napi(object)]
+#[derive(Clone)]
+pub struct SpeakerTurn {
+    pub start: f64,  // Seconds, same timeline as the transcript
+    pub end: f64,
+    pub speaker: String,
+}
+
+/// Tag each transcript segment with the speaker from an external diarization
+/// Segments are assigned to the turn they overlap most; a segment that spans a
+/// speaker change is split at the turn boundary (text is divided by word position)
+#[napi]
+pub fn apply_diarization(transcript: TranscriptWithTimestamps, turns: Vec<SpeakerTurn>) -> TranscriptWithTimestamps {
+    let mut turns = turns;
+    turns.retain(|t| t.end >= t.start);
+    turns.sort_by(|a, b| a.start.partial_cmp(&b.start).unwrap_or(std::cmp::Ordering::Equal));
     
-    segments
+    let mut segments = Vec::with_capacity(transcript.segments.len());
+    for segment in transcript.segments {
+        segments.extend(diarize_segment(segment, &turns));
+    }
+    
+    TranscriptWithTimestamps {
+        segments,
+        full_text: transcript.full_text,
+        language: transcript.language,
+        raw_text: transcript.raw_text,
+        timed_out: transcript.timed_out,
+    }
 }
 
-/// Resample audio using high-quality sinc interpolation
-fn resample_audio(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
-    if from_rate == to_rate || input.is_empty() {
-        return input.to_vec();
+/// Find the turn speaking at `time`, preferring the one overlapping [start, end] most,
+/// and falling back to the nearest turn when nobody is speaking
+fn speaker_turn_at<'a>(turns: &'a [SpeakerTurn], time: f64, start: f64, end: f64) -> Option<&'a SpeakerTurn> {
+    let overlap = |t: &SpeakerTurn| (t.end.min(end) - t.start.max(start)).max(0.0);
+    let distance = |t: &SpeakerTurn| {
+        if time < t.start { t.start - time } else if time > t.end { time - t.end } else { 0.0 }
+    };
+    
+    let containing = turns.iter()
+        .filter(|t| t.start <= time && time <= t.end)
+        .max_by(|a, b| overlap(a).partial_cmp(&overlap(b)).unwrap_or(std::cmp::Ordering::Equal));
+    
+    containing.or_else(|| {
+        turns.iter()
+            .min_by(|a, b| distance(a).partial_cmp(&distance(b)).unwrap_or(std::cmp::Ordering::Equal))
+    })
+}
+
+fn diarize_segment(segment: TranscriptSegment, turns: &[SpeakerTurn]) -> Vec<TranscriptSegment> {
+    let start = segment.start_time;
+    let end = segment.end_time.max(start);
+    let words: Vec<&str> = segment.text.split_whitespace().collect();
+    
+    if turns.is_empty() || words.is_empty() {
+        return vec![segment];
     }
     
-    use rubato::{Resampler, SincFixedIn, SincInterpolationParameters, SincInterpolationType, WindowFunction};
+    // Place each word at its proportional position within the segment
+    let duration = end - start;
+    let word_time = |i: usize| start + duration * (i as f64 + 0.5) / words.len() as f64;
     
-    let ratio = to_rate as f64 / from_rate as f64;
+    // Group consecutive words spoken by the same turn
+    let mut groups: Vec<(&SpeakerTurn, usize, usize)> = Vec::new(); // (turn, first word, last word)
+    for i in 0..words.len() {
+        let Some(turn) = speaker_turn_at(turns, word_time(i), start, end) else { continue };
+        match groups.last_mut() {
+            Some((prev, _, last)) if prev.speaker == turn.speaker => *last = i,
+            _ => groups.push((turn, i, i)),
+        }
+    }
     
-    let params = SincInterpolationParameters {
-        sinc_len: 256,
-        f_cutoff: 0.95,
-        interpolation: SincInterpolationType::Linear,
-        oversampling_factor: 256,
-        window: WindowFunction::BlackmanHarris2,
-    };
+    if groups.len() <= 1 {
+        let speaker = groups.first().map(|(turn, _, _)| turn.speaker.clone());
+        return vec![TranscriptSegment { speaker, ..segment }];
+    }
     
-    let mut resampler = match SincFixedIn::<f32>::new(ratio, 2.0, params, input.len(), 1) {
-        Ok(r) => r,
-        Err(_) => return input.to_vec(),
-    };
+    // Split at the turn boundary, clamped between the neighbouring words
+    let mut split_times = vec![start];
+    for pair in groups.windows(2) {
+        let (_, _, prev_last) = pair[0];
+        let (turn, first, _) = pair[1];
+        split_times.push(turn.start.clamp(word_time(prev_last), word_time(first)));
+    }
+    split_times.push(end);
     
-    let waves_in = vec![input.to_vec()];
-    match resampler.process(&waves_in, None) {
-        Ok(waves_out) => waves_out.into_iter().next().unwrap_or_default(),
-        Err(_) => input.to_vec(),
+    groups.iter()
+        .enumerate()
+        .map(|(i, (turn, first, last))| TranscriptSegment {
+            text: words[*first..=*last].join(" "),
+            start_time: split_times[i],
+            end_time: split_times[i + 1],
+            speaker: Some(turn.speaker.clone()),
+            confidence: segment.confidence,
+        })
+        .collect()
+}
+
+/// Resample audio using high-quality sinc interpolation
+/// Lenient: falls back to the input unchanged on error (hot path)
+fn resample_audio(input: &[f32], from_rate: u32, to_rate: u32) -> Vec<f32> {
+    match try_resample_audio(input, from_rate, to_rate) {
+        Ok(output) => output,
+        Err(e) => {
+            println!("[Parakeet] ⚠️ Resampling {}Hz -> {}Hz failed, using input as-is: {}", from_rate, to_rate, e);
+            input.to_vec()
+        }
     }
 }
 
+/// Resample float samples, returning an error instead of silently passing input through
+/// Intended for tests/CI that need to assert resampling actually happened
 #[napi]
-pub fn delete_parakeet_model() -> Result<bool> {
-    {
-        let mut state = PARAKEET_STATE.lock();
-        *state = None;
+pub fn resample_audio_checked(samples: Float32Array, from_rate: u32, to_rate: u32) -> Result<Float32Array> {
+    try_resample_audio(&samples, from_rate, to_rate)
+        .map(Float32Array::new)
+        .map_err(Error::from_reason)
+}
+
+/// Delete the downloaded model files, unloading the model first unless `keep_loaded` is true
+/// A kept model works until unloaded but can't be loaded again without a new download
+/// (on Windows, files of a loaded model may be locked and fail to delete)
+#[napi]
+pub fn delete_parakeet_model(keep_loaded: Option<bool>) -> Result<bool> {
+    if !keep_loaded.unwrap_or(false) {
+        unload_parakeet();
     }
     
     let model_dir = get_model_dir();
@@ -846,7 +2490,7 @@ pub fn delete_parakeet_model() -> Result<bool> {
                 println!("[Parakeet] ✅ Model deleted");
                 Ok(true)
             }
-            Err(e) => Err(Error::from_reason(format!("Delete failed: {:?}", e)))
+            Err(e) => Err(ModelError::Io(format!("Delete failed: {}", e)).into())
         }
     } else {
         Ok(true)
@@ -858,9 +2502,204 @@ pub fn get_parakeet_model_path() -> String {
     get_model_dir().to_string_lossy().to_string()
 }
 
+/// Free the model's memory but keep the downloaded files; init_parakeet loads it again
 #[napi]
-pub fn shutdown_parakeet() {
+pub fn unload_parakeet() {
+    *PARAKEET_STREAM.lock() = None;
     let mut state = PARAKEET_STATE.lock();
     *state = None;
-    println!("[Parakeet] Shutdown complete");
+    *PARAKEET_ENGINE_STATE.lock() = EngineState::Unloaded;
+    println!("[Parakeet] Unloaded");
+}
+
+/// Same as unload_parakeet
+#[napi]
+pub fn shutdown_parakeet() {
+    unload_parakeet();
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn test_resample_changes_length() {
+        let input: Vec<f32> = (0..48000).map(|i| (i as f32 * 0.01).sin()).collect();
+        let output = try_resample_audio(&input, 48000, 16000).expect("resampling failed");
+        let expected = 16000i64;
+        assert!((output.len() as i64 - expected).abs() <= expected / 100,
+            "expected ~{} samples, got {}", expected, output.len());
+    }
+
+    #[test]
+    fn test_resample_rejects_zero_rate() {
+        assert!(try_resample_audio(&[0.0; 16], 0, 16000).is_err());
+    }
+
+    #[test]
+    fn test_tiny_segment_interval_keeps_words_whole() {
+        let tokens = [" he", "llo", " wor", "ld", "."];
+        let mut builder = SegmentBuilder::with_options(0.0, false);
+        let mut segments: Vec<TranscriptSegment> = tokens.iter()
+            .enumerate()
+            .flat_map(|(i, token)| builder.push(token, i as f32 * 0.08, Some(0.9)))
+            .collect();
+        segments.extend(builder.finish());
+
+        let texts: Vec<&str> = segments.iter().map(|s| s.text.as_str()).collect();
+        assert_eq!(texts, vec!["hello", "world."]);
+    }
+
+    #[test]
+    fn test_join_tokens_spacing() {
+        let join = |tokens: &[&str]| join_tokens(&tokens.iter().map(|t| t.to_string()).collect::<Vec<_>>());
+        
+        assert_eq!(join(&[" hel", "lo", " ", "world", " ,", " (", "ok", ")", "."]), "hello world, (ok).");
+        // Russian and Ukrainian
+        assert_eq!(join(&[" При", "вет", ",", " как", " дела", "?"]), "Привет, как дела?");
+        assert_eq!(join(&[" Дя", "кую", " «", "друже", "»", "."]), "Дякую «друже».");
+        // Greek, whose question mark is ;
+        assert_eq!(join(&[" Καλη", "μέρα", ",", " τι", " κάνεις", ";"]), "Καλημέρα, τι κάνεις;");
+        assert_eq!(join(&[" Ναι", " ", "·", " 42", " ευρώ"]), "Ναι· 42 ευρώ");
+    }
+
+    #[test]
+    fn test_tokens_in_frames_moves_span_onto_stream_timeline() {
+        // Decoded from audio starting at stream frame 100 (8s), 80ms frames
+        let result = TimestampedResult {
+            text: String::new(),
+            tokens: [" one", ".", " two", "."].iter().map(|t| t.to_string()).collect(),
+            timestamps: vec![0.0, 0.16, 0.8, 0.96],
+            confidences: vec![0.9; 4],
+            timed_out: false,
+        };
+        let span = tokens_in_frames(result, 0.08, 100, 100..103);
+        
+        assert_eq!(span.text, "one.");
+        assert_eq!(span.tokens.len(), 2);
+        assert!((span.timestamps[0] - 8.0).abs() < 1e-4);
+        assert!((span.timestamps[1] - 8.16).abs() < 1e-4);
+    }
+
+    #[test]
+    fn test_words_from_tokens_merges_subwords() {
+        let result = TimestampedResult {
+            text: String::new(),
+            tokens: [" ", "he", "llo", " wor", "ld", ",", " ok"].iter().map(|t| t.to_string()).collect(),
+            timestamps: vec![0.0, 0.08, 0.16, 0.4, 0.48, 0.56, 0.8],
+            confidences: vec![0.9; 7],
+            timed_out: false,
+        };
+        let words = words_from_tokens(&result, 0.08);
+        
+        let texts: Vec<&str> = words.iter().map(|w| w.word.as_str()).collect();
+        assert_eq!(texts, vec!["hello", "world,", "ok"]);
+        assert_eq!(words[0].start_time, 0.0);
+        assert!((words[1].start_time - 0.4).abs() < 1e-6);
+        assert!((words[1].end_time - 0.64).abs() < 1e-6);
+    }
+
+    #[test]
+    fn test_normalize_transcript_text() {
+        let text = "hello  world , this is 3.5 percent . how are you ?";
+        assert_eq!(
+            normalize_transcript_text(text, Some("en"), true),
+            "Hello world, this is 3.5 percent. How are you?"
+        );
+        // French keeps the space before ?
+        assert_eq!(normalize_transcript_text("ça va ? oui .", Some("fr"), true), "Ça va ? Oui.");
+        assert_eq!(normalize_transcript_text("¿qué tal? bien", Some("es"), true), "¿Qué tal? Bien");
+    }
+
+    #[test]
+    fn test_normalize_transcript_detects_language() {
+        assert_eq!(detect_language("je pense que c'est très bien"), Some("fr"));
+        assert_eq!(detect_language("Καλημέρα, τι κάνεις;"), Some("el"));
+        assert_eq!(detect_language("this is a test"), None);
+        
+        let mut transcript = TranscriptWithTimestamps {
+            segments: Vec::new(),
+            full_text: "oui , je suis là ! vous avez vu ?".to_string(),
+            language: None,
+            raw_text: None,
+            timed_out: None,
+        };
+        normalize_transcript(&mut transcript);
+        assert_eq!(transcript.full_text, "Oui, je suis là ! Vous avez vu ?");
+    }
+
+    #[test]
+    fn test_pcm16_to_16k_mono_mixes_interleaved_channels() {
+        let pcm: Vec<u8> = [16384i16, -16384, 8192, 8192, 4096]
+            .iter()
+            .flat_map(|s| s.to_le_bytes())
+            .collect();
+        
+        // Stereo frames average to mono; the trailing half frame is dropped
+        assert_eq!(pcm16_to_16k_mono(&pcm, 16000, 2).unwrap(), vec![0.0, 0.25]);
+        assert_eq!(pcm16_to_16k_mono(&pcm, 16000, 1).unwrap(), vec![0.5, -0.5, 0.25, 0.25, 0.125]);
+    }
+
+    #[test]
+    fn test_adopt_legacy_file_checks_pinned_hash() {
+        let path = std::env::temp_dir().join("ghost_parakeet_legacy_test.txt");
+        fs::write(&path, b"abc").unwrap();
+        let pinned = |sha256| ModelFile { name: "legacy.txt", size: 3, sha256: Some(sha256) };
+        
+        assert!(!adopt_legacy_file(&path, &pinned("0000000000000000000000000000000000000000000000000000000000000000")));
+        assert!(!checksum_path(&path).exists());
+        assert!(adopt_legacy_file(&path, &pinned("ba7816bf8f01cfea414140de5dae2223b00361a396177a9cb410ff61f20015ad")));
+        assert!(verify_model_file(&path, &ModelFile { name: "legacy.txt", size: 3, sha256: None }).is_ok());
+        
+        remove_model_file(&path);
+    }
+
+    #[test]
+    fn test_default_thread_count_is_positive() {
+        assert!(default_thread_count() >= 1);
+    }
+
+    /// Needs the downloaded model: cargo test -- --ignored test_session_with_explicit_threads
+    #[test]
+    #[ignore]
+    fn test_session_with_explicit_threads() {
+        let model_dir = get_model_dir();
+        if !model_dir.join("nemo128.onnx").exists() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+        let (session, bytes) = ParakeetModel::init_session(&model_dir, "nemo128", Some(2), false, false)
+            .expect("session with 2 threads failed to build");
+        assert!(!session.inputs.is_empty());
+        assert!(bytes > 0);
+    }
+
+    /// Needs the downloaded model: cargo test --release -- --ignored bench_coreml_encode
+    #[test]
+    #[ignore]
+    fn bench_coreml_encode() {
+        let model_dir = get_model_dir();
+        if !check_model_files() {
+            println!("model not downloaded, skipping");
+            return;
+        }
+
+        let samples: Vec<f32> = (0..SAMPLE_RATE * 30).map(|i| (i as f32 * 0.05).sin() * 0.1).collect();
+        let time_encode = |use_coreml: bool| {
+            let mut model = ParakeetModel::new(&model_dir, true, None, None, use_coreml, default_thread_count()).expect("load failed");
+            let waveforms = Array2::from_shape_vec((1, samples.len()), samples.clone()).unwrap().into_dyn();
+            let lens = Array1::from_vec(vec![samples.len() as i64]).into_dyn();
+            let (features, features_lens) = model.preprocess(&waveforms.view(), &lens.view()).unwrap();
+            // First run includes graph compilation
+            model.encode(&features.view(), &features_lens.view()).unwrap();
+            let start = std::time::Instant::now();
+            model.encode(&features.view(), &features_lens.view()).unwrap();
+            start.elapsed()
+        };
+
+        let cpu = time_encode(false);
+        let coreml = time_encode(true);
+        println!("30s encode: cpu={:?} coreml={:?}", cpu, coreml);
+        assert!(coreml < cpu, "CoreML encode was not faster than CPU");
+    }
 }
@@ -0,0 +1,64 @@
+//! Small JSON settings files in the app data directory, shared by the model engines
+
+use parking_lot::Mutex;
+use serde::de::DeserializeOwned;
+use serde::{Deserialize, Serialize};
+use std::path::PathBuf;
+
+use crate::model_error::ModelError;
+
+/// `<data dir>/ghost`, created on first use
+pub(crate) fn app_data_dir() -> PathBuf {
+    let app_data = dirs::data_dir()
+        .unwrap_or_else(|| PathBuf::from("."))
+        .join("ghost");
+    std::fs::create_dir_all(&app_data).ok();
+    app_data
+}
+
+/// Read `file_name` from the app data directory; missing or unreadable files give the default
+pub(crate) fn load<T: DeserializeOwned + Default>(file_name: &str) -> T {
+    std::fs::read_to_string(app_data_dir().join(file_name))
+        .ok()
+        .and_then(|json| serde_json::from_str(&json).ok())
+        .unwrap_or_default()
+}
+
+pub(crate) fn save<T: Serialize>(file_name: &str, value: &T) -> Result<(), String> {
+    let json = serde_json::to_string_pretty(value).map_err(|e| e.to_string())?;
+    std::fs::write(app_data_dir().join(file_name), json).map_err(|e| e.to_string())
+}
+
+/// Settings of an engine whose model files can live outside the default directory
+#[derive(Default, Serialize, Deserialize)]
+pub(crate) struct ModelDirConfig {
+    pub model_dir: Option<PathBuf>,
+}
+
+/// Validate, persist and apply a directory passed to set_*_model_dir. An empty path restores
+/// the default; anything else must be absolute, and gets its `subdir` created up front
+pub(crate) fn set_model_dir(
+    config_file: &str,
+    subdir: &str,
+    current: &Mutex<Option<PathBuf>>,
+    path: &str,
+) -> Result<(), ModelError> {
+    let path = path.trim();
+    let model_dir = if path.is_empty() {
+        None
+    } else {
+        let base = PathBuf::from(path);
+        if !base.is_absolute() {
+            return Err(ModelError::InvalidInput(format!("Model directory must be an absolute path: {}", path)));
+        }
+        std::fs::create_dir_all(base.join(subdir))
+            .map_err(|e| ModelError::Io(format!("Can't use {}: {}", path, e)))?;
+        Some(base)
+    };
+    
+    if let Err(e) = save(config_file, &ModelDirConfig { model_dir: model_dir.clone() }) {
+        println!("⚠️ Failed to save {}: {}", config_file, e);
+    }
+    *current.lock() = model_dir;
+    Ok(())
+}
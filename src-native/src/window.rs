@@ -1,15 +1,366 @@
 //! Cross-platform window enumeration utilities
 
-use crate::WindowInfo;
+use crate::{MeetingState, WindowInfo};
+use once_cell::sync::Lazy;
+use parking_lot::Mutex;
+use std::collections::{HashMap, HashSet};
+use std::sync::Arc;
+use std::time::{Duration, Instant};
 
 /// Trait for platform-specific window enumeration
 pub trait WindowEnumerator {
     fn enumerate() -> Vec<WindowInfo>;
 }
 
+/// Build a best-effort stable key for a window from pid + bundle id + normalized title
+///
+/// This is a heuristic: it survives the window id changing when an app recreates
+/// a window, but two windows of the same app with identical titles share a key.
+pub fn stable_window_key(pid: i32, bundle_id: Option<&str>, owner_name: &str, title: &str) -> String {
+    let app = bundle_id.unwrap_or(owner_name);
+    let normalized_title = title
+        .split_whitespace()
+        .collect::<Vec<_>>()
+        .join(" ")
+        .to_lowercase();
+    
+    format!("{}:{}:{}", pid, app, normalized_title)
+}
+
+/// Stable order for window lists: the focused window first, then front to back by
+/// z_order, with pid and window_id breaking ties
+pub fn sort_windows(windows: &mut [WindowInfo]) {
+    windows.sort_by_key(|w| (!w.is_focused, w.z_order, w.pid, w.window_id));
+}
+
+// ============================================================================
+// Meeting app detection
+// ============================================================================
+
+/// Native meeting apps, matched against bundle id (macOS) or process name (Windows)
+const DEFAULT_MEETING_APPS: &[&str] = &[
+    "us.zoom.xos",
+    "com.microsoft.teams",
+    "com.microsoft.teams2",
+    "com.cisco.webexmeetingsapp",
+    "com.webex.meetingmanager",
+    "Zoom.exe",
+    "ms-teams.exe",
+    "Teams.exe",
+    "CiscoCollabHost.exe",
+];
+
+/// Browsers whose focused tab URL is checked against MEETING_URLS
+const BROWSER_APPS: &[&str] = &[
+    "com.google.Chrome",
+    "com.apple.Safari",
+    "com.microsoft.edgemac",
+    "com.brave.Browser",
+    "company.thebrowser.Browser",
+    "org.mozilla.firefox",
+    "chrome.exe",
+    "msedge.exe",
+    "brave.exe",
+    "firefox.exe",
+];
+
+const MEETING_URLS: &[&str] = &[
+    "meet.google.com/",
+    "teams.microsoft.com/",
+    "teams.live.com/",
+    "zoom.us/wc/",
+    ".webex.com/",
+];
+
+static MEETING_APPS: Lazy<Mutex<Vec<String>>> =
+    Lazy::new(|| Mutex::new(DEFAULT_MEETING_APPS.iter().map(|s| s.to_string()).collect()));
+
+/// Replace the list of native meeting apps (bundle ids or process names)
+pub fn set_meeting_apps(apps: Vec<String>) {
+    *MEETING_APPS.lock() = apps;
+}
+
+fn app_id(bundle_id: Option<&str>, owner_name: &str) -> String {
+    bundle_id.unwrap_or(owner_name).to_string()
+}
+
+/// Whether a window belongs to a known native meeting app
+pub fn is_meeting_app(bundle_id: Option<&str>, owner_name: &str) -> bool {
+    let app = app_id(bundle_id, owner_name);
+    MEETING_APPS.lock().iter().any(|m| m.eq_ignore_ascii_case(&app))
+}
+
+/// Whether an app is one of the browsers whose URL is checked for meetings
+pub fn is_browser_app(bundle_id: Option<&str>, owner_name: &str) -> bool {
+    let app = app_id(bundle_id, owner_name);
+    BROWSER_APPS.iter().any(|b| b.eq_ignore_ascii_case(&app))
+}
+
+fn is_browser(window: &WindowInfo) -> bool {
+    is_browser_app(window.bundle_id.as_deref(), &window.owner_name)
+}
+
+fn is_meeting_url(url: &str) -> bool {
+    let url = url.to_lowercase();
+    MEETING_URLS.iter().any(|m| url.contains(m))
+}
+
+/// How long a browser window's URL check is reused while its title stays the same
+const BROWSER_URL_CACHE_TTL: Duration = Duration::from_secs(10);
+
+/// Meeting URL checks by (pid, window title), with when they were made
+static BROWSER_URL_CACHE: Lazy<Mutex<HashMap<(i32, String), (bool, Instant)>>> = Lazy::new(|| Mutex::new(HashMap::new()));
+
+/// Flag browser windows showing a meeting URL
+///
+/// Only the URL of each browser's focused window is readable, so just the
+/// frontmost window per browser process is checked (lists are front to back).
+/// Reading it walks the browser's accessibility tree, so results are cached by pid and
+/// title (switching tabs changes the title) for BROWSER_URL_CACHE_TTL
+pub fn mark_meeting_browser_windows(windows: &mut [WindowInfo]) {
+    let mut checked_pids = HashSet::new();
+    
+    for window in windows.iter_mut() {
+        if window.is_meeting_app || !is_browser(window) || !checked_pids.insert(window.pid) {
+            continue;
+        }
+        
+        if browser_shows_meeting(window.pid, &window.title) {
+            window.is_meeting_app = true;
+        }
+    }
+}
+
+fn browser_shows_meeting(pid: i32, title: &str) -> bool {
+    let key = (pid, title.to_string());
+    {
+        let mut cache = BROWSER_URL_CACHE.lock();
+        cache.retain(|_, (_, checked_at)| checked_at.elapsed() < BROWSER_URL_CACHE_TTL);
+        if let Some(&(is_meeting, _)) = cache.get(&key) {
+            return is_meeting;
+        }
+    }
+    
+    let is_meeting = crate::get_browser_url(pid).is_some_and(|url| is_meeting_url(&url));
+    BROWSER_URL_CACHE.lock().insert(key, (is_meeting, Instant::now()));
+    is_meeting
+}
+
+fn title_looks_in_call(window: &WindowInfo) -> bool {
+    let title = window.title.to_lowercase();
+    ["meeting", "call", "meet -"].iter().any(|hint| title.contains(hint))
+}
+
+/// Pick the most likely meeting window: titles that look like a call win,
+/// otherwise the frontmost meeting window
+pub fn pick_active_meeting(windows: Vec<WindowInfo>) -> Option<WindowInfo> {
+    let meetings: Vec<WindowInfo> = windows.into_iter().filter(|w| w.is_meeting_app).collect();
+    let best = meetings.iter().position(title_looks_in_call).unwrap_or(0);
+    meetings.into_iter().nth(best)
+}
+
+// ============================================================================
+// Meeting state heuristic
+// ============================================================================
+
+// Evidence weights; a meeting needs the mic plus a meeting window or URL to reach MEETING_THRESHOLD
+const MIC_WEIGHT: f64 = 0.4;
+const MEETING_WINDOW_WEIGHT: f64 = 0.3;
+const IN_CALL_TITLE_WEIGHT: f64 = 0.1;
+const MIC_OWNER_WEIGHT: f64 = 0.2;
+const MEETING_THRESHOLD: f64 = 0.6;
 
+/// What detect_meeting_state looked at, kept separate so the scoring is testable
+pub struct MeetingSignals {
+    pub mic_in_use: bool,
+    /// Pids of the apps using the mic (empty where the platform can't tell)
+    pub mic_user_pids: Vec<i32>,
+    pub meeting_window: Option<WindowInfo>,
+    /// Meeting URL of the browser showing meeting_window, if it's a browser
+    pub meeting_url: Option<String>,
+}
 
+/// Combine the signals into a confidence (0.0 - 1.0): mic in use, a meeting app or meeting
+/// URL, a call-like window title, and the mic being used by that same app
+pub fn score_meeting(signals: MeetingSignals) -> MeetingState {
+    let mut confidence = 0.0;
+    if signals.mic_in_use {
+        confidence += MIC_WEIGHT;
+    }
+    if let Some(window) = &signals.meeting_window {
+        confidence += MEETING_WINDOW_WEIGHT;
+        if title_looks_in_call(window) {
+            confidence += IN_CALL_TITLE_WEIGHT;
+        }
+        if signals.mic_user_pids.contains(&window.pid) {
+            confidence += MIC_OWNER_WEIGHT;
+        }
+    }
+    let confidence = f64::min(confidence, 1.0);
+    
+    MeetingState {
+        active: confidence >= MEETING_THRESHOLD,
+        app: signals.meeting_window.map(|w| w.owner_name),
+        confidence,
+        url: signals.meeting_url,
+    }
+}
 
+/// Gather the meeting signals from the mic monitor, the window list and the browser URL
+pub fn detect_meeting_state() -> MeetingState {
+    let meeting_window = pick_active_meeting(crate::get_active_windows());
+    let meeting_url = meeting_window
+        .as_ref()
+        .filter(|w| is_browser(w))
+        .and_then(|w| crate::get_browser_url(w.pid))
+        .filter(|url| is_meeting_url(url));
+    
+    score_meeting(MeetingSignals {
+        mic_in_use: crate::is_microphone_in_use(),
+        mic_user_pids: crate::get_microphone_users().into_iter().map(|u| u.pid).collect(),
+        meeting_window,
+        meeting_url,
+    })
+}
 
+// ============================================================================
+// Focus-change notifications
+// ============================================================================
 
+pub type WindowChangeHandler = Arc<dyn Fn(WindowInfo) + Send + Sync>;
 
+struct ActiveWindowWatch {
+    handler: WindowChangeHandler,
+    last_window_id: Option<i32>,
+}
+
+static ACTIVE_WINDOW_WATCH: Mutex<Option<ActiveWindowWatch>> = Mutex::new(None);
+
+/// Install (or with None, remove) the handler that platform watchers report to
+pub fn set_active_window_handler(handler: Option<WindowChangeHandler>) {
+    *ACTIVE_WINDOW_WATCH.lock() = handler.map(|handler| ActiveWindowWatch {
+        handler,
+        last_window_id: None,
+    });
+}
+
+/// Forward a newly focused window to the handler, skipping repeats of the same window
+/// (an app activation and its window focus event often arrive back to back)
+pub fn report_active_window(mut info: WindowInfo) {
+    mark_meeting_browser_windows(std::slice::from_mut(&mut info));
+    
+    let handler = {
+        let mut guard = ACTIVE_WINDOW_WATCH.lock();
+        let watch = match guard.as_mut() {
+            Some(watch) => watch,
+            None => return,
+        };
+        if watch.last_window_id == Some(info.window_id) {
+            return;
+        }
+        watch.last_window_id = Some(info.window_id);
+        watch.handler.clone()
+    };
+    
+    handler(info);
+}
+
+// ============================================================================
+// App icons
+// ============================================================================
+
+/// Encode 8-bit RGBA pixels as a PNG; None if `rgba` doesn't hold width x height pixels
+#[cfg_attr(not(any(target_os = "windows", test)), allow(dead_code))]
+pub(crate) fn encode_png_rgba(width: u32, height: u32, rgba: &[u8]) -> Option<Vec<u8>> {
+    let mut png = Vec::new();
+    let mut encoder = png::Encoder::new(&mut png, width, height);
+    encoder.set_color(png::ColorType::Rgba);
+    encoder.set_depth(png::BitDepth::Eight);
+    
+    let mut writer = encoder.write_header().ok()?;
+    writer.write_image_data(rgba).ok()?;
+    writer.finish().ok()?;
+    Some(png)
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    
+    fn window(pid: i32, title: &str) -> WindowInfo {
+        WindowInfo {
+            pid,
+            window_id: 1,
+            owner_name: "zoom.us".to_string(),
+            title: title.to_string(),
+            bundle_id: Some("us.zoom.xos".to_string()),
+            stable_key: String::new(),
+            is_meeting_app: true,
+            is_minimized: false,
+            is_focused: false,
+            z_order: -1,
+        }
+    }
+    
+    #[test]
+    fn test_score_meeting_needs_mic_and_meeting_window() {
+        let idle_app = score_meeting(MeetingSignals {
+            mic_in_use: false,
+            mic_user_pids: vec![],
+            meeting_window: Some(window(42, "Zoom Meeting")),
+            meeting_url: None,
+        });
+        assert!(!idle_app.active);
+        
+        let mic_only = score_meeting(MeetingSignals {
+            mic_in_use: true,
+            mic_user_pids: vec![7],
+            meeting_window: None,
+            meeting_url: None,
+        });
+        assert!(!mic_only.active);
+        assert_eq!(mic_only.app, None);
+        
+        let in_call = score_meeting(MeetingSignals {
+            mic_in_use: true,
+            mic_user_pids: vec![42],
+            meeting_window: Some(window(42, "Zoom Meeting")),
+            meeting_url: None,
+        });
+        assert!(in_call.active);
+        assert_eq!(in_call.app.as_deref(), Some("zoom.us"));
+        assert!((in_call.confidence - 1.0).abs() < 1e-9);
+    }
+    
+    #[test]
+    fn test_sort_windows_puts_focused_first() {
+        let mut windows: Vec<WindowInfo> = [(3, 2, 30, false), (1, 0, 10, false), (2, 1, 20, true), (2, 1, 5, false)]
+            .into_iter()
+            .map(|(pid, z_order, window_id, is_focused)| WindowInfo {
+                window_id,
+                z_order,
+                is_focused,
+                ..window(pid, "Zoom Meeting")
+            })
+            .collect();
+        
+        sort_windows(&mut windows);
+        
+        let ids: Vec<i32> = windows.iter().map(|w| w.window_id).collect();
+        assert_eq!(ids, vec![20, 10, 5, 30]);
+    }
+    
+    #[test]
+    fn test_encode_png_rgba_round_trips() {
+        let pixels: Vec<u8> = (0..16).map(|i| i * 16).collect();
+        let png = encode_png_rgba(2, 2, &pixels).expect("encoding failed");
+        
+        let mut reader = png::Decoder::new(&png[..]).read_info().unwrap();
+        let mut decoded = vec![0; reader.output_buffer_size()];
+        let info = reader.next_frame(&mut decoded).unwrap();
+        assert_eq!((info.width, info.height, info.color_type), (2, 2, png::ColorType::Rgba));
+        assert_eq!(&decoded[..info.buffer_size()], &pixels[..]);
+        
+        assert!(encode_png_rgba(2, 2, &pixels[..12]).is_none());
+    }
+}
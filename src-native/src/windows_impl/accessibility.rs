@@ -1,18 +1,168 @@
 //! Windows UI Automation for reading browser URLs
 
-/// Get browser URL using UI Automation
+use crate::BrowserTabInfo;
+
+#[cfg(target_os = "windows")]
+use windows::Win32::Foundation::{BOOL, HWND, LPARAM};
+
+/// Get the URL and title of a browser's frontmost window
+/// The window text holds the active tab title (e.g. "Page - Google Chrome")
+#[cfg(target_os = "windows")]
+pub fn get_browser_tab_info(pid: i32) -> Option<BrowserTabInfo> {
+    let url = get_browser_url(pid)?;
+    let title = main_window_for_pid(pid).and_then(window_title);
+    Some(BrowserTabInfo { url, title })
+}
+
+#[cfg(target_os = "windows")]
+struct WindowSearch {
+    pid: u32,
+    first_only: bool,
+    found: Vec<HWND>,
+}
+
+/// Frontmost visible, titled top-level window owned by pid
+#[cfg(target_os = "windows")]
+fn main_window_for_pid(pid: i32) -> Option<HWND> {
+    windows_for_pid(pid, true).into_iter().next()
+}
+
+/// Visible, titled top-level windows owned by pid, front to back
+#[cfg(target_os = "windows")]
+fn windows_for_pid(pid: i32, first_only: bool) -> Vec<HWND> {
+    use windows::Win32::UI::WindowsAndMessaging::EnumWindows;
+    
+    let mut search = WindowSearch { pid: pid as u32, first_only, found: Vec::new() };
+    unsafe {
+        let _ = EnumWindows(Some(find_window_callback), LPARAM(&mut search as *mut WindowSearch as isize));
+    }
+    search.found
+}
+
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn find_window_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
+    use windows::Win32::UI::WindowsAndMessaging::{GetWindowThreadProcessId, IsWindowVisible};
+    
+    let search = &mut *(lparam.0 as *mut WindowSearch);
+    
+    let mut window_pid: u32 = 0;
+    GetWindowThreadProcessId(hwnd, Some(&mut window_pid));
+    
+    if window_pid == search.pid && IsWindowVisible(hwnd).as_bool() && window_title(hwnd).is_some() {
+        search.found.push(hwnd);
+        if search.first_only {
+            return BOOL(0);
+        }
+    }
+    
+    BOOL(1)
+}
+
+#[cfg(target_os = "windows")]
+fn window_title(hwnd: HWND) -> Option<String> {
+    use windows::Win32::UI::WindowsAndMessaging::GetWindowTextW;
+    
+    let mut title_buf = [0u16; 512];
+    let len = unsafe { GetWindowTextW(hwnd, &mut title_buf) };
+    if len <= 0 {
+        return None;
+    }
+    Some(String::from_utf16_lossy(&title_buf[..len as usize]))
+}
+
+// ============================================================================
+// Per-browser address bar strategies
+// ============================================================================
+
+/// How to recognize the address bar in a particular browser's UIA tree
+#[cfg(target_os = "windows")]
+struct AddressBarStrategy {
+    process_names: &'static [&'static str],
+    /// Exact AutomationId of the address bar Edit control
+    automation_ids: &'static [&'static str],
+    /// Lowercase substrings matched against the Edit control's Name
+    name_hints: &'static [&'static str],
+    /// Chromium exposes the page URL as the value of the web content Document
+    document_fallback: bool,
+}
+
+#[cfg(target_os = "windows")]
+const GENERIC_STRATEGY: AddressBarStrategy = AddressBarStrategy {
+    process_names: &[],
+    automation_ids: &["addressbar", "urlbar", "urlbar-input"],
+    name_hints: &["address"],
+    document_fallback: false,
+};
+
+#[cfg(target_os = "windows")]
+const BROWSER_STRATEGIES: &[AddressBarStrategy] = &[
+    // Chromium omnibox: unnamed AutomationId, Name "Address and search bar"
+    AddressBarStrategy {
+        process_names: &["chrome.exe", "msedge.exe", "brave.exe", "vivaldi.exe", "opera.exe"],
+        automation_ids: &[],
+        name_hints: &["address and search bar", "address bar"],
+        document_fallback: true,
+    },
+    // Firefox: AutomationId "urlbar-input", Name "Search with <engine> or enter address"
+    AddressBarStrategy {
+        process_names: &["firefox.exe"],
+        automation_ids: &["urlbar-input"],
+        name_hints: &["enter address"],
+        document_fallback: false,
+    },
+];
+
+#[cfg(target_os = "windows")]
+fn strategy_for_process(process_name: Option<&str>) -> &'static AddressBarStrategy {
+    process_name
+        .and_then(|name| {
+            BROWSER_STRATEGIES
+                .iter()
+                .find(|s| s.process_names.iter().any(|p| p.eq_ignore_ascii_case(name)))
+        })
+        .unwrap_or(&GENERIC_STRATEGY)
+}
+
+/// Get browser URL using UI Automation, searching only the browser's own window
 #[cfg(target_os = "windows")]
 pub fn get_browser_url(pid: i32) -> Option<String> {
+    let hwnd = main_window_for_pid(pid)?;
+    let strategy = strategy_for_process(super::window::get_process_name(pid as u32).as_deref());
+    url_for_window(hwnd, strategy)
+}
+
+/// Get the URLs of all of a browser's top-level windows, front to back, de-duplicated;
+/// windows without a readable address bar are skipped
+#[cfg(target_os = "windows")]
+pub fn get_browser_urls(pid: i32) -> Vec<String> {
+    let strategy = strategy_for_process(super::window::get_process_name(pid as u32).as_deref());
+    let mut urls: Vec<String> = Vec::new();
+    for hwnd in windows_for_pid(pid, false) {
+        if let Some(url) = url_for_window(hwnd, strategy) {
+            if !urls.contains(&url) {
+                urls.push(url);
+            }
+        }
+    }
+    urls
+}
+
+/// Read the address bar of one browser window
+#[cfg(target_os = "windows")]
+fn url_for_window(hwnd: HWND, strategy: &AddressBarStrategy) -> Option<String> {
     use windows::{
+        core::VARIANT,
         Win32::UI::Accessibility::{
-            CUIAutomation, IUIAutomation, IUIAutomationElement, IUIAutomationCondition,
-            TreeScope_Subtree, UIA_EditControlTypeId, UIA_ControlTypePropertyId,
-            UIA_ValueValuePropertyId,
+            CUIAutomation, IUIAutomation, TreeScope_Descendants, UIA_ControlTypePropertyId,
+            UIA_DocumentControlTypeId, UIA_EditControlTypeId,
         },
         Win32::System::Com::{CoCreateInstance, CLSCTX_INPROC_SERVER},
     };
     
     unsafe {
+        // Declared first so it outlives (and uninitializes COM after) every UIA object below
+        let _com = ComGuard::init();
+        
         // Create UI Automation instance
         let automation: IUIAutomation = CoCreateInstance(
             &CUIAutomation,
@@ -20,37 +170,93 @@ pub fn get_browser_url(pid: i32) -> Option<String> {
             CLSCTX_INPROC_SERVER,
         ).ok()?;
         
-        // Get root element for the process
-        // This is a simplified approach - in reality you'd want to:
-        // 1. Get the browser window by PID
-        // 2. Navigate to the address bar element
-        // 3. Read its value
+        let window = automation.ElementFromHandle(hwnd).ok()?;
         
-        let root = automation.GetRootElement().ok()?;
+        let edit_condition = automation
+            .CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(UIA_EditControlTypeId.0))
+            .ok()?;
+        let edits = window.FindAll(TreeScope_Descendants, &edit_condition).ok()?;
         
-        // Create condition for Edit controls (address bar is typically an Edit control)
-        let condition = automation.CreatePropertyCondition(
-            UIA_ControlTypePropertyId,
-            &(UIA_EditControlTypeId as i32).into(),
-        ).ok()?;
+        for i in 0..edits.Length().unwrap_or(0) {
+            let edit = match edits.GetElement(i) {
+                Ok(edit) => edit,
+                Err(_) => continue,
+            };
+            
+            let automation_id = edit.CurrentAutomationId().map(|s| s.to_string()).unwrap_or_default();
+            let name = edit.CurrentName().map(|s| s.to_string().to_lowercase()).unwrap_or_default();
+            
+            let matches = strategy.automation_ids.iter().any(|id| id.eq_ignore_ascii_case(&automation_id))
+                || strategy.name_hints.iter().any(|hint| name.contains(hint));
+            
+            if matches {
+                return element_value(&edit);
+            }
+        }
         
-        // Find the address bar element
-        // This is browser-specific and may need adjustment
-        let element = root.FindFirst(TreeScope_Subtree, &condition).ok()?;
+        if strategy.document_fallback {
+            let document_condition = automation
+                .CreatePropertyCondition(UIA_ControlTypePropertyId, &VARIANT::from(UIA_DocumentControlTypeId.0))
+                .ok()?;
+            let document = window.FindFirst(TreeScope_Descendants, &document_condition).ok()?;
+            return element_value(&document).filter(|v| v.starts_with("http"));
+        }
         
-        // Get the value
-        let value = element.GetCurrentPropertyValue(UIA_ValueValuePropertyId).ok()?;
+        None
+    }
+}
+
+/// Initializes COM on the calling thread and balances it with CoUninitialize when dropped
+#[cfg(target_os = "windows")]
+struct ComGuard {
+    initialized: bool,
+}
+
+#[cfg(target_os = "windows")]
+impl ComGuard {
+    unsafe fn init() -> Self {
+        use windows::Win32::System::Com::{CoInitializeEx, COINIT_MULTITHREADED};
         
-        // Convert VARIANT to String
-        value.try_into().ok()
+        // S_FALSE (already initialized on this thread) still takes a reference to release;
+        // RPC_E_CHANGED_MODE (an STA thread) doesn't
+        Self { initialized: CoInitializeEx(None, COINIT_MULTITHREADED).is_ok() }
     }
 }
 
+#[cfg(target_os = "windows")]
+impl Drop for ComGuard {
+    fn drop(&mut self) {
+        if self.initialized {
+            unsafe { windows::Win32::System::Com::CoUninitialize() };
+        }
+    }
+}
+
+/// Read an element's ValuePattern value, or None if it has none
+#[cfg(target_os = "windows")]
+unsafe fn element_value(element: &windows::Win32::UI::Accessibility::IUIAutomationElement) -> Option<String> {
+    use windows::{core::BSTR, Win32::UI::Accessibility::UIA_ValueValuePropertyId};
+    
+    let value = element.GetCurrentPropertyValue(UIA_ValueValuePropertyId).ok()?;
+    let value = BSTR::try_from(&value).ok()?.to_string();
+    (!value.is_empty()).then_some(value)
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn get_browser_url(_pid: i32) -> Option<String> {
     None
 }
 
+#[cfg(not(target_os = "windows"))]
+pub fn get_browser_urls(_pid: i32) -> Vec<String> {
+    vec![]
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn get_browser_tab_info(_pid: i32) -> Option<BrowserTabInfo> {
+    None
+}
+
 
 
 
@@ -1,19 +1,161 @@
 //! Windows audio capture using WASAPI Loopback
-//! 
-//! This module captures process-specific audio using WASAPI with loopback mode.
-//! On Windows 10 2004+ and Windows 11, we can filter by process ID.
+//!
+//! System audio is captured from the default render endpoint in loopback mode,
+//! and the microphone from the default communications capture endpoint.
+//! COM objects live on a dedicated capture thread, which polls both capture
+//! clients, feeds the same 16kHz stereo chunk stream as macOS (L = system,
+//! R = mic), and keeps the native-rate samples for the WAV written on stop.
 
-use crate::audio::AudioError;
-use parking_lot::Mutex;
+use crate::audio::{apply_auto_gain, chunk_samples, create_private_file, queue_mono_chunks, soft_limit, resample_stream_to_16k, retain_chunk, trim_silence, try_resample_audio, AudioError, SilenceTrim, StreamResampler, WavHeader, WavSampleFormat, TARGET_SAMPLE_RATE};
+use crate::CaptureFormat;
+use parking_lot::{Mutex, RwLock};
+use std::collections::VecDeque;
+use std::sync::atomic::{AtomicBool, Ordering};
 
-static CURRENT_LEVEL: Mutex<f64> = Mutex::new(0.0);
+static SYSTEM_LEVEL: Mutex<f64> = Mutex::new(0.0);
+static MIC_LEVEL: Mutex<f64> = Mutex::new(0.0);
 
-/// Get current audio level
+static CAPTURE_RUNNING: AtomicBool = AtomicBool::new(false);
+/// Packets are drained but dropped while set. The capture thread holds the read lock while it
+/// appends, so a pause can't land between its check and its writes to the buffers
+static IS_PAUSED: RwLock<bool> = RwLock::new(false);
+static CAPTURE_THREAD: Mutex<Option<std::thread::JoinHandle<()>>> = Mutex::new(None);
+static CAPTURE_OUTPUT: Mutex<Option<CaptureOutput>> = Mutex::new(None);
+
+/// Mix formats of the open loopback/mic clients, for get_capture_format
+static SYSTEM_FORMAT: Mutex<Option<MixFormat>> = Mutex::new(None);
+static MIC_FORMAT: Mutex<Option<MixFormat>> = Mutex::new(None);
+
+/// Mono audio at each device's rate, written to the WAV on stop
+static SYSTEM_RECORDING: Mutex<Recording> = Mutex::new(Recording::new(true));
+static MIC_RECORDING: Mutex<Recording> = Mutex::new(Recording::new(true));
+
+/// 16kHz mono samples waiting to be packed into stereo chunks
+static SYSTEM_BUFFER: Mutex<Vec<f32>> = Mutex::new(Vec::new());
+static MIC_BUFFER: Mutex<Vec<f32>> = Mutex::new(Vec::new());
+
+static AUDIO_CHUNK_QUEUE: Mutex<VecDeque<Vec<u8>>> = Mutex::new(VecDeque::new());
+
+const MAX_QUEUED_CHUNKS: usize = 200;
+const MIC_GAIN: f32 = 1.5;
+
+/// How often the capture thread drains the WASAPI buffers
+const POLL_INTERVAL_MS: u64 = 10;
+
+/// Loopback delivers no packets while nothing plays; gaps longer than this are filled with silence
+const LOOPBACK_GAP_SECS: f64 = 0.1;
+
+struct CaptureOutput {
+    path: String,
+    sample_rate: u32,
+    channels: u32,
+    sample_format: WavSampleFormat,
+    save_wav: bool,
+    trim_silence: bool,
+}
+
+struct Recording {
+    samples: Vec<f32>,
+    sample_rate: u32,
+    /// Samples received so far, kept or not
+    length: usize,
+    /// False for streaming-only captures (save_wav off): samples are counted but not kept
+    keep: bool,
+}
+
+impl Recording {
+    const fn new(keep: bool) -> Self {
+        Self { samples: Vec::new(), sample_rate: 0, length: 0, keep }
+    }
+    
+    fn push(&mut self, samples: &[f32]) {
+        self.length += samples.len();
+        if self.keep {
+            self.samples.extend_from_slice(samples);
+        }
+    }
+}
+
+/// The parts of a WASAPI mix format needed to decode its buffers
+#[derive(Clone, Copy, Debug)]
+struct MixFormat {
+    sample_rate: u32,
+    channels: usize,
+    bits_per_sample: u16,
+    is_float: bool,
+}
+
+impl MixFormat {
+    fn block_align(&self) -> usize {
+        self.channels * self.bits_per_sample as usize / 8
+    }
+    
+    /// Decode interleaved frames and average them down to mono
+    fn to_mono(&self, bytes: &[u8]) -> Vec<f32> {
+        let bytes_per_sample = self.bits_per_sample as usize / 8;
+        let decode = |s: &[u8]| -> f32 {
+            match (self.is_float, self.bits_per_sample) {
+                (true, 32) => f32::from_le_bytes([s[0], s[1], s[2], s[3]]),
+                (false, 16) => i16::from_le_bytes([s[0], s[1]]) as f32 / 32768.0,
+                (false, 24) => (i32::from_le_bytes([0, s[0], s[1], s[2]]) >> 8) as f32 / 8_388_608.0,
+                (false, 32) => i32::from_le_bytes([s[0], s[1], s[2], s[3]]) as f32 / 2_147_483_648.0,
+                _ => 0.0,
+            }
+        };
+        
+        bytes
+            .chunks_exact(self.block_align())
+            .map(|frame| {
+                let sum: f32 = frame.chunks_exact(bytes_per_sample).map(decode).sum();
+                sum / self.channels as f32
+            })
+            .collect()
+    }
+}
+
+/// Get current audio level (the louder of system and mic)
 pub fn get_current_level() -> f64 {
-    *CURRENT_LEVEL.lock()
+    get_system_level().max(get_mic_level())
+}
+
+/// Live stream formats; zeros when not capturing
+pub fn get_capture_format() -> CaptureFormat {
+    if !CAPTURE_RUNNING.load(Ordering::SeqCst) {
+        return CaptureFormat::default();
+    }
+    
+    let system = *SYSTEM_FORMAT.lock();
+    let mic = *MIC_FORMAT.lock();
+    CaptureFormat {
+        system_sample_rate: system.map(|f| f.sample_rate).unwrap_or(0),
+        system_channels: system.map(|f| f.channels as u32).unwrap_or(0),
+        mic_sample_rate: mic.map(|f| f.sample_rate).unwrap_or(0),
+        stream_sample_rate: TARGET_SAMPLE_RATE,
+    }
 }
 
-/// Start WASAPI loopback capture for a specific process
+/// Get current system (loopback) audio level
+pub fn get_system_level() -> f64 {
+    *SYSTEM_LEVEL.lock()
+}
+
+/// Get current microphone level
+pub fn get_mic_level() -> f64 {
+    *MIC_LEVEL.lock()
+}
+
+fn calc_level(samples: &[f32]) -> f64 {
+    if samples.is_empty() {
+        return 0.0;
+    }
+    let sum: f64 = samples.iter().map(|&s| (s as f64) * (s as f64)).sum();
+    (sum / samples.len() as f64).sqrt().min(1.0)
+}
+
+/// Start WASAPI loopback capture
+///
+/// Loopback captures the whole render endpoint; per-process filtering needs
+/// ActivateAudioInterfaceAsync (see the commented-out sketch below)
 #[cfg(target_os = "windows")]
 pub fn start_capture(
     pid: i32,
@@ -21,70 +163,62 @@ pub fn start_capture(
     channels: u32,
     output_path: &str,
     include_microphone: bool,
+    sample_format: WavSampleFormat,
+    save_wav: bool,
+    trim_silence: bool,
 ) -> Result<(), AudioError> {
-    use windows::{
-        Win32::Media::Audio::{
-            IMMDeviceEnumerator, MMDeviceEnumerator, eRender, eConsole,
-            IAudioClient, IAudioCaptureClient, AUDCLNT_SHAREMODE_SHARED,
-            AUDCLNT_STREAMFLAGS_LOOPBACK,
-        },
-        Win32::System::Com::{CoCreateInstance, CoInitializeEx, COINIT_MULTITHREADED, CLSCTX_ALL},
-    };
+    if CAPTURE_RUNNING.load(Ordering::SeqCst) {
+        return Err(AudioError::StreamCreationFailed("Capture already running".to_string()));
+    }
     
     tracing::info!(
-        "Starting WASAPI capture for PID {} at {}Hz, {} channels",
-        pid, sample_rate, channels
+        "Starting WASAPI capture for PID {} at {}Hz, {} channels (mic: {})",
+        pid, sample_rate, channels, include_microphone
     );
     
-    unsafe {
-        // Initialize COM
-        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
-        
-        // Get default audio endpoint
-        let enumerator: IMMDeviceEnumerator = CoCreateInstance(
-            &MMDeviceEnumerator,
-            None,
-            CLSCTX_ALL,
-        ).map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        let device = enumerator.GetDefaultAudioEndpoint(eRender, eConsole)
-            .map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Activate audio client
-        let audio_client: IAudioClient = device.Activate(CLSCTX_ALL, None)
-            .map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Get mix format
-        let mix_format = audio_client.GetMixFormat()
-            .map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Initialize for loopback capture
-        // Note: For process-specific capture on Windows 10 2004+, you would use
-        // ActivateAudioInterfaceAsync with AUDIOCLIENT_ACTIVATION_PARAMS
-        audio_client.Initialize(
-            AUDCLNT_SHAREMODE_SHARED,
-            AUDCLNT_STREAMFLAGS_LOOPBACK,
-            10_000_000, // 1 second buffer
-            0,
-            mix_format,
-            None,
-        ).map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Get capture client
-        let capture_client: IAudioCaptureClient = audio_client.GetService()
-            .map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Start capture
-        audio_client.Start()
-            .map_err(|e| AudioError::StreamCreationFailed(e.to_string()))?;
-        
-        // Store state and start capture thread
-        // (In a real implementation, you'd store these handles globally)
-        
-        tracing::info!("WASAPI loopback capture started");
-    }
+    // Reset state from any previous capture
+    *SYSTEM_RECORDING.lock() = Recording::new(save_wav);
+    *MIC_RECORDING.lock() = Recording::new(save_wav);
+    SYSTEM_BUFFER.lock().clear();
+    MIC_BUFFER.lock().clear();
+    AUDIO_CHUNK_QUEUE.lock().clear();
+    *SYSTEM_LEVEL.lock() = 0.0;
+    *MIC_LEVEL.lock() = 0.0;
+    *IS_PAUSED.write() = false;
+    
+    *CAPTURE_OUTPUT.lock() = Some(CaptureOutput {
+        path: output_path.to_string(),
+        sample_rate,
+        channels: channels.max(1),
+        sample_format,
+        save_wav,
+        trim_silence,
+    });
+    
+    CAPTURE_RUNNING.store(true, Ordering::SeqCst);
     
-    Ok(())
+    // The thread reports whether the device opened before we return
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel();
+    let handle = std::thread::spawn(move || capture_thread(ready_tx, include_microphone));
+    
+    match ready_rx.recv() {
+        Ok(Ok(())) => {
+            *CAPTURE_THREAD.lock() = Some(handle);
+            println!("[Audio] ✅ WASAPI loopback capture started");
+            Ok(())
+        }
+        Ok(Err(e)) => {
+            CAPTURE_RUNNING.store(false, Ordering::SeqCst);
+            let _ = handle.join();
+            CAPTURE_OUTPUT.lock().take();
+            Err(AudioError::StreamCreationFailed(e))
+        }
+        Err(_) => {
+            CAPTURE_RUNNING.store(false, Ordering::SeqCst);
+            CAPTURE_OUTPUT.lock().take();
+            Err(AudioError::StreamCreationFailed("Capture thread exited during startup".to_string()))
+        }
+    }
 }
 
 #[cfg(not(target_os = "windows"))]
@@ -94,23 +228,460 @@ pub fn start_capture(
     _channels: u32,
     _output_path: &str,
     _include_microphone: bool,
+    _sample_format: WavSampleFormat,
+    _save_wav: bool,
+    _trim_silence: bool,
 ) -> Result<(), AudioError> {
     Err(AudioError::UnsupportedPlatform)
 }
 
-/// Stop WASAPI capture
+/// Stop WASAPI capture and write the WAV file (unless started with save_wav off)
+/// Returns the silence trimmed from the file and its length
 #[cfg(target_os = "windows")]
-pub fn stop_capture() -> Result<(), AudioError> {
-    // Stop the audio client and write the file
+pub fn stop_capture() -> Result<SilenceTrim, AudioError> {
     tracing::info!("Stopping WASAPI capture");
-    Ok(())
+    
+    CAPTURE_RUNNING.store(false, Ordering::SeqCst);
+    if let Some(handle) = CAPTURE_THREAD.lock().take() {
+        let _ = handle.join();
+    }
+    *IS_PAUSED.write() = false;
+    
+    let output = CAPTURE_OUTPUT.lock().take().ok_or(AudioError::NotCapturing)?;
+    let system = std::mem::replace(&mut *SYSTEM_RECORDING.lock(), Recording::new(true));
+    let mic = std::mem::replace(&mut *MIC_RECORDING.lock(), Recording::new(true));
+    
+    *SYSTEM_LEVEL.lock() = 0.0;
+    *MIC_LEVEL.lock() = 0.0;
+    
+    if !output.save_wav {
+        return Ok(SilenceTrim::default());
+    }
+    write_wav(&output, &system, &mic)
 }
 
 #[cfg(not(target_os = "windows"))]
-pub fn stop_capture() -> Result<(), AudioError> {
+pub fn stop_capture() -> Result<SilenceTrim, AudioError> {
     Err(AudioError::UnsupportedPlatform)
 }
 
+// ============================================================================
+// Capture Thread
+// ============================================================================
+
+#[cfg(target_os = "windows")]
+fn capture_thread(ready: std::sync::mpsc::Sender<Result<(), String>>, include_microphone: bool) {
+    use windows::Win32::Media::Audio::{eCapture, eCommunications, eConsole, eRender};
+    use windows::Win32::System::Com::{CoInitializeEx, CoUninitialize, COINIT_MULTITHREADED};
+    
+    unsafe {
+        // COM must be initialized on the thread that owns the audio clients
+        let _ = CoInitializeEx(None, COINIT_MULTITHREADED);
+        
+        let system = match open_capture_client(eRender, eConsole, true) {
+            Ok(client) => client,
+            Err(e) => {
+                println!("[Audio] ❌ Failed to open loopback device: {}", e);
+                let _ = ready.send(Err(e));
+                CoUninitialize();
+                return;
+            }
+        };
+        
+        println!("[Audio] Loopback format: {:?}", system.format);
+        SYSTEM_RECORDING.lock().sample_rate = system.format.sample_rate;
+        *SYSTEM_FORMAT.lock() = Some(system.format);
+        
+        // A missing or busy mic shouldn't prevent recording the meeting audio
+        let mut mic = if include_microphone {
+            match open_capture_client(eCapture, eCommunications, false) {
+                Ok(client) => {
+                    println!("[Audio] Microphone format: {:?}", client.format);
+                    MIC_RECORDING.lock().sample_rate = client.format.sample_rate;
+                    *MIC_FORMAT.lock() = Some(client.format);
+                    Some(client)
+                }
+                Err(e) => {
+                    println!("[Audio] ⚠️ Failed to open microphone, recording system audio only: {}", e);
+                    None
+                }
+            }
+        } else {
+            None
+        };
+        
+        let _ = ready.send(Ok(()));
+        
+        let started = std::time::Instant::now();
+        let mut paused_at: Option<std::time::Instant> = None;
+        let mut paused_total = std::time::Duration::ZERO;
+        let mut system_resampler: Option<StreamResampler> = None;
+        let mut mic_resampler: Option<StreamResampler> = None;
+        
+        while CAPTURE_RUNNING.load(Ordering::SeqCst) {
+            // Drain both clients even while paused so stale packets aren't delivered on resume
+            let system_samples = match system.read() {
+                Ok(samples) => samples,
+                Err(e) => {
+                    println!("[Audio] ❌ Loopback read failed: {}", e);
+                    break;
+                }
+            };
+            
+            let mut mic_samples = Vec::new();
+            if let Some(client) = mic.as_ref() {
+                match client.read() {
+                    Ok(samples) => mic_samples = samples,
+                    Err(e) => {
+                        println!("[Audio] ⚠️ Microphone read failed, continuing without mic: {}", e);
+                        client.stop();
+                        mic = None;
+                        *MIC_FORMAT.lock() = None;
+                    }
+                }
+            }
+            
+            let paused = IS_PAUSED.read();
+            if *paused {
+                // Drop resampler filter state from before the pause
+                paused_at.get_or_insert_with(std::time::Instant::now);
+                system_resampler = None;
+                mic_resampler = None;
+            } else {
+                if let Some(at) = paused_at.take() {
+                    paused_total += at.elapsed();
+                }
+                
+                if !system_samples.is_empty() {
+                    on_system_samples(&system_samples, system.format.sample_rate, &mut system_resampler);
+                }
+                // Paused time isn't recorded, so it doesn't count as a loopback gap
+                let recording_secs = started.elapsed().saturating_sub(paused_total).as_secs_f64();
+                fill_loopback_gap(recording_secs, system.format.sample_rate, &mut system_resampler);
+                
+                if let Some(client) = mic.as_ref() {
+                    if !mic_samples.is_empty() {
+                        on_mic_samples(&mic_samples, client.format.sample_rate, &mut mic_resampler);
+                    }
+                }
+                
+                build_stereo_chunks();
+            }
+            drop(paused);
+            
+            std::thread::sleep(std::time::Duration::from_millis(POLL_INTERVAL_MS));
+        }
+        
+        system.stop();
+        if let Some(client) = mic.take() {
+            client.stop();
+        }
+        drop(system);
+        *SYSTEM_FORMAT.lock() = None;
+        *MIC_FORMAT.lock() = None;
+        CoUninitialize();
+        
+        // Still set: the loop ended on a read error rather than stop_capture
+        if CAPTURE_RUNNING.load(Ordering::SeqCst) {
+            crate::notify_capture_interrupted();
+        }
+    }
+}
+
+/// An initialized, started WASAPI capture stream
+#[cfg(target_os = "windows")]
+struct CaptureClient {
+    audio_client: windows::Win32::Media::Audio::IAudioClient,
+    capture_client: windows::Win32::Media::Audio::IAudioCaptureClient,
+    format: MixFormat,
+}
+
+#[cfg(target_os = "windows")]
+impl CaptureClient {
+    /// Drain every packet currently available, as mono samples at the device rate
+    unsafe fn read(&self) -> windows::core::Result<Vec<f32>> {
+        use windows::Win32::Media::Audio::AUDCLNT_BUFFERFLAGS_SILENT;
+        
+        let mut samples = Vec::new();
+        
+        while self.capture_client.GetNextPacketSize()? > 0 {
+            let mut data: *mut u8 = std::ptr::null_mut();
+            let mut frames: u32 = 0;
+            let mut flags: u32 = 0;
+            self.capture_client.GetBuffer(&mut data, &mut frames, &mut flags, None, None)?;
+            
+            if flags & AUDCLNT_BUFFERFLAGS_SILENT.0 as u32 != 0 || data.is_null() {
+                samples.extend(std::iter::repeat(0.0).take(frames as usize));
+            } else {
+                let bytes = std::slice::from_raw_parts(data, frames as usize * self.format.block_align());
+                samples.extend(self.format.to_mono(bytes));
+            }
+            
+            self.capture_client.ReleaseBuffer(frames)?;
+        }
+        
+        Ok(samples)
+    }
+    
+    unsafe fn stop(&self) {
+        let _ = self.audio_client.Stop();
+    }
+}
+
+/// Open the default endpoint for `flow`/`role` in shared mode and start it.
+/// `loopback` captures what a render endpoint is playing
+#[cfg(target_os = "windows")]
+unsafe fn open_capture_client(
+    flow: windows::Win32::Media::Audio::EDataFlow,
+    role: windows::Win32::Media::Audio::ERole,
+    loopback: bool,
+) -> Result<CaptureClient, String> {
+    use windows::Win32::Media::Audio::{
+        IMMDeviceEnumerator, MMDeviceEnumerator,
+        IAudioClient, IAudioCaptureClient, AUDCLNT_SHAREMODE_SHARED,
+        AUDCLNT_STREAMFLAGS_LOOPBACK,
+    };
+    use windows::Win32::System::Com::{CoCreateInstance, CoTaskMemFree, CLSCTX_ALL};
+    
+    let enumerator: IMMDeviceEnumerator = CoCreateInstance(&MMDeviceEnumerator, None, CLSCTX_ALL)
+        .map_err(|e| e.to_string())?;
+    
+    let device = enumerator.GetDefaultAudioEndpoint(flow, role)
+        .map_err(|e| e.to_string())?;
+    
+    let audio_client: IAudioClient = device.Activate(CLSCTX_ALL, None)
+        .map_err(|e| e.to_string())?;
+    
+    let mix_format = audio_client.GetMixFormat().map_err(|e| e.to_string())?;
+    let format = parse_mix_format(mix_format);
+    
+    let flags = if loopback { AUDCLNT_STREAMFLAGS_LOOPBACK } else { 0 };
+    let init = audio_client.Initialize(
+        AUDCLNT_SHAREMODE_SHARED,
+        flags,
+        10_000_000, // 1 second buffer
+        0,
+        mix_format,
+        None,
+    );
+    CoTaskMemFree(Some(mix_format as *const _));
+    init.map_err(|e| e.to_string())?;
+    
+    let format = format?;
+    
+    let capture_client: IAudioCaptureClient = audio_client.GetService()
+        .map_err(|e| e.to_string())?;
+    
+    audio_client.Start().map_err(|e| e.to_string())?;
+    
+    Ok(CaptureClient { audio_client, capture_client, format })
+}
+
+/// Read sample rate/channels/encoding from a WAVEFORMATEX(TENSIBLE)
+#[cfg(target_os = "windows")]
+unsafe fn parse_mix_format(format: *const windows::Win32::Media::Audio::WAVEFORMATEX) -> Result<MixFormat, String> {
+    use windows::Win32::Media::Audio::WAVEFORMATEXTENSIBLE;
+    
+    const WAVE_FORMAT_PCM: u16 = 1;
+    const WAVE_FORMAT_IEEE_FLOAT: u16 = 3;
+    const WAVE_FORMAT_EXTENSIBLE: u16 = 0xFFFE;
+    
+    let base = format.read_unaligned();
+    let tag = base.wFormatTag;
+    
+    // EXTENSIBLE formats carry the real encoding in the SubFormat GUID's first field
+    let encoding = if tag == WAVE_FORMAT_EXTENSIBLE {
+        let ext = format as *const WAVEFORMATEXTENSIBLE;
+        std::ptr::addr_of!((*ext).SubFormat).read_unaligned().data1 as u16
+    } else {
+        tag
+    };
+    
+    let is_float = match encoding {
+        WAVE_FORMAT_IEEE_FLOAT => true,
+        WAVE_FORMAT_PCM => false,
+        other => return Err(format!("Unsupported mix format encoding {}", other)),
+    };
+    
+    let format = MixFormat {
+        sample_rate: base.nSamplesPerSec,
+        channels: base.nChannels as usize,
+        bits_per_sample: base.wBitsPerSample,
+        is_float,
+    };
+    
+    match (format.is_float, format.bits_per_sample) {
+        (true, 32) | (false, 16) | (false, 24) | (false, 32) if format.channels > 0 => Ok(format),
+        _ => Err(format!("Unsupported mix format {:?}", format)),
+    }
+}
+
+// ============================================================================
+// Chunk Streaming
+// ============================================================================
+
+fn on_system_samples(samples: &[f32], sample_rate: u32, resampler: &mut Option<StreamResampler>) {
+    *SYSTEM_LEVEL.lock() = calc_level(samples);
+    SYSTEM_RECORDING.lock().push(samples);
+    
+    let resampled = resample_stream_to_16k(resampler, samples, sample_rate as f64);
+    SYSTEM_BUFFER.lock().extend_from_slice(&resampled);
+}
+
+/// Pad system audio with silence while loopback is idle, keeping it aligned with the mic
+fn fill_loopback_gap(elapsed_secs: f64, sample_rate: u32, resampler: &mut Option<StreamResampler>) {
+    let expected = (elapsed_secs * sample_rate as f64) as usize;
+    let recorded = SYSTEM_RECORDING.lock().length;
+    let tolerance = (LOOPBACK_GAP_SECS * sample_rate as f64) as usize;
+    
+    if expected > recorded + tolerance {
+        let silence = vec![0.0f32; expected - recorded];
+        on_system_samples(&silence, sample_rate, resampler);
+    }
+}
+
+fn on_mic_samples(samples: &[f32], sample_rate: u32, resampler: &mut Option<StreamResampler>) {
+    *MIC_LEVEL.lock() = calc_level(samples);
+    MIC_RECORDING.lock().push(samples);
+    
+    let resampled = resample_stream_to_16k(resampler, samples, sample_rate as f64);
+    MIC_BUFFER.lock().extend_from_slice(&resampled);
+}
+
+fn pcm16(sample: f32) -> [u8; 2] {
+    ((sample.clamp(-1.0, 1.0) * 32767.0) as i16).to_le_bytes()
+}
+
+/// Pack buffered 16kHz audio into stereo PCM16 chunks (L = system, R = mic)
+fn build_stereo_chunks() {
+    let mut system = SYSTEM_BUFFER.lock();
+    let mut mic = MIC_BUFFER.lock();
+    
+    // Read once under both locks so L/R stay aligned if the duration changes mid-stream
+    let chunk_size = chunk_samples();
+    while system.len() >= chunk_size || mic.len() >= chunk_size {
+        let samples_to_process = chunk_size.min(system.len().max(mic.len()));
+        
+        let mut stereo_chunk: Vec<u8> = Vec::with_capacity(samples_to_process * 4);
+        for i in 0..samples_to_process {
+            stereo_chunk.extend_from_slice(&pcm16(system.get(i).copied().unwrap_or(0.0)));
+            stereo_chunk.extend_from_slice(&pcm16(mic.get(i).copied().unwrap_or(0.0) * MIC_GAIN));
+        }
+        
+        // Queue the chunk, dropping the oldest if the consumer has fallen behind
+        queue_mono_chunks(&system, &mic, samples_to_process, MIC_GAIN, MAX_QUEUED_CHUNKS);
+        retain_chunk(&stereo_chunk);
+        let mut queue = AUDIO_CHUNK_QUEUE.lock();
+        queue.push_back(stereo_chunk);
+        while queue.len() > MAX_QUEUED_CHUNKS {
+            queue.pop_front();
+        }
+        drop(queue);
+        
+        let system_used = samples_to_process.min(system.len());
+        system.drain(..system_used);
+        let mic_used = samples_to_process.min(mic.len());
+        mic.drain(..mic_used);
+    }
+}
+
+/// Pause capture: the WASAPI clients keep running but their packets are dropped
+pub fn pause_capture() {
+    let mut paused = IS_PAUSED.write();
+    *paused = true;
+    
+    // Flush whole chunks, then drop the partial remainder of both channels so
+    // L/R restart aligned on resume instead of one side being zero-padded
+    build_stereo_chunks();
+    SYSTEM_BUFFER.lock().clear();
+    MIC_BUFFER.lock().clear();
+    drop(paused);
+    println!("[Audio] Capture paused");
+}
+
+/// Resume a paused capture; the capture thread resets its resamplers while paused
+pub fn resume_capture() {
+    *IS_PAUSED.write() = false;
+    println!("[Audio] Capture resumed");
+}
+
+/// Take all queued stereo chunks
+pub fn get_audio_chunks() -> Vec<Vec<u8>> {
+    AUDIO_CHUNK_QUEUE.lock().drain(..).collect()
+}
+
+/// Check if there are audio chunks ready for streaming
+pub fn has_audio_chunks() -> bool {
+    !AUDIO_CHUNK_QUEUE.lock().is_empty()
+}
+
+// ============================================================================
+// WAV Output
+// ============================================================================
+
+fn resample_recording(recording: &Recording, to_rate: u32) -> Result<Vec<f32>, AudioError> {
+    if recording.sample_rate == 0 {
+        return Ok(Vec::new());
+    }
+    try_resample_audio(&recording.samples, recording.sample_rate, to_rate).map_err(AudioError::WriteError)
+}
+
+/// Resample both recordings to the requested rate and write them in the requested sample format with the
+/// requested channel count: mono mixes system and mic, stereo is L = system, R = mic
+/// (extra channels are silent). Without a mic, system audio fills every channel
+fn write_wav(output: &CaptureOutput, system: &Recording, mic: &Recording) -> Result<SilenceTrim, AudioError> {
+    let mut system = resample_recording(system, output.sample_rate)?;
+    let mut mic = resample_recording(mic, output.sample_rate)?;
+    let has_mic = !mic.is_empty();
+    let trim = if output.trim_silence {
+        trim_silence(&mut system, &mut mic, output.sample_rate)
+    } else {
+        SilenceTrim::untrimmed(system.len().max(mic.len()), output.sample_rate)
+    };
+    
+    // Auto gain levels both tracks itself, replacing the fixed mic boost
+    let auto_gain = apply_auto_gain(&mut system, &mut mic);
+    let mic_gain = if auto_gain { 1.0 } else { MIC_GAIN };
+    let frames = system.len().max(mic.len());
+    
+    let channels = output.channels as usize;
+    let mut samples: Vec<f32> = Vec::with_capacity(frames * channels);
+    for i in 0..frames {
+        let system_sample = system.get(i).copied().unwrap_or(0.0);
+        let mic_sample = mic.get(i).copied().unwrap_or(0.0) * mic_gain;
+        
+        for channel in 0..channels {
+            let sample = match (has_mic, channels, channel) {
+                (false, _, _) => system_sample,
+                (true, 1, _) => system_sample + mic_sample,
+                (true, _, 0) => system_sample,
+                (true, _, 1) => mic_sample,
+                _ => 0.0,
+            };
+            samples.push(if auto_gain { soft_limit(sample) } else { sample });
+        }
+    }
+    
+    let data = output.sample_format.encode(&samples);
+    let header = WavHeader::with_format(output.sample_rate, channels as u16, output.sample_format)
+        .write_header(data.len() as u32);
+    
+    let mut file_bytes = header;
+    file_bytes.extend_from_slice(&data);
+    create_private_file(&output.path)
+        .and_then(|mut f| std::io::Write::write_all(&mut f, &file_bytes))
+        .map_err(|e| AudioError::WriteError(e.to_string()))?;
+    
+    println!(
+        "[Audio] ✅ Wrote {} ({:.1}s, {}Hz, {} ch)",
+        output.path,
+        frames as f64 / output.sample_rate.max(1) as f64,
+        output.sample_rate,
+        channels
+    );
+    Ok(trim)
+}
+
 // ============================================================================
 // Process-specific audio capture on Windows 10 2004+ / Windows 11
 // ============================================================================
@@ -148,10 +719,3 @@ fn init_process_loopback(pid: u32) -> Result<(), AudioError> {
     }
 }
 */
-
-
-
-
-
-
-
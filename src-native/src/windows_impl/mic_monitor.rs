@@ -0,0 +1,127 @@
+//! Monitor microphone usage to detect when a meeting ends
+//! Uses the privacy consent store, where Windows records per-app microphone
+//! sessions: an app is using the mic while its LastUsedTimeStop is 0
+
+use windows::core::{PCWSTR, PWSTR};
+use windows::Win32::Foundation::{ERROR_NO_MORE_ITEMS, ERROR_SUCCESS};
+use windows::Win32::System::Registry::{
+    RegCloseKey, RegEnumKeyExW, RegOpenKeyExW, RegQueryValueExW, HKEY, HKEY_CURRENT_USER, KEY_READ,
+};
+
+const CONSENT_STORE_KEY: &str =
+    "Software\\Microsoft\\Windows\\CurrentVersion\\CapabilityAccessManager\\ConsentStore\\microphone";
+
+/// Desktop (non-Store) apps are nested one level deeper, keyed by exe path
+const NON_PACKAGED_KEY: &str = "NonPackaged";
+
+fn wide(s: &str) -> Vec<u16> {
+    s.encode_utf16().chain(std::iter::once(0)).collect()
+}
+
+/// Registry key that closes itself on drop
+struct RegKey(HKEY);
+
+impl RegKey {
+    fn open(parent: HKEY, path: &str) -> Result<Self, String> {
+        let path = wide(path);
+        let mut key = HKEY::default();
+        let status = unsafe { RegOpenKeyExW(parent, PCWSTR(path.as_ptr()), 0, KEY_READ, &mut key) };
+        if status != ERROR_SUCCESS {
+            return Err(format!("RegOpenKeyExW failed: {:?}", status));
+        }
+        Ok(Self(key))
+    }
+
+    fn subkeys(&self) -> Result<Vec<String>, String> {
+        let mut names = Vec::new();
+        let mut index = 0;
+
+        loop {
+            let mut buffer = [0u16; 512];
+            let mut len = buffer.len() as u32;
+            let status = unsafe {
+                RegEnumKeyExW(self.0, index, PWSTR(buffer.as_mut_ptr()), &mut len, None, PWSTR::null(), None, None)
+            };
+
+            if status == ERROR_NO_MORE_ITEMS {
+                break;
+            }
+            if status != ERROR_SUCCESS {
+                return Err(format!("RegEnumKeyExW failed: {:?}", status));
+            }
+
+            names.push(String::from_utf16_lossy(&buffer[..len as usize]));
+            index += 1;
+        }
+
+        Ok(names)
+    }
+
+    fn read_u64(&self, name: &str) -> Option<u64> {
+        let name = wide(name);
+        let mut value: u64 = 0;
+        let mut size = std::mem::size_of::<u64>() as u32;
+        let status = unsafe {
+            RegQueryValueExW(
+                self.0,
+                PCWSTR(name.as_ptr()),
+                None,
+                None,
+                Some(&mut value as *mut u64 as *mut u8),
+                Some(&mut size),
+            )
+        };
+        (status == ERROR_SUCCESS).then_some(value)
+    }
+}
+
+impl Drop for RegKey {
+    fn drop(&mut self) {
+        unsafe {
+            let _ = RegCloseKey(self.0);
+        }
+    }
+}
+
+/// An app entry has started a session that hasn't stopped yet
+fn app_is_using_mic(key: &RegKey) -> bool {
+    let started = key.read_u64("LastUsedTimeStart").unwrap_or(0);
+    let stopped = key.read_u64("LastUsedTimeStop").unwrap_or(1);
+    started != 0 && stopped == 0
+}
+
+fn any_app_using_mic(store: &RegKey) -> Result<bool, String> {
+    for name in store.subkeys()? {
+        let app = match RegKey::open(store.0, &name) {
+            Ok(key) => key,
+            Err(_) => continue,
+        };
+
+        let in_use = if name == NON_PACKAGED_KEY {
+            any_app_using_mic(&app)?
+        } else {
+            app_is_using_mic(&app)
+        };
+
+        if in_use {
+            println!("[Ghost MicMonitor] Microphone in use by {}", name);
+            return Ok(true);
+        }
+    }
+
+    Ok(false)
+}
+
+/// Check if the microphone is currently being used by any application
+pub fn is_microphone_in_use() -> bool {
+    // On error, assume mic is in use to avoid false positives
+    let result = RegKey::open(HKEY_CURRENT_USER, CONSENT_STORE_KEY).and_then(|store| any_app_using_mic(&store));
+
+    match result {
+        Ok(in_use) => in_use,
+        Err(e) => {
+            println!("[Ghost MicMonitor] Failed to read microphone consent store: {}", e);
+            true
+        }
+    }
+}
@@ -3,6 +3,8 @@
 pub mod window;
 pub mod accessibility;
 pub mod audio;
+pub mod mic_monitor;
+pub mod process_stats;
 
 
 
@@ -0,0 +1,29 @@
+//! Resident memory and CPU time of this process
+
+use windows::Win32::Foundation::FILETIME;
+use windows::Win32::System::ProcessStatus::{GetProcessMemoryInfo, PROCESS_MEMORY_COUNTERS};
+use windows::Win32::System::Threading::{GetCurrentProcess, GetProcessTimes};
+
+/// Working set size in bytes (what Task Manager shows as memory)
+pub fn resident_bytes() -> Option<u64> {
+    let mut counters = PROCESS_MEMORY_COUNTERS::default();
+    let size = std::mem::size_of::<PROCESS_MEMORY_COUNTERS>() as u32;
+    unsafe { GetProcessMemoryInfo(GetCurrentProcess(), &mut counters, size) }.ok()?;
+    Some(counters.WorkingSetSize as u64)
+}
+
+/// FILETIME durations are in 100ns units
+fn filetime_secs(time: &FILETIME) -> f64 {
+    let ticks = ((time.dwHighDateTime as u64) << 32) | time.dwLowDateTime as u64;
+    ticks as f64 / 10_000_000.0
+}
+
+/// User + kernel CPU seconds used by the process so far
+pub fn cpu_time_secs() -> Option<f64> {
+    let mut creation = FILETIME::default();
+    let mut exit = FILETIME::default();
+    let mut kernel = FILETIME::default();
+    let mut user = FILETIME::default();
+    unsafe { GetProcessTimes(GetCurrentProcess(), &mut creation, &mut exit, &mut kernel, &mut user) }.ok()?;
+    Some(filetime_secs(&kernel) + filetime_secs(&user))
+}
@@ -1,16 +1,22 @@
 //! Windows window enumeration using Win32 API
 
 use crate::WindowInfo;
+#[cfg(target_os = "windows")]
+use crate::window::{is_meeting_app, stable_window_key};
 
 #[cfg(target_os = "windows")]
 use windows::{
     Win32::Foundation::{BOOL, HWND, LPARAM, WPARAM},
     Win32::System::Threading::{OpenProcess, PROCESS_QUERY_LIMITED_INFORMATION},
     Win32::UI::WindowsAndMessaging::{
-        EnumWindows, GetWindowTextW, GetWindowThreadProcessId, IsWindowVisible,
+        EnumWindows, GetForegroundWindow, GetWindowTextW, GetWindowThreadProcessId, IsIconic, IsWindow, IsWindowVisible,
     },
 };
 
+#[cfg(target_os = "windows")]
+use crate::window::{report_active_window, set_active_window_handler, WindowChangeHandler};
+#[cfg(target_os = "windows")]
+use parking_lot::Mutex;
 #[cfg(target_os = "windows")]
 use std::ffi::OsString;
 #[cfg(target_os = "windows")]
@@ -31,20 +37,63 @@ pub fn get_windows() -> Vec<WindowInfo> {
     windows
 }
 
+/// Get the window that currently has keyboard focus
+#[cfg(target_os = "windows")]
+pub fn get_frontmost_window() -> Option<WindowInfo> {
+    unsafe {
+        let hwnd = GetForegroundWindow();
+        if hwnd.0.is_null() {
+            return None;
+        }
+        
+        window_info(hwnd)
+    }
+}
+
+/// Re-read one window from the id get_windows returned (its HWND); None once it's gone
+#[cfg(target_os = "windows")]
+pub fn get_window_by_id(window_id: i32) -> Option<WindowInfo> {
+    if window_id == 0 {
+        return None;
+    }
+    
+    unsafe {
+        // Window ids are HWNDs truncated to 32 bits, which is lossless: handles fit in 32 bits
+        let hwnd = HWND(window_id as isize as *mut std::ffi::c_void);
+        if !IsWindow(hwnd).as_bool() {
+            return None;
+        }
+        
+        window_info(hwnd)
+    }
+}
+
 #[cfg(target_os = "windows")]
 unsafe extern "system" fn enum_window_callback(hwnd: HWND, lparam: LPARAM) -> BOOL {
     let windows = &mut *(lparam.0 as *mut Vec<WindowInfo>);
     
+    // EnumWindows walks top-level windows in z-order, top first
+    if let Some(mut info) = window_info(hwnd) {
+        info.z_order = windows.len() as i32;
+        windows.push(info);
+    }
+    
+    BOOL(1)
+}
+
+/// Build WindowInfo for a visible, titled window
+#[cfg(target_os = "windows")]
+unsafe fn window_info(hwnd: HWND) -> Option<WindowInfo> {
     // Check if window is visible
     if !IsWindowVisible(hwnd).as_bool() {
-        return BOOL(1);
+        return None;
     }
     
     // Get window title
     let mut title_buf = [0u16; 512];
     let len = GetWindowTextW(hwnd, &mut title_buf);
     if len == 0 {
-        return BOOL(1);
+        return None;
     }
     
     let title = OsString::from_wide(&title_buf[..len as usize])
@@ -53,7 +102,7 @@ unsafe extern "system" fn enum_window_callback(hwnd: HWND, lparam: LPARAM) -> BO
     
     // Skip empty titles
     if title.is_empty() {
-        return BOOL(1);
+        return None;
     }
     
     // Get process ID
@@ -62,20 +111,25 @@ unsafe extern "system" fn enum_window_callback(hwnd: HWND, lparam: LPARAM) -> BO
     
     // Get process name
     let owner_name = get_process_name(pid).unwrap_or_default();
+    let stable_key = stable_window_key(pid as i32, None, &owner_name, &title);
+    let is_meeting_app = is_meeting_app(None, &owner_name);
     
-    windows.push(WindowInfo {
+    Some(WindowInfo {
         pid: pid as i32,
         window_id: hwnd.0 as i32,
         owner_name,
         title,
         bundle_id: None, // Windows doesn't have bundle IDs
-    });
-    
-    BOOL(1)
+        stable_key,
+        is_meeting_app,
+        is_minimized: IsIconic(hwnd).as_bool(),
+        is_focused: GetForegroundWindow() == hwnd,
+        z_order: -1,
+    })
 }
 
 #[cfg(target_os = "windows")]
-fn get_process_name(pid: u32) -> Option<String> {
+pub(crate) fn get_process_name(pid: u32) -> Option<String> {
     use windows::Win32::System::ProcessStatus::GetModuleBaseNameW;
     
     unsafe {
@@ -96,14 +150,231 @@ fn get_process_name(pid: u32) -> Option<String> {
     }
 }
 
+/// Executable name and icon of the process with this pid
+#[cfg(target_os = "windows")]
+pub fn get_app_info(pid: i32) -> Option<crate::AppInfo> {
+    let name = get_process_name(pid as u32)?;
+    let icon_png = unsafe { process_icon_png(pid as u32) }.map(napi::bindgen_prelude::Buffer::from);
+    
+    Some(crate::AppInfo { name, bundle_id: None, icon_png })
+}
+
+/// Extract the large icon from the process's exe and encode it as PNG
+#[cfg(target_os = "windows")]
+unsafe fn process_icon_png(pid: u32) -> Option<Vec<u8>> {
+    use windows::core::{PCWSTR, PWSTR};
+    use windows::Win32::Foundation::CloseHandle;
+    use windows::Win32::Graphics::Gdi::{
+        DeleteObject, GetDC, GetDIBits, ReleaseDC, BITMAPINFO, BITMAPINFOHEADER, BI_RGB, DIB_RGB_COLORS,
+    };
+    use windows::Win32::System::Threading::{QueryFullProcessImageNameW, PROCESS_NAME_WIN32};
+    use windows::Win32::UI::Shell::ExtractIconExW;
+    use windows::Win32::UI::WindowsAndMessaging::{DestroyIcon, GetIconInfo, HICON, ICONINFO};
+
+    let handle = OpenProcess(PROCESS_QUERY_LIMITED_INFORMATION, false, pid).ok()?;
+    let mut path_buf = [0u16; 1024];
+    let mut path_len = path_buf.len() as u32;
+    let queried = QueryFullProcessImageNameW(handle, PROCESS_NAME_WIN32, PWSTR(path_buf.as_mut_ptr()), &mut path_len);
+    let _ = CloseHandle(handle);
+    queried.ok()?;
+    let mut exe_path: Vec<u16> = path_buf[..path_len as usize].to_vec();
+    exe_path.push(0);
+
+    let mut icon = HICON::default();
+    if ExtractIconExW(PCWSTR(exe_path.as_ptr()), 0, Some(&mut icon), None, 1) == 0 || icon.is_invalid() {
+        return None;
+    }
+
+    let mut info = ICONINFO::default();
+    if GetIconInfo(icon, &mut info).is_err() {
+        let _ = DestroyIcon(icon);
+        return None;
+    }
+
+    let dc = GetDC(HWND::default());
+    let mut bmi = BITMAPINFO::default();
+    bmi.bmiHeader.biSize = std::mem::size_of::<BITMAPINFOHEADER>() as u32;
+
+    // First call fills in the bitmap dimensions
+    let mut rgba = None;
+    if GetDIBits(dc, info.hbmColor, 0, 0, None, &mut bmi, DIB_RGB_COLORS) != 0 {
+        let width = bmi.bmiHeader.biWidth.unsigned_abs();
+        let height = bmi.bmiHeader.biHeight.unsigned_abs();
+        bmi.bmiHeader.biBitCount = 32;
+        bmi.bmiHeader.biCompression = BI_RGB.0;
+        bmi.bmiHeader.biHeight = -(height as i32); // top-down rows
+
+        let mut pixels = vec![0u8; (width * height * 4) as usize];
+        let rows = GetDIBits(
+            dc,
+            info.hbmColor,
+            0,
+            height,
+            Some(pixels.as_mut_ptr() as *mut _),
+            &mut bmi,
+            DIB_RGB_COLORS,
+        );
+        if rows == height as i32 {
+            // BGRA -> RGBA; icons without an alpha channel report all zeros
+            let has_alpha = pixels.chunks_exact(4).any(|p| p[3] != 0);
+            for p in pixels.chunks_exact_mut(4) {
+                p.swap(0, 2);
+                if !has_alpha {
+                    p[3] = 255;
+                }
+            }
+            rgba = Some((width, height, pixels));
+        }
+    }
+
+    ReleaseDC(HWND::default(), dc);
+    let _ = DeleteObject(info.hbmColor);
+    let _ = DeleteObject(info.hbmMask);
+    let _ = DestroyIcon(icon);
+
+    rgba.and_then(|(width, height, pixels)| crate::window::encode_png_rgba(width, height, &pixels))
+}
+
+// ============================================================================
+// Focus-change watching
+// ============================================================================
+
+/// Thread running the message loop that receives foreground events
+#[cfg(target_os = "windows")]
+static WATCH_THREAD_ID: Mutex<Option<u32>> = Mutex::new(None);
+
+/// Report the foreground window whenever it changes, via an EVENT_SYSTEM_FOREGROUND hook
+/// Replaces any existing watch
+#[cfg(target_os = "windows")]
+pub fn watch_active_window(handler: WindowChangeHandler) -> Result<(), String> {
+    use windows::Win32::Foundation::HMODULE;
+    use windows::Win32::System::Threading::GetCurrentThreadId;
+    use windows::Win32::UI::Accessibility::{SetWinEventHook, UnhookWinEvent};
+    use windows::Win32::UI::WindowsAndMessaging::{
+        DispatchMessageW, GetMessageW, TranslateMessage, EVENT_SYSTEM_FOREGROUND, MSG, WINEVENT_OUTOFCONTEXT,
+    };
+    
+    unwatch_active_window();
+    set_active_window_handler(Some(handler));
+    
+    let (ready_tx, ready_rx) = std::sync::mpsc::channel::<Result<u32, String>>();
+    
+    // Out-of-context hooks are delivered through the installing thread's message queue
+    std::thread::spawn(move || unsafe {
+        let hook = SetWinEventHook(
+            EVENT_SYSTEM_FOREGROUND,
+            EVENT_SYSTEM_FOREGROUND,
+            HMODULE::default(),
+            Some(on_foreground_changed),
+            0,
+            0,
+            WINEVENT_OUTOFCONTEXT,
+        );
+        if hook.is_invalid() {
+            let _ = ready_tx.send(Err("SetWinEventHook failed".to_string()));
+            return;
+        }
+        let _ = ready_tx.send(Ok(GetCurrentThreadId()));
+        
+        let mut msg = MSG::default();
+        while GetMessageW(&mut msg, HWND::default(), 0, 0).as_bool() {
+            let _ = TranslateMessage(&msg);
+            DispatchMessageW(&msg);
+        }
+        
+        let _ = UnhookWinEvent(hook);
+    });
+    
+    let thread_id = match ready_rx.recv() {
+        Ok(Ok(thread_id)) => thread_id,
+        Ok(Err(e)) => {
+            set_active_window_handler(None);
+            return Err(e);
+        }
+        Err(_) => {
+            set_active_window_handler(None);
+            return Err("Window watch thread exited unexpectedly".to_string());
+        }
+    };
+    *WATCH_THREAD_ID.lock() = Some(thread_id);
+    
+    println!("[Window] ✅ Watching active window");
+    if let Some(info) = get_frontmost_window() {
+        report_active_window(info);
+    }
+    Ok(())
+}
+
+/// Stop the watch started by watch_active_window
+/// Returns false if no watch was active
+#[cfg(target_os = "windows")]
+pub fn unwatch_active_window() -> bool {
+    use windows::Win32::UI::WindowsAndMessaging::{PostThreadMessageW, WM_QUIT};
+    
+    set_active_window_handler(None);
+    
+    let thread_id = match WATCH_THREAD_ID.lock().take() {
+        Some(thread_id) => thread_id,
+        None => return false,
+    };
+    
+    // Ends the message loop, which unhooks on its way out
+    unsafe {
+        let _ = PostThreadMessageW(thread_id, WM_QUIT, WPARAM(0), LPARAM(0));
+    }
+    
+    println!("[Window] Stopped watching active window");
+    true
+}
+
+#[cfg(target_os = "windows")]
+unsafe extern "system" fn on_foreground_changed(
+    _hook: windows::Win32::UI::Accessibility::HWINEVENTHOOK,
+    _event: u32,
+    hwnd: HWND,
+    _id_object: i32,
+    _id_child: i32,
+    _event_thread: u32,
+    _event_time: u32,
+) {
+    if let Some(info) = window_info(hwnd) {
+        report_active_window(info);
+    }
+}
+
 #[cfg(not(target_os = "windows"))]
 pub fn get_windows() -> Vec<WindowInfo> {
     vec![]
 }
 
+#[cfg(not(target_os = "windows"))]
+pub fn get_frontmost_window() -> Option<WindowInfo> {
+    None
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn get_window_by_id(_window_id: i32) -> Option<WindowInfo> {
+    None
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn get_app_info(_pid: i32) -> Option<crate::AppInfo> {
+    None
+}
+
 
 
 
 
 
 
+
+#[cfg(not(target_os = "windows"))]
+pub fn watch_active_window(_handler: crate::window::WindowChangeHandler) -> Result<(), String> {
+    Err("Window watching is not supported on this platform".to_string())
+}
+
+#[cfg(not(target_os = "windows"))]
+pub fn unwatch_active_window() -> bool {
+    false
+}
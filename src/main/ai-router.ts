@@ -45,7 +45,7 @@ async function runLLMStream(messagesJson: string, maxTokens: number = 2000): Pro
     
     try {
       console.log('[AIRouter] Starting LLM stream with maxTokens:', maxTokens);
-      nativeModule.llmChatStream(messagesJson, maxTokens, (chunk: string) => {
+      nativeModule.llmChatStream(messagesJson, (chunk: string) => {
         if (resolved) return;
         
         if (chunk === '[DONE]') {
@@ -65,7 +65,7 @@ async function runLLMStream(messagesJson: string, maxTokens: number = 2000): Pro
             console.log('[AIRouter] LLM streaming... ', fullText.length, 'chars');
           }
         }
-      });
+      }, maxTokens, 0.5);
     } catch (e) {
       resolved = true;
       clearTimeout(timeout);
@@ -3638,9 +3638,11 @@ Answer:`;
   });
 
   // Transcribe audio buffer using Parakeet
-  ipcMain.handle('parakeet-transcribe-buffer', async (_, audioData: Buffer, sampleRate?: number, channels?: number) => {
+  // Renderer buffers are mono PCM16. Native code used to ignore `channels` and now mixes that
+  // many interleaved channels down, so a stale value from the renderer isn't forwarded
+  ipcMain.handle('parakeet-transcribe-buffer', async (_, audioData: Buffer, sampleRate?: number) => {
     try {
-      return nativeModule?.transcribeAudioBuffer?.(audioData, sampleRate, channels) ?? '';
+      return nativeModule?.transcribeAudioBuffer?.(audioData, sampleRate, 1) ?? '';
     } catch (e: any) {
       console.error('[Parakeet] Transcribe buffer error:', e);
       throw new Error(e.message || String(e));
@@ -218,7 +218,7 @@ export class TranscriptionRouter {
       this.cumulativeAudioTime += chunkDurationSeconds;
       
       // Transcribe with timestamps
-      const result = this.nativeModule.transcribeAudioBufferWithTimestamps?.(monoBuffer, 16000, 1);
+      const result = this.nativeModule.transcribeAudioBufferWithTimestamps?.(monoBuffer, { sampleRate: 16000, channels: 1 });
       
       if (result && result.segments && result.segments.length > 0) {
         // Emit each segment with its proper timestamp